@@ -1,15 +1,52 @@
 use anyhow::Context;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 use std::sync::Arc;
-use tokio::sync::{mpsc::Sender, Mutex, oneshot};
+use tokio::{
+    sync::{Mutex, oneshot},
+    task::JoinSet,
+};
 
-use crate::{ArcStr, net::Net};
+use futures_core::Stream;
 
+use crate::{
+    ArcStr,
+    net::{BodyStream, MAX_LINK_PAGES, Net},
+    utils::ShutdownError,
+};
+
+mod cache;
 mod core;
+mod data;
+pub mod diff;
+pub mod feed;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod mail;
 mod message;
+pub mod mirror;
+pub mod nntp;
+pub mod patch;
+pub mod poller;
+#[cfg(feature = "pgp")]
+pub mod signature;
+pub mod sse;
 
 // Re-export public types for external use
-pub use message::LoreApiMessage;
+pub use data::{
+    ActivityStatus, ActivityThresholds, BatchMode, EpochInfo, FeedOffset, FeedUpdate, InboxConfig,
+    ListStats, LoreApiError, LoreApiOptions, MailingList, Maintainer, PatchPreview, RawMessage,
+    Series, SeriesVersion, UrlRewriter,
+};
+pub use diff::ParsedDiff;
+pub use feed::{PatchEntry, PatchFeed};
+pub use mail::{Attachment, MessageLinks, normalize_message_id};
+pub use message::{LoreApiMessage, LoreApiSender};
+pub use nntp::OverviewEntry;
+pub use patch::LineEnding;
+pub use poller::PollBackoff;
+#[cfg(feature = "pgp")]
+pub use signature::SignatureStatus;
 
 /// The Lore API actor that provides a high-level interface for interacting with the Lore Kernel API.
 ///
@@ -29,7 +66,7 @@ pub use message::LoreApiMessage;
 #[derive(Debug, Clone)]
 pub enum LoreApi {
     /// A real Lore API actor that performs HTTP requests through the networking actor
-    Actual(Sender<LoreApiMessage>),
+    Actual(LoreApiSender),
     /// A mock implementation for testing
     Mock(Arc<Mutex<HashMap<String, ArcStr>>>),
 }
@@ -60,6 +97,108 @@ impl LoreApi {
         lore_api
     }
 
+    /// Creates one Lore API actor per domain, all sharing the same networking actor.
+    ///
+    /// Every returned handle has its own Lore API core, but requests from all of
+    /// them go through the given `Net`, so they share its HTTP client, connection
+    /// pool and any settings applied by it, such as retries and the retry budget.
+    ///
+    /// # Arguments
+    /// * `net` - The networking actor shared by all the Lore API actors
+    /// * `domains` - The base domain of each Lore API actor
+    ///
+    /// # Returns
+    /// One Lore API actor per domain, in the same order as `domains`.
+    ///
+    /// # Example
+    /// ```
+    /// let apis = LoreApi::spawn_shared(net, &[ArcStr::from("https://lore.kernel.org")]);
+    /// ```
+    pub fn spawn_shared(net: Net, domains: &[ArcStr]) -> Vec<Self> {
+        domains
+            .iter()
+            .map(|domain| Self::spawn_with_domain(net.clone(), domain.clone()))
+            .collect()
+    }
+
+    /// Creates a new Lore API actor with the given options.
+    ///
+    /// # Arguments
+    /// * `net` - The networking actor for making HTTP requests
+    /// * `options` - The settings for the Lore API actor, such as the domain
+    ///   and the default headers of each mailing list
+    ///
+    /// # Returns
+    /// A new Lore API actor configured with the specified options.
+    pub fn spawn_with_options(net: Net, options: LoreApiOptions) -> Self {
+        let (lore_api, _) = core::Core::with_options(net, options).spawn();
+        lore_api
+    }
+
+    /// Creates a new Lore API actor ready to use with the Lore Kernel Archive.
+    ///
+    /// This is the quickest way to get started: requests go to
+    /// `https://lore.kernel.org` with a polite `User-Agent`, a timeout and a
+    /// retry policy suited to it; see [`LoreApiOptions::kernel_org`] for the
+    /// exact settings. To change any of them, or to use mirrors, a cache or
+    /// another server, adjust the options and spawn with
+    /// [`LoreApi::spawn_with_options`] instead.
+    ///
+    /// # Arguments
+    /// * `net` - The networking actor for making HTTP requests
+    ///
+    /// # Returns
+    /// A new Lore API actor configured for lore.kernel.org.
+    ///
+    /// # Example
+    /// ```
+    /// let lore_api = LoreApi::kernel_org(net);
+    /// let feed = lore_api.get_patch_feed("amd-gfx", 0).await?;
+    /// ```
+    pub fn kernel_org(net: Net) -> Self {
+        Self::spawn_with_options(net, LoreApiOptions::kernel_org())
+    }
+
+    /// Stops the Lore API actor, waiting a bounded time for it to finish.
+    ///
+    /// The actor stops taking messages, handles the operations it already
+    /// received, then stops even if clones of the instance are still alive,
+    /// their later calls failing. If operations are left when the timeout
+    /// expires, the one being handled is aborted and the others are dropped.
+    ///
+    /// # Arguments
+    /// * `timeout` - How long the actor is given to stop
+    ///
+    /// # Returns
+    /// `Ok(())` after a graceful shutdown or if the actor had already stopped,
+    /// or a [`crate::utils::ShutdownError`] telling whether operations were
+    /// aborted or the actor failed.
+    ///
+    /// # Examples
+    /// ```
+    /// let lore_api = LoreApi::spawn(net);
+    /// lore_api.shutdown_timeout(Duration::from_secs(5)).await?;
+    /// ```
+    pub async fn shutdown_timeout(&self, timeout: std::time::Duration) -> anyhow::Result<()> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                if sender
+                    .send(LoreApiMessage::Shutdown { timeout, tx })
+                    .await
+                    .is_err()
+                {
+                    // The actor has already stopped
+                    return Ok(());
+                }
+                rx.await
+                    .unwrap_or_else(|err| Err(ShutdownError::Failed(err.to_string())))?;
+                Ok(())
+            }
+            LoreApi::Mock(_) => Ok(()),
+        }
+    }
+
     /// Creates a new mock Lore API instance for testing.
     ///
     /// # Arguments
@@ -79,6 +218,29 @@ impl LoreApi {
         Self::Mock(Arc::new(Mutex::new(HashMap::new())))
     }
 
+    /// Gets a handle whose requests make up a single operation.
+    ///
+    /// The downloads of all the requests made through the handle count against
+    /// one [`LoreApiOptions::max_bytes`] budget, instead of one budget per
+    /// request. The methods that fetch several documents, such as
+    /// [`LoreApi::list_stats`], use it so that they are bounded as a whole.
+    ///
+    /// # Returns
+    /// A handle to the same actor, whose requests share one byte budget.
+    ///
+    /// # Example
+    /// ```
+    /// let operation = lore_api.operation();
+    /// let first = operation.get_raw_patch("amd-gfx", first_id).await?;
+    /// let second = operation.get_raw_patch("amd-gfx", second_id).await?;
+    /// ```
+    pub fn operation(&self) -> Self {
+        match self {
+            LoreApi::Actual(sender) => LoreApi::Actual(sender.in_operation()),
+            LoreApi::Mock(_) => self.clone(),
+        }
+    }
+
     /// Fetches a patch feed from a specific mailing list with pagination.
     ///
     /// This method retrieves a paginated list of patches from the specified mailing list,
@@ -86,7 +248,7 @@ impl LoreApi {
     ///
     /// # Arguments
     /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
-    /// * `min_index` - The offset for pagination (0-based)
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
     ///
     /// # Returns
     /// The XML feed content as a string, or an error if the request fails.
@@ -98,8 +260,9 @@ impl LoreApi {
     pub async fn get_patch_feed(
         &self,
         target_list: &str,
-        min_index: usize,
+        min_index: impl Into<FeedOffset>,
     ) -> Result<ArcStr, anyhow::Error> {
+        let min_index = min_index.into().items();
         match self {
             LoreApi::Actual(sender) => {
                 let (tx, rx) = oneshot::channel();
@@ -116,12 +279,237 @@ impl LoreApi {
             LoreApi::Mock(responses) => {
                 let responses = responses.lock().await;
                 let key = format!("patch_feed_{}_{}", target_list, min_index);
-                responses
-                    .get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("Patch feed not found in mock responses: {}", key))
+                responses.get(&key).map(ArcStr::clone).ok_or_else(|| {
+                    anyhow::anyhow!("Patch feed not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Fetches a page of the patch feed of a list grouped into series.
+    ///
+    /// This method fetches the feed like [`LoreApi::get_patch_feed`] and groups
+    /// its entries by thread and `[PATCH m/n]` tags, so each series is reported
+    /// once with its cover letter and patches. See [`Series::from_entries`] for
+    /// how the entries are grouped.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The series of the page, most recent first, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// for series in lore_api.recent_series("amd-gfx", 0).await? {
+    ///     // ...
+    /// }
+    /// ```
+    pub async fn recent_series(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<Vec<Series>, anyhow::Error> {
+        let min_index = min_index.into().items();
+        let feed = self.get_patch_feed(target_list, min_index).await?;
+        Ok(Series::from_entries(&feed::parse_entries(&feed)))
+    }
+
+    /// Fetches the cover letters of a page of the patch feed of a list.
+    ///
+    /// This method fetches the feed like [`LoreApi::get_patch_feed`] and keeps
+    /// the `[PATCH 0/n]` messages only, giving one entry per series that has a
+    /// cover letter. Standalone patches and the numbered patches of the series
+    /// are left out; see [`PatchEntry::is_cover_letter`].
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The cover letters of the page, in feed order, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// for cover in lore_api.get_cover_letters("amd-gfx", 0).await? {
+    ///     println!("{}", cover.title);
+    /// }
+    /// ```
+    pub async fn get_cover_letters(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<Vec<PatchEntry>, anyhow::Error> {
+        let min_index = min_index.into().items();
+        let feed = self.get_patch_feed(target_list, min_index).await?;
+        Ok(feed::parse_entries(&feed)
+            .into_iter()
+            .filter(PatchEntry::is_cover_letter)
+            .collect())
+    }
+
+    /// Computes statistics of the recent activity of a list.
+    ///
+    /// This method walks up to `pages` pages of the patch feed, newest first,
+    /// following the offsets published by the server, and aggregates their
+    /// messages with [`ListStats::from_entries`]. A message pushed to the next
+    /// page while walking is only counted once. An empty list, or zero pages,
+    /// gives zeroed statistics.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `pages` - The number of feed pages to aggregate
+    ///
+    /// # Returns
+    /// The statistics of the pages, or an error if a request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let stats = lore_api.list_stats("amd-gfx", 3).await?;
+    /// println!("{} patches in {} series", stats.total, stats.series_count);
+    /// ```
+    pub async fn list_stats(
+        &self,
+        target_list: &str,
+        pages: usize,
+    ) -> Result<ListStats, anyhow::Error> {
+        let lore_api = self.operation();
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        let mut min_index = 0;
+        for _ in 0..pages {
+            let page = lore_api
+                .get_patch_feed_parsed(target_list, min_index)
+                .await?;
+            let next = page.next_offset();
+            entries.extend(
+                page.entries
+                    .into_iter()
+                    .filter(|entry| seen.insert(normalize_message_id(&entry.message_id))),
+            );
+            match next {
+                Some(next) if next > min_index => min_index = next,
+                _ => break,
             }
         }
+        Ok(ListStats::from_entries(&entries))
+    }
+
+    /// Classifies a list as active, dormant or inactive from its newest message.
+    ///
+    /// This method fetches the first page of the patch feed, which holds the
+    /// newest messages, and measures the age of the most recent one against
+    /// the thresholds with [`ActivityThresholds::classify`]. A list whose feed
+    /// is empty is [`ActivityStatus::Inactive`].
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `thresholds` - The ages separating the classes, see [`ActivityThresholds::default`]
+    ///
+    /// # Returns
+    /// The activity status of the list, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let status = lore_api
+    ///     .list_activity_status("amd-gfx", ActivityThresholds::default())
+    ///     .await?;
+    /// ```
+    pub async fn list_activity_status(
+        &self,
+        target_list: &str,
+        thresholds: ActivityThresholds,
+    ) -> Result<ActivityStatus, anyhow::Error> {
+        let page = self.get_patch_feed_parsed(target_list, 0).await?;
+        Ok(thresholds.classify(&page.entries, chrono::Utc::now()))
+    }
+
+    /// Fetches and parses a page of the patch feed of a list.
+    ///
+    /// This method fetches the feed like [`LoreApi::get_patch_feed`] and parses
+    /// it with [`PatchFeed::parse`], giving the feed-level title and update time,
+    /// for instance to display the list, along with the entries and the offsets
+    /// of the neighbouring pages.
+    /// The page isn't checked for completeness; see
+    /// [`LoreApi::get_patch_feed_parsed_strict`] for that.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The parsed page of the feed, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let feed = lore_api.get_patch_feed_parsed("amd-gfx", 0).await?;
+    /// println!("{} entries, updated {:?}", feed.entries.len(), feed.updated);
+    /// ```
+    pub async fn get_patch_feed_parsed(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<PatchFeed, anyhow::Error> {
+        let min_index = min_index.into().items();
+        let feed = self.get_patch_feed(target_list, min_index).await?;
+        Ok(PatchFeed::parse(&feed))
+    }
+
+    /// Fetches and parses a page of the patch feed of a list, checking that
+    /// the page is complete.
+    ///
+    /// This method works like [`LoreApi::get_patch_feed_parsed`], but parses
+    /// the feed with [`PatchFeed::parse_strict`], which catches pages cut
+    /// short by the network that would otherwise parse with missing entries.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The parsed page of the feed, or an error if the request fails. A page
+    /// failing a check fails with [`LoreApiError::InconsistentFeed`].
+    ///
+    /// # Example
+    /// ```
+    /// let feed = lore_api.get_patch_feed_parsed_strict("amd-gfx", 0).await?;
+    /// ```
+    pub async fn get_patch_feed_parsed_strict(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<PatchFeed, anyhow::Error> {
+        let min_index = min_index.into().items();
+        let feed = self.get_patch_feed(target_list, min_index).await?;
+        Ok(PatchFeed::parse_strict(&feed)?)
+    }
+
+    /// Fetches a page of the patch feed of a list converted to RSS 2.0.
+    ///
+    /// This method fetches the feed like [`LoreApi::get_patch_feed`] and
+    /// converts it with [`feed::to_rss`], for feed readers that don't support
+    /// Atom. An empty page gives a channel without items.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The RSS document, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let rss = lore_api.get_patch_feed_as_rss("amd-gfx", 0).await?;
+    /// ```
+    pub async fn get_patch_feed_as_rss(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let min_index = min_index.into().items();
+        let feed = self.get_patch_feed(target_list, min_index).await?;
+        Ok(ArcStr::from(&feed::to_rss(&feed)))
     }
 
     /// Fetches available mailing lists with pagination.
@@ -130,7 +518,7 @@ impl LoreApi {
     /// archived on the Lore Kernel Archive.
     ///
     /// # Arguments
-    /// * `min_index` - The offset for pagination (0-based)
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
     ///
     /// # Returns
     /// The HTML content listing available mailing lists, or an error if the request fails.
@@ -139,7 +527,11 @@ impl LoreApi {
     /// ```
     /// let lists = lore_api.get_available_lists(0).await?;
     /// ```
-    pub async fn get_available_lists(&self, min_index: usize) -> Result<ArcStr, anyhow::Error> {
+    pub async fn get_available_lists(
+        &self,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let min_index = min_index.into().items();
         match self {
             LoreApi::Actual(sender) => {
                 let (tx, rx) = oneshot::channel();
@@ -152,12 +544,80 @@ impl LoreApi {
             LoreApi::Mock(responses) => {
                 let responses = responses.lock().await;
                 let key = format!("available_lists_{}", min_index);
-                responses
-                    .get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("Available lists not found in mock responses: {}", key))
+                responses.get(&key).map(ArcStr::clone).ok_or_else(|| {
+                    anyhow::anyhow!("Available lists not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Fetches the whole list index of the server, following its pagination.
+    ///
+    /// The pages are fetched like [`LoreApi::get_available_lists`] and parsed
+    /// with [`feed::mailing_lists`]. The index is sorted by update time, so a
+    /// list updated during the walk may be listed on two pages; it is only
+    /// returned the first time.
+    ///
+    /// # Returns
+    /// The mailing lists of the index, or an error if a request fails or the
+    /// index spans more than [`MAX_LINK_PAGES`] pages.
+    ///
+    /// # Example
+    /// ```
+    /// let lists = lore_api.list_index().await?;
+    /// ```
+    pub async fn list_index(&self) -> Result<Vec<MailingList>, anyhow::Error> {
+        let lore_api = self.operation();
+        let mut lists = Vec::new();
+        let mut names = HashSet::new();
+        let mut min_index = 0;
+        for _ in 0..MAX_LINK_PAGES {
+            let page = lore_api.get_available_lists(min_index).await?;
+            for list in feed::mailing_lists(&page) {
+                if names.insert(list.name.clone()) {
+                    lists.push(list);
+                }
+            }
+            match PatchFeed::parse(&page).next_offset() {
+                Some(next) if next > min_index => min_index = next,
+                _ => return Ok(lists),
             }
         }
+        anyhow::bail!("List index spans more than {} pages", MAX_LINK_PAGES)
+    }
+
+    /// Fetches the lists that appeared on the server since a snapshot of its index.
+    ///
+    /// The whole index is fetched with [`LoreApi::list_index`] and compared with
+    /// the snapshot. Renames are handled conservatively: a list is only new if
+    /// neither its name nor its URL is in the snapshot, so a list renamed with
+    /// its URL kept, or moved under its old name, is not reported.
+    ///
+    /// # Arguments
+    /// * `previous` - The snapshot, as returned by an earlier call to [`LoreApi::list_index`]
+    ///
+    /// # Returns
+    /// The new lists, in the order of the index, or an error if a request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let snapshot = lore_api.list_index().await?;
+    /// // ...
+    /// for list in lore_api.new_lists_since(&snapshot).await? {
+    ///     println!("New list: {}", list.name);
+    /// }
+    /// ```
+    pub async fn new_lists_since(
+        &self,
+        previous: &[MailingList],
+    ) -> Result<Vec<MailingList>, anyhow::Error> {
+        let names: HashSet<&str> = previous.iter().map(|list| &*list.name).collect();
+        let urls: HashSet<&str> = previous.iter().map(|list| &*list.url).collect();
+        let lists = self.list_index().await?;
+        Ok(lists
+            .into_iter()
+            .filter(|list| !names.contains(&*list.name) && !urls.contains(&*list.url))
+            .collect())
     }
 
     /// Fetches the HTML content of a specific patch.
@@ -197,14 +657,39 @@ impl LoreApi {
             LoreApi::Mock(responses) => {
                 let responses = responses.lock().await;
                 let key = format!("patch_html_{}_{}", target_list, message_id);
-                responses
-                    .get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("Patch HTML not found in mock responses: {}", key))
+                responses.get(&key).map(ArcStr::clone).ok_or_else(|| {
+                    anyhow::anyhow!("Patch HTML not found in mock responses: {}", key)
+                })
             }
         }
     }
 
+    /// Fetches the HTML page of a patch rendered as plain text.
+    ///
+    /// This method fetches the page like [`LoreApi::get_patch_html`] and converts
+    /// it with [`patch::html_to_text`], which keeps the message and its diff
+    /// verbatim and decodes entities.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The text of the patch page, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let text = lore_api.get_patch_text("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn get_patch_text(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let html = self.get_patch_html(target_list, message_id).await?;
+        Ok(patch::html_to_text(&html))
+    }
+
     /// Fetches a raw patch in plain text format.
     ///
     /// This method retrieves the raw patch content in plain text format,
@@ -242,42 +727,117 @@ impl LoreApi {
             LoreApi::Mock(responses) => {
                 let responses = responses.lock().await;
                 let key = format!("raw_patch_{}_{}", target_list, message_id);
-                responses
-                    .get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("Raw patch not found in mock responses: {}", key))
+                responses.get(&key).map(ArcStr::clone).ok_or_else(|| {
+                    anyhow::anyhow!("Raw patch not found in mock responses: {}", key)
+                })
             }
         }
     }
 
-    /// Fetches patch metadata in JSON format.
+    /// Fetches a raw patch with consistent line endings.
     ///
-    /// This method retrieves structured metadata about a patch in JSON format,
-    /// which is useful for programmatic access to patch information.
+    /// Patches on Lore may use CRLF and LF line endings inconsistently, which can
+    /// make `git apply` reject them. This method fetches the raw patch like
+    /// [`LoreApi::get_raw_patch`] and converts every line ending to the given one,
+    /// leaving the rest of the content untouched.
     ///
     /// # Arguments
     /// * `target_list` - The mailing list name
     /// * `message_id` - The unique message ID of the patch
+    /// * `ending` - The line ending to convert to
     ///
     /// # Returns
-    /// The patch metadata as JSON, or an error if the request fails.
+    /// The raw patch content with normalized line endings, or an error if the request fails.
     ///
     /// # Example
     /// ```
-    /// let metadata = lore_api.get_patch_metadata("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// let raw_patch = lore_api
+    ///     .get_raw_patch_normalized("amd-gfx", "20231201.123456.1-1@amd.com", LineEnding::Lf)
+    ///     .await?;
     /// ```
-    pub async fn get_patch_metadata(
+    pub async fn get_raw_patch_normalized(
         &self,
         target_list: &str,
         message_id: &str,
+        ending: LineEnding,
     ) -> Result<ArcStr, anyhow::Error> {
+        let raw_patch = self.get_raw_patch(target_list, message_id).await?;
+        Ok(patch::normalize_line_endings(&raw_patch, ending))
+    }
+
+    /// Fetches a patch and parses its diff.
+    ///
+    /// This method fetches the raw patch like [`LoreApi::get_raw_patch`],
+    /// decodes the transfer encoding of its text part and splits the diff into
+    /// files and hunks with [`ParsedDiff::parse`], giving the status of each file
+    /// and the line numbers of each line on both sides, e.g. to render a
+    /// side-by-side view.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The parsed diff, without files if the message carries none, or an error
+    /// if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let diff = lore_api.get_patch_diff_parsed("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// for file in &diff.files {
+    ///     println!("{:?} {:?}: {} hunks", file.status, file.new_path, file.hunks.len());
+    /// }
+    /// ```
+    pub async fn get_patch_diff_parsed(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ParsedDiff, anyhow::Error> {
+        let raw_patch = self.get_raw_patch(target_list, message_id).await?;
+        let text = mail::MailPart::parse(&raw_patch)
+            .text_part()
+            .map_or(raw_patch, |part| part.decoded_body());
+        Ok(ParsedDiff::parse(&text))
+    }
+
+    /// Fetches the start of a patch and parses what fits, e.g. to preview it.
+    ///
+    /// Only the first `max_bytes` bytes of the raw patch are downloaded, with a
+    /// `Range` request, or by dropping the connection if the mirror ignores it,
+    /// so previewing a multi-megabyte patch stays cheap. The headers are parsed
+    /// and so are the hunks that fit in the budget. See [`PatchPreview::parse`]
+    /// for how a patch cut short is handled.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    /// * `max_bytes` - The maximum number of bytes to download
+    ///
+    /// # Returns
+    /// The preview of the patch, flagged as truncated if the patch is longer than
+    /// `max_bytes`, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let preview = lore_api
+    ///     .get_patch_preview("amd-gfx", "20231201.123456.1-1@amd.com", 64 * 1024)
+    ///     .await?;
+    /// println!("{:?}: {} files", preview.header("subject"), preview.diff.files.len());
+    /// ```
+    pub async fn get_patch_preview(
+        &self,
+        target_list: &str,
+        message_id: &str,
+        max_bytes: usize,
+    ) -> Result<PatchPreview, anyhow::Error> {
         match self {
             LoreApi::Actual(sender) => {
                 let (tx, rx) = oneshot::channel();
                 sender
-                    .send(LoreApiMessage::GetPatchMetadata {
+                    .send(LoreApiMessage::GetPatchPreview {
                         target_list: target_list.to_string(),
                         message_id: message_id.to_string(),
+                        max_bytes,
                         tx,
                     })
                     .await
@@ -286,90 +846,3478 @@ impl LoreApi {
             }
             LoreApi::Mock(responses) => {
                 let responses = responses.lock().await;
-                let key = format!("patch_metadata_{}_{}", target_list, message_id);
+                let key = format!("patch_preview_{}_{}", target_list, message_id);
                 responses
                     .get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("Patch metadata not found in mock responses: {}", key))
+                    .map(|raw_patch| PatchPreview::parse(raw_patch.as_bytes(), max_bytes))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Patch preview not found in mock responses: {}", key)
+                    })
             }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// Fetches a patch and verifies its inline PGP signature.
+    ///
+    /// This method fetches the raw patch like [`LoreApi::get_raw_patch`] and checks
+    /// the signed block it carries against the given keyring. See
+    /// [`signature::verify`] for how the keyring is used.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    /// * `keyring` - The ASCII-armored public keys trusted to sign
+    ///
+    /// # Returns
+    /// The status of the signature, or an error if the request fails or the
+    /// keyring or the signature can't be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// let status = lore_api
+    ///     .verify_patch_signature("amd-gfx", "20231201.123456.1-1@amd.com", &keyring)
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "pgp")]
+    pub async fn verify_patch_signature(
+        &self,
+        target_list: &str,
+        message_id: &str,
+        keyring: &str,
+    ) -> Result<SignatureStatus, anyhow::Error> {
+        let raw_patch = self.get_raw_patch(target_list, message_id).await?;
+        signature::verify(&raw_patch, keyring)
+    }
+
+    /// Fetches patch metadata in JSON format.
+    ///
+    /// This method retrieves structured metadata about a patch in JSON format,
+    /// which is useful for programmatic access to patch information.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The patch metadata as JSON, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let metadata = lore_api.get_patch_metadata("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn get_patch_metadata(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetPatchMetadata {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("patch_metadata_{}_{}", target_list, message_id);
+                responses.get(&key).map(ArcStr::clone).ok_or_else(|| {
+                    anyhow::anyhow!("Patch metadata not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Computes a stable fingerprint of a patch.
+    ///
+    /// This method fetches the raw patch and hashes its normalized diff, ignoring
+    /// the mail headers, the commit message and other volatile content, so the same
+    /// change can be correlated across lists, mirrors and revisions. See
+    /// [`patch::fingerprint`] for exactly what is included in the hash.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The hex-encoded SHA-256 of the normalized diff, or an error if the request
+    /// fails or the message has no diff.
+    ///
+    /// # Example
+    /// ```
+    /// let fingerprint = lore_api.patch_fingerprint("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn patch_fingerprint(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetPatchFingerprint {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("patch_fingerprint_{}_{}", target_list, message_id);
+                responses.get(&key).cloned().ok_or_else(|| {
+                    anyhow::anyhow!("Patch fingerprint not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Fetches the public-inbox configuration of a mailing list.
+    ///
+    /// This method retrieves the configuration snippet public-inbox publishes for
+    /// the list and deserializes it, which allows discovering the list's posting
+    /// address, archive URL and newsgroup.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    ///
+    /// # Returns
+    /// The parsed inbox configuration, or an error if the request fails or the
+    /// deployment doesn't publish the configuration of this list.
+    ///
+    /// # Example
+    /// ```
+    /// let config = lore_api.get_inbox_config("amd-gfx").await?;
+    /// ```
+    pub async fn get_inbox_config(&self, target_list: &str) -> Result<InboxConfig, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetInboxConfig {
+                        target_list: target_list.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("inbox_config_{}", target_list);
+                let config = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Inbox config not found in mock responses: {}", key)
+                })?;
+                InboxConfig::parse(config)
+            }
+        }
+    }
+
+    /// Fetches the HTML content of several patches of a mailing list.
+    ///
+    /// The patches are requested concurrently. In [`BatchMode::Collect`], every
+    /// patch is fetched and the result of each one is returned. In
+    /// [`BatchMode::FailFast`], the outstanding requests are cancelled as soon as
+    /// one patch fails and its error is returned; requests already handed to the
+    /// actor are still performed, but their results are discarded.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_ids` - The unique message IDs of the patches
+    /// * `mode` - How to handle the failure of one of the patches
+    ///
+    /// # Returns
+    /// The result for each patch, in the same order as `message_ids`, or the
+    /// first error in [`BatchMode::FailFast`].
+    ///
+    /// # Example
+    /// ```
+    /// let htmls = lore_api.get_patch_htmls("amd-gfx", &ids, BatchMode::FailFast).await?;
+    /// ```
+    pub async fn get_patch_htmls(
+        &self,
+        target_list: &str,
+        message_ids: &[&str],
+        mode: BatchMode,
+    ) -> Result<Vec<Result<ArcStr, anyhow::Error>>, anyhow::Error> {
+        let operation = self.operation();
+        let mut tasks = JoinSet::new();
+        for (index, message_id) in message_ids.iter().enumerate() {
+            let lore_api = operation.clone();
+            let target_list = target_list.to_string();
+            let message_id = message_id.to_string();
+            tasks.spawn(async move {
+                let result = lore_api.get_patch_html(&target_list, &message_id).await;
+                (index, result)
+            });
+        }
+
+        let mut results: Vec<Option<Result<ArcStr, anyhow::Error>>> =
+            message_ids.iter().map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = joined.context("Joining batch request task")?;
+            if mode == BatchMode::FailFast
+                && let Err(err) = result
+            {
+                tasks.abort_all();
+                return Err(
+                    err.context(format!("Batch aborted on message: {}", message_ids[index]))
+                );
+            }
+            results[index] = Some(result);
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Fetches several raw patches and concatenates them into a single mbox.
+    ///
+    /// The patches are requested concurrently and assembled in the order of
+    /// `message_ids` with [`patch::to_mbox`], so the result can be applied with
+    /// a single `git am`. The outstanding requests are cancelled as soon as one
+    /// patch fails.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_ids` - The unique message IDs of the patches, in the order to apply them
+    ///
+    /// # Returns
+    /// The mbox content, or an error naming the first message that failed.
+    ///
+    /// # Example
+    /// ```
+    /// let mbox = lore_api.collect_mbox("amd-gfx", &ids).await?;
+    /// ```
+    pub async fn collect_mbox(
+        &self,
+        target_list: &str,
+        message_ids: &[&str],
+    ) -> Result<ArcStr, anyhow::Error> {
+        let operation = self.operation();
+        let mut tasks = JoinSet::new();
+        for (index, message_id) in message_ids.iter().enumerate() {
+            let lore_api = operation.clone();
+            let target_list = target_list.to_string();
+            let message_id = message_id.to_string();
+            tasks.spawn(async move {
+                let result = lore_api.get_raw_patch(&target_list, &message_id).await;
+                (index, result)
+            });
+        }
+
+        let mut messages: Vec<ArcStr> = vec![ArcStr::default(); message_ids.len()];
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = joined.context("Joining mbox request task")?;
+            match result {
+                Ok(message) => messages[index] = message,
+                Err(err) => {
+                    tasks.abort_all();
+                    return Err(err.context(format!(
+                        "Collecting mbox failed on message: {}",
+                        message_ids[index]
+                    )));
+                }
+            }
+        }
+
+        Ok(patch::to_mbox(&messages))
+    }
+
+    /// Fetches a page of the feed of every message of a mailing list.
+    ///
+    /// Unlike [`LoreApi::get_patch_feed`], replies and messages without a
+    /// patch tag are listed too, most recent first.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The XML feed content as a string, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let page = PatchFeed::parse(&lore_api.get_archive_page("amd-gfx", 0).await?);
+    /// ```
+    pub async fn get_archive_page(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let min_index = min_index.into().items();
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetArchivePage {
+                        target_list: target_list.to_string(),
+                        min_index,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("archive_page_{}_{}", target_list, min_index);
+                responses.get(&key).cloned().ok_or_else(|| {
+                    anyhow::anyhow!("Archive page not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Hands every message of a mailing list to a callback, one at a time.
+    ///
+    /// This method walks the pages of [`LoreApi::get_archive_page`] and fetches
+    /// the raw content of their messages, at most `concurrency` at a time,
+    /// handing each to `f` as soon as it arrives. Only the messages being
+    /// fetched and the IDs of the ones already handled are held in memory, so
+    /// whole archives can be ingested. A message shifted to the next page by a
+    /// new post is only handed over once.
+    ///
+    /// The walk stops on the first error, whether a request or the callback
+    /// fails, and the outstanding requests are cancelled.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `concurrency` - The maximum number of messages fetched at once, at least 1
+    /// * `f` - The callback each message is handed to
+    ///
+    /// # Returns
+    /// The number of messages handed to the callback, or the first error.
+    ///
+    /// # Example
+    /// ```
+    /// let count = lore_api
+    ///     .for_each_message("amd-gfx", 8, |message| async move {
+    ///         index.add(&message.entry.message_id, &message.content).await
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn for_each_message<F, Fut>(
+        &self,
+        target_list: &str,
+        concurrency: usize,
+        mut f: F,
+    ) -> Result<usize, anyhow::Error>
+    where
+        F: FnMut(RawMessage) -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        let concurrency = concurrency.max(1);
+        let mut seen = HashSet::new();
+        let mut processed = 0;
+        let mut min_index = 0;
+        loop {
+            let page = PatchFeed::parse(&self.get_archive_page(target_list, min_index).await?);
+            let mut entries = page
+                .entries
+                .iter()
+                .filter(|entry| seen.insert(normalize_message_id(&entry.message_id)))
+                .cloned();
+
+            // Dropping the set on error cancels the outstanding requests
+            let mut tasks = JoinSet::new();
+            loop {
+                while tasks.len() < concurrency
+                    && let Some(entry) = entries.next()
+                {
+                    let lore_api = self.clone();
+                    let target_list = target_list.to_string();
+                    tasks.spawn(async move {
+                        let content = lore_api
+                            .get_raw_patch(&target_list, &entry.message_id)
+                            .await
+                            .with_context(|| format!("Fetching message: {}", entry.message_id))?;
+                        anyhow::Ok(RawMessage { entry, content })
+                    });
+                }
+                let Some(joined) = tasks.join_next().await else {
+                    break;
+                };
+                let message = joined.context("Joining message request task")??;
+                f(message).await?;
+                processed += 1;
+            }
+
+            match page.next_offset() {
+                Some(next) if next > min_index => min_index = next,
+                _ => return Ok(processed),
+            }
+        }
+    }
+
+    /// Lists the revisions of a patch series posted to a mailing list.
+    ///
+    /// This method searches the list for messages whose subject matches the
+    /// series and groups them by the version tag of their subject, so that
+    /// `[PATCH 0/3]`, `[PATCH v2 0/3]` and `[PATCH v3 0/4]` are reported as
+    /// versions 1, 2 and 3. See [`SeriesVersion::from_entries`] for how the
+    /// messages are matched.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `subject_base` - The subject of the series, without its tags
+    ///
+    /// # Returns
+    /// The revisions found on the list, sorted by version, or an error if the
+    /// request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let versions = lore_api
+    ///     .list_series_versions("amd-gfx", "drm/amdgpu: fix b")
+    ///     .await?;
+    /// ```
+    pub async fn list_series_versions(
+        &self,
+        target_list: &str,
+        subject_base: &str,
+    ) -> Result<Vec<SeriesVersion>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::ListSeriesVersions {
+                        target_list: target_list.to_string(),
+                        subject_base: subject_base.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("series_versions_{}_{}", target_list, subject_base);
+                let feed = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Series versions not found in mock responses: {}", key)
+                })?;
+                Ok(SeriesVersion::from_entries(
+                    &feed::parse_entries(feed),
+                    subject_base,
+                ))
+            }
+        }
+    }
+
+    /// Finds the mailing lists a message was cross-posted to.
+    ///
+    /// This method searches every list of the server at once for the message
+    /// ID, through the global `all` index, and gathers the lists of the
+    /// results; see [`feed::crosspost_lists`]. Posts to several lists share
+    /// their message ID, so this helps telling the copies of a message apart
+    /// from different messages when merging lists.
+    ///
+    /// # Arguments
+    /// * `message_id` - The unique message ID of the message
+    ///
+    /// # Returns
+    /// The distinct lists carrying the message, a single one if it wasn't
+    /// cross-posted, none if the server doesn't have it, or an error if the
+    /// request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let lists = lore_api
+    ///     .find_crosspost_lists("20231201.123456.1-1@amd.com")
+    ///     .await?;
+    /// ```
+    pub async fn find_crosspost_lists(
+        &self,
+        message_id: &str,
+    ) -> Result<Vec<ArcStr>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::FindCrosspostLists {
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("crosspost_lists_{}", message_id);
+                let feed = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Cross-post search not found in mock responses: {}", key)
+                })?;
+                Ok(feed::crosspost_lists(feed, message_id))
+            }
+        }
+    }
+
+    /// Fetches the text body of a message, without the patch it may carry.
+    ///
+    /// This method is meant for cover letters and discussion messages. It fetches
+    /// the raw message, selects its `text/plain` MIME part, decodes the
+    /// `quoted-printable` or `base64` transfer encoding and the charset, and drops
+    /// the headers and any diff. See [`mail::message_text`] for the details.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the message
+    ///
+    /// # Returns
+    /// The decoded text body, or an error if the request fails or the message
+    /// has no text part.
+    ///
+    /// # Example
+    /// ```
+    /// let text = lore_api.get_message_text("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn get_message_text(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetMessageText {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("message_text_{}_{}", target_list, message_id);
+                responses.get(&key).cloned().ok_or_else(|| {
+                    anyhow::anyhow!("Message text not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Fetches a message quoted for a reply.
+    ///
+    /// This method fetches the raw message like [`LoreApi::get_raw_patch`] and
+    /// quotes its text, patch included, under an `On <date>, <author> wrote:`
+    /// attribution taken from its headers. Lines already quoted nest. See
+    /// [`mail::quote_reply`] for the details.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the message
+    ///
+    /// # Returns
+    /// The attribution and the quoted text, or an error if the request fails
+    /// or the message has no text part.
+    ///
+    /// # Example
+    /// ```
+    /// let quote = lore_api.get_patch_as_quote("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn get_patch_as_quote(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let raw_patch = self.get_raw_patch(target_list, message_id).await?;
+        mail::quote_reply(&raw_patch)
+            .with_context(|| format!("Message has no text part: {}", message_id))
+    }
+
+    /// Fetches the commit message of a patch.
+    ///
+    /// This method fetches the raw patch like [`LoreApi::get_raw_patch`] and
+    /// keeps its title and the text above the `---` separator, trailers
+    /// included, leaving out the notes, diffstat and diff. A cover letter has
+    /// no diff and its whole body is returned. See [`mail::commit_message`]
+    /// for the details.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The commit message, or an error if the request fails or the message
+    /// has no text part.
+    ///
+    /// # Example
+    /// ```
+    /// let message = lore_api.get_commit_message("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn get_commit_message(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let raw_patch = self.get_raw_patch(target_list, message_id).await?;
+        mail::commit_message(&raw_patch)
+            .with_context(|| format!("Message has no text part: {}", message_id))
+    }
+
+    /// Fetches the links of a message to the messages it replies to.
+    ///
+    /// This method fetches the raw message like [`LoreApi::get_raw_patch`] and
+    /// parses its `In-Reply-To` and `References` headers, normalizing the IDs.
+    /// It is the building block for assembling reply trees without the HTML
+    /// thread view of the archive. See [`MessageLinks::parse`] for the details.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the message
+    ///
+    /// # Returns
+    /// The parent and ancestors of the message, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let links = lore_api
+    ///     .get_message_references("amd-gfx", "20231201.123456.1-1@amd.com")
+    ///     .await?;
+    /// if let Some(parent) = links.in_reply_to {
+    ///     println!("Replies to {}", parent);
+    /// }
+    /// ```
+    pub async fn get_message_references(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<MessageLinks, anyhow::Error> {
+        let raw_message = self.get_raw_patch(target_list, message_id).await?;
+        Ok(MessageLinks::parse(&raw_message))
+    }
+
+    /// Sorts patches of a series into the order they apply in.
+    ///
+    /// This method fetches each message like [`LoreApi::get_raw_patch`], one
+    /// after the other, and sorts them by their `[PATCH m/n]` position, or by
+    /// their reply chain and date when they aren't numbered. It is meant for
+    /// applying a subset of a series picked out of order. See
+    /// [`patch::apply_order`] for the details.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_ids` - The message IDs of the patches, in any order
+    ///
+    /// # Returns
+    /// The message IDs as given, in apply order, or an error if a request
+    /// fails or, with [`LoreApiError::NotOneSeries`], if the messages aren't
+    /// from a single series.
+    ///
+    /// # Example
+    /// ```
+    /// let order = lore_api
+    ///     .series_apply_order("amd-gfx", &["20231201.3-1@amd.com", "20231201.2-1@amd.com"])
+    ///     .await?;
+    /// ```
+    pub async fn series_apply_order(
+        &self,
+        target_list: &str,
+        message_ids: &[&str],
+    ) -> Result<Vec<ArcStr>, anyhow::Error> {
+        let lore_api = self.operation();
+        let mut messages = Vec::with_capacity(message_ids.len());
+        for message_id in message_ids {
+            let raw_patch = lore_api.get_raw_patch(target_list, message_id).await?;
+            messages.push((ArcStr::from(*message_id), raw_patch));
+        }
+        Ok(patch::apply_order(&messages)?)
+    }
+
+    /// Fetches the files attached to a message.
+    ///
+    /// The raw message is fetched and its MIME parts are decoded from their
+    /// transfer encoding. The main text of the message, with any inline patch, is
+    /// not an attachment. See [`mail::attachments`] for the details.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the message
+    ///
+    /// # Returns
+    /// The attachments of the message, possibly none, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let attachments = lore_api.get_attachments("amd-gfx", "20231201.123456.1-1@amd.com").await?;
+    /// ```
+    pub async fn get_attachments(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<Vec<Attachment>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetAttachments {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("attachments_{}_{}", target_list, message_id);
+                responses
+                    .get(&key)
+                    .map(|raw_message| mail::attachments(raw_message))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Attachments not found in mock responses: {}", key)
+                    })
+            }
+        }
+    }
+
+    /// Fetches a patch series and combines its patches into a single diff.
+    ///
+    /// The thread of the message is fetched to find the patches of its series, as
+    /// grouped by [`Series::from_entries`], whether the message is the cover letter
+    /// or one of the patches. Each patch is then fetched and their diffs are applied
+    /// one after the other with [`ParsedDiff::combine`], giving the change of the
+    /// whole series as if it were squashed.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the cover letter or of a patch of the series
+    ///
+    /// # Returns
+    /// The combined diff in the unified format, or an error if a request fails or
+    /// a patch doesn't apply on top of the previous ones.
+    ///
+    /// # Example
+    /// ```
+    /// let diff = lore_api
+    ///     .get_series_combined_diff("amd-gfx", "20231201.123456.0-1@amd.com")
+    ///     .await?;
+    /// ```
+    pub async fn get_series_combined_diff(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetSeriesCombinedDiff {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("series_combined_diff_{}_{}", target_list, message_id);
+                responses.get(&key).cloned().ok_or_else(|| {
+                    anyhow::anyhow!("Combined series diff not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Fetches the first page of the patch feed of a list if it changed.
+    ///
+    /// The actor remembers the `ETag` of the last feed fetched for each list and
+    /// sends it back with `If-None-Match`, so the server can answer with
+    /// `304 Not Modified` when nothing changed. This makes polling a list cheap.
+    /// The first call for a list always returns [`FeedUpdate::Changed`].
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    ///
+    /// # Returns
+    /// The new feed content, [`FeedUpdate::Unchanged`], or an error if the
+    /// request fails.
+    ///
+    /// # Example
+    /// ```
+    /// if let FeedUpdate::Changed(feed) = lore_api.get_patch_feed_update("amd-gfx").await? {
+    ///     // ...
+    /// }
+    /// ```
+    pub async fn get_patch_feed_update(
+        &self,
+        target_list: &str,
+    ) -> Result<FeedUpdate, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetPatchFeedUpdate {
+                        target_list: target_list.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                // The mock compares the feed with the last one it returned
+                let mut responses = responses.lock().await;
+                let key = format!("patch_feed_{}_0", target_list);
+                let feed = responses.get(&key).cloned().ok_or_else(|| {
+                    anyhow::anyhow!("Patch feed not found in mock responses: {}", key)
+                })?;
+                let last_key = format!("patch_feed_update_{}", target_list);
+                if responses.get(&last_key) == Some(&feed) {
+                    return Ok(FeedUpdate::Unchanged);
+                }
+                responses.insert(last_key, feed.clone());
+                Ok(FeedUpdate::Changed(feed))
+            }
+        }
+    }
+
+    /// Checks whether a mailing list is archived.
+    ///
+    /// This method sends a HEAD request to the index of the list, which allows
+    /// catching a mistyped list name before fetching anything from it.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    ///
+    /// # Returns
+    /// `true` if the archive serves the list, `false` if it answers
+    /// `404 Not Found`, or an error for any other outcome.
+    ///
+    /// # Example
+    /// ```
+    /// if !lore_api.list_exists("amd-gfx").await? {
+    ///     // ...
+    /// }
+    /// ```
+    pub async fn list_exists(&self, target_list: &str) -> Result<bool, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::ListExists {
+                        target_list: target_list.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("list_exists_{}", target_list);
+                let exists = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("List existence not found in mock responses: {}", key)
+                })?;
+                exists
+                    .parse()
+                    .with_context(|| format!("Invalid mock list existence: {}", exists))
+            }
+        }
+    }
+
+    /// Fetches the HTML content of a specific patch along with the response headers.
+    ///
+    /// This method behaves like [`LoreApi::get_patch_html`], but also returns the
+    /// headers of the response, such as `Content-Type`, `ETag` and
+    /// `Last-Modified`, which is useful for debugging content negotiation and for
+    /// building caches. Header names are in lowercase.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The HTML content of the patch and the response headers, or an error if
+    /// the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let (html, headers) = lore_api
+    ///     .get_patch_html_with_headers("amd-gfx", "20231201.123456.1-1@amd.com")
+    ///     .await?;
+    /// ```
+    pub async fn get_patch_html_with_headers(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<(ArcStr, HashMap<ArcStr, ArcStr>), anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetPatchHtmlWithHeaders {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let key = format!("patch_html_{}_{}", target_list, message_id);
+                Self::mock_with_headers(responses, &key, "Patch HTML").await
+            }
+        }
+    }
+
+    /// Fetches a raw patch in plain text format along with the response headers.
+    ///
+    /// This method behaves like [`LoreApi::get_raw_patch`], but also returns the
+    /// headers of the response. Header names are in lowercase.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The raw patch content and the response headers, or an error if the
+    /// request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let (raw_patch, headers) = lore_api
+    ///     .get_raw_patch_with_headers("amd-gfx", "20231201.123456.1-1@amd.com")
+    ///     .await?;
+    /// ```
+    pub async fn get_raw_patch_with_headers(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<(ArcStr, HashMap<ArcStr, ArcStr>), anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetRawPatchWithHeaders {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let key = format!("raw_patch_{}_{}", target_list, message_id);
+                Self::mock_with_headers(responses, &key, "Raw patch").await
+            }
+        }
+    }
+
+    /// Looks up a mock response along with its headers.
+    ///
+    /// The headers of the response stored under `key` are read from the
+    /// `{key}_headers` entry, one `Name: value` pair per line.
+    async fn mock_with_headers(
+        responses: &Mutex<HashMap<String, ArcStr>>,
+        key: &str,
+        what: &str,
+    ) -> Result<(ArcStr, HashMap<ArcStr, ArcStr>), anyhow::Error> {
+        let responses = responses.lock().await;
+        let body = responses
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("{} not found in mock responses: {}", what, key))?;
+        let headers = responses
+            .get(&format!("{}_headers", key))
+            .map(|headers| {
+                headers
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| {
+                        (
+                            ArcStr::from(&name.trim().to_lowercase()),
+                            ArcStr::from(value.trim()),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok((body, headers))
+    }
+
+    /// Fetches the patches sent by an author to a mailing list with pagination.
+    ///
+    /// This method searches the list with the `f:` operator, which matches the
+    /// sender of each message, filtering for patches and RFCs while excluding
+    /// replies like [`LoreApi::get_patch_feed`]. The author can be given as a
+    /// name, an email address or `Name <email>`; see [`feed::author_term`].
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `author` - The name and/or email address of the author
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// The patches of the requested page, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let patches = lore_api
+    ///     .get_patches_by_author("amd-gfx", "jane@example.com", 0)
+    ///     .await?;
+    /// ```
+    pub async fn get_patches_by_author(
+        &self,
+        target_list: &str,
+        author: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<Vec<PatchEntry>, anyhow::Error> {
+        let min_index = min_index.into().items();
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetPatchesByAuthor {
+                        target_list: target_list.to_string(),
+                        author: author.to_string(),
+                        min_index,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("patches_by_author_{}_{}_{}", target_list, author, min_index);
+                let feed = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Patches by author not found in mock responses: {}", key)
+                })?;
+                Ok(feed::parse_entries(feed))
+            }
+        }
+    }
+
+    /// Fetches the git clone URLs of the archive of a mailing list.
+    ///
+    /// Each list is backed by one or more git repositories, one per epoch on
+    /// large lists, whose clone commands are advertised on the mirroring page
+    /// of the list. Cloning them all gives the full archive for offline work.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    ///
+    /// # Returns
+    /// The clone URLs, oldest epoch first, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// for url in lore_api.get_clone_urls("amd-gfx").await? {
+    ///     // git clone --mirror {url}
+    /// }
+    /// ```
+    pub async fn get_clone_urls(&self, target_list: &str) -> Result<Vec<ArcStr>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetCloneUrls {
+                        target_list: target_list.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("clone_urls_{}", target_list);
+                let page = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Clone URLs not found in mock responses: {}", key)
+                })?;
+                Ok(feed::clone_urls(page))
+            }
+        }
+    }
+
+    /// Lists the epochs of the git archive of a mailing list.
+    ///
+    /// The epochs are read from the clone URLs of the list; see
+    /// [`LoreApi::get_clone_urls`]. Lists archived in a single repository have
+    /// a single epoch.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    ///
+    /// # Returns
+    /// The epochs, oldest first, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// for epoch in lore_api.list_epochs("lkml").await? {
+    ///     // git clone --mirror {epoch.clone_url} lkml/git/{epoch.index}.git
+    /// }
+    /// ```
+    pub async fn list_epochs(&self, target_list: &str) -> Result<Vec<EpochInfo>, anyhow::Error> {
+        let clone_urls = self.get_clone_urls(target_list).await?;
+        Ok(EpochInfo::from_clone_urls(clone_urls))
+    }
+
+    /// Fetches a raw patch from the git archive of a mailing list.
+    ///
+    /// Instead of scraping the archive pages, this method speaks the
+    /// smart-HTTP protocol of git to the repository of the newest epoch, as
+    /// listed by [`LoreApi::list_epochs`]: it fetches the latest
+    /// [`git::SEARCH_DEPTH`] commits of the archive and looks for the message
+    /// among them. See [`git`] for how the archive is queried.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    /// * `message_id` - The unique message ID of the patch
+    ///
+    /// # Returns
+    /// The raw patch, or an error if the request fails. A message older than
+    /// the searched commits, or missing from the archive, fails with
+    /// [`LoreApiError::NotInGitArchive`].
+    ///
+    /// # Example
+    /// ```
+    /// let raw_patch = lore_api
+    ///     .get_raw_patch_via_git("amd-gfx", "20231201.123456.1-1@amd.com")
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "git")]
+    pub async fn get_raw_patch_via_git(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetRawPatchViaGit {
+                        target_list: target_list.to_string(),
+                        message_id: message_id.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("git_raw_patch_{}_{}", target_list, message_id);
+                responses.get(&key).cloned().ok_or_else(|| {
+                    anyhow::anyhow!("Raw patch via git not found in mock responses: {}", key)
+                })
+            }
+        }
+    }
+
+    /// Fetches the maintainers of a mailing list, if the list publishes them.
+    ///
+    /// The maintainers are read from the information page of the list; see
+    /// [`feed::maintainers`] for how the page is parsed.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    ///
+    /// # Returns
+    /// The maintainers, empty if the page has no maintainers section, or an
+    /// error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// for maintainer in lore_api.get_maintainers("amd-gfx").await? {
+    ///     // Cc: {maintainer.email}
+    /// }
+    /// ```
+    pub async fn get_maintainers(
+        &self,
+        target_list: &str,
+    ) -> Result<Vec<Maintainer>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetMaintainers {
+                        target_list: target_list.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("maintainers_{}", target_list);
+                let page = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Maintainers not found in mock responses: {}", key)
+                })?;
+                Ok(feed::maintainers(page))
+            }
+        }
+    }
+
+    /// Searches a mailing list with a `lei q`-style query.
+    ///
+    /// The query is translated with [`feed::lei_query`], which supports the
+    /// `s:`, `f:` and `d:` prefixes and `AND`, and every page of the results is
+    /// fetched. The mock key is built from the translated query, so equivalent
+    /// queries share it.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `lei_query` - The query, e.g. `s:"drm/amdgpu" f:jane@example.com d:2023-12-01..`
+    ///
+    /// # Returns
+    /// The message IDs of the matching messages, most recent first, or an error
+    /// if the query is unsupported or the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let ids = lore_api
+    ///     .query_message_ids("amd-gfx", "s:drm/amdgpu AND d:2.weeks.ago..")
+    ///     .await?;
+    /// ```
+    pub async fn query_message_ids(
+        &self,
+        target_list: &str,
+        lei_query: &str,
+    ) -> Result<Vec<ArcStr>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::QueryMessageIds {
+                        target_list: target_list.to_string(),
+                        lei_query: lei_query.to_string(),
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!(
+                    "message_ids_{}_{}",
+                    target_list,
+                    feed::lei_query(lei_query)?
+                );
+                let feed = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Message IDs not found in mock responses: {}", key)
+                })?;
+                Ok(feed::parse_entries(feed)
+                    .into_iter()
+                    .map(|entry| entry.message_id)
+                    .collect())
+            }
+        }
+    }
+
+    /// Reads the NNTP overview of a range of articles of a mailing list.
+    ///
+    /// The overview gives the subject, author, date, message ID, references and
+    /// size of many messages in a single response, which is far cheaper than
+    /// fetching each of them to sync their metadata. The newsgroup of the list is
+    /// taken from its inbox config, and the overview is read from the NNTP server
+    /// of the actor options rather than through the networking actor. The range
+    /// is narrowed to the articles the newsgroup holds, so one reaching past the
+    /// newest article only gives the existing ones.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "linux-kernel")
+    /// * `range` - The article numbers to read
+    ///
+    /// # Returns
+    /// The entries of the overview in article order, empty if no article of the
+    /// range exists, or an error if the list has no newsgroup or the exchange fails.
+    ///
+    /// # Example
+    /// ```
+    /// for entry in lore_api.get_overview("amd-gfx", 41200..=41299).await? {
+    ///     // ...
+    /// }
+    /// ```
+    pub async fn get_overview(
+        &self,
+        target_list: &str,
+        range: RangeInclusive<u64>,
+    ) -> Result<Vec<OverviewEntry>, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::GetOverview {
+                        target_list: target_list.to_string(),
+                        range,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("overview_{}_{}-{}", target_list, range.start(), range.end());
+                let data = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Overview not found in mock responses: {}", key)
+                })?;
+                Ok(nntp::parse_overview(data))
+            }
+        }
+    }
+
+    /// Streams the entries of a patch feed as the feed is downloaded.
+    ///
+    /// This method fetches the same feed as [`LoreApi::get_patch_feed`], but
+    /// parses it incrementally with [`feed::stream_entries`] instead of
+    /// buffering it, which bounds memory for very large feeds. A feed cut short
+    /// yields an error after its complete entries.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name (e.g., "amd-gfx", "all")
+    /// * `min_index` - The offset for pagination, in items (0-based); see [`FeedOffset`]
+    ///
+    /// # Returns
+    /// A stream of the entries of the feed, or an error if the request fails.
+    ///
+    /// # Example
+    /// ```
+    /// let mut entries = lore_api.stream_patch_feed("all", 0).await?;
+    /// while let Some(entry) = std::future::poll_fn(|cx| Pin::new(&mut entries).poll_next(cx)).await {
+    ///     let entry = entry?;
+    ///     // ...
+    /// }
+    /// ```
+    pub async fn stream_patch_feed(
+        &self,
+        target_list: &str,
+        min_index: impl Into<FeedOffset>,
+    ) -> Result<impl Stream<Item = anyhow::Result<PatchEntry>> + Unpin, anyhow::Error> {
+        let min_index = min_index.into().items();
+        let body = match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::StreamPatchFeed {
+                        target_list: target_list.to_string(),
+                        min_index,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await
+                    .context("Receiving response from LoreApi actor")??
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("patch_feed_{}_{}", target_list, min_index);
+                let feed = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Patch feed not found in mock responses: {}", key)
+                })?;
+                BodyStream::once(Arc::from(feed.as_bytes()))
+            }
+        };
+        Ok(feed::stream_entries(body))
+    }
+
+    /// Subscribes to the live updates of a mailing list.
+    ///
+    /// This method opens a long-lived GET of `/<list>/updates` with
+    /// `Accept: text/event-stream` and yields the entries carried by the
+    /// `data:` fields of the Server-Sent Events as they arrive. When the
+    /// connection drops, it is reopened in the background with the last event
+    /// ID received, backing off while the attempts fail; each failed attempt is
+    /// yielded as an error. Dropping the stream closes the subscription.
+    ///
+    /// The mock yields the entries of the events in `updates_{list}` once.
+    ///
+    /// # Arguments
+    /// * `target_list` - The mailing list name
+    ///
+    /// # Returns
+    /// A stream of the new entries of the list, or an error if the stream can't
+    /// be opened. Mirrors without live updates fail with
+    /// [`LoreApiError::UpdatesUnsupported`].
+    ///
+    /// # Example
+    /// ```
+    /// let mut updates = lore_api.subscribe_updates("amd-gfx").await?;
+    /// while let Some(entry) = std::future::poll_fn(|cx| Pin::new(&mut updates).poll_next(cx)).await {
+    ///     println!("New patch: {}", entry?.title);
+    /// }
+    /// ```
+    pub async fn subscribe_updates(
+        &self,
+        target_list: &str,
+    ) -> Result<impl Stream<Item = anyhow::Result<PatchEntry>> + Unpin, anyhow::Error> {
+        let body = self.open_updates(target_list, None).await?;
+        Ok(match self {
+            LoreApi::Actual(_) => {
+                sse::Subscription::spawn(self.clone(), ArcStr::from(target_list), body)
+            }
+            LoreApi::Mock(_) => sse::Subscription::once(body),
+        })
+    }
+
+    /// Opens the event stream of the live updates of a mailing list
+    async fn open_updates(
+        &self,
+        target_list: &str,
+        last_event_id: Option<ArcStr>,
+    ) -> Result<BodyStream, anyhow::Error> {
+        match self {
+            LoreApi::Actual(sender) => {
+                let (tx, rx) = oneshot::channel();
+                sender
+                    .send(LoreApiMessage::OpenUpdates {
+                        target_list: target_list.to_string(),
+                        last_event_id,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to LoreApi actor")?;
+                rx.await.context("Receiving response from LoreApi actor")?
+            }
+            LoreApi::Mock(responses) => {
+                let responses = responses.lock().await;
+                let key = format!("updates_{}", target_list);
+                let events = responses.get(&key).ok_or_else(|| {
+                    anyhow::anyhow!("Updates not found in mock responses: {}", key)
+                })?;
+                Ok(BodyStream::once(Arc::from(events.as_bytes())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
+    use crate::net::{MockState, message::MockRequestKey, mock::MockResponse};
     use std::collections::HashMap;
 
     #[tokio::test]
-    async fn test_lore_api_creation() {
-        let net = Net::mock_empty();
-        let lore_api = LoreApi::spawn(net);
-        
-        // Test that we can create the actor successfully
-        assert!(matches!(lore_api, LoreApi::Actual(_)));
+    async fn test_lore_api_creation() {
+        let net = Net::mock_empty();
+        let lore_api = LoreApi::spawn(net);
+
+        // Test that we can create the actor successfully
+        assert!(matches!(lore_api, LoreApi::Actual(_)));
+    }
+
+    #[tokio::test]
+    async fn test_lore_api_with_custom_domain() {
+        let net = Net::mock_empty();
+        let custom_domain = ArcStr::from("https://custom.lore.kernel.org");
+        let lore_api = LoreApi::spawn_with_domain(net, custom_domain);
+
+        // Test that we can create the actor with custom domain successfully
+        assert!(matches!(lore_api, LoreApi::Actual(_)));
+    }
+
+    #[tokio::test]
+    async fn test_per_list_headers() {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let net = Net::mock_with_state(state.clone());
+        let mut list_headers = HashMap::new();
+        list_headers.insert(
+            ArcStr::from("amd-gfx"),
+            HashMap::from([(ArcStr::from("X-Mirror"), ArcStr::from("amd"))]),
+        );
+        let lore_api = LoreApi::spawn_with_options(
+            net,
+            LoreApiOptions {
+                list_headers,
+                ..Default::default()
+            },
+        );
+
+        let _ = lore_api.get_raw_patch("amd-gfx", "test-message-id").await;
+        let _ = lore_api
+            .get_raw_patch("linux-kernel", "test-message-id")
+            .await;
+
+        let state = state.lock().await;
+        let requests = state.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].headers.get(&ArcStr::from("X-Mirror")),
+            Some(&ArcStr::from("amd"))
+        );
+        assert!(!requests[1].headers.contains_key(&ArcStr::from("X-Mirror")));
+        assert_eq!(
+            requests[1].headers.get(&ArcStr::from("Accept")),
+            Some(&ArcStr::from("text/plain"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_feed_url_construction() {
+        let net = Net::mock_empty();
+        let lore_api = LoreApi::spawn(net);
+
+        // This test verifies the URL construction logic
+        // The actual request will fail with mock, but we can verify the structure
+        let result = lore_api.get_patch_feed("test-list", 100).await;
+        assert!(result.is_err()); // Expected with mock
+    }
+
+    #[tokio::test]
+    async fn test_new_lists_since() {
+        let entry = |name: &str| {
+            format!(
+                "* 2023-12-01 12:34 - <a\nhref=\"https://lore.kernel.org/{}/\">{}</a>\n  The {} list\n",
+                name, name, name
+            )
+        };
+        let first = format!(
+            "<pre>{}{}</pre><a href=\"?&amp;o=200\" rel=next>next (older)</a>",
+            entry("amd-gfx"),
+            entry("dri-devel")
+        );
+        // The first list was updated during the walk and moved to the second page
+        let second = format!("<pre>{}{}</pre>", entry("linux-media"), entry("amd-gfx"));
+        let lore_api = LoreApi::mock(HashMap::from([
+            ("available_lists_0".to_string(), ArcStr::from(&first)),
+            ("available_lists_200".to_string(), ArcStr::from(&second)),
+        ]));
+
+        let index = lore_api.list_index().await.unwrap();
+        let names: Vec<_> = index.iter().map(|list| &*list.name).collect();
+        assert_eq!(names, ["amd-gfx", "dri-devel", "linux-media"]);
+
+        let snapshot = vec![
+            index[0].clone(),
+            // Renamed, but still at the same URL
+            MailingList {
+                name: ArcStr::from("dri"),
+                ..index[1].clone()
+            },
+        ];
+        let new = lore_api.new_lists_since(&snapshot).await.unwrap();
+        assert_eq!(new, [index[2].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_get_available_lists_url_construction() {
+        let net = Net::mock_empty();
+        let lore_api = LoreApi::spawn(net);
+
+        let result = lore_api.get_available_lists(200).await;
+        assert!(result.is_err()); // Expected with mock
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_html_url_construction() {
+        let net = Net::mock_empty();
+        let lore_api = LoreApi::spawn(net);
+
+        let result = lore_api
+            .get_patch_html("test-list", "test-message-id")
+            .await;
+        assert!(result.is_err()); // Expected with mock
+    }
+
+    #[tokio::test]
+    async fn test_mock_with_typed_keys() {
+        let mut responses = HashMap::new();
+        let test_response = ArcStr::from("<feed>test response</feed>");
+
+        responses.insert("patch_feed_test-list_0".to_string(), test_response.clone());
+
+        let lore_api = LoreApi::mock(responses);
+
+        // This should now work with the mock
+        let result = lore_api.get_patch_feed("test-list", 0).await;
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), test_response);
+    }
+
+    #[tokio::test]
+    async fn test_mock_empty() {
+        let lore_api = LoreApi::mock_empty();
+
+        // Test that mock_empty creates an empty mock
+        let result = lore_api.get_patch_feed("test-list", 0).await;
+        assert!(result.is_err()); // Expected with empty mock
+    }
+
+    #[cfg(feature = "pgp")]
+    #[tokio::test]
+    async fn test_verify_patch_signature() {
+        let mut responses = HashMap::new();
+        responses.insert(
+            "raw_patch_amd-gfx_20261016120000.1-1@example.org".to_string(),
+            ArcStr::from(include_str!("../../samples/signed_patch.eml")),
+        );
+        let lore_api = LoreApi::mock(responses);
+
+        let status = lore_api
+            .verify_patch_signature(
+                "amd-gfx",
+                "20261016120000.1-1@example.org",
+                include_str!("../../samples/signing_key.asc"),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(status, SignatureStatus::Good { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_patch_fingerprint() {
+        let diff = "diff --git a/foo.c b/foo.c\n--- a/foo.c\n+++ b/foo.c\n@@ -1 +1 @@\n-a\n+b\n";
+        let raw_patch = format!(
+            "From: Jane Doe <jane@example.com>\nSubject: [PATCH] foo\n\n{}",
+            diff
+        );
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/test-message-id/raw",
+            )),
+            ArcStr::from(&raw_patch),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let fingerprint = lore_api
+            .patch_fingerprint("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(Some(fingerprint), patch::fingerprint(diff));
+    }
+
+    #[tokio::test]
+    async fn test_max_bytes_aborts_oversized_operation() {
+        let patch = |number: usize| {
+            format!(
+                "From: Jane Doe <jane@example.com>\nSubject: [PATCH {number}/2] foo\n\n{}",
+                "x".repeat(600)
+            )
+        };
+        let mut responses = HashMap::new();
+        for number in 1..=2 {
+            responses.insert(
+                MockRequestKey::get(ArcStr::from(&format!(
+                    "https://lore.kernel.org/test-list/patch-{number}/raw"
+                ))),
+                ArcStr::from(&patch(number)),
+            );
+        }
+        let lore_api = LoreApi::spawn_with_options(
+            Net::mock(responses),
+            LoreApiOptions {
+                max_bytes: Some(1024),
+                ..Default::default()
+            },
+        );
+
+        // Each patch fits in the budget on its own
+        for message_id in ["patch-1", "patch-2"] {
+            lore_api
+                .get_raw_patch("test-list", message_id)
+                .await
+                .unwrap();
+        }
+
+        let err = lore_api
+            .series_apply_order("test-list", &["patch-1", "patch-2"])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LoreApiError>(),
+            Some(&LoreApiError::TooLarge {
+                limit: 1024,
+                downloaded: patch(1).len() + patch(2).len(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_url_rewriter() {
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://mirror.example.org/r/test-list/test-message-id/raw",
+            )),
+            ArcStr::from("raw patch"),
+        );
+        let lore_api = LoreApi::spawn_with_options(
+            Net::mock(responses),
+            LoreApiOptions {
+                domain: ArcStr::from("https://mirror.example.org"),
+                url_rewriter: Some(UrlRewriter::new(|url| {
+                    ArcStr::from(&url.replacen(".org/", ".org/r/", 1))
+                })),
+                ..Default::default()
+            },
+        );
+
+        let raw_patch = lore_api
+            .get_raw_patch("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(raw_patch, ArcStr::from("raw patch"));
+    }
+
+    #[tokio::test]
+    async fn test_kernel_org() {
+        let url = "https://lore.kernel.org/test-list/test-message-id/raw";
+        let mut state = MockState::default();
+        state.insert(
+            MockRequestKey::get(ArcStr::from(url)),
+            MockResponse::ok(ArcStr::from("raw patch")),
+        );
+        state.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/?x=A&q=s%3Adrm&o=0",
+            )),
+            MockResponse::ok(ArcStr::from("<feed></feed>")),
+        );
+        let state = Arc::new(Mutex::new(state));
+        let lore_api = LoreApi::kernel_org(Net::mock_with_state(state.clone()));
+
+        let raw_patch = lore_api
+            .get_raw_patch("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(raw_patch, ArcStr::from("raw patch"));
+
+        let request = state.lock().await.requests()[0].clone();
+        assert_eq!(&*request.url, url);
+        assert!(
+            request
+                .headers
+                .get(&ArcStr::from("User-Agent"))
+                .is_some_and(|agent| agent.starts_with("patch-hub-actors/"))
+        );
+        assert_eq!(request.timeout, Some(std::time::Duration::from_secs(30)));
+        assert!(request.retry_policy.is_some());
+
+        // Searches and previews are sent with the timeout and retries too
+        lore_api
+            .query_message_ids("test-list", "s:drm")
+            .await
+            .unwrap();
+        lore_api
+            .get_patch_preview("test-list", "test-message-id", 1024)
+            .await
+            .unwrap();
+        let state = state.lock().await;
+        let requests = &state.requests()[1..];
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| {
+            request.timeout == Some(std::time::Duration::from_secs(30))
+                && request.retry_policy.is_some()
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_shared_routes_through_same_net() {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let net = Net::mock_with_state(state.clone());
+        let domains = [
+            ArcStr::from("https://lore.kernel.org"),
+            ArcStr::from("https://mirror.example.org"),
+        ];
+        let apis = LoreApi::spawn_shared(net, &domains);
+        assert_eq!(apis.len(), 2);
+
+        for api in &apis {
+            let _ = api.get_raw_patch("test-list", "test-message-id").await;
+        }
+
+        let state = state.lock().await;
+        let urls: Vec<&str> = state.requests().iter().map(|r| &*r.url).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://lore.kernel.org/test-list/test-message-id/raw",
+                "https://mirror.example.org/test-list/test-message-id/raw",
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_timeout() {
+        let url = ArcStr::from(
+            "https://lore.kernel.org/amd-gfx/?x=A&q=((s:patch+OR+s:rfc)+AND+NOT+s:re:)&o=0",
+        );
+        let slow = |delay| {
+            let mut responses = HashMap::new();
+            responses.insert(
+                MockRequestKey::get(url.clone()),
+                (ArcStr::from("feed"), delay),
+            );
+            Net::mock_with_delays(responses)
+        };
+        let timeout = std::time::Duration::from_secs(5);
+
+        // The operation received first is handled, and a clone left alive
+        // neither keeps the actor running nor can use it afterwards
+        let lore_api = LoreApi::spawn(slow(std::time::Duration::from_secs(2)));
+        let pending = tokio::spawn({
+            let lore_api = lore_api.clone();
+            async move { lore_api.get_patch_feed("amd-gfx", 0).await }
+        });
+        tokio::task::yield_now().await;
+        let clone = lore_api.clone();
+        lore_api.shutdown_timeout(timeout).await.unwrap();
+        assert_eq!(pending.await.unwrap().unwrap(), ArcStr::from("feed"));
+        clone.get_patch_feed("amd-gfx", 0).await.unwrap_err();
+
+        // Past the timeout, the operation being handled is aborted
+        let lore_api = LoreApi::spawn(slow(std::time::Duration::from_secs(60)));
+        let pending = tokio::spawn({
+            let lore_api = lore_api.clone();
+            async move { lore_api.get_patch_feed("amd-gfx", 0).await }
+        });
+        tokio::task::yield_now().await;
+        let error = lore_api.shutdown_timeout(timeout).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<crate::utils::ShutdownError>(),
+            Some(&crate::utils::ShutdownError::Forced { timeout })
+        );
+        pending.await.unwrap().unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_get_inbox_config() {
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/amd-gfx/_/text/config/raw",
+            )),
+            ArcStr::from(
+                r#"{"address": "amd-gfx@lists.freedesktop.org", "url": "https://lore.kernel.org/amd-gfx"}"#,
+            ),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let config = lore_api.get_inbox_config("amd-gfx").await.unwrap();
+        assert_eq!(
+            config.address,
+            ArcStr::from("amd-gfx@lists.freedesktop.org")
+        );
+        assert!(lore_api.get_inbox_config("missing-list").await.is_err());
+    }
+
+    fn batch_mock() -> LoreApi {
+        let mut responses = HashMap::new();
+        responses.insert("patch_html_test-list_a".to_string(), ArcStr::from("<a/>"));
+        responses.insert("patch_html_test-list_c".to_string(), ArcStr::from("<c/>"));
+        LoreApi::mock(responses)
     }
 
     #[tokio::test]
-    async fn test_lore_api_with_custom_domain() {
-        let net = Net::mock_empty();
-        let custom_domain = ArcStr::from("https://custom.lore.kernel.org");
-        let lore_api = LoreApi::spawn_with_domain(net, custom_domain);
-        
-        // Test that we can create the actor with custom domain successfully
-        assert!(matches!(lore_api, LoreApi::Actual(_)));
+    async fn test_get_patch_htmls_collect() {
+        let lore_api = batch_mock();
+        let results = lore_api
+            .get_patch_htmls("test-list", &["a", "b", "c"], BatchMode::Collect)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &ArcStr::from("<a/>"));
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap(), &ArcStr::from("<c/>"));
     }
 
     #[tokio::test]
-    async fn test_get_patch_feed_url_construction() {
-        let net = Net::mock_empty();
-        let lore_api = LoreApi::spawn(net);
-        
-        // This test verifies the URL construction logic
-        // The actual request will fail with mock, but we can verify the structure
-        let result = lore_api.get_patch_feed("test-list", 100).await;
-        assert!(result.is_err()); // Expected with mock
+    async fn test_get_patch_htmls_fail_fast() {
+        let lore_api = batch_mock();
+        let result = lore_api
+            .get_patch_htmls("test-list", &["a", "b", "c"], BatchMode::FailFast)
+            .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), "Batch aborted on message: b");
     }
 
     #[tokio::test]
-    async fn test_get_available_lists_url_construction() {
-        let net = Net::mock_empty();
-        let lore_api = LoreApi::spawn(net);
-        
-        let result = lore_api.get_available_lists(200).await;
-        assert!(result.is_err()); // Expected with mock
+    async fn test_collect_mbox() {
+        let lore_api = LoreApi::mock(HashMap::from([
+            (
+                "raw_patch_test-list_a".to_string(),
+                ArcStr::from("Subject: [PATCH 1/2] a\n\n-a\n+b\n"),
+            ),
+            (
+                "raw_patch_test-list_b".to_string(),
+                ArcStr::from("Subject: [PATCH 2/2] b\n\nFrom here\n-c\n+d\n"),
+            ),
+        ]));
+
+        let mbox = lore_api
+            .collect_mbox("test-list", &["a", "b"])
+            .await
+            .unwrap();
+        assert_eq!(
+            &*mbox,
+            "From mboxrd@z Thu Jan  1 00:00:00 1970\n\
+             Subject: [PATCH 1/2] a\n\n-a\n+b\n\n\
+             From mboxrd@z Thu Jan  1 00:00:00 1970\n\
+             Subject: [PATCH 2/2] b\n\n>From here\n-c\n+d\n\n"
+        );
+
+        let err = lore_api
+            .collect_mbox("test-list", &["a", "missing"])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Collecting mbox failed on message: missing"
+        );
     }
 
     #[tokio::test]
-    async fn test_get_patch_html_url_construction() {
-        let net = Net::mock_empty();
-        let lore_api = LoreApi::spawn(net);
-        
-        let result = lore_api.get_patch_html("test-list", "test-message-id").await;
-        assert!(result.is_err()); // Expected with mock
+    async fn test_get_patch_text() {
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "patch_html_test-list_test-message-id".to_string(),
+            ArcStr::from(
+                "<html><body><pre>struct a &lt;b&gt;\n<span class=\"add\">+\tlong b;</span>\n</pre></body></html>",
+            ),
+        )]));
+
+        let text = lore_api
+            .get_patch_text("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(&*text, "struct a <b>\n+\tlong b;");
     }
 
     #[tokio::test]
-    async fn test_mock_with_typed_keys() {
+    async fn test_get_raw_patch_normalized() {
         let mut responses = HashMap::new();
-        let test_response = ArcStr::from("<feed>test response</feed>");
-        
-        responses.insert("patch_feed_test-list_0".to_string(), test_response.clone());
-        
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/test-message-id/raw",
+            )),
+            ArcStr::from("Subject: [PATCH] foo\r\n\r\n-a\r\n+b\r\n"),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let raw = lore_api
+            .get_raw_patch("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert!(raw.contains("\r\n"));
+
+        let normalized = lore_api
+            .get_raw_patch_normalized("test-list", "test-message-id", LineEnding::Lf)
+            .await
+            .unwrap();
+        assert_eq!(&*normalized, "Subject: [PATCH] foo\n\n-a\n+b\n");
+    }
+
+    #[tokio::test]
+    async fn test_list_series_versions() {
+        let entry = |title: &str, id: &str| {
+            format!(
+                "<entry><title>{}</title><link href=\"https://lore.kernel.org/test-list/{}/\"/></entry>",
+                title, id
+            )
+        };
+        let feed = [
+            entry("[PATCH v3 0/4] drm: fix b", "v3-0"),
+            entry("[PATCH v3 1/4] drm: widen b", "v3-1"),
+            entry("Re: [PATCH 0/3] drm: fix b", "reply"),
+            entry("[PATCH 0/3] drm: fix b", "v1-0"),
+            entry("[PATCH 1/3] drm: widen b", "v1-1"),
+        ]
+        .concat();
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/?x=A&q=s%3A%22drm%3A+fix+b%22&o=0",
+            )),
+            ArcStr::from(&format!("<feed>{}</feed>", feed)),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let versions = lore_api
+            .list_series_versions("test-list", "drm: fix b")
+            .await
+            .unwrap();
+        assert_eq!(
+            versions,
+            [
+                SeriesVersion {
+                    version: 1,
+                    cover_message_id: ArcStr::from("v1-0"),
+                    patch_count: 3,
+                },
+                SeriesVersion {
+                    version: 3,
+                    cover_message_id: ArcStr::from("v3-0"),
+                    patch_count: 4,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_series() {
+        let feed = r#"<feed>
+<entry><title>[PATCH 1/1] drm: widen b</title><link href="https://lore.kernel.org/test-list/p-1/"/>
+<thr:in-reply-to ref="urn:uuid:p-0" href="https://lore.kernel.org/test-list/p-0/"/></entry>
+<entry><title>[PATCH 0/1] drm: fix b</title><link href="https://lore.kernel.org/test-list/p-0/"/></entry>
+</feed>"#;
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "patch_feed_test-list_0".to_string(),
+            ArcStr::from(feed),
+        )]));
+
+        let series = lore_api.recent_series("test-list", 0).await.unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(
+            series[0]
+                .cover
+                .as_ref()
+                .map(|cover| cover.message_id.clone()),
+            Some(ArcStr::from("p-0"))
+        );
+        assert_eq!(series[0].patches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_feed_at_feed_offset() {
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "patch_feed_amd-gfx_400".to_string(),
+            ArcStr::from("<feed></feed>"),
+        )]));
+
+        let by_page = lore_api
+            .get_patch_feed("amd-gfx", FeedOffset::from_page(2, 200))
+            .await
+            .unwrap();
+        let by_items = lore_api.get_patch_feed("amd-gfx", 400).await.unwrap();
+        assert_eq!(by_page, by_items);
+        assert!(
+            lore_api
+                .get_patch_feed("amd-gfx", FeedOffset::from_items(2))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_crosspost_lists() {
+        let entry = |list: &str, id: &str| {
+            format!(
+                r#"<entry><title>[PATCH] drm: fix b</title><link href="https://lore.kernel.org/{list}/{id}/"/></entry>"#
+            )
+        };
+        let search = |id: &str| {
+            ArcStr::from(&format!(
+                "https://lore.kernel.org/all/?x=A&q=m%3A{}&o=0",
+                id.replace('@', "%40")
+            ))
+        };
+        let crossposted = format!(
+            "<feed>{}{}{}{}</feed>",
+            entry("amd-gfx", "1-1@amd.com"),
+            entry("dri-devel", "1-1@amd.com"),
+            entry("amd-gfx", "1-1@amd.com"),
+            entry("all", "1-1@amd.com")
+        );
+        let single = format!("<feed>{}</feed>", entry("amd-gfx", "2-1@amd.com"));
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(search("1-1@amd.com")),
+            ArcStr::from(&crossposted),
+        );
+        responses.insert(
+            MockRequestKey::get(search("2-1@amd.com")),
+            ArcStr::from(&single),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        assert_eq!(
+            lore_api
+                .find_crosspost_lists("<1-1@AMD.com>")
+                .await
+                .unwrap(),
+            [ArcStr::from("amd-gfx"), ArcStr::from("dri-devel")]
+        );
+        assert_eq!(
+            lore_api.find_crosspost_lists("2-1@amd.com").await.unwrap(),
+            [ArcStr::from("amd-gfx")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_feed_parsed_strict() {
+        let entry = |id: &str, updated: &str| {
+            format!(
+                r#"<entry><title>[PATCH] drm: fix {id}</title><updated>{updated}</updated><link href="https://lore.kernel.org/amd-gfx/{id}/"/></entry>"#
+            )
+        };
+        let header = "<feed><opensearch:totalResults>3</opensearch:totalResults>";
+        let complete = format!(
+            "{}{}{}{}</feed>",
+            header,
+            entry("c", "2023-12-03T00:00:00Z"),
+            entry("b", "2023-12-02T00:00:00Z"),
+            entry("a", "2023-12-01T00:00:00Z")
+        );
+        let out_of_order = format!(
+            "{}{}{}{}</feed>",
+            header,
+            entry("c", "2023-12-03T00:00:00Z"),
+            entry("a", "2023-12-01T00:00:00Z"),
+            entry("b", "2023-12-02T00:00:00Z")
+        );
+        let short = format!(
+            "{}{}{}</feed>",
+            header,
+            entry("c", "2023-12-03T00:00:00Z"),
+            entry("b", "2023-12-02T00:00:00Z")
+        );
+        let truncated = format!(
+            "{}{}<entry><title>[PA",
+            header,
+            entry("c", "2023-12-03T00:00:00Z")
+        );
+        let lore_api = LoreApi::mock(HashMap::from([
+            ("patch_feed_amd-gfx_0".to_string(), ArcStr::from(&complete)),
+            (
+                "patch_feed_amd-gfx_1".to_string(),
+                ArcStr::from(&out_of_order),
+            ),
+            ("patch_feed_amd-gfx_2".to_string(), ArcStr::from(&short)),
+            ("patch_feed_amd-gfx_3".to_string(), ArcStr::from(&truncated)),
+        ]));
+
+        let feed = lore_api
+            .get_patch_feed_parsed_strict("amd-gfx", 0)
+            .await
+            .unwrap();
+        assert_eq!(feed.entries.len(), 3);
+
+        let reason = |min_index| {
+            let lore_api = lore_api.clone();
+            async move {
+                let err = lore_api
+                    .get_patch_feed_parsed_strict("amd-gfx", min_index)
+                    .await
+                    .unwrap_err();
+                match err.downcast_ref::<LoreApiError>() {
+                    Some(LoreApiError::InconsistentFeed { reason }) => reason.to_string(),
+                    _ => panic!("unexpected error: {:?}", err),
+                }
+            }
+        };
+        assert_eq!(
+            reason(1).await,
+            "entry 2 (b) is newer than the entry before it"
+        );
+        assert_eq!(reason(2).await, "2 entries where the feed announces 3");
+        assert_eq!(reason(3).await, "missing closing </feed> tag");
+
+        // The lenient parse keeps its behavior
+        let feed = lore_api.get_patch_feed_parsed("amd-gfx", 1).await.unwrap();
+        assert_eq!(feed.entries.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_as_quote() {
+        let raw_patch = "From: \"Jane Doe\" <jane@amd.com>\n\
+            Date: Fri, 1 Dec 2023 12:34:56 +0000\n\
+            Subject: Re: [PATCH] drm/amdgpu: fix b\n\
+            Content-Type: text/plain; charset=utf-8\n\
+            Content-Transfer-Encoding: quoted-printable\n\
+            \n\
+            > Why not check it earlier?\n\
+            \n\
+            Good point, done in v2=2E\n\
+            ---\n\
+            diff --git a/a.c b/a.c\n";
+        let anonymous = "Subject: [PATCH] drm: fix c\n\nBody\n";
+        let lore_api = LoreApi::mock(HashMap::from([
+            (
+                "raw_patch_amd-gfx_1-1@amd.com".to_string(),
+                ArcStr::from(raw_patch),
+            ),
+            (
+                "raw_patch_amd-gfx_2-1@amd.com".to_string(),
+                ArcStr::from(anonymous),
+            ),
+        ]));
+
+        let quote = lore_api
+            .get_patch_as_quote("amd-gfx", "1-1@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            quote,
+            ArcStr::from(
+                "On Fri, 1 Dec 2023 12:34:56 +0000, Jane Doe wrote:\n\
+                 > > Why not check it earlier?\n\
+                 >\n\
+                 > Good point, done in v2.\n\
+                 > ---\n\
+                 > diff --git a/a.c b/a.c\n"
+            )
+        );
+
+        let quote = lore_api
+            .get_patch_as_quote("amd-gfx", "2-1@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(quote, ArcStr::from("> Body\n"));
+    }
+
+    #[tokio::test]
+    async fn test_get_commit_message() {
+        let cover = "Subject: [PATCH 0/2] drm: fixes\n\
+            \n\
+            Two fixes.\n\
+            \n\
+            Jane Doe (2):\n\
+            \x20 drm: fix a\n\
+            ---\n\
+            \x201 file changed\n";
+        let lore_api = LoreApi::mock(HashMap::from([
+            (
+                "raw_patch_amd-gfx_3-1@amd.com".to_string(),
+                ArcStr::from(include_str!("../../samples/multi_file.patch")),
+            ),
+            (
+                "raw_patch_amd-gfx_0-1@amd.com".to_string(),
+                ArcStr::from(cover),
+            ),
+        ]));
+
+        let message = lore_api
+            .get_commit_message("amd-gfx", "3-1@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            message,
+            ArcStr::from(
+                "drm/amdgpu: split the b helpers\n\
+                 \n\
+                 Move the b helpers to their own file and drop the unused ones.\n\
+                 \n\
+                 Signed-off-by: Jane Doe <jane@example.com>\n"
+            )
+        );
+        assert!(!message.contains("insertions"));
+        assert!(!message.contains("diff --git"));
+
+        let message = lore_api
+            .get_commit_message("amd-gfx", "0-1@amd.com")
+            .await
+            .unwrap();
+        assert!(message.starts_with("Two fixes.\n"));
+        assert!(message.contains("1 file changed"));
+    }
+
+    #[tokio::test]
+    async fn test_get_message_references() {
+        let lore_api = LoreApi::mock(HashMap::from([
+            (
+                "raw_patch_amd-gfx_reply@amd.com".to_string(),
+                ArcStr::from(include_str!("../../samples/reply.eml")),
+            ),
+            (
+                "raw_patch_amd-gfx_unthreaded@amd.com".to_string(),
+                ArcStr::from(
+                    "Subject: Re: [PATCH] drm: fix b\n\
+                     References: 20231201.0-1@AMD.com\n\
+                     \n\
+                     Body\n",
+                ),
+            ),
+            (
+                "raw_patch_amd-gfx_root@amd.com".to_string(),
+                ArcStr::from("Subject: [PATCH] drm: fix c\n\nBody\n"),
+            ),
+        ]));
+
+        let links = lore_api
+            .get_message_references("amd-gfx", "reply@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            links,
+            MessageLinks {
+                in_reply_to: Some(ArcStr::from("20231201.2-1@amd.com")),
+                references: vec![
+                    ArcStr::from("20231201.0-1@amd.com"),
+                    ArcStr::from("20231201.1-1@amd.com"),
+                    ArcStr::from("20231201.2-1@amd.com"),
+                ],
+            }
+        );
+
+        // Without In-Reply-To, the parent is the last reference
+        let links = lore_api
+            .get_message_references("amd-gfx", "unthreaded@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            links.in_reply_to,
+            Some(ArcStr::from("20231201.0-1@amd.com"))
+        );
+
+        let links = lore_api
+            .get_message_references("amd-gfx", "root@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(links, MessageLinks::default());
+    }
+
+    #[tokio::test]
+    async fn test_series_apply_order() {
+        let unnumbered = |subject: &str, id: &str, parent: &str, date: &str| {
+            ArcStr::from(&format!(
+                "Subject: [PATCH] {subject}\nMessage-ID: <{id}>\nIn-Reply-To: <{parent}>\n\
+                 Date: {date}\n\nBody\n"
+            ))
+        };
+        let lore_api = LoreApi::mock(HashMap::from([
+            (
+                "raw_patch_amd-gfx_20231201.4-2@amd.com".to_string(),
+                ArcStr::from(include_str!("../../samples/series_1.patch")),
+            ),
+            (
+                "raw_patch_amd-gfx_20231201.4-3@amd.com".to_string(),
+                ArcStr::from(include_str!("../../samples/series_2.patch")),
+            ),
+            (
+                "raw_patch_amd-gfx_other@amd.com".to_string(),
+                ArcStr::from("Subject: [PATCH v2 1/3] drm: fix b\n\nBody\n"),
+            ),
+            (
+                "raw_patch_amd-gfx_b@amd.com".to_string(),
+                unnumbered(
+                    "drm: fix b",
+                    "b@amd.com",
+                    "a@amd.com",
+                    "Fri, 1 Dec 2023 12:00:02 +0000",
+                ),
+            ),
+            (
+                "raw_patch_amd-gfx_a@amd.com".to_string(),
+                unnumbered(
+                    "drm: fix a",
+                    "a@amd.com",
+                    "c@amd.com",
+                    "Fri, 1 Dec 2023 12:00:01 +0000",
+                ),
+            ),
+        ]));
+
+        let order = lore_api
+            .series_apply_order("amd-gfx", &["20231201.4-3@amd.com", "20231201.4-2@amd.com"])
+            .await
+            .unwrap();
+        assert_eq!(
+            order,
+            vec![
+                ArcStr::from("20231201.4-2@amd.com"),
+                ArcStr::from("20231201.4-3@amd.com"),
+            ]
+        );
+
+        // Without numbers, a patch replying to another one applies after it
+        let order = lore_api
+            .series_apply_order("amd-gfx", &["b@amd.com", "a@amd.com"])
+            .await
+            .unwrap();
+        assert_eq!(
+            order,
+            vec![ArcStr::from("a@amd.com"), ArcStr::from("b@amd.com")]
+        );
+
+        let err = lore_api
+            .series_apply_order("amd-gfx", &["20231201.4-3@amd.com", "other@amd.com"])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LoreApiError>(),
+            Some(LoreApiError::NotOneSeries { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_cover_letters() {
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "patch_feed_amd-gfx_0".to_string(),
+            ArcStr::from(include_str!("../../samples/patch_feed.xml")),
+        )]));
+
+        let covers = lore_api.get_cover_letters("amd-gfx", 0).await.unwrap();
+        assert_eq!(covers.len(), 1);
+        assert_eq!(
+            covers[0].title,
+            ArcStr::from("[PATCH 0/2] drm/amdgpu: fix b")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_activity_status() {
+        let feed = |ages_in_days: &[i64]| {
+            let entries: String = ages_in_days
+                .iter()
+                .enumerate()
+                .map(|(i, days)| {
+                    let updated = chrono::Utc::now() - chrono::TimeDelta::days(*days);
+                    format!(
+                        r#"<entry><title>[PATCH] drm: fix {i}</title><updated>{}</updated><link href="https://lore.kernel.org/list/{i}-1@amd.com/"/></entry>"#,
+                        updated.to_rfc3339()
+                    )
+                })
+                .collect();
+            ArcStr::from(&format!("<feed>{entries}</feed>"))
+        };
+        let lore_api = LoreApi::mock(HashMap::from([
+            // The newest message isn't necessarily the first one
+            ("patch_feed_active_0".to_string(), feed(&[40, 2, 400])),
+            ("patch_feed_dormant_0".to_string(), feed(&[90, 200])),
+            ("patch_feed_inactive_0".to_string(), feed(&[800])),
+            ("patch_feed_empty_0".to_string(), feed(&[])),
+        ]));
+
+        let thresholds = ActivityThresholds::default();
+        for (list, status) in [
+            ("active", ActivityStatus::Active),
+            ("dormant", ActivityStatus::Dormant),
+            ("inactive", ActivityStatus::Inactive),
+            ("empty", ActivityStatus::Inactive),
+        ] {
+            assert_eq!(
+                lore_api
+                    .list_activity_status(list, thresholds)
+                    .await
+                    .unwrap(),
+                status,
+                "{list}"
+            );
+        }
+
+        // Tighter thresholds move the same lists down
+        let thresholds = ActivityThresholds {
+            active: std::time::Duration::from_secs(24 * 60 * 60),
+            dormant: std::time::Duration::from_secs(100 * 24 * 60 * 60),
+        };
+        assert_eq!(
+            lore_api
+                .list_activity_status("active", thresholds)
+                .await
+                .unwrap(),
+            ActivityStatus::Dormant
+        );
+        assert_eq!(
+            lore_api
+                .list_activity_status("dormant", thresholds)
+                .await
+                .unwrap(),
+            ActivityStatus::Dormant
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_stats() {
+        let entry = |title: &str, id: &str, author: &str, updated: &str, parent: Option<&str>| {
+            let reply = parent
+                .map(|parent| {
+                    format!(
+                        r#"<thr:in-reply-to href="https://lore.kernel.org/amd-gfx/{parent}/"/>"#
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                r#"<entry><author><name>{author}</name><email>{author}@amd.com</email></author><title>{title}</title><updated>{updated}</updated><link href="https://lore.kernel.org/amd-gfx/{id}/"/>{reply}</entry>"#
+            )
+        };
+        let first = format!(
+            r#"<feed><link rel="next" href="./?x=A&amp;o=3"/>{}{}{}</feed>"#,
+            entry(
+                "[PATCH] drm/radeon: fix typo",
+                "c",
+                "alex",
+                "2023-12-02T09:00:00Z",
+                None
+            ),
+            entry(
+                "[PATCH 2/2] drm: widen b",
+                "b-2",
+                "jane",
+                "2023-12-01T23:30:00-02:00",
+                Some("b-0")
+            ),
+            entry(
+                "[PATCH 1/2] drm: split b",
+                "b-1",
+                "jane",
+                "2023-12-01T12:00:00Z",
+                Some("b-0")
+            ),
+        );
+        // A message posted during the walk pushed b-1 to the second page
+        let second = format!(
+            "<feed>{}{}</feed>",
+            entry(
+                "[PATCH 1/2] drm: split b",
+                "b-1",
+                "jane",
+                "2023-12-01T12:00:00Z",
+                Some("b-0")
+            ),
+            entry(
+                "[PATCH 0/2] drm: fix b",
+                "b-0",
+                "jane",
+                "2023-11-30T08:00:00Z",
+                None
+            ),
+        );
+        let lore_api = LoreApi::mock(HashMap::from([
+            ("patch_feed_amd-gfx_0".to_string(), ArcStr::from(&first)),
+            ("patch_feed_amd-gfx_3".to_string(), ArcStr::from(&second)),
+            (
+                "patch_feed_empty_0".to_string(),
+                ArcStr::from("<feed></feed>"),
+            ),
+        ]));
+
+        let stats = lore_api.list_stats("amd-gfx", 5).await.unwrap();
+        assert_eq!(stats.total, 4);
+        assert_eq!(
+            stats.per_author,
+            HashMap::from([
+                (ArcStr::from("jane@amd.com"), 3),
+                (ArcStr::from("alex@amd.com"), 1),
+            ])
+        );
+        let day = |month, day| chrono::NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+        assert_eq!(
+            stats.per_day,
+            std::collections::BTreeMap::from([(day(11, 30), 1), (day(12, 1), 1), (day(12, 2), 2)])
+        );
+        assert_eq!(stats.series_count, 1);
+
+        // Only the requested pages are aggregated
+        let stats = lore_api.list_stats("amd-gfx", 1).await.unwrap();
+        assert_eq!((stats.total, stats.series_count), (3, 1));
+
+        assert_eq!(
+            lore_api.list_stats("empty", 5).await.unwrap(),
+            ListStats::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_for_each_message() {
+        let entry = |id: &str| {
+            format!(
+                r#"<entry><title>Re: [PATCH] drm: fix {id}</title><link href="https://lore.kernel.org/amd-gfx/{id}/"/></entry>"#
+            )
+        };
+        let first = format!(
+            r#"<feed><link rel="next" href="./?x=A&amp;o=2"/>{}{}</feed>"#,
+            entry("m-1"),
+            entry("m-2")
+        );
+        // A message posted during the walk pushed m-2 to the second page
+        let second = format!("<feed>{}{}</feed>", entry("m-2"), entry("m-3"));
+        let mut responses = HashMap::from([
+            ("archive_page_amd-gfx_0".to_string(), ArcStr::from(&first)),
+            ("archive_page_amd-gfx_2".to_string(), ArcStr::from(&second)),
+        ]);
+        for id in ["m-1", "m-2", "m-3"] {
+            responses.insert(
+                format!("raw_patch_amd-gfx_{}", id),
+                ArcStr::from(&format!("Message-ID: <{}>", id)),
+            );
+        }
         let lore_api = LoreApi::mock(responses);
-        
-        // This should now work with the mock
-        let result = lore_api.get_patch_feed("test-list", 0).await;
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), test_response);
+
+        let mut received = Vec::new();
+        let count = lore_api
+            .for_each_message("amd-gfx", 2, |message| {
+                received.push((message.entry.message_id, message.content));
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+        received.sort();
+        assert_eq!(
+            received,
+            ["m-1", "m-2", "m-3"].map(|id| (
+                ArcStr::from(id),
+                ArcStr::from(&format!("Message-ID: <{}>", id))
+            ))
+        );
+
+        // The walk stops at the first failing callback
+        let mut calls = 0;
+        let err = lore_api
+            .for_each_message("amd-gfx", 1, |_| {
+                calls += 1;
+                async { Err(anyhow::anyhow!("index full")) }
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "index full");
+        assert_eq!(calls, 1);
     }
 
     #[tokio::test]
-    async fn test_mock_empty() {
-        let lore_api = LoreApi::mock_empty();
-        
-        // Test that mock_empty creates an empty mock
-        let result = lore_api.get_patch_feed("test-list", 0).await;
-        assert!(result.is_err()); // Expected with empty mock
+    async fn test_get_patch_feed_parsed() {
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "patch_feed_amd-gfx_0".to_string(),
+            ArcStr::from(include_str!("../../samples/patch_feed.xml")),
+        )]));
+
+        let feed = lore_api.get_patch_feed_parsed("amd-gfx", 0).await.unwrap();
+        assert_eq!(
+            feed.title,
+            Some(ArcStr::from("[PATCH] search results - amd-gfx"))
+        );
+        assert_eq!(feed.updated, Some(ArcStr::from("2023-12-01T12:34:57Z")));
+        assert_eq!(
+            feed.entries[0].message_id,
+            ArcStr::from("20231201.1-1@amd.com")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_diff_parsed() {
+        let raw_patch = "Subject: [PATCH] foo: use =3D\n\
+                         Content-Transfer-Encoding: quoted-printable\n\
+                         \n\
+                         diff --git a/foo.c b/foo.c\n\
+                         --- a/foo.c\n\
+                         +++ b/foo.c\n\
+                         @@ -3 +3 @@\n\
+                         -a =3D=3D b;\n\
+                         +a =3D b;\n";
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "raw_patch_test-list_test-message-id".to_string(),
+            ArcStr::from(raw_patch),
+        )]));
+
+        let diff = lore_api
+            .get_patch_diff_parsed("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(diff.files.len(), 1);
+        let lines = &diff.files[0].hunks[0].lines;
+        assert_eq!(lines[0].content, ArcStr::from("a == b;"));
+        assert_eq!(
+            (lines[1].content.clone(), lines[1].new_line),
+            (ArcStr::from("a = b;"), Some(3))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_feed_as_rss() {
+        let feed = r#"<feed><title>test-list</title>
+<entry><title>[PATCH 1/2] a</title><link href="https://lore.kernel.org/test-list/p-1/"/></entry>
+<entry><title>[PATCH 2/2] b</title><link href="https://lore.kernel.org/test-list/p-2/"/></entry>
+</feed>"#;
+        let lore_api = LoreApi::mock(HashMap::from([
+            ("patch_feed_test-list_0".to_string(), ArcStr::from(feed)),
+            (
+                "patch_feed_test-list_200".to_string(),
+                ArcStr::from("<feed><title>test-list</title></feed>"),
+            ),
+        ]));
+
+        let rss = lore_api
+            .get_patch_feed_as_rss("test-list", 0)
+            .await
+            .unwrap();
+        assert!(
+            rss.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">")
+        );
+        assert!(rss.trim_end().ends_with("</rss>"));
+        assert_eq!(rss.matches("<item>").count(), 2);
+        assert_eq!(rss.matches("</item>").count(), 2);
+
+        let empty = lore_api
+            .get_patch_feed_as_rss("test-list", 200)
+            .await
+            .unwrap();
+        assert!(empty.contains("<channel>"));
+        assert_eq!(empty.matches("<item>").count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_message_text() {
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/test-message-id/raw",
+            )),
+            ArcStr::from(
+                "Subject: [PATCH 0/2] foo\n\
+                 Content-Transfer-Encoding: quoted-printable\n\
+                 \n\
+                 Na=C3=AFve cover letter.\n",
+            ),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let text = lore_api
+            .get_message_text("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(text, ArcStr::from("Na\u{ef}ve cover letter.\n"));
+    }
+
+    #[tokio::test]
+    async fn test_get_attachments() {
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/test-message-id/raw",
+            )),
+            ArcStr::from(include_str!("../../samples/attachment.eml")),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let attachments = lore_api
+            .get_attachments("test-list", "test-message-id")
+            .await
+            .unwrap();
+        let names: Vec<_> = attachments
+            .iter()
+            .map(|attachment| attachment.filename.clone().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![ArcStr::from("config-6.7"), ArcStr::from("hang.png")]
+        );
+        assert_eq!(
+            &*attachments[1].bytes,
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\xff\0\x7f"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_feed_update() {
+        let url = ArcStr::from(
+            "https://lore.kernel.org/test-list/?x=A&q=((s:patch+OR+s:rfc)+AND+NOT+s:re:)&o=0",
+        );
+        let mut state = MockState::default();
+        state.push(
+            MockRequestKey::get(url.clone()),
+            MockResponse::ok(ArcStr::from("<feed/>")).header("ETag", "\"v1\""),
+        );
+        state.push(
+            MockRequestKey::get(url),
+            MockResponse::with_status(304, ArcStr::from("")),
+        );
+        let state = Arc::new(Mutex::new(state));
+        let lore_api = LoreApi::spawn(Net::mock_with_state(state.clone()));
+
+        assert_eq!(
+            lore_api.get_patch_feed_update("test-list").await.unwrap(),
+            FeedUpdate::Changed(ArcStr::from("<feed/>"))
+        );
+        assert_eq!(
+            lore_api.get_patch_feed_update("test-list").await.unwrap(),
+            FeedUpdate::Unchanged
+        );
+
+        let state = state.lock().await;
+        let etags: Vec<Option<&ArcStr>> = state
+            .requests()
+            .iter()
+            .map(|request| request.headers.get(&ArcStr::from("If-None-Match")))
+            .collect();
+        assert_eq!(etags, [None, Some(&ArcStr::from("\"v1\""))]);
+    }
+
+    #[tokio::test]
+    async fn test_get_series_combined_diff() {
+        let entry = |number: usize, in_reply_to: &str| {
+            format!(
+                r#"<entry><title>[PATCH {number}/2] foo: fix lines</title><link href="https://lore.kernel.org/test-list/20231201.4-{}@amd.com/"/>{in_reply_to}</entry>"#,
+                number + 1
+            )
+        };
+        let reply =
+            r#"<thr:in-reply-to href="https://lore.kernel.org/test-list/20231201.4-1@amd.com/"/>"#;
+        let thread = format!(
+            "<feed>{}{}{}</feed>",
+            entry(0, ""),
+            entry(2, reply),
+            entry(1, reply)
+        );
+        let url = |path: &str| ArcStr::from(&format!("https://lore.kernel.org/test-list/{}", path));
+        let responses = HashMap::from([
+            (
+                MockRequestKey::get(url("20231201.4-3@amd.com/t.atom")),
+                ArcStr::from(&thread),
+            ),
+            (
+                MockRequestKey::get(url("20231201.4-2@amd.com/raw")),
+                ArcStr::from(include_str!("../../samples/series_1.patch")),
+            ),
+            (
+                MockRequestKey::get(url("20231201.4-3@amd.com/raw")),
+                ArcStr::from(include_str!("../../samples/series_2.patch")),
+            ),
+        ]);
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let diff = lore_api
+            .get_series_combined_diff("test-list", "20231201.4-3@amd.com")
+            .await
+            .unwrap();
+
+        assert!(diff.starts_with("diff --git a/src/foo.c b/src/foo.c\n"));
+        assert!(diff.contains("-line 4\n+line 4 fixed\n+line 4.75\n"));
+        assert!(!diff.contains("line 4.5"));
+        assert!(!diff.contains("line 21.5"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_subscribe_updates() {
+        let url = ArcStr::from("https://lore.kernel.org/amd-gfx/updates");
+        let event = |id: usize| {
+            let entry = format!(
+                r#"<entry><title>[PATCH] drm/amdgpu: fix {id}</title><link href="https://lore.kernel.org/amd-gfx/20231201.{id}-1@amd.com/"/></entry>"#
+            );
+            MockResponse::ok(ArcStr::from(&format!("id: {id}\ndata: {entry}\n\n")))
+                .header("Content-Type", "text/event-stream")
+        };
+        let mut state = MockState::default();
+        // The first connection drops after one event
+        state.push(MockRequestKey::get(url.clone()), event(1));
+        state.push(MockRequestKey::get(url.clone()), event(2));
+        state.insert(
+            MockRequestKey::get(ArcStr::from("https://lore.kernel.org/plain/updates")),
+            MockResponse::ok(ArcStr::from("<html></html>")).header("Content-Type", "text/html"),
+        );
+        let state = Arc::new(Mutex::new(state));
+        let lore_api = LoreApi::spawn(Net::mock_with_state(state.clone()));
+
+        let mut updates = lore_api.subscribe_updates("amd-gfx").await.unwrap();
+        let mut message_ids = Vec::new();
+        for _ in 0..2 {
+            let entry = std::future::poll_fn(|cx| std::pin::Pin::new(&mut updates).poll_next(cx))
+                .await
+                .unwrap()
+                .unwrap();
+            message_ids.push(entry.message_id);
+        }
+        assert_eq!(
+            message_ids,
+            [
+                ArcStr::from("20231201.1-1@amd.com"),
+                ArcStr::from("20231201.2-1@amd.com")
+            ]
+        );
+
+        {
+            let state = state.lock().await;
+            let requests = state.requests();
+            assert_eq!(
+                requests[0].headers.get(&ArcStr::from("Accept")),
+                Some(&ArcStr::from("text/event-stream"))
+            );
+            assert!(
+                !requests[0]
+                    .headers
+                    .contains_key(&ArcStr::from("Last-Event-ID"))
+            );
+            assert_eq!(
+                requests[1].headers.get(&ArcStr::from("Last-Event-ID")),
+                Some(&ArcStr::from("1"))
+            );
+        }
+
+        let err = lore_api.subscribe_updates("plain").await.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<LoreApiError>(),
+            Some(LoreApiError::UpdatesUnsupported { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_preview() {
+        let mut large = String::from(
+            "From: Jane Doe <jane@example.com>\n\
+             Subject: [PATCH] drm/amdgpu: rework\n\
+             \tevery table\n\
+             Message-ID: <20231201.5-1@amd.com>\n\
+             \n\
+             Rework every table.\n\
+             ---\n\
+             diff --git a/big.c b/big.c\n\
+             --- a/big.c\n\
+             +++ b/big.c\n",
+        );
+        for index in 0..2000 {
+            let line = index * 10 + 1;
+            large.push_str(&format!(
+                "@@ -{line},3 +{line},3 @@\n \tint a;\n-\tint b{index};\n+\tlong b{index};\n \tint c;\n"
+            ));
+        }
+        let url = |id: &str| ArcStr::from(&format!("https://lore.kernel.org/amd-gfx/{}/raw", id));
+        let mut state = MockState::default();
+        state.insert(
+            MockRequestKey::get(url("large")),
+            MockResponse::ok(ArcStr::from(&large)),
+        );
+        state.insert(
+            MockRequestKey::get(url("small")),
+            MockResponse::ok(ArcStr::from(include_str!("../../samples/multi_file.patch"))),
+        );
+        let state = Arc::new(Mutex::new(state));
+        let lore_api = LoreApi::spawn(Net::mock_with_state(state.clone()));
+
+        let preview = lore_api
+            .get_patch_preview("amd-gfx", "large", 4096)
+            .await
+            .unwrap();
+        assert!(preview.truncated);
+        assert_eq!(
+            preview.header("Subject"),
+            Some("[PATCH] drm/amdgpu: rework every table")
+        );
+        assert_eq!(preview.header("message-id"), Some("<20231201.5-1@amd.com>"));
+        let hunks = &preview.diff.files[0].hunks;
+        assert!(!hunks.is_empty() && hunks.len() < 2000);
+        assert!(hunks.iter().all(|hunk| hunk.is_complete()));
+        assert_eq!(
+            state.lock().await.requests()[0]
+                .headers
+                .get(&ArcStr::from("Range")),
+            Some(&ArcStr::from("bytes=0-4096"))
+        );
+
+        let preview = lore_api
+            .get_patch_preview("amd-gfx", "small", 1 << 20)
+            .await
+            .unwrap();
+        assert!(!preview.truncated);
+        assert_eq!(preview.diff.files.len(), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_refetches_feeds_past_ttl() {
+        let feed_url = ArcStr::from(
+            "https://lore.kernel.org/test-list/?x=A&q=((s:patch+OR+s:rfc)+AND+NOT+s:re:)&o=0",
+        );
+        let raw_url = ArcStr::from("https://lore.kernel.org/test-list/test-id/raw");
+        let mut state = MockState::default();
+        state.insert(
+            MockRequestKey::get(feed_url.clone()),
+            MockResponse::ok(ArcStr::from("<feed/>")),
+        );
+        state.insert(
+            MockRequestKey::get(raw_url.clone()),
+            MockResponse::ok(ArcStr::from("patch")),
+        );
+        let state = Arc::new(Mutex::new(state));
+        let lore_api = LoreApi::spawn_with_options(
+            Net::mock_with_state(state.clone()),
+            LoreApiOptions {
+                cache: true,
+                feed_ttl: Some(std::time::Duration::from_secs(60)),
+                ..Default::default()
+            },
+        );
+        let fetches = |url: ArcStr| {
+            let state = state.clone();
+            async move {
+                let state = state.lock().await;
+                state
+                    .requests()
+                    .iter()
+                    .filter(|request| request.url == url)
+                    .count()
+            }
+        };
+
+        for _ in 0..2 {
+            lore_api.get_patch_feed("test-list", 0).await.unwrap();
+            lore_api
+                .get_raw_patch("test-list", "test-id")
+                .await
+                .unwrap();
+        }
+        assert_eq!(fetches(feed_url.clone()).await, 1);
+        assert_eq!(fetches(raw_url.clone()).await, 1);
+
+        tokio::time::advance(std::time::Duration::from_secs(61)).await;
+        lore_api.get_patch_feed("test-list", 0).await.unwrap();
+        lore_api
+            .get_raw_patch("test-list", "test-id")
+            .await
+            .unwrap();
+        assert_eq!(fetches(feed_url).await, 2);
+        assert_eq!(fetches(raw_url).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_exists() {
+        let mut state = MockState::default();
+        state.insert(
+            MockRequestKey::head(ArcStr::from("https://lore.kernel.org/amd-gfx/")),
+            MockResponse::ok(ArcStr::from("")),
+        );
+        state.insert(
+            MockRequestKey::head(ArcStr::from("https://lore.kernel.org/amd-gxf/")),
+            MockResponse::with_status(404, ArcStr::from("")),
+        );
+        state.insert(
+            MockRequestKey::head(ArcStr::from("https://lore.kernel.org/broken/")),
+            MockResponse::with_status(500, ArcStr::from("")),
+        );
+        let lore_api = LoreApi::spawn(Net::mock_with_state(Arc::new(Mutex::new(state))));
+
+        assert!(lore_api.list_exists("amd-gfx").await.unwrap());
+        assert!(!lore_api.list_exists("amd-gxf").await.unwrap());
+        assert!(lore_api.list_exists("broken").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_patch_html_with_headers() {
+        let mut state = MockState::default();
+        state.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/test-message-id/",
+            )),
+            MockResponse::ok(ArcStr::from("<html/>"))
+                .header("Content-Type", "text/html; charset=UTF-8")
+                .header("ETag", "\"abc\"")
+                .header("Last-Modified", "Fri, 01 Dec 2023 12:34:56 GMT"),
+        );
+        let lore_api = LoreApi::spawn(Net::mock_with_state(Arc::new(Mutex::new(state))));
+
+        let (html, headers) = lore_api
+            .get_patch_html_with_headers("test-list", "test-message-id")
+            .await
+            .unwrap();
+        assert_eq!(html, ArcStr::from("<html/>"));
+        let header = |key: &str, value: &str| (ArcStr::from(key), ArcStr::from(value));
+        assert_eq!(
+            headers,
+            HashMap::from([
+                header("content-type", "text/html; charset=UTF-8"),
+                header("etag", "\"abc\""),
+                header("last-modified", "Fri, 01 Dec 2023 12:34:56 GMT"),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_raw_patch_with_headers() {
+        let mut responses = HashMap::new();
+        responses.insert("raw_patch_list_id".to_string(), ArcStr::from("patch"));
+        responses.insert(
+            "raw_patch_list_id_headers".to_string(),
+            ArcStr::from("Content-Type: text/plain\nETag: \"abc\""),
+        );
+        let lore_api = LoreApi::mock(responses);
+
+        let (raw_patch, headers) = lore_api
+            .get_raw_patch_with_headers("list", "id")
+            .await
+            .unwrap();
+        assert_eq!(raw_patch, ArcStr::from("patch"));
+        assert_eq!(
+            headers.get(&ArcStr::from("etag")),
+            Some(&ArcStr::from("\"abc\""))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_patches_by_author() {
+        let feed = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+<entry><author><name>Jane Doe</name><email>jane@example.com</email></author>
+<title>[PATCH] drm: fix b</title>
+<link href="https://lore.kernel.org/test-list/20231201.1-1@example.com/"/></entry>
+<entry><author><name>Jane Doe</name><email>jane@example.com</email></author>
+<title>[PATCH v2 1/2] drm: widen c</title>
+<link href="https://lore.kernel.org/test-list/20231202.1-1@example.com/"/></entry>
+</feed>"#;
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/?x=A\
+                 &q=%28%28s%3Apatch+OR+s%3Arfc%29+AND+NOT+s%3Are%3A%29+AND+f%3Ajane%40example.com\
+                 &o=200",
+            )),
+            ArcStr::from(feed),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let patches = lore_api
+            .get_patches_by_author("test-list", "Jane Doe <jane@example.com>", 200)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = patches.iter().map(|patch| &*patch.message_id).collect();
+        assert_eq!(
+            ids,
+            ["20231201.1-1@example.com", "20231202.1-1@example.com"]
+        );
+        assert!(
+            patches
+                .iter()
+                .all(|patch| patch.author_email == Some(ArcStr::from("jane@example.com")))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_weighted_mirrors_fail_over() {
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://mirror.example.org/test-list/test-message-id/raw",
+            )),
+            ArcStr::from("raw patch"),
+        );
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://mirror.example.org/test-list/?x=A&q=s%3Adrm&o=0",
+            )),
+            ArcStr::from(
+                r#"<feed><entry><title>[PATCH] drm: fix</title><link href="https://mirror.example.org/test-list/p-1/"/></entry></feed>"#,
+            ),
+        );
+        let state = Arc::new(Mutex::new(MockState::from(responses)));
+        let lore_api = LoreApi::spawn_with_options(
+            Net::mock_with_state(state.clone()),
+            LoreApiOptions {
+                mirrors: vec![
+                    (ArcStr::from("https://down.example.org"), 1),
+                    (ArcStr::from("https://mirror.example.org"), 1),
+                ],
+                mirror_seed: Some(3),
+                ..Default::default()
+            },
+        );
+
+        for _ in 0..10 {
+            let raw = lore_api
+                .get_raw_patch("test-list", "test-message-id")
+                .await
+                .unwrap();
+            assert_eq!(raw, ArcStr::from("raw patch"));
+
+            // Searches fail over too
+            let ids = lore_api
+                .query_message_ids("test-list", "s:drm")
+                .await
+                .unwrap();
+            assert_eq!(ids, [ArcStr::from("p-1")]);
+        }
+
+        let state = state.lock().await;
+        assert!(
+            state
+                .requests()
+                .iter()
+                .all(|request| !request.url.starts_with("https://lore.kernel.org"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_clone_urls() {
+        let page = r#"<html><body><pre>This public inbox may be cloned and mirrored by anyone:
+
+	git clone --mirror <a href="https://lore.kernel.org/lkml/0">https://lore.kernel.org/lkml/0</a> lkml/git/0.git
+	git clone --mirror https://lore.kernel.org/lkml/1 lkml/git/1.git
+	git clone --mirror https://lore.kernel.org/lkml/2 lkml/git/2.git
+
+	# If you have public-inbox 1.1+ installed, you may
+	# initialize and index your mirror using the following commands:
+	public-inbox-init -V2 lkml lkml/ https://lore.kernel.org/lkml
+</pre></body></html>"#;
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from("https://lore.kernel.org/lkml/_/text/mirror/")),
+            ArcStr::from(page),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let urls = lore_api.get_clone_urls("lkml").await.unwrap();
+        assert_eq!(
+            urls,
+            [
+                ArcStr::from("https://lore.kernel.org/lkml/0"),
+                ArcStr::from("https://lore.kernel.org/lkml/1"),
+                ArcStr::from("https://lore.kernel.org/lkml/2"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "git")]
+    #[tokio::test]
+    async fn test_get_raw_patch_via_git() {
+        let mirror = r#"<html><body><pre>This public inbox may be cloned and mirrored by anyone:
+
+	git clone --mirror https://lore.kernel.org/amd-gfx/0 amd-gfx/git/0.git
+	git clone --mirror https://lore.kernel.org/amd-gfx/1 amd-gfx/git/1.git
+</pre></body></html>"#;
+        let first = "Message-ID: <1-1@amd.com>\nSubject: [PATCH 1/2] drm: a\n\nfirst\n";
+        let second = "Message-ID: <1-2@amd.com>\nSubject: [PATCH 2/2] drm: b\n\nsecond\n";
+        let (mut objects, parent) = git::message_commit(first, None);
+        let (commit, head) = git::message_commit(second, Some(&parent));
+        objects.extend(commit);
+        let refs = format!(
+            "{}{}0000",
+            git::encode_packet(&format!("{} HEAD\n", head)),
+            git::encode_packet(&format!("{} refs/heads/master\n", head))
+        );
+
+        let upload_pack = ArcStr::from("https://lore.kernel.org/amd-gfx/1/git-upload-pack");
+        let mut state = MockState::default();
+        state.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/amd-gfx/_/text/mirror/",
+            )),
+            MockResponse::ok(ArcStr::from(mirror)),
+        );
+        for _ in 0..2 {
+            state.push(
+                MockRequestKey::post(upload_pack.clone()),
+                MockResponse::ok(ArcStr::from(&refs)),
+            );
+            state.push(
+                MockRequestKey::post(upload_pack.clone()),
+                MockResponse {
+                    status: 200,
+                    headers: HashMap::new(),
+                    body: Arc::from(git::fetch_response(&objects)),
+                },
+            );
+        }
+        let state = Arc::new(tokio::sync::Mutex::new(state));
+        let lore_api = LoreApi::spawn(Net::mock_with_state(state.clone()));
+
+        let raw_patch = lore_api
+            .get_raw_patch_via_git("amd-gfx", "1-1@amd.com")
+            .await
+            .unwrap();
+        assert_eq!(raw_patch, ArcStr::from(first));
+        {
+            let state = state.lock().await;
+            let fetch = &state.requests()[2];
+            assert_eq!(
+                fetch.headers.get(&ArcStr::from("Git-Protocol")),
+                Some(&ArcStr::from("version=2"))
+            );
+            assert!(
+                fetch
+                    .body
+                    .as_deref()
+                    .is_some_and(|body| body.contains(&format!("want {}", head)))
+            );
+        }
+
+        let err = lore_api
+            .get_raw_patch_via_git("amd-gfx", "missing@amd.com")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LoreApiError>(),
+            Some(&LoreApiError::NotInGitArchive {
+                message_id: ArcStr::from("missing@amd.com"),
+                url: ArcStr::from("https://lore.kernel.org/amd-gfx/1"),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_epochs() {
+        let page = r#"<html><body><pre>This public inbox may be cloned and mirrored by anyone:
+
+	git clone --mirror https://lore.kernel.org/lkml/0 lkml/git/0.git
+	git clone --mirror https://lore.kernel.org/lkml/1 lkml/git/1.git
+	git clone --mirror https://lore.kernel.org/lkml/2 lkml/git/2.git
+</pre></body></html>"#;
+        let single = r#"<html><body><pre>This public inbox may be cloned and mirrored by anyone:
+
+	git clone --mirror https://lore.kernel.org/git git.git
+</pre></body></html>"#;
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from("https://lore.kernel.org/lkml/_/text/mirror/")),
+            ArcStr::from(page),
+        );
+        responses.insert(
+            MockRequestKey::get(ArcStr::from("https://lore.kernel.org/git/_/text/mirror/")),
+            ArcStr::from(single),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let epochs = lore_api.list_epochs("lkml").await.unwrap();
+        assert_eq!(
+            epochs,
+            (0..3)
+                .map(|index| EpochInfo {
+                    index,
+                    clone_url: ArcStr::from(&format!("https://lore.kernel.org/lkml/{index}")),
+                })
+                .collect::<Vec<_>>()
+        );
+
+        let epochs = lore_api.list_epochs("git").await.unwrap();
+        assert_eq!(
+            epochs,
+            [EpochInfo {
+                index: 0,
+                clone_url: ArcStr::from("https://lore.kernel.org/git"),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_message_ids() {
+        let feed = r#"<feed>
+<entry><title>[PATCH] drm: fix b</title><link href="https://lore.kernel.org/test-list/p-1/"/></entry>
+<entry><title>[PATCH] drm: fix c</title><link href="https://lore.kernel.org/test-list/p-2/"/></entry>
+</feed>"#;
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/?x=A&q=s%3A%22drm%3A+fix%22+AND+f%3Ajane%40example.com+AND+d%3A20231201..20231215&o=0",
+            )),
+            ArcStr::from(feed),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let ids = lore_api
+            .query_message_ids(
+                "test-list",
+                r#"s:"drm: fix" f:jane@example.com and d:2023-12-01..2023-12-15"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids, [ArcStr::from("p-1"), ArcStr::from("p-2")]);
+        assert!(
+            lore_api
+                .query_message_ids("test-list", "s:drm OR s:amdgpu")
+                .await
+                .is_err()
+        );
+
+        let mock = LoreApi::mock(HashMap::from([(
+            "message_ids_test-list_s:drm AND f:jane".to_string(),
+            ArcStr::from(feed),
+        )]));
+        assert_eq!(
+            mock.query_message_ids("test-list", "s:drm && f:jane")
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_overview() {
+        let lore_api = LoreApi::mock(HashMap::from([(
+            "overview_amd-gfx_41200-41299".to_string(),
+            ArcStr::from(include_str!("../../samples/overview.txt")),
+        )]));
+        let entries = lore_api
+            .get_overview("amd-gfx", 41200..=41299)
+            .await
+            .unwrap();
+        assert_eq!(
+            entries.iter().map(|entry| entry.number).collect::<Vec<_>>(),
+            [41200, 41201, 41202]
+        );
+        assert_eq!(entries[0].message_id, ArcStr::from("20231201.0-1@amd.com"));
+
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/amd-gfx/_/text/config/raw",
+            )),
+            ArcStr::from(
+                r#"{"address": "amd-gfx@lists.freedesktop.org", "url": "https://lore.kernel.org/amd-gfx"}"#,
+            ),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+        let error = lore_api
+            .get_overview("amd-gfx", 41200..=41299)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains("No newsgroup published for list: amd-gfx"));
+    }
+
+    #[tokio::test]
+    async fn test_get_maintainers() {
+        let page = r#"<html><body><pre>amd-gfx: AMD graphics drivers
+
+Maintainers:
+  Jane Doe &lt;jane@example.com&gt;
+  M: John Roe &lt;john@example.com&gt;
+</pre></body></html>"#;
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from("https://lore.kernel.org/amd-gfx/_/text/help/")),
+            ArcStr::from(page),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let maintainers = lore_api.get_maintainers("amd-gfx").await.unwrap();
+        assert_eq!(
+            maintainers
+                .iter()
+                .map(|maintainer| maintainer.email.clone())
+                .collect::<Vec<_>>(),
+            [
+                ArcStr::from("jane@example.com"),
+                ArcStr::from("john@example.com"),
+            ]
+        );
+
+        let empty = LoreApi::mock(HashMap::from([(
+            "maintainers_amd-gfx".to_string(),
+            ArcStr::from("<html><pre>No maintainers here</pre></html>"),
+        )]));
+        assert!(empty.get_maintainers("amd-gfx").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_patch_feed() {
+        let feed = r#"<feed><entry><title>[PATCH] drm: fix b</title>
+<link href="https://lore.kernel.org/test-list/1@example.com/"/></entry>
+<entry><title>[PATCH] drm: fix c</title>
+<link href="https://lore.kernel.org/test-list/2@example.com/"/></entry></feed>"#;
+        let mut responses = HashMap::new();
+        responses.insert(
+            MockRequestKey::get(ArcStr::from(
+                "https://lore.kernel.org/test-list/?x=A&q=((s:patch+OR+s:rfc)+AND+NOT+s:re:)&o=0",
+            )),
+            ArcStr::from(feed),
+        );
+        let lore_api = LoreApi::spawn(Net::mock(responses));
+
+        let mut entries = lore_api.stream_patch_feed("test-list", 0).await.unwrap();
+        let mut ids = Vec::new();
+        while let Some(entry) =
+            std::future::poll_fn(|cx| std::pin::Pin::new(&mut entries).poll_next(cx)).await
+        {
+            ids.push(entry.unwrap().message_id);
+        }
+        assert_eq!(
+            ids,
+            [ArcStr::from("1@example.com"), ArcStr::from("2@example.com")]
+        );
     }
 }
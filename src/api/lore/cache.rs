@@ -0,0 +1,77 @@
+//! The cache of the responses of the Lore API actor.
+
+use std::{collections::HashMap, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::ArcStr;
+
+/// How long the responses of an operation may be served from the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CacheKind {
+    /// Content addressed by message ID, such as a patch, which never changes
+    Immutable,
+    /// Content that changes as messages arrive, such as a feed or an index
+    Listing,
+}
+
+/// Responses kept by key, each until its expiry, if any.
+///
+/// Feeds are keyed by URL and messages by list and normalized message ID, so a
+/// message asked for with a differently written ID is still found. Expired
+/// entries are only dropped when they are looked up, so the cache is meant for
+/// the bounded set of requests of a session rather than as a long-lived store.
+#[derive(Debug, Default)]
+pub(super) struct ResponseCache {
+    /// The cached responses and the instant they expire at
+    entries: HashMap<String, (ArcStr, Option<Instant>)>,
+}
+
+impl ResponseCache {
+    /// Looks up a response, dropping it if it expired.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the response
+    ///
+    /// # Returns
+    /// The cached response, or `None` if there is none or it expired.
+    pub(super) fn get(&mut self, key: &str) -> Option<ArcStr> {
+        let (body, expiry) = self.entries.get(key)?;
+        if expiry.is_some_and(|expiry| Instant::now() >= expiry) {
+            self.entries.remove(key);
+            return None;
+        }
+        Some(body.clone())
+    }
+
+    /// Caches a response.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the response
+    /// * `body` - The response
+    /// * `ttl` - How long the response may be served, `None` for ever
+    pub(super) fn insert(&mut self, key: &str, body: ArcStr, ttl: Option<Duration>) {
+        let expiry = ttl.map(|ttl| Instant::now() + ttl);
+        self.entries.insert(key.to_string(), (body, expiry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_entries_expire_on_access() {
+        let mut cache = ResponseCache::default();
+        cache.insert("feed", ArcStr::from("page"), Some(Duration::from_secs(60)));
+        cache.insert("raw", ArcStr::from("patch"), None);
+
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert_eq!(cache.get("feed"), Some(ArcStr::from("page")));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(cache.get("feed"), None);
+        assert!(!cache.entries.contains_key("feed"));
+        assert_eq!(cache.get("raw"), Some(ArcStr::from("patch")));
+    }
+}
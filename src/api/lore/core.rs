@@ -1,8 +1,46 @@
 use anyhow::Context;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::RangeInclusive,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 use tokio::task::JoinHandle;
 
-use crate::{ArcStr, net::Net, api::lore::message::LoreApiMessage};
+#[cfg(feature = "git")]
+use crate::api::lore::{data::EpochInfo, git};
+use crate::{
+    ArcStr,
+    api::lore::{
+        UrlRewriter,
+        cache::{CacheKind, ResponseCache},
+        data::{
+            FeedUpdate, InboxConfig, LoreApiError, LoreApiOptions, Maintainer, PatchPreview,
+            Series, SeriesVersion,
+        },
+        diff::ParsedDiff,
+        feed::{self, PatchEntry, PatchFeed},
+        mail,
+        message::{LoreApiMessage, LoreApiSender},
+        mirror::{MirrorRng, MirrorSelector},
+        nntp::{self, OverviewEntry},
+        patch,
+    },
+    net::{
+        BodyStream, MAX_LINK_PAGES, Net, NetError, NetRequest, NetResponse, ResponseStart,
+        RetryPolicy, message::HttpMethod,
+    },
+    utils::Shutdown,
+};
+
+/// How many messages the actor holds before their senders wait
+const MAILBOX_CAPACITY: usize = 100;
+
+/// How long an NNTP exchange may take as a whole
+const NNTP_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// The core of the Lore API system that handles Lore-specific HTTP requests.
 ///
@@ -13,6 +51,9 @@ use crate::{ArcStr, net::Net, api::lore::message::LoreApiMessage};
 /// # Features
 /// - Thread-safe Lore API operations through actor pattern
 /// - Domain-specific URL construction and request handling
+/// - Optional cap on the bytes downloaded by each operation
+/// - Optional response cache, keeping patches for ever and feeds for a TTL
+/// - NNTP overviews of the lists, read straight from the NNTP server
 /// - Integration with networking system
 /// - Proper error handling and context
 ///
@@ -31,6 +72,31 @@ pub struct Core {
     net: Net,
     /// The base domain for Lore API requests
     domain: ArcStr,
+    /// Weighted mirrors picked for each request instead of `domain`, if any
+    mirrors: Option<Mutex<MirrorSelector>>,
+    /// Default headers applied to the requests of each mailing list
+    list_headers: HashMap<ArcStr, HashMap<ArcStr, ArcStr>>,
+    /// ETag of the last fetched first page of the patch feed of each list
+    etags: HashMap<String, ArcStr>,
+    /// Maximum number of bytes downloaded by a single operation, if bounded
+    max_bytes: Option<usize>,
+    /// Number of bytes downloaded so far by the operation being handled,
+    /// shared by the messages of the operation
+    downloaded: Arc<AtomicUsize>,
+    /// Rewrites the URL of each request before it is sent, if set
+    url_rewriter: Option<UrlRewriter>,
+    /// The NNTP server the overviews of the lists are read from
+    nntp_server: ArcStr,
+    /// The cache of the responses, if enabled
+    cache: Option<Mutex<ResponseCache>>,
+    /// How long cached feeds and list indexes are served, if they are cached
+    feed_ttl: Option<Duration>,
+    /// Default headers applied to every request
+    headers: HashMap<ArcStr, ArcStr>,
+    /// Timeout of each attempt of a request, if it replaces the one of the networking actor
+    timeout: Option<Duration>,
+    /// Retry policy of the requests, if it replaces the one of the networking actor
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl Core {
@@ -42,10 +108,7 @@ impl Core {
     /// # Returns
     /// A new instance of `Core` configured for the Lore Kernel Archive.
     pub fn new(net: Net) -> Self {
-        Self {
-            net,
-            domain: ArcStr::from("https://lore.kernel.org"),
-        }
+        Self::with_options(net, LoreApiOptions::default())
     }
 
     /// Creates a new Lore API core instance with a custom domain.
@@ -57,14 +120,57 @@ impl Core {
     /// # Returns
     /// A new instance of `Core` configured with the specified domain.
     pub fn with_domain(net: Net, domain: ArcStr) -> Self {
-        Self { net, domain }
+        Self::with_options(
+            net,
+            LoreApiOptions {
+                domain,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Creates a new Lore API core instance with the given options.
+    ///
+    /// # Arguments
+    /// * `net` - The networking actor for making HTTP requests
+    /// * `options` - The settings for the Lore API actor
+    ///
+    /// # Returns
+    /// A new instance of `Core` configured with the specified options.
+    pub fn with_options(net: Net, options: LoreApiOptions) -> Self {
+        let mirrors = (!options.mirrors.is_empty()).then(|| {
+            let rng = match options.mirror_seed {
+                Some(seed) => MirrorRng::with_seed(seed),
+                None => MirrorRng::from_entropy(),
+            };
+            Mutex::new(MirrorSelector::new(options.mirrors, rng))
+        });
+
+        Self {
+            net,
+            domain: options.domain,
+            mirrors,
+            list_headers: options.list_headers,
+            etags: HashMap::new(),
+            max_bytes: options.max_bytes,
+            downloaded: Arc::default(),
+            url_rewriter: options.url_rewriter,
+            nntp_server: options.nntp_server,
+            cache: options.cache.then(|| Mutex::new(ResponseCache::default())),
+            feed_ttl: options.feed_ttl,
+            headers: options.headers,
+            timeout: options.timeout,
+            retry_policy: options.retry_policy,
+        }
     }
 
     /// Transforms the Lore API core instance into an actor.
     ///
     /// This method spawns a new task that will handle Lore API operations
     /// asynchronously through a message channel. All operations are processed
-    /// sequentially to ensure consistency.
+    /// sequentially to ensure consistency. A shutdown message stops the actor
+    /// once the messages sent before it are handled, or aborts what is left of
+    /// them at its deadline.
     ///
     /// # Returns
     /// A tuple containing:
@@ -73,99 +179,412 @@ impl Core {
     ///
     /// # Panics
     /// This function will panic if the underlying task fails to spawn.
-    pub fn spawn(self) -> (crate::api::lore::LoreApi, JoinHandle<()>) {
-        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+    pub fn spawn(mut self) -> (crate::api::lore::LoreApi, JoinHandle<()>) {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(MAILBOX_CAPACITY);
 
         let handle = tokio::spawn(async move {
-            while let Some(message) = rx.recv().await {
-                match message {
-                    LoreApiMessage::GetPatchFeed {
-                        target_list,
-                        min_index,
-                        tx,
-                    } => {
-                        let response = self
-                            .handle_get_patch_feed(&target_list, min_index)
-                            .await
-                            .with_context(|| format!("GET patch feed failed for list: {}", target_list));
-                        let _ = tx.send(response);
-                    }
-                    LoreApiMessage::GetAvailableLists { min_index, tx } => {
-                        let response = self
-                            .handle_get_available_lists(min_index)
-                            .await
-                            .with_context(|| format!("GET available lists failed for index: {}", min_index));
-                        let _ = tx.send(response);
-                    }
-                    LoreApiMessage::GetPatchHtml {
-                        target_list,
-                        message_id,
-                        tx,
-                    } => {
-                        let response = self
-                            .handle_get_patch_html(&target_list, &message_id)
-                            .await
-                            .with_context(|| {
-                                format!(
-                                    "GET patch HTML failed for list: {}, message: {}",
-                                    target_list, message_id
-                                )
-                            });
-                        let _ = tx.send(response);
-                    }
-                    LoreApiMessage::GetRawPatch {
-                        target_list,
-                        message_id,
-                        tx,
-                    } => {
-                        let response = self
-                            .handle_get_raw_patch(&target_list, &message_id)
-                            .await
-                            .with_context(|| {
-                                format!(
-                                    "GET raw patch failed for list: {}, message: {}",
-                                    target_list, message_id
-                                )
-                            });
-                        let _ = tx.send(response);
-                    }
-                    LoreApiMessage::GetPatchMetadata {
-                        target_list,
-                        message_id,
-                        tx,
-                    } => {
-                        let response = self
-                            .handle_get_patch_metadata(&target_list, &message_id)
-                            .await
-                            .with_context(|| {
-                                format!(
-                                    "GET patch metadata failed for list: {}, message: {}",
-                                    target_list, message_id
-                                )
-                            });
-                        let _ = tx.send(response);
+            // Messages received while another one is handled, so a shutdown
+            // behind them is seen in time
+            let mut waiting = VecDeque::new();
+            let mut open = true;
+            let mut shutdown = None;
+            let mut forced = false;
+            'actor: loop {
+                let message = match waiting.pop_front() {
+                    Some(message) => message,
+                    None if open => match rx.recv().await {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    None => break,
+                };
+                if let LoreApiMessage::Shutdown { timeout, tx } = message {
+                    // The messages already sent are still handled, the later
+                    // ones fail to be sent
+                    rx.close();
+                    Shutdown::request(&mut shutdown, timeout, tx);
+                    continue;
+                }
+
+                let handled = self.handle_message(message);
+                tokio::pin!(handled);
+                loop {
+                    let deadline = Shutdown::deadline(&shutdown);
+                    tokio::select! {
+                        biased;
+                        _ = Shutdown::expired(deadline) => {
+                            forced = true;
+                            break 'actor;
+                        }
+                        _ = &mut handled => break,
+                        message = rx.recv(), if open && waiting.len() < MAILBOX_CAPACITY => {
+                            match message {
+                                Some(LoreApiMessage::Shutdown { timeout, tx }) => {
+                                    rx.close();
+                                    Shutdown::request(&mut shutdown, timeout, tx);
+                                }
+                                Some(message) => waiting.push_back(message),
+                                None => open = false,
+                            }
+                        }
                     }
                 }
             }
+
+            if let Some(shutdown) = shutdown {
+                shutdown.acknowledge(forced);
+            }
         });
 
-        (crate::api::lore::LoreApi::Actual(tx), handle)
+        (
+            crate::api::lore::LoreApi::Actual(LoreApiSender::new(tx)),
+            handle,
+        )
+    }
+
+    /// Handles a message, answering on its response channel
+    async fn handle_message(&mut self, message: LoreApiMessage) {
+        let message = match message {
+            LoreApiMessage::InOperation {
+                downloaded,
+                message,
+            } => {
+                self.downloaded = downloaded;
+                *message
+            }
+            message => {
+                self.downloaded = Arc::default();
+                message
+            }
+        };
+        match message {
+            LoreApiMessage::GetPatchFeed {
+                target_list,
+                min_index,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patch_feed(&target_list, min_index)
+                    .await
+                    .with_context(|| format!("GET patch feed failed for list: {}", target_list));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetArchivePage {
+                target_list,
+                min_index,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_archive_page(&target_list, min_index)
+                    .await
+                    .with_context(|| format!("GET archive page failed for list: {}", target_list));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetAvailableLists { min_index, tx } => {
+                let response = self
+                    .handle_get_available_lists(min_index)
+                    .await
+                    .with_context(|| {
+                        format!("GET available lists failed for index: {}", min_index)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchHtml {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patch_html(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "GET patch HTML failed for list: {}, message: {}",
+                            target_list, message_id
+                        )
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetRawPatch {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_raw_patch(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "GET raw patch failed for list: {}, message: {}",
+                            target_list, message_id
+                        )
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchPreview {
+                target_list,
+                message_id,
+                max_bytes,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patch_preview(&target_list, &message_id, max_bytes)
+                    .await
+                    .with_context(|| {
+                        format!("GET patch preview failed for message: {}", message_id)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchMetadata {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patch_metadata(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "GET patch metadata failed for list: {}, message: {}",
+                            target_list, message_id
+                        )
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchFingerprint {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patch_fingerprint(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Computing patch fingerprint failed for list: {}, message: {}",
+                            target_list, message_id
+                        )
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetInboxConfig { target_list, tx } => {
+                let response = self
+                    .handle_get_inbox_config(&target_list)
+                    .await
+                    .with_context(|| format!("GET inbox config failed for list: {}", target_list));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::ListSeriesVersions {
+                target_list,
+                subject_base,
+                tx,
+            } => {
+                let response = self
+                    .handle_list_series_versions(&target_list, &subject_base)
+                    .await
+                    .with_context(|| {
+                        format!("Listing series versions failed for list: {}", target_list)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::FindCrosspostLists { message_id, tx } => {
+                let response = self
+                    .handle_find_crosspost_lists(&message_id)
+                    .await
+                    .with_context(|| {
+                        format!("Cross-post search failed for message: {}", message_id)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetMessageText {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_message_text(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!("GET message text failed for message: {}", message_id)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetAttachments {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_raw_patch(&target_list, &message_id)
+                    .await
+                    .map(|raw_message| mail::attachments(&raw_message))
+                    .with_context(|| format!("GET attachments failed for message: {}", message_id));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetSeriesCombinedDiff {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_series_combined_diff(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!("Combining series diff failed for message: {}", message_id)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchFeedUpdate { target_list, tx } => {
+                let response = self
+                    .handle_get_patch_feed_update(&target_list)
+                    .await
+                    .with_context(|| {
+                        format!("GET patch feed update failed for list: {}", target_list)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::ListExists { target_list, tx } => {
+                let response = self
+                    .handle_list_exists(&target_list)
+                    .await
+                    .with_context(|| {
+                        format!("Checking existence failed for list: {}", target_list)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchHtmlWithHeaders {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patch_html_with_headers(&target_list, &message_id)
+                    .await
+                    .with_context(|| format!("GET patch HTML failed for message: {}", message_id));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetRawPatchWithHeaders {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_raw_patch_with_headers(&target_list, &message_id)
+                    .await
+                    .with_context(|| format!("GET raw patch failed for message: {}", message_id));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetPatchesByAuthor {
+                target_list,
+                author,
+                min_index,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_patches_by_author(&target_list, &author, min_index)
+                    .await
+                    .with_context(|| {
+                        format!("GET patches by author failed for author: {}", author)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetCloneUrls { target_list, tx } => {
+                let response = self
+                    .handle_get_clone_urls(&target_list)
+                    .await
+                    .with_context(|| format!("GET clone URLs failed for list: {}", target_list));
+                let _ = tx.send(response);
+            }
+            #[cfg(feature = "git")]
+            LoreApiMessage::GetRawPatchViaGit {
+                target_list,
+                message_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_raw_patch_via_git(&target_list, &message_id)
+                    .await
+                    .with_context(|| {
+                        format!("GET raw patch via git failed for message: {}", message_id)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetMaintainers { target_list, tx } => {
+                let response = self
+                    .handle_get_maintainers(&target_list)
+                    .await
+                    .with_context(|| format!("GET maintainers failed for list: {}", target_list));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::QueryMessageIds {
+                target_list,
+                lei_query,
+                tx,
+            } => {
+                let response = self
+                    .handle_query_message_ids(&target_list, &lei_query)
+                    .await
+                    .with_context(|| format!("Search failed for lei query: {}", lei_query));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::GetOverview {
+                target_list,
+                range,
+                tx,
+            } => {
+                let response = self
+                    .handle_get_overview(&target_list, range)
+                    .await
+                    .with_context(|| format!("Reading overview failed for list: {}", target_list));
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::StreamPatchFeed {
+                target_list,
+                min_index,
+                tx,
+            } => {
+                let response = self
+                    .handle_stream_patch_feed(&target_list, min_index)
+                    .await
+                    .with_context(|| {
+                        format!("GET patch feed stream failed for list: {}", target_list)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::OpenUpdates {
+                target_list,
+                last_event_id,
+                tx,
+            } => {
+                let response = self
+                    .handle_open_updates(&target_list, last_event_id)
+                    .await
+                    .with_context(|| {
+                        format!("Opening live updates failed for list: {}", target_list)
+                    });
+                let _ = tx.send(response);
+            }
+            LoreApiMessage::InOperation { .. } => unreachable!("Operations aren't nested"),
+            LoreApiMessage::Shutdown { .. } => unreachable!("The actor loop handles its shutdown"),
+        }
     }
 
     /// Handles GET patch feed requests
-    async fn handle_get_patch_feed(&self, target_list: &str, min_index: usize) -> anyhow::Result<ArcStr> {
-        let url = format!(
-            "{}/{}/?x=A&q=((s:patch+OR+s:rfc)+AND+NOT+s:re:)&o={}",
-            self.domain, target_list, min_index
-        );
+    async fn handle_get_patch_feed(
+        &self,
+        target_list: &str,
+        min_index: usize,
+    ) -> anyhow::Result<ArcStr> {
+        let url = self.patch_feed_url(target_list, min_index);
 
-        let mut headers = HashMap::new();
-        headers.insert(
-            ArcStr::from("Accept"),
-            ArcStr::from("text/html,application/xhtml+xml,application/xml"),
+        let headers = self.headers(
+            Some(target_list),
+            "text/html,application/xhtml+xml,application/xml",
         );
 
-        let response = self.net.get(ArcStr::from(&url), Some(headers)).await?;
+        let response = self
+            .get_cached(&url, &url, headers, CacheKind::Listing)
+            .await?;
 
         // Check for end of feed indicator
         if <ArcStr as AsRef<str>>::as_ref(&response) == "</feed>" {
@@ -175,49 +594,703 @@ impl Core {
         Ok(response)
     }
 
+    /// Handles GET archive page requests, listing every message, replies included
+    async fn handle_get_archive_page(
+        &self,
+        target_list: &str,
+        min_index: usize,
+    ) -> anyhow::Result<ArcStr> {
+        let url = format!(
+            "{}/{}/?x=A&q=rt:..&o={}",
+            self.domain, target_list, min_index
+        );
+        let headers = self.headers(Some(target_list), "application/atom+xml");
+
+        self.get_cached(&url, &url, headers, CacheKind::Listing)
+            .await
+    }
+
     /// Handles GET available lists requests
     async fn handle_get_available_lists(&self, min_index: usize) -> anyhow::Result<ArcStr> {
         let url = format!("{}/?&o={}", self.domain, min_index);
 
-        let mut headers = HashMap::new();
-        headers.insert(
-            ArcStr::from("Accept"),
-            ArcStr::from("text/html,application/xhtml+xml,application/xml"),
-        );
+        let headers = self.headers(None, "text/html,application/xhtml+xml,application/xml");
 
-        self.net.get(ArcStr::from(&url), Some(headers)).await
+        self.get_cached(&url, &url, headers, CacheKind::Listing)
+            .await
     }
 
     /// Handles GET patch HTML requests
-    async fn handle_get_patch_html(&self, target_list: &str, message_id: &str) -> anyhow::Result<ArcStr> {
+    async fn handle_get_patch_html(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
         let url = format!("{}/{}/{}/", self.domain, target_list, message_id);
+        let key = message_key(target_list, message_id, "html");
 
-        let mut headers = HashMap::new();
-        headers.insert(
-            ArcStr::from("Accept"),
-            ArcStr::from("text/html,application/xhtml+xml,application/xml"),
+        let headers = self.headers(
+            Some(target_list),
+            "text/html,application/xhtml+xml,application/xml",
         );
 
-        self.net.get(ArcStr::from(&url), Some(headers)).await
+        self.get_cached(&key, &url, headers, CacheKind::Immutable)
+            .await
     }
 
     /// Handles GET raw patch requests
-    async fn handle_get_raw_patch(&self, target_list: &str, message_id: &str) -> anyhow::Result<ArcStr> {
+    async fn handle_get_raw_patch(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
+        let url = format!("{}/{}/{}/raw", self.domain, target_list, message_id);
+        let key = message_key(target_list, message_id, "raw");
+
+        let headers = self.headers(Some(target_list), "text/plain");
+
+        self.get_cached(&key, &url, headers, CacheKind::Immutable)
+            .await
+    }
+
+    /// Handles patch preview requests
+    async fn handle_get_patch_preview(
+        &self,
+        target_list: &str,
+        message_id: &str,
+        max_bytes: usize,
+    ) -> anyhow::Result<PatchPreview> {
         let url = format!("{}/{}/{}/raw", self.domain, target_list, message_id);
 
-        let mut headers = HashMap::new();
-        headers.insert(ArcStr::from("Accept"), ArcStr::from("text/plain"));
+        let headers = self.headers(Some(target_list), "text/plain");
 
-        self.net.get(ArcStr::from(&url), Some(headers)).await
+        // One byte over the budget tells whether the patch is longer
+        let prefix = self
+            .get_prefix(&url, max_bytes.saturating_add(1), headers)
+            .await?;
+        Ok(PatchPreview::parse(&prefix, max_bytes))
     }
 
     /// Handles GET patch metadata requests
-    async fn handle_get_patch_metadata(&self, target_list: &str, message_id: &str) -> anyhow::Result<ArcStr> {
+    async fn handle_get_patch_metadata(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
         let url = format!("{}/{}/{}/json", self.domain, target_list, message_id);
+        let key = message_key(target_list, message_id, "json");
+
+        let headers = self.headers(Some(target_list), "application/json");
+
+        self.get_cached(&key, &url, headers, CacheKind::Immutable)
+            .await
+    }
+
+    /// Handles patch fingerprint requests
+    async fn handle_get_patch_fingerprint(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
+        let raw_patch = self.handle_get_raw_patch(target_list, message_id).await?;
+        patch::fingerprint(&raw_patch).ok_or_else(|| anyhow::anyhow!("No diff found in patch"))
+    }
+
+    /// Handles GET inbox config requests
+    async fn handle_get_inbox_config(&self, target_list: &str) -> anyhow::Result<InboxConfig> {
+        let url = format!("{}/{}/_/text/config/raw", self.domain, target_list);
+
+        let headers = self.headers(Some(target_list), "text/plain,application/json");
+
+        let response = self.get(&url, headers).await?;
+        InboxConfig::parse(&response)
+            .with_context(|| format!("No public-inbox config published for list: {}", target_list))
+    }
+
+    /// Handles series versions requests
+    async fn handle_list_series_versions(
+        &self,
+        target_list: &str,
+        subject_base: &str,
+    ) -> anyhow::Result<Vec<SeriesVersion>> {
+        let feed = self
+            .handle_search(target_list, &format!("s:\"{}\"", subject_base), 0)
+            .await?;
+        Ok(SeriesVersion::from_entries(
+            &feed::parse_entries(&feed),
+            subject_base,
+        ))
+    }
+
+    /// Handles combined series diff requests
+    async fn handle_get_series_combined_diff(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
+        let url = format!("{}/{}/{}/t.atom", self.domain, target_list, message_id);
+        let headers = self.headers(Some(target_list), "application/atom+xml");
+        let thread = self.get(&url, headers).await?;
+
+        let series = Series::from_entries(&feed::parse_entries(&thread))
+            .into_iter()
+            .find(|series| {
+                series
+                    .cover
+                    .iter()
+                    .chain(&series.patches)
+                    .any(|entry| mail::message_ids_equal(&entry.message_id, message_id))
+            })
+            .ok_or_else(|| anyhow::anyhow!("No series found in the thread of {}", message_id))?;
+
+        let mut diffs = Vec::with_capacity(series.patches.len());
+        for patch in &series.patches {
+            let raw_patch = self
+                .handle_get_raw_patch(target_list, &patch.message_id)
+                .await
+                .with_context(|| format!("Fetching patch: {}", patch.message_id))?;
+            let text = mail::MailPart::parse(&raw_patch)
+                .text_part()
+                .map_or(raw_patch, |part| part.decoded_body());
+            diffs.push(ParsedDiff::parse(&text));
+        }
+
+        Ok(ParsedDiff::combine(&diffs)?.to_unified())
+    }
+
+    /// Searches a mailing list and returns a page of the results as an Atom feed
+    async fn handle_search(
+        &self,
+        target_list: &str,
+        query: &str,
+        min_index: usize,
+    ) -> anyhow::Result<ArcStr> {
+        let mut url = reqwest::Url::parse(&format!("{}/{}/", self.domain, target_list))
+            .context("Building search URL")?;
+        url.query_pairs_mut()
+            .append_pair("x", "A")
+            .append_pair("q", query)
+            .append_pair("o", &min_index.to_string());
+
+        let headers = self.headers(Some(target_list), "application/atom+xml");
+
+        self.get(url.as_str(), headers).await
+    }
+
+    /// Handles cross-post list requests, searching every list of the server at once
+    async fn handle_find_crosspost_lists(&self, message_id: &str) -> anyhow::Result<Vec<ArcStr>> {
+        let message_id = mail::normalize_message_id(message_id);
+        let feed = self
+            .handle_search("all", &format!("m:{}", message_id), 0)
+            .await?;
+        Ok(feed::crosspost_lists(&feed, &message_id))
+    }
+
+    /// Handles message text requests
+    async fn handle_get_message_text(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
+        let raw_message = self.handle_get_raw_patch(target_list, message_id).await?;
+        mail::message_text(&raw_message)
+            .ok_or_else(|| anyhow::anyhow!("No text/plain part found in message"))
+    }
+
+    /// Handles conditional patch feed requests, remembering the ETag of each list
+    async fn handle_get_patch_feed_update(
+        &mut self,
+        target_list: &str,
+    ) -> anyhow::Result<FeedUpdate> {
+        let url = self.patch_feed_url(target_list, 0);
+
+        let mut headers = self.headers(
+            Some(target_list),
+            "text/html,application/xhtml+xml,application/xml",
+        );
+        if let Some(etag) = self.etags.get(target_list) {
+            headers.insert(ArcStr::from("If-None-Match"), etag.clone());
+        }
+
+        let request = NetRequest::new(HttpMethod::Get, ArcStr::from(&url), Some(headers), None);
+        let response = self.request(request).await?;
+        match response.status {
+            304 => Ok(FeedUpdate::Unchanged),
+            200..=299 => {
+                match response.headers.get(&ArcStr::from("etag")) {
+                    Some(etag) => self.etags.insert(target_list.to_string(), etag.clone()),
+                    None => self.etags.remove(target_list),
+                };
+                Ok(FeedUpdate::Changed(response.text()))
+            }
+            status => Err(anyhow::anyhow!(
+                "Unexpected status {} for URL: {}",
+                status,
+                url
+            )),
+        }
+    }
 
-        let mut headers = HashMap::new();
-        headers.insert(ArcStr::from("Accept"), ArcStr::from("application/json"));
+    /// Builds the URL of a page of the patch feed of a list
+    fn patch_feed_url(&self, target_list: &str, min_index: usize) -> String {
+        format!(
+            "{}/{}/?x=A&q=((s:patch+OR+s:rfc)+AND+NOT+s:re:)&o={}",
+            self.domain, target_list, min_index
+        )
+    }
+
+    /// Handles list existence requests
+    async fn handle_list_exists(&self, target_list: &str) -> anyhow::Result<bool> {
+        let url = format!("{}/{}/", self.domain, target_list);
+
+        let headers = self.headers(Some(target_list), "text/html");
+
+        let request = NetRequest::new(HttpMethod::Head, ArcStr::from(&url), Some(headers), None);
+        let response = self.request(request).await?;
+        match response.status {
+            200..=299 => Ok(true),
+            404 => Ok(false),
+            status => Err(anyhow::anyhow!(
+                "Unexpected status {} for URL: {}",
+                status,
+                url
+            )),
+        }
+    }
+
+    /// Handles GET patch HTML requests that keep the response headers
+    async fn handle_get_patch_html_with_headers(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<(ArcStr, HashMap<ArcStr, ArcStr>)> {
+        let url = format!("{}/{}/{}/", self.domain, target_list, message_id);
+
+        let headers = self.headers(
+            Some(target_list),
+            "text/html,application/xhtml+xml,application/xml",
+        );
+
+        self.get_with_headers(&url, headers).await
+    }
 
-        self.net.get(ArcStr::from(&url), Some(headers)).await
+    /// Handles GET raw patch requests that keep the response headers
+    async fn handle_get_raw_patch_with_headers(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<(ArcStr, HashMap<ArcStr, ArcStr>)> {
+        let url = format!("{}/{}/{}/raw", self.domain, target_list, message_id);
+
+        let headers = self.headers(Some(target_list), "text/plain");
+
+        self.get_with_headers(&url, headers).await
+    }
+
+    /// Performs a GET request and returns its body along with the response headers
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: HashMap<ArcStr, ArcStr>,
+    ) -> anyhow::Result<(ArcStr, HashMap<ArcStr, ArcStr>)> {
+        let request = NetRequest::new(HttpMethod::Get, ArcStr::from(url), Some(headers), None);
+        let response = self.request(request).await?;
+        Ok((response.text(), response.headers))
+    }
+
+    /// Performs a GET request through the response cache, if enabled for its kind,
+    /// storing the response under the given key
+    async fn get_cached(
+        &self,
+        key: &str,
+        url: &str,
+        headers: HashMap<ArcStr, ArcStr>,
+        kind: CacheKind,
+    ) -> anyhow::Result<ArcStr> {
+        let ttl = match kind {
+            CacheKind::Immutable => None,
+            CacheKind::Listing => match self.feed_ttl {
+                Some(ttl) => Some(ttl),
+                None => return self.get(url, headers).await,
+            },
+        };
+        let Some(cache) = &self.cache else {
+            return self.get(url, headers).await;
+        };
+
+        if let Some(response) = cache.lock().unwrap().get(key) {
+            return Ok(response);
+        }
+        let response = self.get(url, headers).await?;
+        cache.lock().unwrap().insert(key, response.clone(), ttl);
+        Ok(response)
+    }
+
+    /// Performs a GET request, failing over across the mirrors
+    async fn get(&self, url: &str, headers: HashMap<ArcStr, ArcStr>) -> anyhow::Result<ArcStr> {
+        let mut last_error = None;
+        for domain in self.domains() {
+            let url = self.rewrite(self.on_domain(url, &domain));
+            match self.get_once(url, headers.clone()).await {
+                Ok(response) => {
+                    self.count(response.len())?;
+                    return Ok(response);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Lore domain to send the request to")))
+    }
+
+    /// Performs a GET request on a single domain, with the timeout and retry
+    /// policy of the actor if it has any
+    async fn get_once(
+        &self,
+        url: ArcStr,
+        headers: HashMap<ArcStr, ArcStr>,
+    ) -> anyhow::Result<ArcStr> {
+        if self.timeout.is_none() && self.retry_policy.is_none() {
+            return self.net.get(url, Some(headers)).await;
+        }
+        let request = self.prepared(NetRequest::new(HttpMethod::Get, url, Some(headers), None));
+        let response = self.net.request(request.clone()).await?;
+        Ok(request.check_status(response)?.text())
+    }
+
+    /// Fetches the start of a body, failing over across the mirrors
+    async fn get_prefix(
+        &self,
+        url: &str,
+        max_bytes: usize,
+        headers: HashMap<ArcStr, ArcStr>,
+    ) -> anyhow::Result<Arc<[u8]>> {
+        let mut last_error = None;
+        for domain in self.domains() {
+            let url = self.rewrite(self.on_domain(url, &domain));
+            match self.get_prefix_once(url, max_bytes, headers.clone()).await {
+                Ok(prefix) => {
+                    self.count(prefix.len())?;
+                    return Ok(prefix);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Lore domain to send the request to")))
+    }
+
+    /// Fetches the start of a body on a single domain, with the timeout and
+    /// retry policy of the actor if it has any
+    async fn get_prefix_once(
+        &self,
+        url: ArcStr,
+        max_bytes: usize,
+        headers: HashMap<ArcStr, ArcStr>,
+    ) -> anyhow::Result<Arc<[u8]>> {
+        if max_bytes == 0 || (self.timeout.is_none() && self.retry_policy.is_none()) {
+            return self.net.get_prefix(url, max_bytes, Some(headers)).await;
+        }
+        let request = self.prepared(
+            NetRequest::new(HttpMethod::Get, url, Some(headers), None).with_prefix(max_bytes),
+        );
+        let response = self.net.request(request.clone()).await?;
+        Ok(request.check_status(response)?.body)
+    }
+
+    /// Performs a request, failing over across the mirrors
+    async fn request(&self, request: NetRequest) -> anyhow::Result<NetResponse> {
+        let mut last_error = None;
+        for domain in self.domains() {
+            let mut request = self.prepared(request.clone());
+            request.url = self.rewrite(self.on_domain(&request.url, &domain));
+            match self.net.request(request).await {
+                Ok(response) => {
+                    self.count(response.body.len())?;
+                    return Ok(response);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Lore domain to send the request to")))
+    }
+
+    /// Applies the timeout and retry policy of the actor to a request that doesn't set its own
+    fn prepared(&self, mut request: NetRequest) -> NetRequest {
+        request.timeout = request.timeout.or(self.timeout);
+        request.retry_policy = request.retry_policy.or(self.retry_policy);
+        request
+    }
+
+    /// Counts downloaded bytes against the limit of the operation being handled
+    fn count(&self, bytes: usize) -> anyhow::Result<()> {
+        let downloaded = self.downloaded.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        match self.max_bytes {
+            Some(limit) if downloaded > limit => {
+                Err(LoreApiError::TooLarge { limit, downloaded }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Draws the domains to try for a request, in order
+    fn domains(&self) -> Vec<ArcStr> {
+        match &self.mirrors {
+            Some(mirrors) => mirrors
+                .lock()
+                .map(|mut mirrors| mirrors.order())
+                .unwrap_or_else(|_| vec![self.domain.clone()]),
+            None => vec![self.domain.clone()],
+        }
     }
-} 
\ No newline at end of file
+
+    /// Applies the URL rewriter, if any, to the final URL of a request
+    fn rewrite(&self, url: ArcStr) -> ArcStr {
+        match &self.url_rewriter {
+            Some(rewriter) => rewriter.rewrite(url),
+            None => url,
+        }
+    }
+
+    /// Moves a URL built on the base domain to another domain
+    fn on_domain(&self, url: &str, domain: &str) -> ArcStr {
+        match url.strip_prefix(&*self.domain) {
+            Some(path) => ArcStr::from(&format!("{}{}", domain, path)),
+            None => ArcStr::from(url),
+        }
+    }
+
+    /// Handles GET patches by author requests
+    async fn handle_get_patches_by_author(
+        &self,
+        target_list: &str,
+        author: &str,
+        min_index: usize,
+    ) -> anyhow::Result<Vec<PatchEntry>> {
+        let query = format!(
+            "((s:patch OR s:rfc) AND NOT s:re:) AND {}",
+            feed::author_term(author)
+        );
+        let feed = self.handle_search(target_list, &query, min_index).await?;
+        Ok(feed::parse_entries(&feed))
+    }
+
+    /// Handles GET clone URLs requests
+    async fn handle_get_clone_urls(&self, target_list: &str) -> anyhow::Result<Vec<ArcStr>> {
+        let url = format!("{}/{}/_/text/mirror/", self.domain, target_list);
+        let headers = self.headers(Some(target_list), "text/html,text/plain");
+
+        let page = self.get(&url, headers).await?;
+        Ok(feed::clone_urls(&page))
+    }
+
+    /// Handles GET raw patch via git requests, searching the latest commits of
+    /// the newest epoch of the archive
+    #[cfg(feature = "git")]
+    async fn handle_get_raw_patch_via_git(
+        &self,
+        target_list: &str,
+        message_id: &str,
+    ) -> anyhow::Result<ArcStr> {
+        let epochs = EpochInfo::from_clone_urls(self.handle_get_clone_urls(target_list).await?);
+        let newest = epochs
+            .last()
+            .with_context(|| format!("No git archive advertised for list: {}", target_list))?;
+        let not_found = || LoreApiError::NotInGitArchive {
+            message_id: ArcStr::from(message_id),
+            url: newest.clone_url.clone(),
+        };
+        let url = format!("{}/git-upload-pack", newest.clone_url.trim_end_matches('/'));
+
+        let refs = self
+            .upload_pack(target_list, &url, git::ls_refs_request())
+            .await?;
+        let Some(head) = git::parse_head(&refs)? else {
+            return Err(not_found().into());
+        };
+        let response = self
+            .upload_pack(
+                target_list,
+                &url,
+                git::fetch_request(&head, git::SEARCH_DEPTH),
+            )
+            .await?;
+        let objects = git::parse_pack(&git::packfile(&response)?)?;
+        git::find_message(&objects, message_id).ok_or_else(|| not_found().into())
+    }
+
+    /// Sends a request of the version 2 of the git protocol to an upload-pack endpoint
+    #[cfg(feature = "git")]
+    async fn upload_pack(
+        &self,
+        target_list: &str,
+        url: &str,
+        body: String,
+    ) -> anyhow::Result<Arc<[u8]>> {
+        let mut headers = self.headers(Some(target_list), git::UPLOAD_PACK_RESULT);
+        headers.insert(
+            ArcStr::from("Content-Type"),
+            ArcStr::from(git::UPLOAD_PACK_REQUEST),
+        );
+        headers.insert(ArcStr::from("Git-Protocol"), ArcStr::from("version=2"));
+        let request = NetRequest::new(
+            HttpMethod::Post,
+            ArcStr::from(url),
+            Some(headers),
+            Some(ArcStr::from(&body)),
+        );
+        let response = self.request(request.clone()).await?;
+        Ok(request.check_status(response)?.body)
+    }
+
+    /// Handles GET maintainers requests
+    async fn handle_get_maintainers(&self, target_list: &str) -> anyhow::Result<Vec<Maintainer>> {
+        let url = format!("{}/{}/_/text/help/", self.domain, target_list);
+        let headers = self.headers(Some(target_list), "text/html,text/plain");
+
+        let page = self.get(&url, headers).await?;
+        Ok(feed::maintainers(&page))
+    }
+
+    /// Handles lei query requests, following the pages of the search results
+    async fn handle_query_message_ids(
+        &self,
+        target_list: &str,
+        lei_query: &str,
+    ) -> anyhow::Result<Vec<ArcStr>> {
+        let query = feed::lei_query(lei_query)?;
+
+        let mut message_ids = Vec::new();
+        let mut min_index = 0;
+        for _ in 0..MAX_LINK_PAGES {
+            let page = PatchFeed::parse(&self.handle_search(target_list, &query, min_index).await?);
+            message_ids.extend(page.entries.iter().map(|entry| entry.message_id.clone()));
+            match page.next_offset() {
+                Some(next) if next > min_index => min_index = next,
+                _ => return Ok(message_ids),
+            }
+        }
+        anyhow::bail!(
+            "Search results span more than {} pages, narrow the query",
+            MAX_LINK_PAGES
+        )
+    }
+
+    /// Handles overview requests, reading the newsgroup of the list from its inbox config
+    async fn handle_get_overview(
+        &self,
+        target_list: &str,
+        range: RangeInclusive<u64>,
+    ) -> anyhow::Result<Vec<OverviewEntry>> {
+        let config = self.handle_get_inbox_config(target_list).await?;
+        let newsgroup = config
+            .newsgroup
+            .with_context(|| format!("No newsgroup published for list: {}", target_list))?;
+
+        let data = tokio::time::timeout(
+            NNTP_TIMEOUT,
+            nntp::fetch_overview(&self.nntp_server, &newsgroup, range),
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("NNTP exchange timed out after {:?}", NNTP_TIMEOUT))??;
+        self.count(data.len())?;
+        Ok(nntp::parse_overview(&data))
+    }
+
+    /// Handles streamed GET patch feed requests, failing over across the mirrors
+    async fn handle_stream_patch_feed(
+        &self,
+        target_list: &str,
+        min_index: usize,
+    ) -> anyhow::Result<BodyStream> {
+        let url = self.patch_feed_url(target_list, min_index);
+        let headers = self.headers(
+            Some(target_list),
+            "text/html,application/xhtml+xml,application/xml",
+        );
+
+        let mut last_error = None;
+        for domain in self.domains() {
+            let url = self.rewrite(self.on_domain(&url, &domain));
+            match self.net.get_stream(url, Some(headers.clone())).await {
+                Ok(body) => return Ok(body),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Lore domain to send the request to")))
+    }
+
+    /// Opens the event stream of the live updates of a list, failing over across the mirrors
+    async fn handle_open_updates(
+        &self,
+        target_list: &str,
+        last_event_id: Option<ArcStr>,
+    ) -> anyhow::Result<BodyStream> {
+        let url = format!("{}/{}/updates", self.domain, target_list);
+        let mut headers = self.headers(Some(target_list), "text/event-stream");
+        headers.insert(ArcStr::from("Cache-Control"), ArcStr::from("no-cache"));
+        if let Some(id) = last_event_id {
+            headers.insert(ArcStr::from("Last-Event-ID"), id);
+        }
+
+        let mut last_error = None;
+        for domain in self.domains() {
+            let url = self.rewrite(self.on_domain(&url, &domain));
+            match self
+                .net
+                .get_response_start(url.clone(), Some(headers.clone()))
+                .await
+            {
+                Ok(start) => match event_stream(url, start) {
+                    Ok(body) => return Ok(body),
+                    Err(err) => last_error = Some(err),
+                },
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Lore domain to send the request to")))
+    }
+
+    /// Builds the headers of a request, applying the default headers of the
+    /// actor, then the ones of the target list, so the headers set by the
+    /// request itself take precedence
+    fn headers(&self, target_list: Option<&str>, accept: &str) -> HashMap<ArcStr, ArcStr> {
+        let mut headers = self.headers.clone();
+        if let Some(list_headers) =
+            target_list.and_then(|list| self.list_headers.get(&ArcStr::from(list)))
+        {
+            headers.extend(list_headers.clone());
+        }
+        headers.insert(ArcStr::from("Accept"), ArcStr::from(accept));
+        headers
+    }
+}
+
+/// Builds the cache key of a view of a message, so that equivalent message IDs share it
+fn message_key(target_list: &str, message_id: &str, view: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        target_list,
+        mail::normalize_message_id(message_id),
+        view
+    )
+}
+
+/// Checks that a response opens an event stream.
+///
+/// Mirrors without live updates either don't know the URL or answer it with
+/// another kind of content.
+fn event_stream(url: ArcStr, start: ResponseStart) -> anyhow::Result<BodyStream> {
+    let is_event_stream = start
+        .headers
+        .get(&ArcStr::from("content-type"))
+        .is_some_and(|kind| kind.starts_with("text/event-stream"));
+    match start.status {
+        200..300 if is_event_stream => Ok(start.body),
+        200..300 | 404 | 405 | 406 | 501 => Err(LoreApiError::UpdatesUnsupported { url }.into()),
+        status => Err(NetError::Status {
+            method: HttpMethod::Get,
+            url,
+            status,
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,981 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::{
+    ArcStr,
+    api::lore::{
+        diff::ParsedDiff,
+        feed::PatchEntry,
+        mail::{MailPart, normalize_message_id},
+        patch::PatchSubject,
+    },
+    net::RetryPolicy,
+};
+
+/// Settings used when spawning a Lore API actor.
+///
+/// # Examples
+/// ```
+/// let options = LoreApiOptions {
+///     domain: ArcStr::from("https://lore.kernel.org"),
+///     ..Default::default()
+/// };
+/// let lore_api = LoreApi::spawn_with_options(net, options);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoreApiOptions {
+    /// The base domain for API requests
+    pub domain: ArcStr,
+    /// Default headers applied to every request targeting a mailing list,
+    /// keyed by the list name. Headers set by the request itself take precedence.
+    pub list_headers: HashMap<ArcStr, HashMap<ArcStr, ArcStr>>,
+    /// Weighted mirrors to spread the requests across. When not empty, each
+    /// request goes to a mirror drawn by weight instead of `domain`, failing
+    /// over to the other mirrors on error.
+    pub mirrors: Vec<(ArcStr, u32)>,
+    /// Seed of the mirror selection, for reproducible runs. A random seed is
+    /// used when not given.
+    pub mirror_seed: Option<u64>,
+    /// Maximum number of bytes a single operation may download across all of
+    /// its requests, including the operations fetching several documents and
+    /// those made through [`crate::api::lore::LoreApi::operation`]. Operations
+    /// going over it fail with [`LoreApiError::TooLarge`]. Unbounded when not given.
+    pub max_bytes: Option<usize>,
+    /// Rewrites the URL of every request once it is built for the domain or
+    /// mirror it is sent to, to adapt to mirrors with a different path layout.
+    pub url_rewriter: Option<UrlRewriter>,
+    /// The NNTP server the overviews of the lists are read from, as `host:port`
+    pub nntp_server: ArcStr,
+    /// Caches the responses of the actor. The HTML, raw and metadata of a
+    /// message never change and are kept for the life of the actor, feeds and
+    /// list indexes are only cached for `feed_ttl`.
+    pub cache: bool,
+    /// How long cached feeds and list indexes are served before being fetched
+    /// again. They aren't cached when not given.
+    pub feed_ttl: Option<Duration>,
+    /// Default headers applied to every request, under the headers of its
+    /// mailing list and the ones set by the request itself
+    pub headers: HashMap<ArcStr, ArcStr>,
+    /// Timeout of each attempt of a request, replacing the default timeout of
+    /// the networking actor. Streamed requests only bound their headers with it.
+    pub timeout: Option<Duration>,
+    /// Retry policy of the requests, replacing the one of the networking actor
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+impl Default for LoreApiOptions {
+    fn default() -> Self {
+        Self {
+            domain: ArcStr::from("https://lore.kernel.org"),
+            list_headers: HashMap::new(),
+            mirrors: Vec::new(),
+            mirror_seed: None,
+            max_bytes: None,
+            url_rewriter: None,
+            nntp_server: ArcStr::from("nntp.lore.kernel.org:119"),
+            cache: false,
+            feed_ttl: None,
+            headers: HashMap::new(),
+            timeout: None,
+            retry_policy: None,
+        }
+    }
+}
+
+impl LoreApiOptions {
+    /// Gets the settings used by [`crate::api::lore::LoreApi::kernel_org`].
+    ///
+    /// Requests go to `https://lore.kernel.org` with a `User-Agent` naming
+    /// this crate, time out after 30 seconds and are retried up to 3 times,
+    /// backing off from 1 second to 10 seconds and giving up after 1 minute.
+    ///
+    /// # Returns
+    /// The settings, to be adjusted before spawning if needed.
+    pub fn kernel_org() -> Self {
+        Self {
+            domain: ArcStr::from("https://lore.kernel.org"),
+            headers: HashMap::from([(
+                ArcStr::from("User-Agent"),
+                ArcStr::from(KERNEL_ORG_USER_AGENT),
+            )]),
+            timeout: Some(Duration::from_secs(30)),
+            retry_policy: Some(RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_secs(1),
+                max_backoff: Some(Duration::from_secs(10)),
+                max_total_elapsed: Some(Duration::from_secs(60)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// The `User-Agent` sent by [`LoreApiOptions::kernel_org`], so the admins of
+/// kernel.org can tell who is sending the requests
+const KERNEL_ORG_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (lore.kernel.org client)"
+);
+
+/// A function rewriting the URLs of the requests of a Lore API actor.
+///
+/// # Examples
+/// ```
+/// let rewriter = UrlRewriter::new(|url| ArcStr::from(&url.replacen("/r/", "/", 1)));
+/// ```
+#[derive(Clone)]
+pub struct UrlRewriter(Arc<dyn Fn(ArcStr) -> ArcStr + Send + Sync>);
+
+impl UrlRewriter {
+    /// Creates a URL rewriter from a function.
+    ///
+    /// # Arguments
+    /// * `rewrite` - Maps the URL of a request to the URL to send it to
+    ///
+    /// # Returns
+    /// A new URL rewriter.
+    pub fn new(rewrite: impl Fn(ArcStr) -> ArcStr + Send + Sync + 'static) -> Self {
+        Self(Arc::new(rewrite))
+    }
+
+    /// Rewrites a URL.
+    ///
+    /// # Arguments
+    /// * `url` - The URL built for a request
+    ///
+    /// # Returns
+    /// The URL to send the request to.
+    pub fn rewrite(&self, url: ArcStr) -> ArcStr {
+        (self.0)(url)
+    }
+}
+
+impl fmt::Debug for UrlRewriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UrlRewriter")
+    }
+}
+
+/// Errors reported by the Lore API actor that callers may want to tell apart.
+///
+/// They are wrapped in [`anyhow::Error`] like any other failure and can be
+/// recovered with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LoreApiError {
+    /// An operation downloaded more than the configured maximum
+    #[error("Operation downloaded {downloaded} bytes, over the limit of {limit} bytes")]
+    TooLarge {
+        /// The maximum number of bytes of an operation
+        limit: usize,
+        /// The number of bytes downloaded when the operation was aborted
+        downloaded: usize,
+    },
+    /// The message isn't among the latest commits of the git archive of the list
+    #[error("Message {message_id} not found in the git archive at URL: {url}")]
+    NotInGitArchive {
+        /// The ID of the message looked for
+        message_id: ArcStr,
+        /// The clone URL of the epoch searched
+        url: ArcStr,
+    },
+    /// A feed parsed in strict mode is incomplete or inconsistent
+    #[error("Inconsistent feed: {reason}")]
+    InconsistentFeed {
+        /// What the feed failed
+        reason: ArcStr,
+    },
+    /// Messages expected to be patches of one series aren't
+    #[error("Messages aren't patches of a single series: {reason}")]
+    NotOneSeries {
+        /// How the messages differ
+        reason: ArcStr,
+    },
+    /// The mirror doesn't serve live updates as Server-Sent Events
+    #[error("Live updates aren't supported by the mirror at URL: {url}")]
+    UpdatesUnsupported {
+        /// The URL of the update stream
+        url: ArcStr,
+    },
+}
+
+/// The offset of a page of a feed, counted in items.
+///
+/// Lore paginates its feeds and listings with the number of items to skip, not
+/// with page numbers. The constructors make the unit explicit at the call
+/// site, and plain `usize` offsets are still accepted where a `FeedOffset` is
+/// expected, as item counts.
+///
+/// # Examples
+/// ```
+/// let feed = lore_api.get_patch_feed("amd-gfx", FeedOffset::from_page(2, 200)).await?;
+/// let same = lore_api.get_patch_feed("amd-gfx", 400).await?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FeedOffset(usize);
+
+impl FeedOffset {
+    /// Creates the offset skipping the given number of items.
+    ///
+    /// # Arguments
+    /// * `items` - The number of items to skip
+    ///
+    /// # Returns
+    /// The offset of the page starting after those items.
+    pub fn from_items(items: usize) -> Self {
+        Self(items)
+    }
+
+    /// Creates the offset of a page of the given size.
+    ///
+    /// # Arguments
+    /// * `page` - The number of the page, counting from 0
+    /// * `page_size` - The number of items of each page
+    ///
+    /// # Returns
+    /// The offset of the first item of the page, saturating at `usize::MAX`.
+    pub fn from_page(page: usize, page_size: usize) -> Self {
+        Self(page.saturating_mul(page_size))
+    }
+
+    /// Gets the number of items the offset skips.
+    pub fn items(self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for FeedOffset {
+    fn from(items: usize) -> Self {
+        Self::from_items(items)
+    }
+}
+
+/// The outcome of a conditional fetch of a patch feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedUpdate {
+    /// The feed changed since the last fetch, with its new content
+    Changed(ArcStr),
+    /// The feed is the same as in the last fetch
+    Unchanged,
+}
+
+/// A message of a mailing list, as handed over by
+/// [`crate::api::lore::LoreApi::for_each_message`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawMessage {
+    /// The entry of the message in the feed of the archive
+    pub entry: PatchEntry,
+    /// The raw content of the message, headers included
+    pub content: ArcStr,
+}
+
+/// How a batch operation handles the failure of one of its items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchMode {
+    /// Fetch every item and return the result of each one
+    #[default]
+    Collect,
+    /// Abort the whole batch and return the first error as soon as an item fails
+    FailFast,
+}
+
+/// The configuration of a public-inbox archive, as published by Lore.
+///
+/// Public-inbox exposes the configuration snippet of each inbox, which allows
+/// discovering the settings of a mailing list. Deployments publish it either in
+/// the `git-config` format used by public-inbox itself or as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InboxConfig {
+    /// The posting address of the mailing list
+    pub address: ArcStr,
+    /// The URL of the archive
+    pub url: ArcStr,
+    /// A short description of the mailing list, if published
+    #[serde(default)]
+    pub description: Option<ArcStr>,
+    /// The NNTP newsgroup mirroring the mailing list, if any
+    #[serde(default)]
+    pub newsgroup: Option<ArcStr>,
+}
+
+impl InboxConfig {
+    /// Parses an inbox configuration published as JSON or in the `git-config` format.
+    ///
+    /// For the `git-config` format, only the first `[publicinbox "..."]` section is
+    /// read and, for keys given more than once, the first value is used.
+    ///
+    /// # Arguments
+    /// * `content` - The published configuration
+    ///
+    /// # Returns
+    /// The parsed configuration, or an error if it lacks the address or the URL.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        if content.trim_start().starts_with('{') {
+            return serde_json::from_str(content).context("Parsing inbox config JSON");
+        }
+
+        let mut in_section = false;
+        let mut values: HashMap<String, ArcStr> = HashMap::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                if in_section {
+                    break;
+                }
+                in_section = line.starts_with("[publicinbox ");
+                continue;
+            }
+            if !in_section || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                values
+                    .entry(key.trim().to_lowercase())
+                    .or_insert_with(|| ArcStr::from(value.trim()));
+            }
+        }
+
+        Ok(Self {
+            address: values
+                .remove("address")
+                .context("Inbox config has no address")?,
+            url: values.remove("url").context("Inbox config has no url")?,
+            description: values.remove("description"),
+            newsgroup: values.remove("newsgroup"),
+        })
+    }
+}
+
+/// A mailing list archived on Lore, as listed on the index of the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailingList {
+    /// The name of the list, as used in the URLs of its archive
+    pub name: ArcStr,
+    /// The URL of the archive of the list, as linked by the index
+    pub url: ArcStr,
+    /// The description of the list, if given
+    pub description: Option<ArcStr>,
+}
+
+/// An epoch of the git archive of a mailing list.
+///
+/// public-inbox splits the archive of large lists into several repositories,
+/// `0.git`, `1.git` and so on, each holding the messages of a period of time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochInfo {
+    /// The number of the epoch, counting from 0
+    pub index: usize,
+    /// The URL to clone the repository of the epoch from
+    pub clone_url: ArcStr,
+}
+
+impl EpochInfo {
+    /// Numbers the clone URLs of the archive of a list.
+    ///
+    /// The number of an epoch is read from the last segment of its URL, as in
+    /// `https://lore.kernel.org/lkml/2`. A URL without a number, as advertised
+    /// by archives kept in a single repository, is numbered by its position.
+    ///
+    /// # Arguments
+    /// * `clone_urls` - The clone URLs of the archive, in the order of its mirroring page
+    ///
+    /// # Returns
+    /// The epochs, oldest first.
+    pub fn from_clone_urls(clone_urls: Vec<ArcStr>) -> Vec<Self> {
+        let mut epochs: Vec<Self> = clone_urls
+            .into_iter()
+            .enumerate()
+            .map(|(position, clone_url)| {
+                let segment = clone_url.trim_end_matches('/').rsplit('/').next();
+                let index = segment
+                    .map(|segment| segment.trim_end_matches(".git"))
+                    .and_then(|number| number.parse().ok())
+                    .unwrap_or(position);
+                Self { index, clone_url }
+            })
+            .collect();
+        epochs.sort_by_key(|epoch| epoch.index);
+        epochs
+    }
+}
+
+/// A maintainer of a mailing list, as published on its information page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Maintainer {
+    /// The name of the maintainer, if given
+    pub name: Option<ArcStr>,
+    /// The email address of the maintainer
+    pub email: ArcStr,
+}
+
+impl Maintainer {
+    /// Parses a maintainer line.
+    ///
+    /// Lines can be given as `Name <email>` or as a bare email address,
+    /// optionally preceded by a list bullet or the `M:` tag of the kernel
+    /// `MAINTAINERS` file.
+    ///
+    /// # Arguments
+    /// * `line` - The line to parse
+    ///
+    /// # Returns
+    /// The maintainer, or `None` if the line has no email address.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim().trim_start_matches(['-', '*']).trim_start();
+        let line = line.strip_prefix("M:").unwrap_or(line).trim();
+
+        if let (Some(start), Some(end)) = (line.find('<'), line.rfind('>'))
+            && start < end
+        {
+            let email = line[start + 1..end].trim();
+            let name = line[..start].trim().trim_matches('"').trim();
+            return email.contains('@').then(|| Self {
+                name: (!name.is_empty()).then(|| ArcStr::from(name)),
+                email: ArcStr::from(email),
+            });
+        }
+
+        (line.contains('@') && !line.contains(char::is_whitespace)).then(|| Self {
+            name: None,
+            email: ArcStr::from(line),
+        })
+    }
+}
+
+/// A revision of a patch series found on a mailing list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesVersion {
+    /// The revision number, `1` for the untagged first revision
+    pub version: usize,
+    /// The message ID of the cover letter of the revision, or of its first
+    /// patch when the revision has no cover letter
+    pub cover_message_id: ArcStr,
+    /// The number of patches in the revision
+    pub patch_count: usize,
+}
+
+impl SeriesVersion {
+    /// Groups the feed entries of a series by revision.
+    ///
+    /// Only entries whose subject, without its tags, matches `subject_base`
+    /// (ignoring case) are considered, and replies are ignored. When a revision
+    /// was posted more than once, the first matching message wins.
+    ///
+    /// # Arguments
+    /// * `entries` - The feed entries to group
+    /// * `subject_base` - The subject of the series, without its tags
+    ///
+    /// # Returns
+    /// The revisions found, sorted by version.
+    pub fn from_entries(entries: &[PatchEntry], subject_base: &str) -> Vec<Self> {
+        let subject_base = subject_base.trim();
+        // Sorting key of the message picked for each version, lower is better
+        let mut versions: BTreeMap<usize, (usize, Self)> = BTreeMap::new();
+
+        for entry in entries {
+            let Some(subject) = PatchSubject::parse(&entry.title) else {
+                continue;
+            };
+            if !subject.title.eq_ignore_ascii_case(subject_base) {
+                continue;
+            }
+
+            let (rank, patch_count) = match subject.number {
+                Some((index, total)) => (index, total),
+                None => (0, 1),
+            };
+            let candidate = Self {
+                version: subject.version,
+                cover_message_id: entry.message_id.clone(),
+                patch_count,
+            };
+            match versions.get(&subject.version) {
+                Some((best, _)) if *best <= rank => {}
+                _ => {
+                    versions.insert(subject.version, (rank, candidate));
+                }
+            }
+        }
+
+        versions.into_values().map(|(_, version)| version).collect()
+    }
+}
+
+/// A patch series found in a feed, or a standalone patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Series {
+    /// The cover letter of the series, if it has one and it is in the feed
+    pub cover: Option<PatchEntry>,
+    /// The patches of the series found in the feed, in their order in the series
+    pub patches: Vec<PatchEntry>,
+}
+
+/// A series being grouped: its cover letter, if found yet, and its patches
+/// with their position in the series
+type SeriesParts = (Option<PatchEntry>, Vec<(usize, PatchEntry)>);
+
+impl Series {
+    /// Groups the entries of a feed into series.
+    ///
+    /// A numbered patch (`[PATCH m/n]` with `m > 0`) joins the series of the
+    /// message it replies to when that message is numbered with the same
+    /// version and total, as `git send-email` threads a series either under its
+    /// cover letter or under its first patch. Patches replying to the same
+    /// message outside of the feed are grouped together, since their cover is
+    /// on another page. Every other message starts its own series, so
+    /// standalone patches form single-patch series. Replies are ignored.
+    /// Message IDs are compared once normalized with [`normalize_message_id`].
+    ///
+    /// # Arguments
+    /// * `entries` - The feed entries to group
+    ///
+    /// # Returns
+    /// The series, in the order their first message appears in the feed.
+    pub fn from_entries(entries: &[PatchEntry]) -> Vec<Self> {
+        let subjects: Vec<Option<PatchSubject>> = entries
+            .iter()
+            .map(|entry| PatchSubject::parse(&entry.title))
+            .collect();
+        let ids: Vec<ArcStr> = entries
+            .iter()
+            .map(|entry| normalize_message_id(&entry.message_id))
+            .collect();
+        let positions: HashMap<&ArcStr, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (id, index))
+            .collect();
+
+        // Finds the key of the series of an entry by walking up its thread
+        let series_key = |mut index: usize| -> String {
+            for _ in 0..entries.len() {
+                let Some(subject) = &subjects[index] else {
+                    break;
+                };
+                let (Some((position, total)), Some(parent)) =
+                    (subject.number, &entries[index].in_reply_to)
+                else {
+                    break;
+                };
+                if position == 0 {
+                    break;
+                }
+                let parent = normalize_message_id(parent);
+                match positions.get(&parent) {
+                    Some(&parent_index) => match &subjects[parent_index] {
+                        Some(parent_subject)
+                            if parent_subject.version == subject.version
+                                && parent_subject.number.map(|(_, total)| total) == Some(total) =>
+                        {
+                            index = parent_index;
+                        }
+                        _ => break,
+                    },
+                    None => return format!("{} v{} {}", parent, subject.version, total),
+                }
+            }
+            ids[index].to_string()
+        };
+
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        let mut series: Vec<SeriesParts> = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let Some(subject) = &subjects[index] else {
+                continue;
+            };
+            let slot = *keys.entry(series_key(index)).or_insert_with(|| {
+                series.push((None, Vec::new()));
+                series.len() - 1
+            });
+            match subject.number {
+                Some((0, _)) => {
+                    series[slot].0.get_or_insert_with(|| entry.clone());
+                }
+                Some((position, _)) => series[slot].1.push((position, entry.clone())),
+                None => series[slot].1.push((1, entry.clone())),
+            }
+        }
+
+        series
+            .into_iter()
+            .map(|(cover, mut patches)| {
+                patches.sort_by_key(|(position, _)| *position);
+                Self {
+                    cover,
+                    patches: patches.into_iter().map(|(_, patch)| patch).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Statistics of the recent activity of a mailing list, as computed by
+/// [`crate::api::lore::LoreApi::list_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListStats {
+    /// The number of messages
+    pub total: usize,
+    /// The number of messages of each author, keyed by email address, or by
+    /// name for authors without one
+    pub per_author: HashMap<ArcStr, usize>,
+    /// The number of messages updated on each day, in UTC
+    pub per_day: BTreeMap<NaiveDate, usize>,
+    /// The number of series with a cover letter or several numbered patches,
+    /// standalone patches left out
+    pub series_count: usize,
+}
+
+impl ListStats {
+    /// Aggregates the statistics of feed entries.
+    ///
+    /// Messages without an author are only counted in the total, and so are
+    /// messages without a valid update time in the days. Series are grouped
+    /// with [`Series::from_entries`].
+    ///
+    /// # Arguments
+    /// * `entries` - The feed entries, each message once
+    ///
+    /// # Returns
+    /// The statistics of the entries, zeroed if there are none.
+    pub fn from_entries(entries: &[PatchEntry]) -> Self {
+        let mut stats = Self {
+            total: entries.len(),
+            ..Default::default()
+        };
+        for entry in entries {
+            if let Some(author) = entry.author_email.as_ref().or(entry.author_name.as_ref()) {
+                *stats.per_author.entry(author.clone()).or_default() += 1;
+            }
+            let day = entry
+                .updated
+                .as_deref()
+                .and_then(|updated| chrono::DateTime::parse_from_rfc3339(updated).ok())
+                .map(|updated| updated.to_utc().date_naive());
+            if let Some(day) = day {
+                *stats.per_day.entry(day).or_default() += 1;
+            }
+        }
+        stats.series_count = Series::from_entries(entries)
+            .iter()
+            .filter(|series| {
+                series.cover.is_some()
+                    || series.patches.iter().any(|patch| {
+                        PatchSubject::parse(&patch.title)
+                            .and_then(|subject| subject.number)
+                            .is_some_and(|(_, total)| total > 1)
+                    })
+            })
+            .count();
+        stats
+    }
+}
+
+/// How recently a mailing list saw a message, as classified by
+/// [`crate::api::lore::LoreApi::list_activity_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActivityStatus {
+    /// The newest message is more recent than the active threshold
+    Active,
+    /// The newest message is older than the active threshold but more recent
+    /// than the dormant one
+    Dormant,
+    /// The newest message is older than the dormant threshold, or the list has
+    /// no message with a valid update time
+    Inactive,
+}
+
+/// The ages of the newest message of a mailing list separating its
+/// [`ActivityStatus`] classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityThresholds {
+    /// Age under which the list is [`ActivityStatus::Active`], 30 days by default
+    pub active: Duration,
+    /// Age under which the list is [`ActivityStatus::Dormant`], one year by default
+    pub dormant: Duration,
+}
+
+impl Default for ActivityThresholds {
+    fn default() -> Self {
+        Self {
+            active: Duration::from_secs(30 * 24 * 60 * 60),
+            dormant: Duration::from_secs(365 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl ActivityThresholds {
+    /// Classifies a mailing list from its newest messages.
+    ///
+    /// The newest update time among the entries is used, whatever their order.
+    /// Entries without a valid update time are ignored, and a message dated in
+    /// the future counts as brand new.
+    ///
+    /// # Arguments
+    /// * `entries` - The newest entries of the feed of the list
+    /// * `now` - The time the age of the newest message is measured at
+    ///
+    /// # Returns
+    /// The activity status of the list, [`ActivityStatus::Inactive`] if no
+    /// entry has a valid update time.
+    pub fn classify(&self, entries: &[PatchEntry], now: DateTime<Utc>) -> ActivityStatus {
+        let newest = entries
+            .iter()
+            .filter_map(|entry| entry.updated.as_deref())
+            .filter_map(|updated| DateTime::parse_from_rfc3339(updated).ok())
+            .map(|updated| updated.to_utc())
+            .max();
+        let Some(newest) = newest else {
+            return ActivityStatus::Inactive;
+        };
+
+        let age = (now - newest).to_std().unwrap_or_default();
+        if age < self.active {
+            ActivityStatus::Active
+        } else if age < self.dormant {
+            ActivityStatus::Dormant
+        } else {
+            ActivityStatus::Inactive
+        }
+    }
+}
+
+/// The start of a patch, as fetched by [`crate::api::lore::LoreApi::get_patch_preview`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchPreview {
+    /// The unfolded headers of the message, with lowercase names, in order
+    pub headers: Vec<(String, String)>,
+    /// The files and hunks of the diff that fit in the preview
+    pub diff: ParsedDiff,
+    /// Whether the patch is longer than the preview
+    pub truncated: bool,
+}
+
+impl PatchPreview {
+    /// Parses the start of a raw patch.
+    ///
+    /// At most `max_bytes` bytes are read. When the patch is longer, its last
+    /// line is dropped unless complete, and so is the last hunk of the diff, so
+    /// the preview only holds whole hunks. Passing one byte more than the
+    /// budget tells a patch of exactly `max_bytes` bytes from a longer one.
+    ///
+    /// # Arguments
+    /// * `raw` - The start of the raw patch, possibly longer than `max_bytes`
+    /// * `max_bytes` - The maximum number of bytes to read
+    ///
+    /// # Returns
+    /// The preview of the patch.
+    pub fn parse(raw: &[u8], max_bytes: usize) -> Self {
+        let truncated = raw.len() > max_bytes;
+        let mut raw = &raw[..raw.len().min(max_bytes)];
+        if truncated {
+            let end = raw
+                .iter()
+                .rposition(|&byte| byte == b'\n')
+                .map_or(0, |newline| newline + 1);
+            raw = &raw[..end];
+        }
+        let raw = String::from_utf8_lossy(raw);
+
+        let message = MailPart::parse(&raw);
+        let text = message
+            .text_part()
+            .map_or(ArcStr::from(&*raw), |part| part.decoded_body());
+        let mut diff = ParsedDiff::parse(&text);
+        if truncated
+            && let Some(file) = diff.files.last_mut()
+            && file.hunks.last().is_some_and(|hunk| !hunk.is_complete())
+        {
+            file.hunks.pop();
+        }
+
+        Self {
+            headers: message.headers,
+            diff,
+            truncated,
+        }
+    }
+
+    /// Gets the value of the first header with the given name.
+    ///
+    /// # Arguments
+    /// * `name` - The header name, in any case
+    ///
+    /// # Returns
+    /// The header value, or `None` if the header is missing or was cut off.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::lore::feed;
+
+    #[test]
+    fn test_inbox_config_from_json() {
+        let json = r#"{
+            "address": "amd-gfx@lists.freedesktop.org",
+            "url": "https://lore.kernel.org/amd-gfx",
+            "description": "AMD graphics drivers",
+            "newsgroup": "org.freedesktop.lists.amd-gfx"
+        }"#;
+        let config = InboxConfig::parse(json).unwrap();
+        assert_eq!(
+            config.address,
+            ArcStr::from("amd-gfx@lists.freedesktop.org")
+        );
+        assert_eq!(config.url, ArcStr::from("https://lore.kernel.org/amd-gfx"));
+        assert_eq!(
+            config.description,
+            Some(ArcStr::from("AMD graphics drivers"))
+        );
+        assert_eq!(
+            config.newsgroup,
+            Some(ArcStr::from("org.freedesktop.lists.amd-gfx"))
+        );
+    }
+
+    #[test]
+    fn test_inbox_config_from_git_config() {
+        let raw = "; example public-inbox config snippet for \"amd-gfx\"\n\
+                   [publicinbox \"amd-gfx\"]\n\
+                   \tinboxdir = /path/to/top-level-inbox\n\
+                   \turl = https://lore.kernel.org/amd-gfx\n\
+                   \taddress = amd-gfx@lists.freedesktop.org\n\
+                   \tnewsgroup = org.freedesktop.lists.amd-gfx\n";
+        let config = InboxConfig::parse(raw).unwrap();
+        assert_eq!(
+            config.address,
+            ArcStr::from("amd-gfx@lists.freedesktop.org")
+        );
+        assert_eq!(config.url, ArcStr::from("https://lore.kernel.org/amd-gfx"));
+        assert_eq!(config.description, None);
+    }
+
+    #[test]
+    fn test_inbox_config_missing() {
+        assert!(InboxConfig::parse("<html>404 Not Found</html>").is_err());
+    }
+
+    #[test]
+    fn test_maintainer_parse() {
+        assert_eq!(
+            Maintainer::parse("M:\t\"Jane Doe\" <jane@example.com>"),
+            Some(Maintainer {
+                name: Some(ArcStr::from("Jane Doe")),
+                email: ArcStr::from("jane@example.com"),
+            })
+        );
+        assert_eq!(
+            Maintainer::parse("- john@example.com"),
+            Some(Maintainer {
+                name: None,
+                email: ArcStr::from("john@example.com"),
+            })
+        );
+        assert_eq!(Maintainer::parse("See the archive"), None);
+    }
+
+    #[test]
+    fn test_feed_offset() {
+        assert_eq!(FeedOffset::from_items(250).items(), 250);
+        assert_eq!(FeedOffset::from_page(0, 200).items(), 0);
+        assert_eq!(FeedOffset::from_page(3, 200).items(), 600);
+        assert_eq!(FeedOffset::from(400), FeedOffset::from_page(2, 200));
+        assert_eq!(FeedOffset::from_page(usize::MAX, 2).items(), usize::MAX);
+        assert_eq!(FeedOffset::default().items(), 0);
+    }
+
+    #[test]
+    fn test_series_from_interleaved_entries() {
+        let entry = |title: &str, id: &str, parent: Option<&str>| {
+            let reply = parent
+                .map(|parent| {
+                    format!(
+                        "<thr:in-reply-to ref=\"urn:uuid:{0}\" href=\"https://lore.kernel.org/amd-gfx/{0}/\"/>",
+                        parent
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                "<entry><title>{}</title><link href=\"https://lore.kernel.org/amd-gfx/{}/\"/>{}</entry>",
+                title, id, reply
+            )
+        };
+        let feed = [
+            entry("[PATCH v2 2/2] drm: widen b", "b-2", Some("b-0")),
+            entry("[PATCH 3/3] drm/amdgpu: drop c", "a-3", Some("a-1")),
+            entry("[PATCH v2 1/2] drm: split b", "b-1", Some("b-0")),
+            entry("[PATCH] drm/radeon: fix typo", "c", None),
+            entry("[PATCH 2/3] drm/amdgpu: move c", "a-2", Some("a-1")),
+            entry("[PATCH v2 0/2] drm: fix b", "b-0", None),
+            entry("[PATCH 1/3] drm/amdgpu: rework c", "a-1", None),
+        ]
+        .concat();
+        let entries = feed::parse_entries(&format!("<feed>{}</feed>", feed));
+
+        let ids = |series: &Series| {
+            (
+                series
+                    .cover
+                    .as_ref()
+                    .map(|cover| cover.message_id.to_string()),
+                series
+                    .patches
+                    .iter()
+                    .map(|patch| patch.message_id.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let series: Vec<_> = Series::from_entries(&entries).iter().map(ids).collect();
+        assert_eq!(
+            series,
+            [
+                (
+                    Some("b-0".to_string()),
+                    vec!["b-1".to_string(), "b-2".to_string()]
+                ),
+                (
+                    None,
+                    vec!["a-1".to_string(), "a-2".to_string(), "a-3".to_string()]
+                ),
+                (None, vec!["c".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_series_with_cover_on_another_page() {
+        let entry = |title: &str, id: &str| {
+            format!(
+                "<entry><title>{}</title><link href=\"https://lore.kernel.org/amd-gfx/{}/\"/><thr:in-reply-to href=\"https://lore.kernel.org/amd-gfx/cover/\"/></entry>",
+                title, id
+            )
+        };
+        let feed = format!(
+            "<feed>{}{}</feed>",
+            entry("[PATCH 2/2] drm: b", "p-2"),
+            entry("[PATCH 1/2] drm: a", "p-1")
+        );
+
+        let series = Series::from_entries(&feed::parse_entries(&feed));
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].cover, None);
+        assert_eq!(series[0].patches[0].message_id, ArcStr::from("p-1"));
+        assert_eq!(series[0].patches[1].message_id, ArcStr::from("p-2"));
+    }
+}
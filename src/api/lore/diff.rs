@@ -0,0 +1,848 @@
+//! Parsing of the unified diffs carried by patches, as produced by `git format-patch`.
+//!
+//! The diff is split into files and hunks, with the line numbers of every line
+//! on both sides, which is what a side-by-side view needs. Only the parts of
+//! the git diff format that matter for displaying a patch are interpreted.
+
+use std::{fmt::Write, iter::Peekable, sync::LazyLock};
+
+use anyhow::{Context, bail};
+use regex::Regex;
+
+use crate::ArcStr;
+
+/// Matches a hunk header, capturing the old and new ranges and the section heading
+static HUNK_HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@ ?(.*)$").unwrap());
+
+/// How many lines of context combined hunks keep around their changes
+const CONTEXT_LINES: usize = 3;
+
+/// The diff of a patch, file by file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedDiff {
+    /// The files touched by the patch, in the order of the diff
+    pub files: Vec<FileDiff>,
+}
+
+/// The changes made to a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    /// The path of the file before the change, `None` for new files
+    pub old_path: Option<ArcStr>,
+    /// The path of the file after the change, `None` for deleted files
+    pub new_path: Option<ArcStr>,
+    /// What happened to the file
+    pub status: FileStatus,
+    /// Whether the content of the file is binary, in which case it has no hunks
+    pub binary: bool,
+    /// The hunks of the diff of the file, in order
+    pub hunks: Vec<Hunk>,
+}
+
+/// What a patch does to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The content or the mode of an existing file changed
+    Modified,
+    /// The file is created
+    Added,
+    /// The file is removed
+    Deleted,
+    /// The file is moved, with the similarity of its content in percent, if given
+    Renamed(Option<u8>),
+    /// The file is copied, with the similarity of its content in percent, if given
+    Copied(Option<u8>),
+}
+
+/// A hunk of a file diff: a range of lines of the old file and its replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The first line of the range in the old file
+    pub old_start: usize,
+    /// The number of lines of the range in the old file
+    pub old_lines: usize,
+    /// The first line of the range in the new file
+    pub new_start: usize,
+    /// The number of lines of the range in the new file
+    pub new_lines: usize,
+    /// The heading following the ranges, usually the enclosing function, if any
+    pub section: Option<ArcStr>,
+    /// The lines of the hunk, in order
+    pub lines: Vec<DiffLine>,
+}
+
+/// A line of a hunk, with its position on each side of the diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    /// Whether the line is kept, added or removed
+    pub kind: LineKind,
+    /// The line number in the old file, `None` for added lines
+    pub old_line: Option<usize>,
+    /// The line number in the new file, `None` for removed lines
+    pub new_line: Option<usize>,
+    /// The content of the line, without the diff marker and the line ending
+    pub content: ArcStr,
+}
+
+/// The role of a line in a hunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// The line is in both files
+    Context,
+    /// The line is only in the new file
+    Added,
+    /// The line is only in the old file
+    Removed,
+}
+
+impl ParsedDiff {
+    /// Parses the diff of a patch.
+    ///
+    /// Everything before the first `diff --git` line, such as the mail headers,
+    /// the commit message and the diffstat, is skipped, as is anything between
+    /// the file diffs that isn't a diff header, like the trailing signature.
+    /// Hunks are read according to the line counts of their header, so a
+    /// removed line looking like a signature separator is still part of its
+    /// hunk. `\ No newline at end of file` markers are skipped, and lines left
+    /// empty by mailers stripping trailing whitespace are read as context.
+    ///
+    /// # Arguments
+    /// * `patch` - The content of the patch
+    ///
+    /// # Returns
+    /// The parsed diff, without files if the patch has no diff.
+    ///
+    /// # Examples
+    /// ```
+    /// let diff = ParsedDiff::parse(&raw_patch);
+    /// ```
+    pub fn parse(patch: &str) -> Self {
+        let mut files: Vec<FileDiff> = Vec::new();
+        let mut lines = patch
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .peekable();
+
+        while let Some(line) = lines.next() {
+            if let Some(paths) = line.strip_prefix("diff --git ") {
+                let (old_path, new_path) = git_paths(paths);
+                files.push(FileDiff {
+                    old_path,
+                    new_path,
+                    status: FileStatus::Modified,
+                    binary: false,
+                    hunks: Vec::new(),
+                });
+                continue;
+            }
+            let Some(file) = files.last_mut() else {
+                continue;
+            };
+
+            if let Some(captures) = HUNK_HEADER.captures(line) {
+                let number = |index| {
+                    captures
+                        .get(index)
+                        .map_or(Some(1), |count| count.as_str().parse().ok())
+                };
+                let (Some(old_start), Some(old_lines), Some(new_start), Some(new_lines)) =
+                    (number(1), number(2), number(3), number(4))
+                else {
+                    continue;
+                };
+                let section = captures[5].trim();
+                let mut hunk = Hunk {
+                    old_start,
+                    old_lines,
+                    new_start,
+                    new_lines,
+                    section: (!section.is_empty()).then(|| ArcStr::from(section)),
+                    lines: Vec::new(),
+                };
+                read_hunk(&mut hunk, &mut lines);
+                file.hunks.push(hunk);
+            } else if line.starts_with("new file mode ") {
+                file.status = FileStatus::Added;
+                file.old_path = None;
+            } else if line.starts_with("deleted file mode ") {
+                file.status = FileStatus::Deleted;
+                file.new_path = None;
+            } else if let Some(similarity) = line.strip_prefix("similarity index ") {
+                let similarity = similarity.trim_end_matches('%').parse().ok();
+                file.status = match file.status {
+                    FileStatus::Copied(_) => FileStatus::Copied(similarity),
+                    _ => FileStatus::Renamed(similarity),
+                };
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                file.status = FileStatus::Renamed(status_similarity(file.status));
+                file.old_path = Some(ArcStr::from(path));
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.new_path = Some(ArcStr::from(path));
+            } else if let Some(path) = line.strip_prefix("copy from ") {
+                file.status = FileStatus::Copied(status_similarity(file.status));
+                file.old_path = Some(ArcStr::from(path));
+            } else if let Some(path) = line.strip_prefix("copy to ") {
+                file.new_path = Some(ArcStr::from(path));
+            } else if let Some(path) = line.strip_prefix("--- ") {
+                file.old_path = side_path(path, "a/");
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                file.new_path = side_path(path, "b/");
+            } else if line == "GIT binary patch"
+                || (line.starts_with("Binary files ") && line.ends_with(" differ"))
+            {
+                file.binary = true;
+            }
+        }
+
+        Self { files }
+    }
+
+    /// Combines the diffs of a series of patches into the diff of the whole series.
+    ///
+    /// The diffs are applied in order, each hunk against the file as left by the
+    /// previous patches, so the combined diff goes straight from the original files
+    /// to the files after the last patch. Lines added by a patch and removed by a
+    /// later one vanish, and so do files added and later deleted. A file touched by
+    /// several patches is followed across renames and gets a single diff.
+    ///
+    /// Only the lines shown by the diffs are known, so the combined hunks have
+    /// context only where some patch gave it and carry no section headings. Copies
+    /// are taken from the original file.
+    ///
+    /// # Arguments
+    /// * `diffs` - The diffs of the patches, in the order they apply
+    ///
+    /// # Returns
+    /// The combined diff, or an error if a patch touches a binary file or doesn't
+    /// apply on top of the previous ones.
+    ///
+    /// # Examples
+    /// ```
+    /// let combined = ParsedDiff::combine(&[first_diff, second_diff])?;
+    /// ```
+    pub fn combine(diffs: &[ParsedDiff]) -> anyhow::Result<Self> {
+        let mut files: Vec<FileState> = Vec::new();
+        for (index, diff) in diffs.iter().enumerate() {
+            for file in &diff.files {
+                let path = file.new_path.as_ref().or(file.old_path.as_ref());
+                let path = path.map_or("", |path| &**path);
+                if file.binary {
+                    bail!("Patch {} changes the binary file {}", index + 1, path);
+                }
+
+                let existing = match file.status {
+                    FileStatus::Added | FileStatus::Copied(_) => None,
+                    _ => files.iter().position(|state| {
+                        state.new_path.is_some() && state.new_path == file.old_path
+                    }),
+                };
+                let state = match existing {
+                    Some(position) => &mut files[position],
+                    None => {
+                        files.push(FileState::new(file));
+                        files.last_mut().unwrap()
+                    }
+                };
+
+                // Hunks are numbered against the file before the patch, so they are
+                // applied from the bottom to keep the lines above them in place
+                for hunk in file.hunks.iter().rev() {
+                    state.apply(hunk).with_context(|| {
+                        format!(
+                            "Patch {} doesn't apply to {} at line {}",
+                            index + 1,
+                            path,
+                            hunk.old_start
+                        )
+                    })?;
+                }
+                state.new_path = file.new_path.clone();
+            }
+        }
+
+        Ok(Self {
+            files: files.iter().filter_map(FileState::to_diff).collect(),
+        })
+    }
+
+    /// Renders the diff in the unified format of `git diff`.
+    ///
+    /// File modes and blob hashes aren't kept by [`ParsedDiff::parse`], so the
+    /// `index` and mode lines are left out.
+    ///
+    /// # Returns
+    /// The diff as text, empty if it has no files.
+    pub fn to_unified(&self) -> ArcStr {
+        let mut text = String::new();
+        for file in &self.files {
+            let old_path = file.old_path.as_ref().or(file.new_path.as_ref());
+            let new_path = file.new_path.as_ref().or(file.old_path.as_ref());
+            let (Some(old_path), Some(new_path)) = (old_path, new_path) else {
+                continue;
+            };
+            let _ = writeln!(text, "diff --git a/{} b/{}", old_path, new_path);
+            match file.status {
+                FileStatus::Renamed(_) => {
+                    let _ = writeln!(text, "rename from {}\nrename to {}", old_path, new_path);
+                }
+                FileStatus::Copied(_) => {
+                    let _ = writeln!(text, "copy from {}\ncopy to {}", old_path, new_path);
+                }
+                _ => {}
+            }
+            if file.binary {
+                let _ = writeln!(
+                    text,
+                    "Binary files a/{} and b/{} differ",
+                    old_path, new_path
+                );
+                continue;
+            }
+            if file.hunks.is_empty() {
+                continue;
+            }
+
+            let side = |path: &Option<ArcStr>, prefix: &str| {
+                path.as_ref().map_or(String::from("/dev/null"), |path| {
+                    format!("{}{}", prefix, path)
+                })
+            };
+            let _ = writeln!(text, "--- {}", side(&file.old_path, "a/"));
+            let _ = writeln!(text, "+++ {}", side(&file.new_path, "b/"));
+            for hunk in &file.hunks {
+                let section = hunk
+                    .section
+                    .as_ref()
+                    .map_or(String::new(), |section| format!(" {}", section));
+                let _ = writeln!(
+                    text,
+                    "@@ -{},{} +{},{} @@{}",
+                    hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines, section
+                );
+                for line in &hunk.lines {
+                    let marker = match line.kind {
+                        LineKind::Context => ' ',
+                        LineKind::Added => '+',
+                        LineKind::Removed => '-',
+                    };
+                    let _ = writeln!(text, "{}{}", marker, line.content);
+                }
+            }
+        }
+        ArcStr::from(&text)
+    }
+}
+
+/// A file being rebuilt while combining diffs.
+///
+/// The file is kept as the lines seen so far, removed ones included, in order.
+/// The lines of the original file past them are implied and read as needed.
+#[derive(Debug)]
+struct FileState {
+    /// The path of the file before the series, `None` if the series adds it
+    old_path: Option<ArcStr>,
+    /// The path of the file after the patches applied so far, `None` once deleted
+    new_path: Option<ArcStr>,
+    /// Whether the file is copied from `old_path` rather than changed in place
+    copied: bool,
+    /// The lines of the file read so far
+    slots: Vec<Slot>,
+    /// The number of lines of the original file in `slots`
+    original_lines: usize,
+}
+
+/// A line of a file being rebuilt while combining diffs.
+#[derive(Debug)]
+struct Slot {
+    /// Whether the line is kept from the original file, added or removed
+    kind: LineKind,
+    /// The line number in the original file, `None` for added lines
+    old_line: Option<usize>,
+    /// The content of the line, `None` until a diff shows it
+    content: Option<ArcStr>,
+}
+
+impl FileState {
+    /// Starts rebuilding the file changed by a file diff
+    fn new(file: &FileDiff) -> Self {
+        Self {
+            old_path: file.old_path.clone(),
+            new_path: file.old_path.clone(),
+            copied: matches!(file.status, FileStatus::Copied(_)),
+            slots: Vec::new(),
+            original_lines: 0,
+        }
+    }
+
+    /// Finds the index of the next line of the current file from `index`,
+    /// reading a line of the original file if the known ones are exhausted
+    fn next_line(&mut self, mut index: usize) -> anyhow::Result<usize> {
+        loop {
+            if index == self.slots.len() {
+                if self.old_path.is_none() {
+                    bail!("The hunk goes past the end of the file");
+                }
+                self.original_lines += 1;
+                self.slots.push(Slot {
+                    kind: LineKind::Context,
+                    old_line: Some(self.original_lines),
+                    content: None,
+                });
+            }
+            if self.slots[index].kind != LineKind::Removed {
+                return Ok(index);
+            }
+            index += 1;
+        }
+    }
+
+    /// Checks that a line of the file is the one a hunk expects, learning its content
+    fn check(&mut self, index: usize, content: &ArcStr) -> anyhow::Result<()> {
+        let slot = &mut self.slots[index];
+        match &slot.content {
+            Some(known) if known != content => {
+                bail!("Expected {:?}, found {:?}", content, known)
+            }
+            Some(_) => {}
+            None => slot.content = Some(content.clone()),
+        }
+        Ok(())
+    }
+
+    /// Applies a hunk to the current file
+    fn apply(&mut self, hunk: &Hunk) -> anyhow::Result<()> {
+        // A hunk without old lines inserts after its start line
+        let skipped = match hunk.old_lines {
+            0 => hunk.old_start,
+            _ => hunk.old_start.saturating_sub(1),
+        };
+        let mut index = 0;
+        for _ in 0..skipped {
+            index = self.next_line(index)? + 1;
+        }
+
+        for line in &hunk.lines {
+            match line.kind {
+                LineKind::Context => {
+                    let found = self.next_line(index)?;
+                    self.check(found, &line.content)?;
+                    index = found + 1;
+                }
+                LineKind::Removed => {
+                    let found = self.next_line(index)?;
+                    self.check(found, &line.content)?;
+                    if self.slots[found].kind == LineKind::Added {
+                        self.slots.remove(found);
+                        index = found;
+                    } else {
+                        self.slots[found].kind = LineKind::Removed;
+                        index = found + 1;
+                    }
+                }
+                LineKind::Added => {
+                    // Keep additions after the removals they replace
+                    while self
+                        .slots
+                        .get(index)
+                        .is_some_and(|slot| slot.kind == LineKind::Removed)
+                    {
+                        index += 1;
+                    }
+                    self.slots.insert(
+                        index,
+                        Slot {
+                            kind: LineKind::Added,
+                            old_line: None,
+                            content: Some(line.content.clone()),
+                        },
+                    );
+                    index += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the diff of the file from the original to its current state
+    ///
+    /// Returns `None` if the file is unchanged, or added and then deleted.
+    fn to_diff(&self) -> Option<FileDiff> {
+        let status = match (&self.old_path, &self.new_path) {
+            (None, None) => return None,
+            (None, Some(_)) => FileStatus::Added,
+            (Some(_), None) => FileStatus::Deleted,
+            (Some(_), Some(_)) if self.copied => FileStatus::Copied(None),
+            (Some(old), Some(new)) if old != new => FileStatus::Renamed(None),
+            (Some(_), Some(_)) => FileStatus::Modified,
+        };
+
+        // The new line number of every slot, or of the last line before it
+        let mut new_line = 0;
+        let new_lines: Vec<usize> = self
+            .slots
+            .iter()
+            .map(|slot| {
+                if slot.kind != LineKind::Removed {
+                    new_line += 1;
+                }
+                new_line
+            })
+            .collect();
+        let is_context = |index: usize| {
+            let slot = &self.slots[index];
+            slot.kind == LineKind::Context && slot.content.is_some()
+        };
+
+        // Group the changes whose gap is short and fully known into ranges of slots
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for (index, slot) in self.slots.iter().enumerate() {
+            if slot.kind == LineKind::Context {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some((_, end))
+                    if index - *end <= 2 * CONTEXT_LINES && (*end..index).all(is_context) =>
+                {
+                    *end = index + 1;
+                }
+                _ => ranges.push((index, index + 1)),
+            }
+        }
+
+        let hunks: Vec<Hunk> = ranges
+            .into_iter()
+            .map(|(mut start, mut end)| {
+                for _ in 0..CONTEXT_LINES {
+                    if start > 0 && is_context(start - 1) {
+                        start -= 1;
+                    }
+                    if end < self.slots.len() && is_context(end) {
+                        end += 1;
+                    }
+                }
+
+                let lines: Vec<DiffLine> = (start..end)
+                    .map(|index| {
+                        let slot = &self.slots[index];
+                        DiffLine {
+                            kind: slot.kind,
+                            old_line: slot.old_line,
+                            new_line: (slot.kind != LineKind::Removed).then_some(new_lines[index]),
+                            content: slot.content.clone().unwrap_or_default(),
+                        }
+                    })
+                    .collect();
+                let old_lines = lines.iter().filter(|line| line.old_line.is_some()).count();
+                let new_lines_count = lines.iter().filter(|line| line.new_line.is_some()).count();
+                // Empty ranges start at the line before them
+                let old_start = match lines.iter().find_map(|line| line.old_line) {
+                    Some(line) => line,
+                    None => self.slots[..start]
+                        .iter()
+                        .rev()
+                        .find_map(|slot| slot.old_line)
+                        .unwrap_or(0),
+                };
+                let new_start = match lines.iter().find_map(|line| line.new_line) {
+                    Some(line) => line,
+                    None if start == 0 => 0,
+                    None => new_lines[start - 1],
+                };
+
+                Hunk {
+                    old_start,
+                    old_lines,
+                    new_start,
+                    new_lines: new_lines_count,
+                    section: None,
+                    lines,
+                }
+            })
+            .collect();
+
+        if hunks.is_empty() && status == FileStatus::Modified {
+            return None;
+        }
+        Some(FileDiff {
+            old_path: self.old_path.clone(),
+            new_path: self.new_path.clone(),
+            status,
+            binary: false,
+            hunks,
+        })
+    }
+}
+
+impl Hunk {
+    /// Checks whether the hunk has all the lines announced by its header.
+    ///
+    /// # Returns
+    /// `false` if the diff ended before the end of the hunk.
+    pub fn is_complete(&self) -> bool {
+        let old_lines = self
+            .lines
+            .iter()
+            .filter(|line| line.kind != LineKind::Added)
+            .count();
+        let new_lines = self
+            .lines
+            .iter()
+            .filter(|line| line.kind != LineKind::Removed)
+            .count();
+        old_lines == self.old_lines && new_lines == self.new_lines
+    }
+}
+
+/// Reads the lines of a hunk, numbering them from the start of its ranges.
+///
+/// Reading stops early, before the offending line, if the hunk is shorter than announced.
+fn read_hunk<'a, I: Iterator<Item = &'a str>>(hunk: &mut Hunk, lines: &mut Peekable<I>) {
+    let (mut old_line, mut new_line) = (hunk.old_start, hunk.new_start);
+    let (mut old_left, mut new_left) = (hunk.old_lines, hunk.new_lines);
+
+    while old_left > 0 || new_left > 0 {
+        let Some(&line) = lines.peek() else {
+            return;
+        };
+        let mut chars = line.chars();
+        let (kind, content) = match chars.next() {
+            Some('+') if new_left > 0 => (LineKind::Added, chars.as_str()),
+            Some('-') if old_left > 0 => (LineKind::Removed, chars.as_str()),
+            Some(' ') if old_left > 0 && new_left > 0 => (LineKind::Context, chars.as_str()),
+            None if old_left > 0 && new_left > 0 => (LineKind::Context, ""),
+            Some('\\') => {
+                lines.next();
+                continue;
+            }
+            _ => return,
+        };
+        lines.next();
+
+        hunk.lines.push(DiffLine {
+            kind,
+            old_line: (kind != LineKind::Added).then_some(old_line),
+            new_line: (kind != LineKind::Removed).then_some(new_line),
+            content: ArcStr::from(content),
+        });
+        if kind != LineKind::Added {
+            old_line += 1;
+            old_left -= 1;
+        }
+        if kind != LineKind::Removed {
+            new_line += 1;
+            new_left -= 1;
+        }
+    }
+}
+
+/// Splits the paths of a `diff --git a/<old> b/<new>` line
+fn git_paths(paths: &str) -> (Option<ArcStr>, Option<ArcStr>) {
+    match paths
+        .strip_prefix("a/")
+        .and_then(|rest| rest.split_once(" b/"))
+    {
+        Some((old, new)) => (Some(ArcStr::from(old)), Some(ArcStr::from(new))),
+        None => (None, None),
+    }
+}
+
+/// Gets the path of a `---` or `+++` line, `None` for `/dev/null`
+fn side_path(path: &str, prefix: &str) -> Option<ArcStr> {
+    // A tab separates the path from an optional timestamp
+    let path = path.split('\t').next().unwrap_or(path).trim_end();
+    (path != "/dev/null").then(|| ArcStr::from(path.strip_prefix(prefix).unwrap_or(path)))
+}
+
+/// Gets the similarity already read for a renamed or copied file
+fn status_similarity(status: FileStatus) -> Option<u8> {
+    match status {
+        FileStatus::Renamed(similarity) | FileStatus::Copied(similarity) => similarity,
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = include_str!("../../../samples/multi_file.patch");
+
+    #[test]
+    fn test_parse_multi_file_patch() {
+        let diff = ParsedDiff::parse(PATCH);
+        let summary: Vec<_> = diff
+            .files
+            .iter()
+            .map(|file| {
+                (
+                    file.old_path.as_deref(),
+                    file.new_path.as_deref(),
+                    file.status,
+                    file.binary,
+                    file.hunks.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    Some("drivers/gpu/drm/amd/amdgpu/amdgpu.h"),
+                    Some("drivers/gpu/drm/amd/amdgpu/amdgpu.h"),
+                    FileStatus::Modified,
+                    false,
+                    1
+                ),
+                (
+                    None,
+                    Some("drivers/gpu/drm/amd/amdgpu/amdgpu_b.c"),
+                    FileStatus::Added,
+                    false,
+                    1
+                ),
+                (
+                    Some("drivers/gpu/drm/amd/amdgpu/amdgpu_old.c"),
+                    None,
+                    FileStatus::Deleted,
+                    false,
+                    1
+                ),
+                (
+                    Some("drivers/gpu/drm/amd/amdgpu/b.h"),
+                    Some("drivers/gpu/drm/amd/amdgpu/amdgpu_b.h"),
+                    FileStatus::Renamed(Some(90)),
+                    false,
+                    1
+                ),
+                (
+                    None,
+                    Some("firmware/amdgpu/b.bin"),
+                    FileStatus::Added,
+                    true,
+                    0
+                ),
+            ]
+        );
+
+        let hunk = &diff.files[0].hunks[0];
+        assert_eq!(
+            (
+                hunk.old_start,
+                hunk.old_lines,
+                hunk.new_start,
+                hunk.new_lines
+            ),
+            (10, 4, 10, 6)
+        );
+        assert_eq!(hunk.section, Some(ArcStr::from("struct amdgpu_device {")));
+        let numbers: Vec<_> = hunk
+            .lines
+            .iter()
+            .map(|line| (line.kind, line.old_line, line.new_line))
+            .collect();
+        assert_eq!(
+            numbers,
+            [
+                (LineKind::Context, Some(10), Some(10)),
+                (LineKind::Removed, Some(11), None),
+                (LineKind::Added, None, Some(11)),
+                (LineKind::Added, None, Some(12)),
+                (LineKind::Added, None, Some(13)),
+                (LineKind::Context, Some(12), Some(14)),
+                (LineKind::Context, Some(13), Some(15)),
+            ]
+        );
+        assert_eq!(hunk.lines[2].content, ArcStr::from("\tlong b;"));
+
+        // The blank line of the new file and the removed lines of the deleted one
+        let added = &diff.files[1].hunks[0];
+        assert_eq!(added.lines.len(), 5);
+        assert_eq!(added.lines[1].content, ArcStr::from(""));
+        assert_eq!(added.lines[4].new_line, Some(5));
+        let deleted = &diff.files[2].hunks[0];
+        assert!(
+            deleted
+                .lines
+                .iter()
+                .all(|line| line.kind == LineKind::Removed && line.new_line.is_none())
+        );
+        // The missing newline marker isn't a line
+        assert_eq!(diff.files[3].hunks[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn test_combine_two_patch_series() {
+        let diffs = [
+            ParsedDiff::parse(include_str!("../../../samples/series_1.patch")),
+            ParsedDiff::parse(include_str!("../../../samples/series_2.patch")),
+        ];
+
+        let combined = ParsedDiff::combine(&diffs).unwrap();
+
+        // The note added by the first patch and dropped by the second is gone
+        assert_eq!(
+            &*combined.to_unified(),
+            "diff --git a/src/foo.c b/src/foo.c\n\
+             --- a/src/foo.c\n\
+             +++ b/src/foo.c\n\
+             @@ -2,5 +2,6 @@\n\
+             \x20line 2\n\
+             \x20line 3\n\
+             -line 4\n\
+             +line 4 fixed\n\
+             +line 4.75\n\
+             \x20line 5\n\
+             \x20line 6\n\
+             @@ -10,5 +11,5 @@\n\
+             \x20line 10\n\
+             \x20line 11\n\
+             -line 12\n\
+             +line 12 changed\n\
+             \x20line 13\n\
+             \x20line 14\n"
+        );
+    }
+
+    #[test]
+    fn test_combine_follows_added_files() {
+        let added = ParsedDiff::parse(
+            "diff --git a/bar.c b/bar.c\n\
+             new file mode 100644\n\
+             --- /dev/null\n\
+             +++ b/bar.c\n\
+             @@ -0,0 +1,2 @@\n\
+             +a\n\
+             +b\n",
+        );
+        let changed = ParsedDiff::parse(
+            "diff --git a/bar.c b/bar.c\n\
+             --- a/bar.c\n\
+             +++ b/bar.c\n\
+             @@ -1,2 +1,2 @@\n\
+             \x20a\n\
+             -b\n\
+             +c\n",
+        );
+
+        let combined = ParsedDiff::combine(&[added.clone(), changed.clone()]).unwrap();
+        assert_eq!(combined.files.len(), 1);
+        assert_eq!(combined.files[0].status, FileStatus::Added);
+        assert_eq!(
+            &*combined.to_unified(),
+            "diff --git a/bar.c b/bar.c\n--- /dev/null\n+++ b/bar.c\n@@ -0,0 +1,2 @@\n+a\n+c\n"
+        );
+
+        // The second patch doesn't apply without the first
+        let err = ParsedDiff::combine(&[changed.clone(), changed]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Patch 2 doesn't apply to bar.c"));
+    }
+
+    #[test]
+    fn test_parse_without_diff() {
+        assert_eq!(
+            ParsedDiff::parse("Subject: Re: [PATCH] foo\n\nLooks good.\n"),
+            ParsedDiff::default()
+        );
+    }
+}
@@ -0,0 +1,1310 @@
+//! Parsing of the Atom feeds served by the Lore Kernel Archive.
+//!
+//! Lore serves search results and list listings as Atom feeds when the `x=A`
+//! query parameter is given. Only the parts of the feed used by this crate are
+//! extracted, so this is deliberately not a general purpose XML parser.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    pin::Pin,
+    sync::{Arc, LazyLock},
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use regex::Regex;
+
+use crate::{
+    ArcStr,
+    api::lore::{
+        LoreApi,
+        data::{LoreApiError, MailingList, Maintainer},
+        mail::normalize_message_id,
+        patch::PatchSubject,
+    },
+};
+
+/// Matches a whole `<entry>` element of a feed
+static ENTRY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<entry>(.*?)</entry>").unwrap());
+/// Matches the `href` attribute of the first `<link>` element
+static LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<link\b[^>]*?\bhref="([^"]*)""#).unwrap());
+/// Matches the `href` attribute of the `<thr:in-reply-to>` element (RFC 4685)
+static IN_REPLY_TO: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<thr:in-reply-to\b[^>]*?\bhref="([^"]*)""#).unwrap());
+/// Matches the opening tag of an anchor or link element
+static NAVIGATION_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(?:a|link)\s[^>]*>").unwrap());
+/// Matches any markup tag
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+/// Matches the URL of a `git clone` command
+static GIT_CLONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"git clone (?:--mirror )?(\S+)").unwrap());
+/// Matches an entry of the list index: the link to the list and its indented description, if any
+static LIST_ENTRY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\* [^\n]*? - <a\s+href="([^"]*)"\s*>([^<]*)</a>[ \t]*(?:\n[ \t]+([^\n<]*))?"#)
+        .unwrap()
+});
+/// Matches an attribute of a tag, with a double-quoted, single-quoted or unquoted value
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+
+/// A page of a Lore feed or list page, with the pagination links of the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchFeed {
+    /// The title of the feed or page, if any
+    pub title: Option<ArcStr>,
+    /// The time the feed was last updated, as given in its `<updated>` element
+    pub updated: Option<ArcStr>,
+    /// The unique identifier of the feed, as given in its `<id>` element
+    pub id: Option<ArcStr>,
+    /// The entries of the page, in document order
+    pub entries: Vec<PatchEntry>,
+    /// The offset of the next (older) page, if any
+    next_offset: Option<usize>,
+    /// The offset of the previous (newer) page, if any
+    prev_offset: Option<usize>,
+}
+
+impl PatchFeed {
+    /// Parses a page of a feed or an HTML list page.
+    ///
+    /// The pagination offsets are taken from the `o=` parameter of the links
+    /// marked `rel="next"` and `rel="prev"`, as published by the server, whether
+    /// they are `<link>` elements of a feed or `<a>` elements of an HTML page.
+    /// The feed-level title, update time and identifier are read from the part
+    /// of the page before the first entry, and are `None` when missing.
+    ///
+    /// # Arguments
+    /// * `page` - The content of the page
+    ///
+    /// # Returns
+    /// The parsed page.
+    ///
+    /// # Examples
+    /// ```
+    /// let feed = PatchFeed::parse(&lore_api.get_patch_feed("amd-gfx", 0).await?);
+    /// ```
+    pub fn parse(page: &str) -> Self {
+        let mut next_offset = None;
+        let mut prev_offset = None;
+        for tag in NAVIGATION_TAG.find_iter(page) {
+            let mut rel = None;
+            let mut href = None;
+            for attribute in ATTRIBUTE.captures_iter(tag.as_str()) {
+                let value = attribute
+                    .get(2)
+                    .or_else(|| attribute.get(3))
+                    .or_else(|| attribute.get(4))
+                    .map_or("", |value| value.as_str());
+                match attribute[1].to_ascii_lowercase().as_str() {
+                    "rel" => rel = Some(value.to_ascii_lowercase()),
+                    "href" => href = Some(unescape(value)),
+                    _ => {}
+                }
+            }
+            let (Some(rel), Some(href)) = (rel, href) else {
+                continue;
+            };
+            let slot = match rel
+                .split_whitespace()
+                .find(|rel| *rel == "next" || *rel == "prev")
+            {
+                Some("next") => &mut next_offset,
+                Some(_) => &mut prev_offset,
+                None => continue,
+            };
+            if slot.is_none() {
+                *slot = Some(offset_of(&href));
+            }
+        }
+
+        let header = &page[..page.find("<entry>").unwrap_or(page.len())];
+        let header_text = |tag| element_text(header, tag).map(|text| ArcStr::from(&text));
+
+        Self {
+            title: header_text("title"),
+            updated: header_text("updated"),
+            id: header_text("id"),
+            entries: parse_entries(page),
+            next_offset,
+            prev_offset,
+        }
+    }
+
+    /// Parses a page of a feed like [`PatchFeed::parse`], checking that it is
+    /// complete.
+    ///
+    /// A feed cut short can still parse, with fewer entries. In strict mode the
+    /// page must end with its closing `</feed>` tag, its entries must be sorted
+    /// newest first by update time, entries without a valid time being left
+    /// out of the check, and their count must match the OpenSearch
+    /// `totalResults`, `startIndex` and `itemsPerPage` hints of the feed, when
+    /// it gives them.
+    ///
+    /// # Arguments
+    /// * `page` - The content of the page
+    ///
+    /// # Returns
+    /// The parsed page, or [`LoreApiError::InconsistentFeed`] naming the first
+    /// check it fails.
+    ///
+    /// # Examples
+    /// ```
+    /// let feed = PatchFeed::parse_strict(&lore_api.get_patch_feed("amd-gfx", 0).await?)?;
+    /// ```
+    pub fn parse_strict(page: &str) -> Result<Self, LoreApiError> {
+        let inconsistent = |reason: String| LoreApiError::InconsistentFeed {
+            reason: ArcStr::from(&reason),
+        };
+        if !page.trim_end().ends_with("</feed>") {
+            return Err(inconsistent("missing closing </feed> tag".to_string()));
+        }
+        let feed = Self::parse(page);
+
+        let mut previous: Option<chrono::DateTime<chrono::FixedOffset>> = None;
+        for (index, entry) in feed.entries.iter().enumerate() {
+            let Some(updated) = entry
+                .updated
+                .as_deref()
+                .and_then(|updated| chrono::DateTime::parse_from_rfc3339(updated).ok())
+            else {
+                continue;
+            };
+            if previous.is_some_and(|previous| updated > previous) {
+                return Err(inconsistent(format!(
+                    "entry {} ({}) is newer than the entry before it",
+                    index, entry.message_id
+                )));
+            }
+            previous = Some(updated);
+        }
+
+        let header = &page[..page.find("<entry>").unwrap_or(page.len())];
+        let hint = |tag: &str| {
+            element_text(header, &format!("opensearch:{}", tag))
+                .and_then(|text| text.trim().parse::<usize>().ok())
+        };
+        if let Some(total) = hint("totalResults") {
+            // The start index of OpenSearch counts from 1
+            let skipped = hint("startIndex").map_or(0, |start| start.saturating_sub(1));
+            let remaining = total.saturating_sub(skipped);
+            let expected =
+                hint("itemsPerPage").map_or(remaining, |per_page| per_page.min(remaining));
+            if feed.entries.len() != expected {
+                return Err(inconsistent(format!(
+                    "{} entries where the feed announces {}",
+                    feed.entries.len(),
+                    expected
+                )));
+            }
+        }
+        Ok(feed)
+    }
+
+    /// Gets the offset of the next (older) page, as linked by the server.
+    ///
+    /// # Returns
+    /// The offset to request next, or `None` on the last page.
+    pub fn next_offset(&self) -> Option<usize> {
+        self.next_offset
+    }
+
+    /// Gets the offset of the previous (newer) page, as linked by the server.
+    ///
+    /// # Returns
+    /// The offset of the previous page, or `None` on the first page.
+    pub fn prev_offset(&self) -> Option<usize> {
+        self.prev_offset
+    }
+}
+
+/// The changes between two snapshots of a feed, as computed by [`diff_feeds`].
+///
+/// Entries are matched by message ID, normalized with [`normalize_message_id`]
+/// so that mirrors writing the domain differently agree. Each group keeps the order of the feed
+/// it comes from: the new one, except for removed entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedDiff {
+    /// Entries only in the new snapshot
+    pub added: Vec<PatchEntry>,
+    /// Entries only in the old snapshot
+    pub removed: Vec<PatchEntry>,
+    /// Entries in both snapshots whose title, link, author, date or parent
+    /// differ, as found in the new snapshot
+    pub changed: Vec<PatchEntry>,
+    /// Entries identical in both snapshots
+    pub unchanged: Vec<PatchEntry>,
+}
+
+impl FeedDiff {
+    /// Checks whether the snapshots hold the same entries.
+    ///
+    /// # Returns
+    /// `true` if no entry was added, removed or changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares two snapshots of a feed.
+///
+/// This detects what happened to a list between two fetches of the same page
+/// without keeping any state besides the previous snapshot. Note that on a busy
+/// list, entries pushed to the next page by new messages are reported as removed.
+///
+/// # Arguments
+/// * `old` - The previous snapshot
+/// * `new` - The current snapshot
+///
+/// # Returns
+/// The entries added, removed, changed and left unchanged.
+///
+/// # Examples
+/// ```
+/// let new = PatchFeed::parse(&lore_api.get_patch_feed("amd-gfx", 0).await?);
+/// for entry in diff_feeds(&old, &new).added {
+///     // ...
+/// }
+/// ```
+pub fn diff_feeds(old: &PatchFeed, new: &PatchFeed) -> FeedDiff {
+    let old_entries: HashMap<ArcStr, &PatchEntry> = old
+        .entries
+        .iter()
+        .map(|entry| (normalize_message_id(&entry.message_id), entry))
+        .collect();
+    let new_ids: HashSet<ArcStr> = new
+        .entries
+        .iter()
+        .map(|entry| normalize_message_id(&entry.message_id))
+        .collect();
+
+    let mut diff = FeedDiff::default();
+    for entry in &new.entries {
+        match old_entries.get(&normalize_message_id(&entry.message_id)) {
+            None => diff.added.push(entry.clone()),
+            Some(previous) if *previous == entry => diff.unchanged.push(entry.clone()),
+            Some(_) => diff.changed.push(entry.clone()),
+        }
+    }
+    diff.removed = old
+        .entries
+        .iter()
+        .filter(|entry| !new_ids.contains(&normalize_message_id(&entry.message_id)))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Extracts the `git clone` URLs advertised on the mirroring page of a list.
+///
+/// Large lists are archived in several git repositories, one per epoch, each
+/// with its own clone command. Markup is ignored and URLs advertised more than
+/// once are only returned the first time.
+///
+/// # Arguments
+/// * `page` - The content of the page
+///
+/// # Returns
+/// The clone URLs, in the order of the page.
+///
+/// # Examples
+/// ```
+/// let urls = clone_urls(&page);
+/// ```
+pub fn clone_urls(page: &str) -> Vec<ArcStr> {
+    let text = unescape(&TAG.replace_all(page, ""));
+    let mut urls: Vec<ArcStr> = Vec::new();
+    for captures in GIT_CLONE.captures_iter(&text) {
+        let url = ArcStr::from(&captures[1]);
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Extracts the lists carrying a message from the results of a global search.
+///
+/// The list of each result is taken from its link, as with
+/// [`PatchEntry::target_list`]. Results for other messages and results only
+/// linked under the `all` pseudo-list are skipped.
+///
+/// # Arguments
+/// * `feed` - The results of the search, as an Atom feed
+/// * `message_id` - The message ID searched for
+///
+/// # Returns
+/// The distinct lists, in the order of the results.
+///
+/// # Examples
+/// ```
+/// let lists = crosspost_lists(&results, "20231201.1-1@amd.com");
+/// ```
+pub fn crosspost_lists(feed: &str, message_id: &str) -> Vec<ArcStr> {
+    let message_id = normalize_message_id(message_id);
+    let mut lists: Vec<ArcStr> = Vec::new();
+    for entry in parse_entries(feed) {
+        if normalize_message_id(&entry.message_id) != message_id {
+            continue;
+        }
+        let Some(list) = entry.target_list().filter(|list| *list != "all") else {
+            continue;
+        };
+        let list = ArcStr::from(list);
+        if !lists.contains(&list) {
+            lists.push(list);
+        }
+    }
+    lists
+}
+
+/// Extracts the mailing lists of a page of the list index of the server.
+///
+/// Each list is given on the index as a bullet with its update time and a link
+/// to its archive, followed by its description on the next line. A list whose
+/// link has no text is named after the last segment of its URL.
+///
+/// # Arguments
+/// * `page` - The content of the page
+///
+/// # Returns
+/// The mailing lists, in the order of the page.
+///
+/// # Examples
+/// ```
+/// let lists = mailing_lists(&lore_api.get_available_lists(0).await?);
+/// ```
+pub fn mailing_lists(page: &str) -> Vec<MailingList> {
+    LIST_ENTRY
+        .captures_iter(page)
+        .filter_map(|captures| {
+            let url = unescape(&captures[1]);
+            let name = unescape(captures[2].trim());
+            let name = match name.is_empty() {
+                true => message_id_of(&url)?,
+                false => name,
+            };
+            let description = unescape(captures.get(3).map_or("", |text| text.as_str()).trim());
+            Some(MailingList {
+                name: ArcStr::from(&name),
+                url: ArcStr::from(&url),
+                description: (!description.is_empty()).then(|| ArcStr::from(&description)),
+            })
+        })
+        .collect()
+}
+
+/// Extracts the maintainers published on the information page of a list.
+///
+/// Markup is ignored. The maintainers are read from the lines following a
+/// `Maintainers:` (or `Maintainer:`) heading, up to the first blank line after
+/// them; see [`Maintainer::parse`] for the accepted line formats.
+///
+/// # Arguments
+/// * `page` - The content of the page
+///
+/// # Returns
+/// The maintainers, in the order of the page, empty if the page has no
+/// maintainers section.
+///
+/// # Examples
+/// ```
+/// let maintainers = maintainers(&page);
+/// ```
+pub fn maintainers(page: &str) -> Vec<Maintainer> {
+    let text = unescape(&TAG.replace_all(page, ""));
+    let mut lines = text.lines().map(str::trim);
+    let heading = lines.by_ref().any(|line| {
+        let line = line.trim_end_matches(':').trim_end();
+        line.eq_ignore_ascii_case("maintainers") || line.eq_ignore_ascii_case("maintainer")
+    });
+    if !heading {
+        return Vec::new();
+    }
+
+    lines
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .filter_map(Maintainer::parse)
+        .collect()
+}
+
+/// Translates a `lei q`-style query into the query of a Lore search.
+///
+/// The supported subset is the `s:` (subject), `f:` (sender) and `d:` (date or
+/// date range) prefixes, bare words, double-quoted phrases and the `AND`
+/// operator. Terms next to each other are joined with an explicit `AND`, as
+/// `lei` does, and ISO dates (`2023-12-01`) are converted to the `YYYYMMDD`
+/// form of public-inbox. Relative dates like `2.weeks.ago` are passed as is.
+///
+/// # Arguments
+/// * `query` - The `lei` query, e.g. `s:"drm: fix" f:jane@example.com d:2023-12-01..`
+///
+/// # Returns
+/// The normalized search query, or an error for empty queries and for
+/// operators outside the supported subset.
+///
+/// # Examples
+/// ```
+/// assert_eq!(lei_query("s:drm and f:jane")?, "s:drm AND f:jane");
+/// ```
+pub fn lei_query(query: &str) -> anyhow::Result<String> {
+    let mut terms: Vec<String> = Vec::new();
+    let mut expects_term = true;
+    for token in lei_tokens(query)? {
+        if token.eq_ignore_ascii_case("and") || token == "&&" {
+            if expects_term {
+                anyhow::bail!("Misplaced AND in lei query: {}", query);
+            }
+            expects_term = true;
+            continue;
+        }
+        if token.eq_ignore_ascii_case("or") || token.eq_ignore_ascii_case("not") {
+            anyhow::bail!("Unsupported operator in lei query: {}", token);
+        }
+        let date = match token.split_once(':') {
+            Some((prefix, value)) if !token.starts_with('"') => match prefix {
+                "s" | "f" if !value.is_empty() => None,
+                "d" if !value.is_empty() => Some(format!("d:{}", lei_date_range(value))),
+                _ => anyhow::bail!("Unsupported term in lei query: {}", token),
+            },
+            _ => None,
+        };
+        terms.push(date.unwrap_or(token));
+        expects_term = false;
+    }
+    if terms.is_empty() {
+        anyhow::bail!("Empty lei query");
+    }
+    if expects_term {
+        anyhow::bail!("Misplaced AND in lei query: {}", query);
+    }
+    Ok(terms.join(" AND "))
+}
+
+/// Splits a query into whitespace-separated tokens, keeping quoted phrases whole
+fn lei_tokens(query: &str) -> anyhow::Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("Unterminated quote in lei query: {}", query);
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Converts the ISO dates of a `d:` range to the `YYYYMMDD` form, keeping the rest as is
+fn lei_date_range(range: &str) -> String {
+    range
+        .split("..")
+        .map(
+            |date| match chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(date) => date.format("%Y%m%d").to_string(),
+                Err(_) => date.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("..")
+}
+
+/// Builds the search term matching the sender of a message.
+///
+/// Authors can be given as a name (`Jane Doe`), an email address
+/// (`jane@example.com`) or both (`Jane Doe <jane@example.com>`), in which case
+/// only the address is searched, as it is the more precise of the two.
+///
+/// # Arguments
+/// * `author` - The author to search for
+///
+/// # Returns
+/// The `f:` search term, quoted when it contains spaces.
+pub fn author_term(author: &str) -> String {
+    let author = match (author.find('<'), author.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &author[start + 1..end],
+        _ => author,
+    };
+    let author = author.trim().replace('"', "");
+    if author.contains(char::is_whitespace) {
+        format!("f:\"{}\"", author)
+    } else {
+        format!("f:{}", author)
+    }
+}
+
+/// Gets the `o=` offset of a pagination link, `0` when the link has none
+fn offset_of(href: &str) -> usize {
+    href.split(['?', '&', '#'])
+        .find_map(|param| param.strip_prefix("o="))
+        .and_then(|offset| offset.parse().ok())
+        .unwrap_or(0)
+}
+
+/// An entry of a Lore Atom feed, that is, a single message of a mailing list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchEntry {
+    /// The subject of the message
+    pub title: ArcStr,
+    /// The URL of the message in the archive
+    pub link: ArcStr,
+    /// The message ID, taken from the last segment of the link
+    pub message_id: ArcStr,
+    /// The name of the author of the message, if given
+    pub author_name: Option<ArcStr>,
+    /// The email address of the author of the message, if given
+    pub author_email: Option<ArcStr>,
+    /// When the message was last updated, as an RFC 3339 timestamp
+    pub updated: Option<ArcStr>,
+    /// The message ID of the message this one replies to, if given
+    pub in_reply_to: Option<ArcStr>,
+}
+
+impl PatchEntry {
+    /// Gets the mailing list of the message, taken from the segment of the
+    /// link preceding the message ID.
+    ///
+    /// # Returns
+    /// The list name, or `None` if the link has no such segment.
+    pub fn target_list(&self) -> Option<&str> {
+        let mut segments = self.link.trim_end_matches('/').rsplit('/').skip(1);
+        segments
+            .next()
+            .filter(|list| !list.is_empty() && !list.contains(':'))
+    }
+
+    /// Checks whether the message is the cover letter of a series, numbered
+    /// `[PATCH 0/n]`.
+    ///
+    /// Standalone patches, which have no cover letter, and replies aren't
+    /// cover letters.
+    ///
+    /// # Returns
+    /// `true` if the subject is numbered with position `0`.
+    pub fn is_cover_letter(&self) -> bool {
+        PatchSubject::parse(&self.title)
+            .is_some_and(|subject| matches!(subject.number, Some((0, total)) if total > 0))
+    }
+
+    /// Fetches the HTML page of the message with [`LoreApi::get_patch_html`].
+    ///
+    /// # Arguments
+    /// * `lore_api` - The Lore API actor to fetch with
+    ///
+    /// # Returns
+    /// The HTML content of the message, or an error if the link has no list or
+    /// the request fails.
+    ///
+    /// # Examples
+    /// ```
+    /// for entry in parse_entries(&feed) {
+    ///     let html = entry.fetch_html(&lore_api).await?;
+    /// }
+    /// ```
+    pub async fn fetch_html(&self, lore_api: &LoreApi) -> anyhow::Result<ArcStr> {
+        lore_api
+            .get_patch_html(self.list()?, &self.message_id)
+            .await
+    }
+
+    /// Fetches the raw message with [`LoreApi::get_raw_patch`].
+    ///
+    /// # Arguments
+    /// * `lore_api` - The Lore API actor to fetch with
+    ///
+    /// # Returns
+    /// The raw message, or an error if the link has no list or the request fails.
+    pub async fn fetch_raw(&self, lore_api: &LoreApi) -> anyhow::Result<ArcStr> {
+        lore_api.get_raw_patch(self.list()?, &self.message_id).await
+    }
+
+    /// Fetches the metadata of the message with [`LoreApi::get_patch_metadata`].
+    ///
+    /// # Arguments
+    /// * `lore_api` - The Lore API actor to fetch with
+    ///
+    /// # Returns
+    /// The JSON metadata of the message, or an error if the link has no list or
+    /// the request fails.
+    pub async fn fetch_metadata(&self, lore_api: &LoreApi) -> anyhow::Result<ArcStr> {
+        lore_api
+            .get_patch_metadata(self.list()?, &self.message_id)
+            .await
+    }
+
+    /// Gets the mailing list of the message, failing if the link has none
+    fn list(&self) -> anyhow::Result<&str> {
+        self.target_list()
+            .ok_or_else(|| anyhow::anyhow!("No mailing list in entry link: {}", self.link))
+    }
+}
+
+/// Parses the entries of a Lore Atom feed.
+///
+/// Entries without a title or a link are skipped. XML entities in the extracted
+/// values are decoded.
+///
+/// # Arguments
+/// * `feed` - The content of the feed
+///
+/// # Returns
+/// The entries of the feed, in document order.
+///
+/// # Examples
+/// ```
+/// let entries = parse_entries(&feed);
+/// ```
+pub fn parse_entries(feed: &str) -> Vec<PatchEntry> {
+    ENTRY
+        .captures_iter(feed)
+        .filter_map(|captures| parse_entry(&captures[1]))
+        .collect()
+}
+
+/// Parses the entries of a feed incrementally, as its body is downloaded.
+///
+/// Each entry is yielded as soon as its closing tag is received, and only the
+/// bytes of the entry being received are kept, so memory stays bounded for
+/// feeds of any size, such as the firehose of `/all/`. A body ending before
+/// `</feed>`, or in the middle of an entry, yields an error after the complete
+/// entries, as does a body that fails to download.
+///
+/// # Arguments
+/// * `body` - The chunks of the feed, e.g. from [`crate::net::Net::get_stream`]
+///
+/// # Returns
+/// A stream of the entries of the feed, in document order.
+///
+/// # Examples
+/// ```
+/// let mut entries = stream_entries(net.get_stream(url, None).await?);
+/// while let Some(entry) = std::future::poll_fn(|cx| Pin::new(&mut entries).poll_next(cx)).await {
+///     let entry = entry?;
+///     // ...
+/// }
+/// ```
+pub fn stream_entries<S>(body: S) -> impl Stream<Item = anyhow::Result<PatchEntry>> + Unpin
+where
+    S: Stream<Item = anyhow::Result<Arc<[u8]>>> + Unpin,
+{
+    EntryStream {
+        body,
+        buffer: Vec::new(),
+        entries: VecDeque::new(),
+        closed: false,
+        done: false,
+    }
+}
+
+/// The stream of entries returned by [`stream_entries`]
+struct EntryStream<S> {
+    /// The chunks of the feed
+    body: S,
+    /// The bytes received and not consumed yet
+    buffer: Vec<u8>,
+    /// The entries parsed and not yielded yet
+    entries: VecDeque<PatchEntry>,
+    /// Whether the closing tag of the feed was received
+    closed: bool,
+    /// Whether the body ended
+    done: bool,
+}
+
+impl<S> EntryStream<S> {
+    /// Parses the complete entries of the buffer and drops the consumed bytes
+    fn drain_entries(&mut self) {
+        const OPEN: &[u8] = b"<entry>";
+        const CLOSE: &[u8] = b"</entry>";
+
+        let mut consumed = 0;
+        while let Some(start) = find(&self.buffer[consumed..], OPEN).map(|i| consumed + i) {
+            let body_start = start + OPEN.len();
+            let Some(end) = find(&self.buffer[body_start..], CLOSE).map(|i| body_start + i) else {
+                consumed = start;
+                break;
+            };
+            let entry = String::from_utf8_lossy(&self.buffer[body_start..end]);
+            if let Some(entry) = parse_entry(&entry) {
+                self.entries.push_back(entry);
+            }
+            consumed = end + CLOSE.len();
+        }
+
+        if !self.closed && find(&self.buffer[consumed..], b"</feed>").is_some() {
+            self.closed = true;
+        }
+        if find(&self.buffer[consumed..], OPEN).is_none() {
+            // Keep a tail long enough to hold the start of a split tag
+            consumed = consumed.max(self.buffer.len().saturating_sub(OPEN.len()));
+        }
+        self.buffer.drain(..consumed);
+    }
+}
+
+impl<S> Stream for EntryStream<S>
+where
+    S: Stream<Item = anyhow::Result<Arc<[u8]>>> + Unpin,
+{
+    type Item = anyhow::Result<PatchEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(entry) = self.entries.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match Pin::new(&mut self.body).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.buffer.extend_from_slice(&chunk);
+                    self.drain_entries();
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(err.context("Reading feed"))));
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    if !self.closed || find(&self.buffer, b"<entry>").is_some() {
+                        return Poll::Ready(Some(Err(anyhow::anyhow!(
+                            "Feed truncated before its end"
+                        ))));
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Converts a Lore Atom feed to an RSS 2.0 document.
+///
+/// The channel takes the title and the first link of the feed, and each entry
+/// becomes an item with its title, link, author and date. Feed readers that
+/// don't support Atom can then follow a list. A feed without entries gives a
+/// valid channel without items.
+///
+/// # Arguments
+/// * `feed` - The content of the Atom feed
+///
+/// # Returns
+/// The RSS document.
+///
+/// # Examples
+/// ```
+/// let rss = to_rss(&lore_api.get_patch_feed("amd-gfx", 0).await?);
+/// ```
+pub fn to_rss(feed: &str) -> String {
+    let header = &feed[..feed.find("<entry>").unwrap_or(feed.len())];
+    let title = element_text(header, "title").unwrap_or_else(|| "Lore patch feed".to_string());
+    let link = LINK
+        .captures(header)
+        .map(|captures| unescape(&captures[1]))
+        .unwrap_or_default();
+
+    let mut rss = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    rss.push_str("<rss version=\"2.0\">\n<channel>\n");
+    rss.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    rss.push_str(&format!("<link>{}</link>\n", escape(&link)));
+    rss.push_str(&format!("<description>{}</description>\n", escape(&title)));
+    for entry in parse_entries(feed) {
+        rss.push_str("<item>\n");
+        rss.push_str(&format!("<title>{}</title>\n", escape(&entry.title)));
+        rss.push_str(&format!("<link>{}</link>\n", escape(&entry.link)));
+        rss.push_str(&format!("<guid>{}</guid>\n", escape(&entry.link)));
+        // RSS wants an address in the author element, with the name in parentheses
+        if let Some(email) = &entry.author_email {
+            let author = match &entry.author_name {
+                Some(name) => format!("{} ({})", email, name),
+                None => email.to_string(),
+            };
+            rss.push_str(&format!("<author>{}</author>\n", escape(&author)));
+        }
+        if let Some(date) = entry
+            .updated
+            .as_deref()
+            .and_then(|updated| chrono::DateTime::parse_from_rfc3339(updated).ok())
+        {
+            rss.push_str(&format!("<pubDate>{}</pubDate>\n", date.to_rfc2822()));
+        }
+        rss.push_str("</item>\n");
+    }
+    rss.push_str("</channel>\n</rss>\n");
+    rss
+}
+
+/// Escapes the characters that can't appear as is in XML text
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Finds the first occurrence of a byte pattern
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Parses the content of a single `<entry>` element
+fn parse_entry(entry: &str) -> Option<PatchEntry> {
+    let title = element_text(entry, "title")?;
+    let link = LINK
+        .captures(entry)
+        .map(|captures| unescape(&captures[1]))?;
+    let message_id = message_id_of(&link)?;
+    let in_reply_to = IN_REPLY_TO
+        .captures(entry)
+        .and_then(|captures| message_id_of(&unescape(&captures[1])));
+    let author = element_text(entry, "author");
+
+    Some(PatchEntry {
+        title: ArcStr::from(&title),
+        message_id: ArcStr::from(&message_id),
+        link: ArcStr::from(&link),
+        author_name: author
+            .as_deref()
+            .and_then(|author| element_text(author, "name"))
+            .map(|name| ArcStr::from(&name)),
+        author_email: author
+            .as_deref()
+            .and_then(|author| element_text(author, "email"))
+            .map(|email| ArcStr::from(&email)),
+        updated: element_text(entry, "updated").map(|updated| ArcStr::from(&updated)),
+        in_reply_to: in_reply_to.map(|id| ArcStr::from(&id)),
+    })
+}
+
+/// Gets the message ID of a message URL, from its last segment
+fn message_id_of(link: &str) -> Option<String> {
+    link.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+/// Gets the trimmed, unescaped text of the first element with the given tag.
+///
+/// Nested markup is kept as is, which is enough for the flat elements of a feed entry.
+pub(crate) fn element_text(content: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+
+    let mut search = 0;
+    let start = loop {
+        let start = search + content[search..].find(&open)?;
+        let rest = &content[start + open.len()..];
+        match rest.chars().next() {
+            Some('>') | Some(' ') | Some('\t') | Some('\n') | Some('\r') => break start,
+            _ => search = start + open.len(),
+        }
+    };
+    let body_start = start + content[start..].find('>')? + 1;
+    let body_end = body_start + content[body_start..].find(&close)?;
+    Some(unescape(content[body_start..body_end].trim()))
+}
+
+/// Decodes the predefined XML entities and numeric character references
+pub(crate) fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semicolon];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let feed = r#"<?xml version="1.0" encoding="us-ascii"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>amd-gfx</title>
+<entry><author><name>Jane Doe</name><email>jane@example.com</email></author>
+<title>[PATCH v2 0/2] drm/amdgpu: fix &amp; cleanup</title>
+<updated>2023-12-01T12:34:56Z</updated>
+<link href="https://lore.kernel.org/amd-gfx/20231201.1-1@amd.com/"/>
+<id>urn:uuid:1</id></entry>
+<entry><title>no link</title></entry>
+</feed>"#;
+
+        let entries = parse_entries(feed);
+        assert_eq!(
+            entries,
+            [PatchEntry {
+                title: ArcStr::from("[PATCH v2 0/2] drm/amdgpu: fix & cleanup"),
+                link: ArcStr::from("https://lore.kernel.org/amd-gfx/20231201.1-1@amd.com/"),
+                message_id: ArcStr::from("20231201.1-1@amd.com"),
+                author_name: Some(ArcStr::from("Jane Doe")),
+                author_email: Some(ArcStr::from("jane@example.com")),
+                updated: Some(ArcStr::from("2023-12-01T12:34:56Z")),
+                in_reply_to: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_filter_cover_letters() {
+        let entry = |title: &str, id: &str| {
+            format!(
+                r#"<entry><title>{title}</title><link href="https://lore.kernel.org/amd-gfx/{id}/"/></entry>"#
+            )
+        };
+        let feed = [
+            entry("[PATCH v2 0/3] drm/amdgpu: fix b", "cover-v2"),
+            entry("[PATCH v2 1/3] drm/amdgpu: widen b", "patch-v2-1"),
+            entry("[PATCH] drm/radeon: drop unused c", "single"),
+            entry("Re: [PATCH 0/2] drm: tidy d", "reply"),
+            entry("[RFC PATCH 00/12] drm/sched: rework e", "cover-rfc"),
+            entry("[PATCH 1/1] drm: fix f", "patch-1-1"),
+        ]
+        .concat();
+
+        let covers: Vec<ArcStr> = parse_entries(&feed)
+            .into_iter()
+            .filter(PatchEntry::is_cover_letter)
+            .map(|entry| entry.message_id)
+            .collect();
+        assert_eq!(
+            covers,
+            [ArcStr::from("cover-v2"), ArcStr::from("cover-rfc")]
+        );
+    }
+
+    #[test]
+    fn test_parse_feed_metadata() {
+        let feed = PatchFeed::parse(include_str!("../../../samples/patch_feed.xml"));
+        assert_eq!(
+            feed.title,
+            Some(ArcStr::from("[PATCH] search results - amd-gfx"))
+        );
+        assert_eq!(feed.updated, Some(ArcStr::from("2023-12-01T12:34:57Z")));
+        assert_eq!(
+            feed.id,
+            Some(ArcStr::from("mailto:amd-gfx@lists.freedesktop.org"))
+        );
+        assert_eq!(feed.entries.len(), 2);
+        assert_eq!(feed.next_offset(), Some(200));
+
+        // The feed-level fields are optional, and those of the entries don't count
+        let feed = PatchFeed::parse(
+            r#"<feed><entry><title>a</title><updated>2023-12-01T12:34:56Z</updated><id>1</id>
+<link href="https://lore.kernel.org/amd-gfx/1@a/"/></entry></feed>"#,
+        );
+        assert_eq!((feed.title, feed.updated, feed.id), (None, None, None));
+        assert_eq!(feed.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_feeds() {
+        let entry = |id: &str, title: &str| {
+            format!(
+                r#"<entry><title>{}</title><link href="https://lore.kernel.org/amd-gfx/{}/"/></entry>"#,
+                title, id
+            )
+        };
+        let old = PatchFeed::parse(&format!(
+            "<feed>{}{}{}</feed>",
+            entry("1@a", "[PATCH 1/3] a"),
+            entry("2@a", "[PATCH 2/3] b"),
+            entry("3@a", "[PATCH 3/3] c"),
+        ));
+        let new = PatchFeed::parse(&format!(
+            "<feed>{}{}{}</feed>",
+            entry("4@a", "[PATCH] d"),
+            entry("1@a", "[PATCH 1/3] a"),
+            entry("2@a", "[PATCH 2/3] b, reworded"),
+        ));
+
+        let ids = |entries: &[PatchEntry]| {
+            entries
+                .iter()
+                .map(|entry| entry.message_id.to_string())
+                .collect::<Vec<_>>()
+        };
+        let diff = diff_feeds(&old, &new);
+        assert_eq!(ids(&diff.added), ["4@a"]);
+        assert_eq!(ids(&diff.removed), ["3@a"]);
+        assert_eq!(ids(&diff.changed), ["2@a"]);
+        assert_eq!(
+            diff.changed[0].title,
+            ArcStr::from("[PATCH 2/3] b, reworded")
+        );
+        assert_eq!(ids(&diff.unchanged), ["1@a"]);
+        assert!(!diff.is_empty());
+        assert!(diff_feeds(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_feeds_matches_equivalent_message_ids() {
+        let feed = |id: &str| {
+            PatchFeed::parse(&format!(
+                r#"<feed><entry><title>[PATCH] a</title><link href="https://lore.kernel.org/amd-gfx/{}/"/></entry></feed>"#,
+                id
+            ))
+        };
+
+        let diff = diff_feeds(&feed("1@amd.com"), &feed("1@AMD.com"));
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+    }
+
+    #[test]
+    fn test_lei_query() {
+        assert_eq!(lei_query("s:drm f:jane").unwrap(), "s:drm AND f:jane");
+        assert_eq!(
+            lei_query(r#"s:"drm: fix b" AND f:"Jane Doe""#).unwrap(),
+            r#"s:"drm: fix b" AND f:"Jane Doe""#
+        );
+        assert_eq!(
+            lei_query("d:2023-12-01.. and amdgpu").unwrap(),
+            "d:20231201.. AND amdgpu"
+        );
+        assert_eq!(lei_query("d:..2.weeks.ago").unwrap(), "d:..2.weeks.ago");
+
+        assert!(lei_query("").is_err());
+        assert!(lei_query("s:drm AND").is_err());
+        assert!(lei_query("AND s:drm").is_err());
+        assert!(lei_query("s:drm OR s:amdgpu").is_err());
+        assert!(lei_query("b:fix").is_err());
+        assert!(lei_query(r#"s:"unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_to_rss() {
+        let feed = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>amd-gfx</title>
+<link rel="alternate" type="text/html" href="https://lore.kernel.org/amd-gfx/"/>
+<entry><author><name>Jane Doe</name><email>jane@example.com</email></author>
+<title>[PATCH] drm/amdgpu: fix &amp; cleanup</title>
+<updated>2023-12-12T12:34:56Z</updated>
+<link href="https://lore.kernel.org/amd-gfx/1@amd.com/"/></entry>
+</feed>"#;
+
+        let rss = to_rss(feed);
+        assert!(rss.contains("<link>https://lore.kernel.org/amd-gfx/</link>"));
+        assert!(rss.contains("<title>[PATCH] drm/amdgpu: fix &amp; cleanup</title>"));
+        assert!(rss.contains("<author>jane@example.com (Jane Doe)</author>"));
+        assert!(rss.contains("<pubDate>Tue, 12 Dec 2023 12:34:56 +0000</pubDate>"));
+
+        let empty = to_rss("<feed><title>amd-gfx</title></feed>");
+        assert!(empty.contains("<channel>\n<title>amd-gfx</title>"));
+        assert!(!empty.contains("<item>"));
+        assert!(empty.ends_with("</channel>\n</rss>\n"));
+    }
+
+    #[tokio::test]
+    async fn test_entry_fetch_html() {
+        let feed = r#"<feed><entry><title>[PATCH] drm/amdgpu: fix b</title>
+<link href="https://lore.kernel.org/amd-gfx/20231201.1-1@amd.com/"/></entry></feed>"#;
+        let entry = &parse_entries(feed)[0];
+        assert_eq!(entry.target_list(), Some("amd-gfx"));
+
+        let lore_api = LoreApi::mock(std::collections::HashMap::from([(
+            "patch_html_amd-gfx_20231201.1-1@amd.com".to_string(),
+            ArcStr::from("<html>patch</html>"),
+        )]));
+        assert_eq!(
+            entry.fetch_html(&lore_api).await.unwrap(),
+            ArcStr::from("<html>patch</html>")
+        );
+        assert!(entry.fetch_raw(&lore_api).await.is_err());
+    }
+
+    /// Polls the next item of a stream without waiting, `None` if it isn't ready
+    fn poll_now<S: Stream + Unpin>(stream: &mut S) -> Option<Option<S::Item>> {
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+        match Pin::new(stream).poll_next(&mut cx) {
+            Poll::Ready(item) => Some(item),
+            Poll::Pending => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_entries_incrementally() {
+        let (tx, body) = crate::net::BodyStream::channel();
+        let mut entries = stream_entries(body);
+        let chunk = |text: &str| Ok(Arc::from(text.as_bytes()));
+
+        tx.send(chunk(r#"<feed><entry><title>[PATCH 1/2] a</title><link href="https://lore.kernel.org/x/1@a/"/></entry><ent"#))
+            .await
+            .unwrap();
+        let first = poll_now(&mut entries).unwrap().unwrap().unwrap();
+        assert_eq!(&*first.message_id, "1@a");
+        assert!(poll_now(&mut entries).is_none());
+
+        tx.send(chunk(r#"ry><title>[PATCH 2/2] b</title><link href="https://lore.kernel.org/x/2@a/"/></entry><entry><title>[PATCH] c"#))
+            .await
+            .unwrap();
+        let second = poll_now(&mut entries).unwrap().unwrap().unwrap();
+        assert_eq!(&*second.message_id, "2@a");
+        assert!(poll_now(&mut entries).is_none());
+
+        // The body ends in the middle of the third entry
+        drop(tx);
+        assert!(poll_now(&mut entries).unwrap().unwrap().is_err());
+        assert!(poll_now(&mut entries).unwrap().is_none());
+
+        let mut complete = stream_entries(crate::net::BodyStream::once(Arc::from(
+            &b"<feed><entry><title>t</title><link href=\"https://lore.kernel.org/x/3@a/\"/></entry></feed>"[..],
+        )));
+        assert!(poll_now(&mut complete).unwrap().unwrap().is_ok());
+        assert!(poll_now(&mut complete).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pagination_offsets() {
+        let page = r#"<html><body><pre>
+<a href="/amd-gfx/20231201.1-1@amd.com/">[PATCH] drm/amdgpu: fix b</a>
+</pre><hr><pre>page: <a
+href="?q=s:patch&amp;o=400"
+rel=next>next (older)</a> | <a href="?q=s:patch&amp;o=0" rel="prev">prev (newer)</a>
+</pre></body></html>"#;
+
+        let feed = PatchFeed::parse(page);
+        assert_eq!(feed.next_offset(), Some(400));
+        assert_eq!(feed.prev_offset(), Some(0));
+
+        let last = PatchFeed::parse(r#"<feed><link rel="prev" href="./?x=A&amp;o=200"/></feed>"#);
+        assert_eq!(last.next_offset(), None);
+        assert_eq!(last.prev_offset(), Some(200));
+    }
+
+    #[test]
+    fn test_mailing_lists() {
+        let page = r#"<html><body><pre>
+* 2023-12-01 12:34 - <a
+href="https://lore.kernel.org/amd-gfx/">amd-gfx</a>
+  AMD GPU drivers &amp; firmware
+* 2023-11-30 08:00 - <a
+href="https://lore.kernel.org/dri-devel/"></a>
+* 2023-11-29 17:00 - <a
+href="https://lore.kernel.org/linux-media/">linux-media</a>
+</pre><a
+href="?&amp;o=200" rel=next>next (older)</a></body></html>"#;
+
+        assert_eq!(
+            mailing_lists(page),
+            [
+                MailingList {
+                    name: ArcStr::from("amd-gfx"),
+                    url: ArcStr::from("https://lore.kernel.org/amd-gfx/"),
+                    description: Some(ArcStr::from("AMD GPU drivers & firmware")),
+                },
+                MailingList {
+                    name: ArcStr::from("dri-devel"),
+                    url: ArcStr::from("https://lore.kernel.org/dri-devel/"),
+                    description: None,
+                },
+                MailingList {
+                    name: ArcStr::from("linux-media"),
+                    url: ArcStr::from("https://lore.kernel.org/linux-media/"),
+                    description: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_maintainers() {
+        let page = r#"<html><body><pre>amd-gfx: AMD graphics drivers
+
+Maintainers:
+  Jane Doe &lt;jane@example.com&gt;
+  <a href="mailto:john@example.com">john@example.com</a>
+
+Other help topics
+</pre></body></html>"#;
+        assert_eq!(
+            maintainers(page),
+            [
+                Maintainer {
+                    name: Some(ArcStr::from("Jane Doe")),
+                    email: ArcStr::from("jane@example.com"),
+                },
+                Maintainer {
+                    name: None,
+                    email: ArcStr::from("john@example.com"),
+                },
+            ]
+        );
+        assert!(maintainers("<html><pre>No maintainers here</pre></html>").is_empty());
+    }
+
+    #[test]
+    fn test_author_term() {
+        assert_eq!(author_term("Jane Doe"), "f:\"Jane Doe\"");
+        assert_eq!(author_term("jane@example.com"), "f:jane@example.com");
+        assert_eq!(
+            author_term("Jane Doe <jane@example.com>"),
+            "f:jane@example.com"
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape("a &lt;b&gt; &#39;c&#x27; &bogus; &"),
+            "a <b> 'c' &bogus; &"
+        );
+    }
+}
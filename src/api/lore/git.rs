@@ -0,0 +1,571 @@
+//! Fetching of messages from the git archives of mailing lists over the
+//! smart-HTTP protocol.
+//!
+//! public-inbox stores each message of a list as the blob `m` of its own
+//! commit. The archive is queried with version 2 of the protocol: `ls-refs`
+//! gives the tip of the newest epoch, and a shallow `fetch` of its history
+//! gives a pack holding the latest messages, which is searched for the
+//! message ID.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use miniz_oxide::{
+    DataFormat, MZFlush, MZStatus,
+    inflate::stream::{InflateState, inflate},
+};
+
+use crate::{
+    ArcStr,
+    api::lore::mail::{MailPart, message_ids_equal},
+};
+
+/// The content type of the requests sent to `git-upload-pack`
+pub const UPLOAD_PACK_REQUEST: &str = "application/x-git-upload-pack-request";
+
+/// The content type of the responses of `git-upload-pack`
+pub const UPLOAD_PACK_RESULT: &str = "application/x-git-upload-pack-result";
+
+/// How many of the latest commits of an archive are searched for a message
+pub const SEARCH_DEPTH: usize = 1000;
+
+/// The SHA-1 id of a git object
+pub type ObjectId = [u8; 20];
+
+/// A packet of the pkt-line framing of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packet<'a> {
+    /// A packet carrying data
+    Data(&'a [u8]),
+    /// The end of a message (`0000`)
+    Flush,
+    /// The end of a section of a message (`0001`)
+    Delim,
+    /// The end of a response, in stateless connections (`0002`)
+    ResponseEnd,
+}
+
+/// The type of a git object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    /// Gets the name of the type, as hashed with the content of an object
+    fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::Blob => "blob",
+            ObjectKind::Tag => "tag",
+        }
+    }
+}
+
+/// Frames a payload as a pkt-line.
+///
+/// # Arguments
+/// * `payload` - The payload, including its trailing newline if any
+///
+/// # Returns
+/// The payload prefixed with its length, as four hexadecimal digits.
+pub fn encode_packet(payload: &str) -> String {
+    format!("{:04x}{}", payload.len() + 4, payload)
+}
+
+/// Splits a body into its pkt-lines.
+///
+/// # Arguments
+/// * `body` - The body, made of pkt-lines only
+///
+/// # Returns
+/// The packets, in order, or an error if the framing is broken.
+pub fn decode_packets(mut body: &[u8]) -> anyhow::Result<Vec<Packet<'_>>> {
+    let mut packets = Vec::new();
+    while !body.is_empty() {
+        let length = body
+            .get(..4)
+            .and_then(|length| std::str::from_utf8(length).ok())
+            .and_then(|length| usize::from_str_radix(length, 16).ok())
+            .context("Invalid pkt-line length")?;
+        let packet = match length {
+            0 => Packet::Flush,
+            1 => Packet::Delim,
+            2 => Packet::ResponseEnd,
+            3 => anyhow::bail!("Invalid pkt-line length: 3"),
+            _ => Packet::Data(body.get(4..length).context("Truncated pkt-line")?),
+        };
+        packets.push(packet);
+        body = &body[length.max(4)..];
+    }
+    Ok(packets)
+}
+
+/// Builds an `ls-refs` request listing `HEAD` and the branches of a repository.
+pub fn ls_refs_request() -> String {
+    [
+        encode_packet("command=ls-refs\n"),
+        "0001".to_string(),
+        encode_packet("ref-prefix HEAD\n"),
+        encode_packet("ref-prefix refs/heads/\n"),
+        "0000".to_string(),
+    ]
+    .concat()
+}
+
+/// Picks the tip of a repository in the response to an `ls-refs` request.
+///
+/// # Arguments
+/// * `body` - The body of the response
+///
+/// # Returns
+/// The hex id of the commit of `HEAD`, of the first branch if `HEAD` isn't
+/// listed, `None` for an empty repository, or an error if the response is
+/// malformed.
+pub fn parse_head(body: &[u8]) -> anyhow::Result<Option<String>> {
+    let mut refs = Vec::new();
+    for packet in decode_packets(body)? {
+        let Packet::Data(line) = packet else {
+            continue;
+        };
+        let line = std::str::from_utf8(line).context("Invalid ref advertisement")?;
+        if let Some(message) = line.strip_prefix("ERR ") {
+            anyhow::bail!("Server error: {}", message.trim());
+        }
+        let mut fields = line.trim_end().split(' ');
+        let (Some(id), Some(name)) = (fields.next(), fields.next()) else {
+            anyhow::bail!("Invalid ref advertisement: {}", line.trim_end());
+        };
+        if parse_hex(id).is_some() {
+            refs.push((name.to_string(), id.to_string()));
+        }
+    }
+    let head = refs
+        .iter()
+        .find(|(name, _)| name == "HEAD")
+        .or_else(|| refs.first());
+    Ok(head.map(|(_, id)| id.clone()))
+}
+
+/// Builds a `fetch` request for the latest commits leading to a tip.
+///
+/// # Arguments
+/// * `want` - The hex id of the tip
+/// * `depth` - How many commits of its history to fetch
+///
+/// # Returns
+/// The body of the request.
+pub fn fetch_request(want: &str, depth: usize) -> String {
+    [
+        encode_packet("command=fetch\n"),
+        "0001".to_string(),
+        encode_packet("no-progress\n"),
+        encode_packet(&format!("want {}\n", want)),
+        encode_packet(&format!("deepen {}\n", depth)),
+        encode_packet("done\n"),
+        "0000".to_string(),
+    ]
+    .concat()
+}
+
+/// Extracts the pack of the response to a `fetch` request.
+///
+/// The pack is sent in the `packfile` section, multiplexed with progress
+/// messages and errors on the side-band channels.
+///
+/// # Arguments
+/// * `body` - The body of the response
+///
+/// # Returns
+/// The pack, or an error if the server reported one or sent no pack.
+pub fn packfile(body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut packets = decode_packets(body)?.into_iter();
+    for packet in packets.by_ref() {
+        match packet {
+            Packet::Data(b"packfile\n") => break,
+            Packet::Data(line) if line.starts_with(b"ERR ") => anyhow::bail!(
+                "Server error: {}",
+                String::from_utf8_lossy(&line[4..]).trim()
+            ),
+            _ => {}
+        }
+    }
+
+    let mut pack = Vec::new();
+    for packet in packets {
+        let Packet::Data(line) = packet else {
+            break;
+        };
+        match line.split_first() {
+            Some((1, data)) => pack.extend_from_slice(data),
+            Some((3, message)) => {
+                anyhow::bail!("Server error: {}", String::from_utf8_lossy(message).trim())
+            }
+            _ => {}
+        }
+    }
+    anyhow::ensure!(!pack.is_empty(), "No pack in the fetch response");
+    Ok(pack)
+}
+
+/// Unpacks the objects of a pack, resolving the deltas.
+///
+/// # Arguments
+/// * `pack` - The pack, as sent by the server
+///
+/// # Returns
+/// The objects keyed by id, or an error if the pack is malformed or holds a
+/// delta whose base it doesn't have.
+pub fn parse_pack(pack: &[u8]) -> anyhow::Result<HashMap<ObjectId, (ObjectKind, Vec<u8>)>> {
+    anyhow::ensure!(pack.starts_with(b"PACK"), "Invalid pack signature");
+    let header = pack.get(4..12).context("Truncated pack header")?;
+    let version = u32::from_be_bytes(header[..4].try_into()?);
+    anyhow::ensure!(
+        matches!(version, 2 | 3),
+        "Unsupported pack version: {}",
+        version
+    );
+    let count = u32::from_be_bytes(header[4..].try_into()?);
+
+    let mut objects: HashMap<ObjectId, (ObjectKind, Vec<u8>)> = HashMap::new();
+    let mut offsets: HashMap<usize, ObjectId> = HashMap::new();
+    let mut position = 12;
+    for _ in 0..count {
+        let start = position;
+        let mut byte = read_byte(pack, &mut position)?;
+        let kind = (byte >> 4) & 0x7;
+        let mut size = usize::from(byte & 0xf);
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_byte(pack, &mut position)?;
+            size |= usize::from(byte & 0x7f)
+                .checked_shl(shift)
+                .context("Invalid object size")?;
+            shift += 7;
+        }
+
+        let base = match kind {
+            6 => {
+                let mut byte = read_byte(pack, &mut position)?;
+                let mut distance = usize::from(byte & 0x7f);
+                while byte & 0x80 != 0 {
+                    byte = read_byte(pack, &mut position)?;
+                    distance = ((distance + 1) << 7) | usize::from(byte & 0x7f);
+                }
+                let offset = start
+                    .checked_sub(distance)
+                    .context("Invalid delta offset")?;
+                Some(
+                    *offsets
+                        .get(&offset)
+                        .context("Delta base missing from the pack")?,
+                )
+            }
+            7 => {
+                let id = pack
+                    .get(position..position + 20)
+                    .context("Truncated delta base")?;
+                position += 20;
+                Some(id.try_into()?)
+            }
+            _ => None,
+        };
+
+        let (data, consumed) = inflate_object(&pack[position..], size)?;
+        position += consumed;
+        let (kind, content) = match base {
+            Some(base) => {
+                let (kind, base) = objects
+                    .get(&base)
+                    .context("Delta base missing from the pack")?;
+                (*kind, apply_delta(base, &data)?)
+            }
+            None => {
+                let kind = match kind {
+                    1 => ObjectKind::Commit,
+                    2 => ObjectKind::Tree,
+                    3 => ObjectKind::Blob,
+                    4 => ObjectKind::Tag,
+                    _ => anyhow::bail!("Invalid object type: {}", kind),
+                };
+                (kind, data)
+            }
+        };
+        let id = object_id(kind, &content);
+        offsets.insert(start, id);
+        objects.insert(id, (kind, content));
+    }
+    Ok(objects)
+}
+
+/// Looks for a message in the commits of a pack.
+///
+/// # Arguments
+/// * `objects` - The objects of the pack, as given by [`parse_pack`]
+/// * `message_id` - The message ID to look for, with or without angle brackets
+///
+/// # Returns
+/// The raw message, or `None` if no commit of the pack holds it.
+pub fn find_message(
+    objects: &HashMap<ObjectId, (ObjectKind, Vec<u8>)>,
+    message_id: &str,
+) -> Option<ArcStr> {
+    objects
+        .values()
+        .filter(|(kind, _)| *kind == ObjectKind::Commit)
+        .filter_map(|(_, commit)| {
+            let tree = std::str::from_utf8(commit).ok()?.lines().next()?;
+            let (_, tree) = objects.get(&parse_hex(tree.strip_prefix("tree ")?)?)?;
+            let (_, blob) = objects.get(&tree_entry(tree, "m")?)?;
+            Some(String::from_utf8_lossy(blob))
+        })
+        .find(|message| {
+            MailPart::parse(message)
+                .header("message-id")
+                .is_some_and(|id| message_ids_equal(id, message_id))
+        })
+        .map(|message| ArcStr::from(&message.into_owned()))
+}
+
+/// Computes the id of an object from its type and content.
+pub(crate) fn object_id(kind: ObjectKind, content: &[u8]) -> ObjectId {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY);
+    context.update(format!("{} {}\0", kind.name(), content.len()).as_bytes());
+    context.update(content);
+    context
+        .finish()
+        .as_ref()
+        .try_into()
+        .expect("SHA-1 digests are 20 bytes long")
+}
+
+/// Reads the byte at a position of a pack and moves past it
+fn read_byte(pack: &[u8], position: &mut usize) -> anyhow::Result<u8> {
+    let byte = *pack.get(*position).context("Truncated pack")?;
+    *position += 1;
+    Ok(byte)
+}
+
+/// Inflates an object of known size, returning it with the number of compressed bytes read
+fn inflate_object(input: &[u8], size: usize) -> anyhow::Result<(Vec<u8>, usize)> {
+    let mut state = InflateState::new_boxed(DataFormat::Zlib);
+    // One spare byte tells an object larger than announced from a complete one
+    let mut output = vec![0; size + 1];
+    let result = inflate(&mut state, input, &mut output, MZFlush::Finish);
+    anyhow::ensure!(
+        result.status == Ok(MZStatus::StreamEnd) && result.bytes_written == size,
+        "Corrupt object in pack"
+    );
+    output.truncate(size);
+    Ok((output, result.bytes_consumed))
+}
+
+/// Rebuilds an object from its base and a delta
+fn apply_delta(base: &[u8], delta: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut position = 0;
+    let read_size = |position: &mut usize| -> anyhow::Result<usize> {
+        let mut size = 0;
+        let mut shift = 0;
+        loop {
+            let byte = read_byte(delta, position)?;
+            size |= usize::from(byte & 0x7f)
+                .checked_shl(shift)
+                .context("Invalid delta size")?;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(size);
+            }
+        }
+    };
+    let base_size = read_size(&mut position)?;
+    anyhow::ensure!(base_size == base.len(), "Delta base size mismatch");
+    let size = read_size(&mut position)?;
+
+    let mut object = Vec::with_capacity(size);
+    while position < delta.len() {
+        let op = read_byte(delta, &mut position)?;
+        if op & 0x80 != 0 {
+            let mut offset = 0;
+            for bit in 0..4 {
+                if op & (1 << bit) != 0 {
+                    offset |= usize::from(read_byte(delta, &mut position)?) << (8 * bit);
+                }
+            }
+            let mut length = 0;
+            for bit in 0..3 {
+                if op & (0x10 << bit) != 0 {
+                    length |= usize::from(read_byte(delta, &mut position)?) << (8 * bit);
+                }
+            }
+            if length == 0 {
+                length = 0x10000;
+            }
+            let copied = base
+                .get(offset..offset + length)
+                .context("Delta copies past its base")?;
+            object.extend_from_slice(copied);
+        } else if op != 0 {
+            let inserted = delta
+                .get(position..position + usize::from(op))
+                .context("Truncated delta")?;
+            object.extend_from_slice(inserted);
+            position += usize::from(op);
+        } else {
+            anyhow::bail!("Invalid delta instruction");
+        }
+    }
+    anyhow::ensure!(object.len() == size, "Delta result size mismatch");
+    Ok(object)
+}
+
+/// Finds the id of an entry of a tree by name
+fn tree_entry(tree: &[u8], name: &str) -> Option<ObjectId> {
+    let mut rest = tree;
+    while !rest.is_empty() {
+        let nul = rest.iter().position(|byte| *byte == 0)?;
+        let (_, entry_name) = std::str::from_utf8(&rest[..nul]).ok()?.split_once(' ')?;
+        let id: ObjectId = rest.get(nul + 1..nul + 21)?.try_into().ok()?;
+        if entry_name == name {
+            return Some(id);
+        }
+        rest = &rest[nul + 21..];
+    }
+    None
+}
+
+/// Parses the hex form of an object id
+fn parse_hex(hex: &str) -> Option<ObjectId> {
+    if hex.len() != 40 || !hex.is_ascii() {
+        return None;
+    }
+    let mut id = [0; 20];
+    for (index, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(id)
+}
+
+/// Formats an object id in hex
+fn to_hex(id: &ObjectId) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Builds the response to a `fetch` request sending the given objects, as
+/// a server without deltas would
+#[cfg(test)]
+pub(crate) fn fetch_response(objects: &[(ObjectKind, Vec<u8>)]) -> Vec<u8> {
+    let mut pack = b"PACK".to_vec();
+    pack.extend_from_slice(&2u32.to_be_bytes());
+    pack.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+    for (kind, content) in objects {
+        let kind = match kind {
+            ObjectKind::Commit => 1,
+            ObjectKind::Tree => 2,
+            ObjectKind::Blob => 3,
+            ObjectKind::Tag => 4,
+        };
+        pack.extend_from_slice(&object_header(kind, content.len()));
+        pack.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(content, 6));
+    }
+    pack.extend_from_slice(&[0; 20]);
+
+    let mut response = encode_packet("packfile\n").into_bytes();
+    for chunk in pack.chunks(1000) {
+        response.extend_from_slice(format!("{:04x}\u{1}", chunk.len() + 5).as_bytes());
+        response.extend_from_slice(chunk);
+    }
+    response.extend_from_slice(b"0000");
+    response
+}
+
+/// Encodes the type and size of an object of a pack
+#[cfg(test)]
+fn object_header(kind: u8, size: usize) -> Vec<u8> {
+    let mut header = vec![(kind << 4) | (size & 0xf) as u8];
+    let mut size = size >> 4;
+    while size > 0 {
+        *header.last_mut().unwrap() |= 0x80;
+        header.push((size & 0x7f) as u8);
+        size >>= 7;
+    }
+    header
+}
+
+/// Builds the commit of a message, as public-inbox stores it, returning its
+/// objects and the id of the commit
+#[cfg(test)]
+pub(crate) fn message_commit(
+    message: &str,
+    parent: Option<&str>,
+) -> (Vec<(ObjectKind, Vec<u8>)>, String) {
+    let blob = message.as_bytes().to_vec();
+    let mut tree = b"100644 m\0".to_vec();
+    tree.extend_from_slice(&object_id(ObjectKind::Blob, &blob));
+    let mut commit = format!("tree {}\n", to_hex(&object_id(ObjectKind::Tree, &tree)));
+    if let Some(parent) = parent {
+        commit.push_str(&format!("parent {}\n", parent));
+    }
+    commit.push_str("author a <a@example.com> 1701434096 +0000\n");
+    commit.push_str("committer a <a@example.com> 1701434096 +0000\n\nmessage\n");
+    let id = to_hex(&object_id(ObjectKind::Commit, commit.as_bytes()));
+    (
+        vec![
+            (ObjectKind::Blob, blob),
+            (ObjectKind::Tree, tree),
+            (ObjectKind::Commit, commit.into_bytes()),
+        ],
+        id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_packets() {
+        let body = format!("{}0001{}0000", encode_packet("a\n"), encode_packet("bc"));
+        assert_eq!(
+            decode_packets(body.as_bytes()).unwrap(),
+            [
+                Packet::Data(b"a\n"),
+                Packet::Delim,
+                Packet::Data(b"bc"),
+                Packet::Flush,
+            ]
+        );
+        assert!(decode_packets(b"00ffab").is_err());
+    }
+
+    #[test]
+    fn test_parse_pack_with_delta() {
+        let base = b"Message-ID: <a@b>\n\nfirst version of the body\n".to_vec();
+        let target = b"Message-ID: <a@b>\n\nfirst version of the body, amended\n";
+        // Copy the whole base but its newline, then insert the amendment
+        let mut delta = vec![base.len() as u8, target.len() as u8];
+        delta.extend_from_slice(&[0x80 | 0x10, (base.len() - 1) as u8]);
+        delta.push(10);
+        delta.extend_from_slice(b", amended\n");
+
+        let mut pack = b"PACK\0\0\0\x02\0\0\0\x02".to_vec();
+        let base_offset = pack.len();
+        pack.extend_from_slice(&object_header(3, base.len()));
+        pack.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(&base, 6));
+        let delta_offset = pack.len();
+        pack.extend_from_slice(&object_header(6, delta.len()));
+        pack.push((delta_offset - base_offset) as u8);
+        pack.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(&delta, 6));
+
+        let objects = parse_pack(&pack).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            objects.get(&object_id(ObjectKind::Blob, target)),
+            Some(&(ObjectKind::Blob, target.to_vec()))
+        );
+        assert!(parse_pack(&pack[..pack.len() - 4]).is_err());
+    }
+}
@@ -0,0 +1,678 @@
+//! Helpers for reading the raw messages served by the Lore Kernel Archive.
+
+use std::sync::Arc;
+
+use base64::Engine;
+
+use crate::{ArcStr, api::lore::patch::PatchSubject};
+
+/// A file attached to a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// The name of the file, if the sender gave one
+    pub filename: Option<ArcStr>,
+    /// The lowercase MIME type of the attachment
+    pub content_type: ArcStr,
+    /// The content of the attachment, decoded from its transfer encoding
+    pub bytes: Arc<[u8]>,
+}
+
+/// The links of a message to the messages it replies to, for reconstructing
+/// its thread.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageLinks {
+    /// The normalized message ID of the parent of the message, if any
+    pub in_reply_to: Option<ArcStr>,
+    /// The normalized message IDs of the ancestors of the message, oldest first
+    pub references: Vec<ArcStr>,
+}
+
+impl MessageLinks {
+    /// Parses the `In-Reply-To` and `References` headers of a message.
+    ///
+    /// The IDs are taken from between angle brackets, so comments and phrases
+    /// some clients add around them are ignored, and are normalized with
+    /// [`normalize_message_id`]. A header without any bracketed ID falls back
+    /// to its whitespace-separated words that look like IDs. Repeated
+    /// references are kept once, at their first position. A message without
+    /// `In-Reply-To` takes its parent from the last reference, as threading
+    /// clients do.
+    ///
+    /// # Arguments
+    /// * `raw` - The raw message
+    ///
+    /// # Returns
+    /// The links of the message, empty for a message starting a thread.
+    pub fn parse(raw: &str) -> Self {
+        let message = MailPart::parse(raw);
+        let mut references: Vec<ArcStr> = Vec::new();
+        for id in message_ids(message.header("references").unwrap_or_default()) {
+            if !references.contains(&id) {
+                references.push(id);
+            }
+        }
+        let in_reply_to = message
+            .header("in-reply-to")
+            .and_then(|header| message_ids(header).into_iter().next())
+            .or_else(|| references.last().cloned());
+
+        Self {
+            in_reply_to,
+            references,
+        }
+    }
+}
+
+/// Extracts the normalized message IDs of a header
+fn message_ids(header: &str) -> Vec<ArcStr> {
+    let bracketed: Vec<ArcStr> = header
+        .split('<')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('>'))
+        .map(|(id, _)| id.trim())
+        .filter(|id| !id.is_empty())
+        .map(normalize_message_id)
+        .collect();
+    if !bracketed.is_empty() {
+        return bracketed;
+    }
+    header
+        .split_whitespace()
+        .filter(|word| word.contains('@'))
+        .map(normalize_message_id)
+        .collect()
+}
+
+/// A parsed message or MIME part: its headers and its undecoded body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailPart {
+    /// The unfolded headers, with lowercase names, in order
+    pub headers: Vec<(String, String)>,
+    /// The body, still in its transfer encoding
+    pub body: String,
+}
+
+impl MailPart {
+    /// Parses the headers and body of a message or MIME part.
+    ///
+    /// Line endings are normalized to LF. A message without a blank line after
+    /// its headers has an empty body.
+    ///
+    /// # Arguments
+    /// * `raw` - The raw message
+    ///
+    /// # Returns
+    /// The parsed message.
+    pub fn parse(raw: &str) -> Self {
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut lines = raw.lines();
+
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            } else if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+        }
+
+        let mut body = lines.collect::<Vec<_>>().join("\n");
+        if raw.ends_with('\n') && !body.is_empty() {
+            body.push('\n');
+        }
+
+        Self { headers, body }
+    }
+
+    /// Gets the value of the first header with the given name.
+    ///
+    /// # Arguments
+    /// * `name` - The header name, in any case
+    ///
+    /// # Returns
+    /// The header value, or `None` if the header is missing.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets the lowercase MIME type of the part, `text/plain` when not given.
+    pub fn mime_type(&self) -> String {
+        self.header("content-type")
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|mime| mime.trim().to_lowercase())
+            .filter(|mime| !mime.is_empty())
+            .unwrap_or_else(|| "text/plain".to_string())
+    }
+
+    /// Gets a parameter of the `Content-Type` header, such as `charset` or `boundary`.
+    pub fn content_type_param(&self, param: &str) -> Option<String> {
+        self.header_param("content-type", param)
+    }
+
+    /// Gets the file name of the part, from its `Content-Disposition` header or,
+    /// failing that, from the `name` parameter of its `Content-Type` header.
+    pub fn filename(&self) -> Option<String> {
+        self.header_param("content-disposition", "filename")
+            .or_else(|| self.content_type_param("name"))
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Tells whether the part is marked as an attachment by its `Content-Disposition` header.
+    pub fn is_attachment(&self) -> bool {
+        self.header("content-disposition")
+            .and_then(|disposition| disposition.split(';').next())
+            .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("attachment"))
+    }
+
+    /// Gets a parameter of a structured header such as `Content-Type`
+    fn header_param(&self, name: &str, param: &str) -> Option<String> {
+        self.header(name)?.split(';').skip(1).find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case(param)
+                .then(|| value.trim().trim_matches('"').to_string())
+        })
+    }
+
+    /// Gets the sub-parts of a multipart part, or `None` if it isn't multipart.
+    pub fn parts(&self) -> Option<Vec<MailPart>> {
+        if !self.mime_type().starts_with("multipart/") {
+            return None;
+        }
+        let boundary = self.content_type_param("boundary")?;
+        let delimiter = format!("--{}", boundary);
+        let end = format!("--{}--", boundary);
+
+        let mut parts = Vec::new();
+        let mut current: Option<Vec<&str>> = None;
+        for line in self.body.lines() {
+            let line_trimmed = line.trim_end();
+            if line_trimmed == end || line_trimmed == delimiter {
+                if let Some(lines) = current.take() {
+                    parts.push(MailPart::parse(&lines.join("\n")));
+                }
+                if line_trimmed == end {
+                    break;
+                }
+                current = Some(Vec::new());
+            } else if let Some(lines) = current.as_mut() {
+                lines.push(line);
+            }
+        }
+        if let Some(lines) = current {
+            parts.push(MailPart::parse(&lines.join("\n")));
+        }
+
+        Some(parts)
+    }
+
+    /// Decodes the body of the part from its transfer encoding.
+    ///
+    /// `quoted-printable` and `base64` bodies are decoded, other bodies are
+    /// returned as they are. A malformed `base64` body is returned undecoded.
+    ///
+    /// # Returns
+    /// The decoded bytes of the body.
+    pub fn decoded_bytes(&self) -> Vec<u8> {
+        let encoding = self
+            .header("content-transfer-encoding")
+            .map(|encoding| encoding.trim().to_lowercase());
+        match encoding.as_deref() {
+            Some("quoted-printable") => decode_quoted_printable(&self.body),
+            Some("base64") => {
+                let compact: String = self.body.split_whitespace().collect();
+                base64::engine::general_purpose::STANDARD
+                    .decode(compact.as_bytes())
+                    .unwrap_or_else(|_| self.body.as_bytes().to_vec())
+            }
+            _ => self.body.as_bytes().to_vec(),
+        }
+    }
+
+    /// Decodes the body of the part from its transfer encoding and charset.
+    ///
+    /// `quoted-printable` and `base64` bodies are decoded, and the result is
+    /// converted from the charset of the part, UTF-8 when not given. Bytes that
+    /// are invalid in the charset are replaced.
+    ///
+    /// # Returns
+    /// The decoded body.
+    pub fn decoded_body(&self) -> ArcStr {
+        let bytes = self.decoded_bytes();
+        let charset = self
+            .content_type_param("charset")
+            .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8);
+        let (text, _, _) = charset.decode(&bytes);
+        ArcStr::from(&*text)
+    }
+
+    /// Finds the first `text/plain` part, searching multipart parts depth-first.
+    ///
+    /// # Returns
+    /// The text part, or `None` if the message has none.
+    pub fn text_part(&self) -> Option<MailPart> {
+        match self.parts() {
+            Some(parts) => parts.iter().find_map(MailPart::text_part),
+            None => (self.mime_type() == "text/plain").then(|| self.clone()),
+        }
+    }
+}
+
+/// Extracts the prose of a message, without headers and without any patch.
+///
+/// The first `text/plain` part of the message is decoded and, if it carries a
+/// patch, everything from the `---` line preceding the diff (or from the diff
+/// itself if there is no such line) is dropped. Trailing blank lines are removed.
+///
+/// # Arguments
+/// * `raw` - The raw message
+///
+/// # Returns
+/// The text body of the message, or `None` if the message has no text part.
+///
+/// # Examples
+/// ```
+/// let text = message_text(&raw_message);
+/// ```
+pub fn message_text(raw: &str) -> Option<ArcStr> {
+    let text = MailPart::parse(raw).text_part()?.decoded_body();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut end = lines
+        .iter()
+        .position(|line| line.starts_with("diff --git "))
+        .unwrap_or(lines.len());
+    if end < lines.len()
+        && let Some(separator) = lines[..end]
+            .iter()
+            .rposition(|line| line.trim_end() == "---")
+    {
+        end = separator;
+    }
+    while end > 0 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+
+    let mut prose = lines[..end].join("\n");
+    if !prose.is_empty() {
+        prose.push('\n');
+    }
+    Some(ArcStr::from(&prose))
+}
+
+/// Quotes a message for a reply.
+///
+/// The decoded `text/plain` part of the message, patch included, is prefixed
+/// with `> ` line by line, so lines already quoted nest as `> > `, and blank
+/// lines become a bare `>`. It is preceded by an `On <date>, <author> wrote:`
+/// attribution, taking the date verbatim from the `Date` header and the author
+/// from the display name of the `From` header, or its address when it has no
+/// name. The parts missing from the headers are left out of the attribution.
+///
+/// # Arguments
+/// * `raw` - The raw message
+///
+/// # Returns
+/// The attribution and the quoted text, or `None` if the message has no text part.
+///
+/// # Examples
+/// ```
+/// let quote = quote_reply(&raw_message);
+/// ```
+pub fn quote_reply(raw: &str) -> Option<ArcStr> {
+    let message = MailPart::parse(raw);
+    let text = message.text_part()?.decoded_body();
+
+    let author = message.header("from").map(|from| {
+        let name = match from.rfind('<') {
+            Some(start) => from[..start].trim().trim_matches('"').trim(),
+            None => "",
+        };
+        if name.is_empty() {
+            from.trim().trim_start_matches('<').trim_end_matches('>')
+        } else {
+            name
+        }
+    });
+    let mut quote = match (message.header("date"), author) {
+        (Some(date), Some(author)) => format!("On {}, {} wrote:\n", date.trim(), author),
+        (Some(date), None) => format!("On {}, someone wrote:\n", date.trim()),
+        (None, Some(author)) => format!("{} wrote:\n", author),
+        (None, None) => String::new(),
+    };
+    for line in text.lines() {
+        if line.is_empty() {
+            quote.push_str(">\n");
+        } else {
+            quote.push_str("> ");
+            quote.push_str(line);
+            quote.push('\n');
+        }
+    }
+    Some(ArcStr::from(&quote))
+}
+
+/// Extracts the commit message of a patch, as `git am` would record it.
+///
+/// The title is the `Subject` header without its bracketed tags, followed by
+/// the body of the first `text/plain` part up to the first `---` line before
+/// the diff, or up to the diff itself if there is no such line, so the notes
+/// and diffstat below the separator are left out while trailers are kept.
+/// Leading in-body `From:`, `Date:` and `Subject:` lines are dropped, an
+/// in-body `Subject:` replacing the title. A message without a diff, such as a
+/// cover letter, has no commit message to cut and its whole body is returned.
+///
+/// # Arguments
+/// * `raw` - The raw message
+///
+/// # Returns
+/// The commit message, or `None` if the message has no text part.
+///
+/// # Examples
+/// ```
+/// let message = commit_message(&raw_patch);
+/// ```
+pub fn commit_message(raw: &str) -> Option<ArcStr> {
+    let message = MailPart::parse(raw);
+    let text = message.text_part()?.decoded_body();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let Some(diff) = lines
+        .iter()
+        .position(|line| line.starts_with("diff --git "))
+    else {
+        return Some(text);
+    };
+    let end = lines[..diff]
+        .iter()
+        .position(|line| line.trim_end() == "---")
+        .unwrap_or(diff);
+
+    let subject = message.header("subject").unwrap_or_default();
+    let mut title = PatchSubject::parse(subject)
+        .map(|subject| subject.title)
+        .unwrap_or_else(|| ArcStr::from(subject.trim()));
+    let mut start = 0;
+    while start < end {
+        let line = lines[start];
+        if let Some(subject) = line.strip_prefix("Subject:") {
+            title = PatchSubject::parse(subject)
+                .map(|subject| subject.title)
+                .unwrap_or_else(|| ArcStr::from(subject.trim()));
+        } else if !line.starts_with("From:") && !line.starts_with("Date:") {
+            break;
+        }
+        start += 1;
+    }
+
+    let mut body = &lines[start..end];
+    while let [first, rest @ ..] = body
+        && first.trim().is_empty()
+    {
+        body = rest;
+    }
+    while let [rest @ .., last] = body
+        && last.trim().is_empty()
+    {
+        body = rest;
+    }
+
+    let mut commit_message = format!("{}\n", title);
+    if !body.is_empty() {
+        commit_message.push('\n');
+        commit_message.push_str(&body.join("\n"));
+        commit_message.push('\n');
+    }
+    Some(ArcStr::from(&commit_message))
+}
+
+/// Extracts the files attached to a message.
+///
+/// The leaf parts of the message are walked depth-first. A part is an
+/// attachment if its `Content-Disposition` says so, if it has a file name, or
+/// if it isn't text. The main text of the message, which holds the prose and
+/// any inline patch, is thus skipped, while a patch or a config sent as a named
+/// text file is kept. A message that isn't multipart has no attachments unless
+/// its whole body is one.
+///
+/// # Arguments
+/// * `raw` - The raw message
+///
+/// # Returns
+/// The attachments of the message, decoded, in the order they appear.
+///
+/// # Examples
+/// ```
+/// let attachments = attachments(&raw_message);
+/// ```
+pub fn attachments(raw: &str) -> Vec<Attachment> {
+    let mut attachments = Vec::new();
+    collect_attachments(&MailPart::parse(raw), &mut attachments);
+    attachments
+}
+
+/// Normalizes a message ID so that equivalent IDs compare equal.
+///
+/// Surrounding whitespace and angle brackets are removed and the domain part,
+/// after the last `@`, is lowercased, as domains are case-insensitive. The
+/// local part is kept as is, since only its sender knows whether its case
+/// matters. Use it to key and deduplicate messages, rather than to build URLs.
+///
+/// # Arguments
+/// * `id` - The message ID, with or without angle brackets
+///
+/// # Returns
+/// The normalized message ID.
+///
+/// # Examples
+/// ```
+/// assert_eq!(
+///     normalize_message_id(" <20231201.1-1@AMD.com> "),
+///     ArcStr::from("20231201.1-1@amd.com")
+/// );
+/// ```
+pub fn normalize_message_id(id: &str) -> ArcStr {
+    let id = id.trim();
+    let id = id.strip_prefix('<').unwrap_or(id);
+    let id = id.strip_suffix('>').unwrap_or(id).trim();
+    match id.rsplit_once('@') {
+        Some((local, domain)) => ArcStr::from(&format!("{}@{}", local, domain.to_lowercase())),
+        None => ArcStr::from(id),
+    }
+}
+
+/// Checks whether two message IDs designate the same message.
+///
+/// # Arguments
+/// * `a` - A message ID
+/// * `b` - Another message ID
+///
+/// # Returns
+/// `true` if the IDs are the same once normalized with [`normalize_message_id`].
+pub fn message_ids_equal(a: &str, b: &str) -> bool {
+    normalize_message_id(a) == normalize_message_id(b)
+}
+
+/// Collects the attachments among the leaf parts of a part, depth-first
+fn collect_attachments(part: &MailPart, attachments: &mut Vec<Attachment>) {
+    if let Some(parts) = part.parts() {
+        for part in &parts {
+            collect_attachments(part, attachments);
+        }
+        return;
+    }
+
+    let mime_type = part.mime_type();
+    let filename = part.filename();
+    if part.is_attachment() || filename.is_some() || !mime_type.starts_with("text/") {
+        attachments.push(Attachment {
+            filename: filename.map(|name| ArcStr::from(&name)),
+            content_type: ArcStr::from(&mime_type),
+            bytes: Arc::from(part.decoded_bytes()),
+        });
+    }
+}
+
+/// Decodes a quoted-printable body, keeping malformed escapes as they are
+fn decode_quoted_printable(body: &str) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(body.len());
+    for line in body.split_inclusive('\n') {
+        let (content, newline) = match line.strip_suffix('\n') {
+            Some(content) => (content.trim_end_matches([' ', '\t', '\r']), true),
+            None => (line, false),
+        };
+        let (content, soft_break) = match content.strip_suffix('=') {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+
+        let bytes = content.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'='
+                && let Some(hex) = content.get(i + 1..i + 3)
+                && let Ok(byte) = u8::from_str_radix(hex, 16)
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+
+        if newline && !soft_break {
+            decoded.push(b'\n');
+        }
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_text_quoted_printable() {
+        let raw = "From: Jane Doe <jane@example.com>\r\n\
+                   Subject: [PATCH 0/2] drm: caf\u{e9} fixes\r\n\
+                   Content-Type: text/plain; charset=\"iso-8859-1\"\r\n\
+                   Content-Transfer-Encoding: quoted-printable\r\n\
+                   \r\n\
+                   This series fixes the caf=E9 driver. The line is long enough to be =\r\n\
+                   soft-wrapped by the encoder.\r\n\
+                   \r\n\
+                   Jane\r\n";
+        assert_eq!(
+            message_text(raw).unwrap(),
+            ArcStr::from(
+                "This series fixes the caf\u{e9} driver. The line is long enough to be \
+                 soft-wrapped by the encoder.\n\nJane\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_message_text_selects_plain_part_and_drops_patch() {
+        let raw = "Subject: [PATCH] foo\n\
+                   Content-Type: multipart/mixed; boundary=\"xyz\"\n\
+                   \n\
+                   --xyz\n\
+                   Content-Type: text/html\n\
+                   \n\
+                   <p>html</p>\n\
+                   --xyz\n\
+                   Content-Type: text/plain\n\
+                   Content-Transfer-Encoding: base64\n\
+                   \n\
+                   V2lkZW4gYi4KLS0tCmRpZmYgLS1naXQgYS9mb28uYyBiL2Zvby5jCg==\n\
+                   --xyz--\n";
+        assert_eq!(message_text(raw).unwrap(), ArcStr::from("Widen b.\n"));
+    }
+
+    #[test]
+    fn test_commit_message_in_body_headers() {
+        let raw = "Subject: [PATCH v2 1/2] drm: fix a\n\
+                   \n\
+                   From: Jane Doe <jane@example.com>\n\
+                   Subject: [PATCH] drm: fix a properly\n\
+                   \n\
+                   Body.\n\
+                   diff --git a/a.c b/a.c\n";
+        assert_eq!(
+            commit_message(raw).unwrap(),
+            ArcStr::from("drm: fix a properly\n\nBody.\n")
+        );
+    }
+
+    #[test]
+    fn test_attachments_decodes_base64() {
+        let raw = include_str!("../../../samples/attachment.eml");
+        let attachments = attachments(raw);
+        assert_eq!(
+            attachments,
+            vec![
+                Attachment {
+                    filename: Some(ArcStr::from("config-6.7")),
+                    content_type: ArcStr::from("text/plain"),
+                    bytes: Arc::from(&b"CONFIG_DRM_AMDGPU=m\nCONFIG_DRM_AMDGPU_SI=y\n"[..]),
+                },
+                Attachment {
+                    filename: Some(ArcStr::from("hang.png")),
+                    content_type: ArcStr::from("image/png"),
+                    bytes: Arc::from(
+                        &[
+                            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d,
+                            0x49, 0x48, 0x44, 0x52, 0xff, 0x00, 0x7f,
+                        ][..]
+                    ),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_ids_equal() {
+        let variants = [
+            "20231201.1-1@amd.com",
+            "<20231201.1-1@amd.com>",
+            " <20231201.1-1@AMD.Com>\t",
+            "20231201.1-1@Amd.COM",
+        ];
+        for variant in variants {
+            assert_eq!(
+                normalize_message_id(variant),
+                ArcStr::from("20231201.1-1@amd.com")
+            );
+            assert!(message_ids_equal(variant, variants[0]));
+        }
+
+        // The local part keeps its case
+        assert_eq!(
+            normalize_message_id("<CAHk-Abc@mail.GMAIL.com>"),
+            ArcStr::from("CAHk-Abc@mail.gmail.com")
+        );
+        assert!(!message_ids_equal(
+            "CAHk-Abc@gmail.com",
+            "cahk-abc@gmail.com"
+        ));
+        assert_eq!(
+            normalize_message_id("<no-domain>"),
+            ArcStr::from("no-domain")
+        );
+    }
+
+    #[test]
+    fn test_attachments_of_plain_message() {
+        assert!(attachments("Subject: [PATCH] foo\n\ndiff --git a/foo b/foo\n").is_empty());
+    }
+}
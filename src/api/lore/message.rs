@@ -1,6 +1,23 @@
-use tokio::sync::oneshot::Sender;
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::{Arc, atomic::AtomicUsize},
+    time::Duration,
+};
 
-use crate::ArcStr;
+use tokio::sync::{mpsc, oneshot::Sender};
+
+use crate::{
+    ArcStr,
+    api::lore::{
+        data::{FeedUpdate, InboxConfig, Maintainer, PatchPreview, SeriesVersion},
+        feed::PatchEntry,
+        mail::Attachment,
+        nntp::OverviewEntry,
+    },
+    net::BodyStream,
+    utils::ShutdownError,
+};
 
 /// Messages that can be sent to a [`LoreApiCore`] actor.
 ///
@@ -17,6 +34,15 @@ pub enum LoreApiMessage {
         /// Response channel for the operation result
         tx: Sender<anyhow::Result<ArcStr>>,
     },
+    /// Fetches a page of the Atom feed of every message of a mailing list
+    GetArchivePage {
+        /// The mailing list name (e.g., "amd-gfx", "linux-kernel")
+        target_list: String,
+        /// The offset for pagination (0-based)
+        min_index: usize,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<ArcStr>>,
+    },
     /// Fetches available mailing lists with pagination
     GetAvailableLists {
         /// The offset for pagination (0-based)
@@ -42,6 +68,17 @@ pub enum LoreApiMessage {
         /// Response channel for the operation result
         tx: Sender<anyhow::Result<ArcStr>>,
     },
+    /// Fetches the start of a patch and parses what fits
+    GetPatchPreview {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the patch
+        message_id: String,
+        /// The maximum number of bytes to download
+        max_bytes: usize,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<PatchPreview>>,
+    },
     /// Fetches patch metadata in JSON format
     GetPatchMetadata {
         /// The mailing list name
@@ -51,6 +88,251 @@ pub enum LoreApiMessage {
         /// Response channel for the operation result
         tx: Sender<anyhow::Result<ArcStr>>,
     },
+    /// Computes the fingerprint of a patch from its normalized diff
+    GetPatchFingerprint {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the patch
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<ArcStr>>,
+    },
+    /// Fetches the public-inbox configuration of a mailing list
+    GetInboxConfig {
+        /// The mailing list name
+        target_list: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<InboxConfig>>,
+    },
+    /// Lists the revisions of a patch series found on a mailing list
+    ListSeriesVersions {
+        /// The mailing list name
+        target_list: String,
+        /// The subject of the series, without its tags
+        subject_base: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<SeriesVersion>>>,
+    },
+    /// Finds the mailing lists a message was posted to through the global search
+    FindCrosspostLists {
+        /// The unique message ID of the message
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<ArcStr>>>,
+    },
+    /// Fetches the prose of a message, without headers and without any patch
+    GetMessageText {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the message
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<ArcStr>>,
+    },
+    /// Fetches the files attached to a message
+    GetAttachments {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the message
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<Attachment>>>,
+    },
+    /// Fetches a patch series and combines its diffs into one
+    GetSeriesCombinedDiff {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the cover letter or of a patch of the series
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<ArcStr>>,
+    },
+    /// Fetches the first page of a patch feed if it changed since the last fetch
+    GetPatchFeedUpdate {
+        /// The mailing list name
+        target_list: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<FeedUpdate>>,
+    },
+    /// Checks whether a mailing list is archived
+    ListExists {
+        /// The mailing list name
+        target_list: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<bool>>,
+    },
+    /// Fetches the HTML content of a specific patch along with the response headers
+    GetPatchHtmlWithHeaders {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the patch
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<(ArcStr, HashMap<ArcStr, ArcStr>)>>,
+    },
+    /// Fetches a raw patch in plain text format along with the response headers
+    GetRawPatchWithHeaders {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the patch
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<(ArcStr, HashMap<ArcStr, ArcStr>)>>,
+    },
+    /// Fetches the patches sent by an author to a mailing list with pagination
+    GetPatchesByAuthor {
+        /// The mailing list name
+        target_list: String,
+        /// The name and/or email address of the author
+        author: String,
+        /// The offset for pagination (0-based)
+        min_index: usize,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<PatchEntry>>>,
+    },
+    /// Fetches the git clone URLs of the archive of a mailing list
+    GetCloneUrls {
+        /// The mailing list name
+        target_list: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<ArcStr>>>,
+    },
+    /// Fetches a raw patch from the git archive of a mailing list over smart-HTTP
+    #[cfg(feature = "git")]
+    GetRawPatchViaGit {
+        /// The mailing list name
+        target_list: String,
+        /// The unique message ID of the patch
+        message_id: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<ArcStr>>,
+    },
+    /// Fetches the maintainers published on the information page of a mailing list
+    GetMaintainers {
+        /// The mailing list name
+        target_list: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<Maintainer>>>,
+    },
+    /// Searches a mailing list with a `lei q`-style query for the matching message IDs
+    QueryMessageIds {
+        /// The mailing list name
+        target_list: String,
+        /// The `lei` query
+        lei_query: String,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<ArcStr>>>,
+    },
+    /// Reads the NNTP overview of a range of articles of a mailing list
+    GetOverview {
+        /// The mailing list name
+        target_list: String,
+        /// The article numbers to read
+        range: RangeInclusive<u64>,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<Vec<OverviewEntry>>>,
+    },
+    /// Fetches a patch feed from a specific mailing list as a stream of its body
+    StreamPatchFeed {
+        /// The mailing list name
+        target_list: String,
+        /// The offset for pagination (0-based)
+        min_index: usize,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<BodyStream>>,
+    },
+    /// Opens the stream of live updates of a mailing list
+    OpenUpdates {
+        /// The mailing list name
+        target_list: String,
+        /// The ID of the last event received, to resume the stream from
+        last_event_id: Option<ArcStr>,
+        /// Response channel for the operation result
+        tx: Sender<anyhow::Result<BodyStream>>,
+    },
+    /// Handles a message as part of a larger operation
+    InOperation {
+        /// The number of bytes downloaded so far by the operation, which the
+        /// downloads of the message add to
+        downloaded: Arc<AtomicUsize>,
+        /// The message itself
+        message: Box<LoreApiMessage>,
+    },
+    /// Stops the actor once the messages already sent are handled
+    Shutdown {
+        /// How long the actor is given to stop before aborting what is left
+        timeout: Duration,
+        /// Response channel for the outcome of the shutdown
+        tx: Sender<Result<(), ShutdownError>>,
+    },
+}
+
+/// The sending end of the channel of a Lore API actor.
+///
+/// A sender may belong to an operation, see [`LoreApiSender::in_operation`]:
+/// the messages it sends are then wrapped in [`LoreApiMessage::InOperation`]
+/// so the actor counts their downloads against the bytes of the whole
+/// operation rather than of each message.
+#[derive(Debug, Clone)]
+pub struct LoreApiSender {
+    /// The channel of the actor
+    sender: mpsc::Sender<LoreApiMessage>,
+    /// The number of bytes downloaded so far by the operation of the sender, if any
+    operation: Option<Arc<AtomicUsize>>,
+}
+
+impl LoreApiSender {
+    /// Creates a sender that doesn't belong to an operation.
+    ///
+    /// # Arguments
+    /// * `sender` - The channel of the actor
+    ///
+    /// # Returns
+    /// A new sender.
+    pub fn new(sender: mpsc::Sender<LoreApiMessage>) -> Self {
+        Self {
+            sender,
+            operation: None,
+        }
+    }
+
+    /// Gets a sender whose messages belong to an operation.
+    ///
+    /// A sender that already belongs to an operation keeps it, so operations
+    /// built from other ones are counted as a whole.
+    ///
+    /// # Returns
+    /// A sender on the same channel, belonging to an operation.
+    pub fn in_operation(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            operation: Some(self.operation.clone().unwrap_or_default()),
+        }
+    }
+
+    /// Sends a message to the actor, as part of the operation of the sender if any.
+    ///
+    /// # Arguments
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    /// `Ok(())` once the message is sent, or an error with the message if the
+    /// actor has stopped.
+    pub async fn send(
+        &self,
+        message: LoreApiMessage,
+    ) -> Result<(), mpsc::error::SendError<LoreApiMessage>> {
+        let message = match &self.operation {
+            Some(downloaded) if !matches!(message, LoreApiMessage::Shutdown { .. }) => {
+                LoreApiMessage::InOperation {
+                    downloaded: downloaded.clone(),
+                    message: Box::new(message),
+                }
+            }
+            _ => message,
+        };
+        self.sender.send(message).await
+    }
 }
 
 /// Response types for Lore API operations.
@@ -99,4 +381,4 @@ impl LoreApiResponse {
             LoreApiResponse::Error(error) => Err(anyhow::anyhow!(error)),
         }
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,167 @@
+//! Weighted selection among the mirrors of the Lore Kernel Archive.
+
+use std::hash::{BuildHasher, RandomState};
+
+use crate::ArcStr;
+
+/// A small seedable pseudo-random number generator (SplitMix64).
+///
+/// It is only used to spread requests across mirrors, so statistical quality
+/// matters far more than unpredictability. A fixed seed makes the sequence of
+/// selected mirrors reproducible in tests.
+///
+/// # Examples
+/// ```
+/// let mut rng = MirrorRng::with_seed(42);
+/// let value = rng.next_u64();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MirrorRng {
+    /// The internal state, advanced on every draw
+    state: u64,
+}
+
+impl MirrorRng {
+    /// Creates a generator producing a fixed sequence for the given seed.
+    ///
+    /// # Arguments
+    /// * `seed` - The seed of the sequence
+    ///
+    /// # Returns
+    /// A new generator.
+    pub fn with_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded from the randomness of the standard library.
+    ///
+    /// # Returns
+    /// A new generator with an unpredictable seed.
+    pub fn from_entropy() -> Self {
+        Self::with_seed(RandomState::new().hash_one(0u64))
+    }
+
+    /// Draws the next value of the sequence.
+    ///
+    /// # Returns
+    /// A uniformly distributed 64-bit value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Draws a value in `0..bound`, `bound` being greater than zero
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+/// Picks the mirror serving each request according to the weight of each mirror.
+///
+/// Every request gets a full failover order: the first mirror is drawn with a
+/// probability proportional to its weight, then the next one among the rest,
+/// and so on. Mirrors with a weight of `0` are never picked first and are only
+/// tried once every weighted mirror failed.
+///
+/// # Examples
+/// ```
+/// let mut selector = MirrorSelector::new(
+///     vec![(ArcStr::from("https://lore.kernel.org"), 3), (ArcStr::from("https://mirror.example.org"), 1)],
+///     MirrorRng::from_entropy(),
+/// );
+/// let domains = selector.order();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MirrorSelector {
+    /// The base domain of each mirror with its weight
+    mirrors: Vec<(ArcStr, u32)>,
+    /// The generator used to draw the mirrors
+    rng: MirrorRng,
+}
+
+impl MirrorSelector {
+    /// Creates a new selector.
+    ///
+    /// # Arguments
+    /// * `mirrors` - The base domain of each mirror with its weight
+    /// * `rng` - The generator used to draw the mirrors
+    ///
+    /// # Returns
+    /// A new selector.
+    pub fn new(mirrors: Vec<(ArcStr, u32)>, rng: MirrorRng) -> Self {
+        Self { mirrors, rng }
+    }
+
+    /// Draws the order in which the mirrors are tried for a request.
+    ///
+    /// # Returns
+    /// Every mirror domain, the one to try first leading.
+    pub fn order(&mut self) -> Vec<ArcStr> {
+        let mut remaining = self.mirrors.clone();
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let total: u64 = remaining.iter().map(|(_, weight)| *weight as u64).sum();
+            let index = if total == 0 {
+                0
+            } else {
+                let mut pick = self.rng.below(total);
+                remaining
+                    .iter()
+                    .position(|(_, weight)| {
+                        if pick < *weight as u64 {
+                            true
+                        } else {
+                            pick -= *weight as u64;
+                            false
+                        }
+                    })
+                    .unwrap_or(0)
+            };
+            order.push(remaining.remove(index).0);
+        }
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selector(seed: u64) -> MirrorSelector {
+        MirrorSelector::new(
+            vec![
+                (ArcStr::from("https://a.example.org"), 3),
+                (ArcStr::from("https://b.example.org"), 1),
+                (ArcStr::from("https://c.example.org"), 0),
+            ],
+            MirrorRng::with_seed(seed),
+        )
+    }
+
+    #[test]
+    fn test_weighted_distribution_with_fixed_seed() {
+        let mut selector = selector(42);
+        let mut first_a = 0;
+        for _ in 0..4000 {
+            let order = selector.order();
+            assert_eq!(order.len(), 3);
+            assert_eq!(&*order[2], "https://c.example.org");
+            if &*order[0] == "https://a.example.org" {
+                first_a += 1;
+            }
+        }
+        // Weights 3:1 put the first mirror first about 75% of the time
+        assert!((2800..3200).contains(&first_a), "{}", first_a);
+    }
+
+    #[test]
+    fn test_same_seed_same_order() {
+        let (mut first, mut second) = (selector(7), selector(7));
+        for _ in 0..100 {
+            assert_eq!(first.order(), second.order());
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Access to the NNTP overview of the newsgroups mirroring Lore mailing lists.
+//!
+//! Public-inbox serves every archived list as a newsgroup, whose overview
+//! (`OVER` command, RFC 3977) gives the main headers of a range of articles in
+//! a single response. This is much cheaper than fetching each message to sync
+//! their metadata. Only the commands needed to read an overview are spoken.
+
+use std::ops::RangeInclusive;
+
+use anyhow::Context;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::ArcStr;
+
+/// An entry of the overview of a newsgroup, that is, the headers of a single article.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverviewEntry {
+    /// The number of the article in the newsgroup
+    pub number: u64,
+    /// The subject of the message
+    pub subject: ArcStr,
+    /// The author of the message, as given in its `From` header
+    pub from: ArcStr,
+    /// The date of the message, as given in its `Date` header
+    pub date: ArcStr,
+    /// The message ID, without angle brackets
+    pub message_id: ArcStr,
+    /// The message IDs of the `References` header, without angle brackets, oldest first
+    pub references: Vec<ArcStr>,
+    /// The size of the article in bytes
+    pub bytes: usize,
+    /// The number of lines of the body of the article
+    pub lines: usize,
+}
+
+/// Parses the response of an `OVER` command.
+///
+/// Each line holds the tab-separated fields of an article. The status line and
+/// the terminating `.` line are accepted but not required, and lines that
+/// aren't overview entries are skipped, as are the optional fields following
+/// the mandatory ones.
+///
+/// # Arguments
+/// * `response` - The overview data
+///
+/// # Returns
+/// The entries of the overview, in the order of the response.
+///
+/// # Examples
+/// ```
+/// let entries = parse_overview(&data);
+/// ```
+pub fn parse_overview(response: &str) -> Vec<OverviewEntry> {
+    response
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .take_while(|line| *line != ".")
+        .filter_map(parse_overview_line)
+        .collect()
+}
+
+/// Parses a single line of an overview
+fn parse_overview_line(line: &str) -> Option<OverviewEntry> {
+    let mut fields = line.split('\t');
+    let number = fields.next()?.parse().ok()?;
+    let subject = fields.next()?;
+    let from = fields.next()?;
+    let date = fields.next()?;
+    let message_id = strip_brackets(fields.next()?);
+    let references = fields.next()?;
+    let bytes = fields.next()?.trim().parse().unwrap_or(0);
+    let lines = fields.next()?.trim().parse().unwrap_or(0);
+    if message_id.is_empty() {
+        return None;
+    }
+
+    Some(OverviewEntry {
+        number,
+        subject: ArcStr::from(subject),
+        from: ArcStr::from(from),
+        date: ArcStr::from(date),
+        message_id: ArcStr::from(message_id),
+        references: references
+            .split_whitespace()
+            .map(|reference| ArcStr::from(strip_brackets(reference)))
+            .collect(),
+        bytes,
+        lines,
+    })
+}
+
+/// Removes the angle brackets around a message ID
+fn strip_brackets(message_id: &str) -> &str {
+    let message_id = message_id.trim();
+    message_id
+        .strip_prefix('<')
+        .and_then(|id| id.strip_suffix('>'))
+        .unwrap_or(message_id)
+}
+
+/// Reads the overview of a range of articles of a newsgroup from an NNTP server.
+///
+/// The range is narrowed to the articles the newsgroup holds, as reported by the
+/// server when the group is selected, so a range reaching past the newest
+/// article, or not overlapping the group at all, is not an error.
+///
+/// # Arguments
+/// * `server` - The address of the server, as `host:port`
+/// * `newsgroup` - The name of the newsgroup
+/// * `range` - The article numbers to read
+///
+/// # Returns
+/// The overview data, without its status and terminating lines, empty if no
+/// article of the range exists, or an error if the exchange fails.
+pub(crate) async fn fetch_overview(
+    server: &str,
+    newsgroup: &str,
+    range: RangeInclusive<u64>,
+) -> anyhow::Result<String> {
+    let stream = TcpStream::connect(server)
+        .await
+        .with_context(|| format!("Connecting to NNTP server: {}", server))?;
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let (code, _) = read_status(&mut reader).await?;
+    if code != 200 && code != 201 {
+        anyhow::bail!("NNTP server refused the connection with status {}", code);
+    }
+
+    writer
+        .write_all(format!("GROUP {}\r\n", newsgroup).as_bytes())
+        .await
+        .context("Sending NNTP GROUP command")?;
+    let (code, status) = read_status(&mut reader).await?;
+    if code != 211 {
+        anyhow::bail!("Selecting newsgroup {} failed: {}", newsgroup, status);
+    }
+    // 211 <count> <low> <high> <group>
+    let mut numbers = status.split_whitespace().skip(1).map(str::parse::<u64>);
+    let (Some(Ok(count)), Some(Ok(low)), Some(Ok(high))) =
+        (numbers.next(), numbers.next(), numbers.next())
+    else {
+        anyhow::bail!("Malformed NNTP GROUP response: {}", status);
+    };
+
+    let start = (*range.start()).max(low);
+    let end = (*range.end()).min(high);
+    let mut data = String::new();
+    if count > 0 && start <= end {
+        writer
+            .write_all(format!("OVER {}-{}\r\n", start, end).as_bytes())
+            .await
+            .context("Sending NNTP OVER command")?;
+        let (code, status) = read_status(&mut reader).await?;
+        match code {
+            224 => data = read_data_block(&mut reader).await?,
+            // No article in the range, e.g. all of them were expired
+            423 => {}
+            _ => anyhow::bail!("Reading the overview of {} failed: {}", newsgroup, status),
+        }
+    }
+
+    let _ = writer.write_all(b"QUIT\r\n").await;
+    Ok(data)
+}
+
+/// Reads a status line, returning its code and the whole line
+async fn read_status<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<(u16, String)> {
+    let mut line = String::new();
+    if reader
+        .read_line(&mut line)
+        .await
+        .context("Reading NNTP response")?
+        == 0
+    {
+        anyhow::bail!("NNTP server closed the connection");
+    }
+    let line = line.trim_end().to_string();
+    let code = line
+        .get(..3)
+        .and_then(|code| code.parse().ok())
+        .with_context(|| format!("Malformed NNTP status line: {}", line))?;
+    Ok((code, line))
+}
+
+/// Reads a multi-line data block up to its terminating `.` line, undoing the dot-stuffing
+async fn read_data_block<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<String> {
+    let mut data = String::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader
+            .read_line(&mut line)
+            .await
+            .context("Reading NNTP data block")?
+            == 0
+        {
+            anyhow::bail!("NNTP data block truncated before its end");
+        }
+        let content = line.trim_end_matches(['\r', '\n']);
+        if content == "." {
+            return Ok(data);
+        }
+        data.push_str(content.strip_prefix('.').unwrap_or(content));
+        data.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::net::TcpListener;
+
+    const OVERVIEW: &str = include_str!("../../../samples/overview.txt");
+
+    #[test]
+    fn test_parse_overview() {
+        let entries = parse_overview(OVERVIEW);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[1],
+            OverviewEntry {
+                number: 41201,
+                subject: ArcStr::from("[PATCH 1/2] drm/amdgpu: widen b"),
+                from: ArcStr::from("Jane Doe <jane@example.com>"),
+                date: ArcStr::from("Fri, 1 Dec 2023 12:34:57 +0000"),
+                message_id: ArcStr::from("20231201.1-1@amd.com"),
+                references: vec![ArcStr::from("20231201.0-1@amd.com")],
+                bytes: 5480,
+                lines: 97,
+            }
+        );
+        assert!(entries[0].references.is_empty());
+        assert_eq!(entries[2].references.len(), 2);
+    }
+
+    /// Serves a single NNTP session holding articles 41200 to 41202 of a group
+    async fn serve_group(data: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"200 ready\r\n").await.unwrap();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let reply = if line.starts_with("GROUP ") {
+                    "211 3 41200 41202 org.freedesktop.lists.amd-gfx\r\n".to_string()
+                } else if line == "OVER 41201-41202" {
+                    format!("224 Overview follows\r\n{}.\r\n", data)
+                } else if line == "QUIT" {
+                    break;
+                } else {
+                    "500 unexpected\r\n".to_string()
+                };
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_overview_clamps_range() {
+        let server = serve_group("41201\ta\tJ <j@a>\tdate\t<1@a>\t\t10\t1\r\n..dot\r\n").await;
+        let data = fetch_overview(&server, "org.freedesktop.lists.amd-gfx", 41201..=50000)
+            .await
+            .unwrap();
+        assert_eq!(data, "41201\ta\tJ <j@a>\tdate\t<1@a>\t\t10\t1\n.dot\n");
+
+        // A range past the newest article doesn't even send OVER
+        let server = serve_group("").await;
+        let data = fetch_overview(&server, "org.freedesktop.lists.amd-gfx", 60000..=60100)
+            .await
+            .unwrap();
+        assert!(data.is_empty());
+    }
+}
@@ -0,0 +1,608 @@
+//! Helpers for inspecting the content of patches fetched from the Lore Kernel Archive.
+
+use std::{collections::HashSet, sync::LazyLock};
+
+use chrono::DateTime;
+use regex::Regex;
+
+use crate::{
+    ArcStr,
+    api::lore::{
+        LoreApiError,
+        feed::unescape,
+        mail::{MailPart, MessageLinks, normalize_message_id},
+    },
+};
+
+/// Matches an HTML comment or the opening or closing tag of an element,
+/// capturing the slash of closing tags and the element name
+static HTML_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<!--.*?-->|<(/?)([a-z][a-z0-9]*)\b[^>]*>").unwrap());
+
+/// The line ending a patch is normalized to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Unix line endings (`\n`), as expected by `git apply`
+    #[default]
+    Lf,
+    /// Windows line endings (`\r\n`)
+    CrLf,
+}
+
+impl LineEnding {
+    /// Gets the characters terminating a line.
+    ///
+    /// # Returns
+    /// The line terminator as a string slice.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Converts every line ending of a patch to the given one.
+///
+/// Both `\r\n` and `\n` terminated lines are rewritten, so patches mixing the two
+/// come out consistent. Lone `\r` characters are part of the line content and are
+/// kept as they are, as is everything else in the patch.
+///
+/// # Arguments
+/// * `patch` - The raw patch content
+/// * `ending` - The line ending to use
+///
+/// # Returns
+/// The patch with normalized line endings.
+///
+/// # Examples
+/// ```
+/// let patch = normalize_line_endings(&raw_patch, LineEnding::Lf);
+/// ```
+pub fn normalize_line_endings(patch: &str, ending: LineEnding) -> ArcStr {
+    let mut normalized = String::with_capacity(patch.len());
+    for line in patch.split_inclusive('\n') {
+        match line.strip_suffix('\n') {
+            Some(content) => {
+                normalized.push_str(content.strip_suffix('\r').unwrap_or(content));
+                normalized.push_str(ending.as_str());
+            }
+            None => normalized.push_str(line),
+        }
+    }
+    ArcStr::from(&normalized)
+}
+
+/// The `From ` line separating the messages of the mboxes built by [`to_mbox`],
+/// the same one Lore uses for its own mboxrd downloads
+pub const MBOX_SEPARATOR: &str = "From mboxrd@z Thu Jan  1 00:00:00 1970";
+
+/// Concatenates raw messages into a single mbox.
+///
+/// The mbox uses the mboxrd format understood by `git am`: each message is
+/// preceded by a [`MBOX_SEPARATOR`] line and followed by a blank line, and body
+/// lines starting with `From `, after any number of `>`, get one more `>` so
+/// they can't be taken for a separator. Line endings are normalized to LF.
+///
+/// # Arguments
+/// * `messages` - The raw messages, in the order they should appear
+///
+/// # Returns
+/// The mbox content.
+///
+/// # Examples
+/// ```
+/// let mbox = to_mbox(&[first_patch, second_patch]);
+/// ```
+pub fn to_mbox<S: AsRef<str>>(messages: &[S]) -> ArcStr {
+    let mut mbox = String::new();
+    for message in messages {
+        mbox.push_str(MBOX_SEPARATOR);
+        mbox.push('\n');
+        for line in message.as_ref().lines() {
+            if line.trim_start_matches('>').starts_with("From ") {
+                mbox.push('>');
+            }
+            mbox.push_str(line);
+            mbox.push('\n');
+        }
+        mbox.push('\n');
+    }
+    ArcStr::from(&mbox)
+}
+
+/// Renders an HTML page as plain text.
+///
+/// Tags are dropped and entities decoded. The content of `<pre>` elements,
+/// where Lore puts messages and their diffs, is kept verbatim, so diff lines
+/// and indentation survive. Elsewhere, whitespace is collapsed as a browser
+/// would, `<br>` starts a new line, and block elements such as paragraphs,
+/// headings and list items start on their own line. The `<head>`, `<script>`
+/// and `<style>` elements are dropped with their content.
+///
+/// # Arguments
+/// * `html` - The HTML page
+///
+/// # Returns
+/// The text of the page.
+///
+/// # Examples
+/// ```
+/// let text = html_to_text(&lore_api.get_patch_html("amd-gfx", message_id).await?);
+/// ```
+pub fn html_to_text(html: &str) -> ArcStr {
+    let mut text = String::new();
+    let mut pre_depth = 0usize;
+    let mut skipped: Option<String> = None;
+    let mut last = 0;
+
+    for token in HTML_TOKEN.captures_iter(html) {
+        let whole = token.get(0).expect("Capture group 0 always matches");
+        if skipped.is_none() {
+            push_html_text(&mut text, &html[last..whole.start()], pre_depth > 0);
+        }
+        last = whole.end();
+
+        let Some(name) = token.get(2) else {
+            continue;
+        };
+        let name = name.as_str().to_ascii_lowercase();
+        let closing = !token[1].is_empty();
+        if let Some(element) = &skipped {
+            if closing && *element == name {
+                skipped = None;
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "head" | "script" | "style" if !closing => skipped = Some(name),
+            "pre" => {
+                pre_depth = if closing {
+                    pre_depth.saturating_sub(1)
+                } else {
+                    pre_depth + 1
+                };
+                start_line(&mut text);
+            }
+            "br" => text.push('\n'),
+            "p" | "div" | "hr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "li" | "ul" | "ol"
+            | "tr" | "table" | "blockquote" => start_line(&mut text),
+            _ => {}
+        }
+    }
+    if skipped.is_none() {
+        push_html_text(&mut text, &html[last..], pre_depth > 0);
+    }
+
+    let text = unescape(&text.replace("&nbsp;", " "));
+    ArcStr::from(text.trim_start_matches('\n').trim_end())
+}
+
+/// Appends the text found between two tags, collapsing its whitespace outside `<pre>`
+fn push_html_text(text: &mut String, chunk: &str, preformatted: bool) {
+    if preformatted {
+        text.push_str(chunk);
+        return;
+    }
+    for (index, word) in chunk.split_whitespace().enumerate() {
+        let separated = index > 0 || chunk.starts_with(char::is_whitespace);
+        if separated && !text.is_empty() && !text.ends_with([' ', '\n']) {
+            text.push(' ');
+        }
+        text.push_str(word);
+    }
+    if chunk.ends_with(char::is_whitespace) && !text.is_empty() && !text.ends_with([' ', '\n']) {
+        text.push(' ');
+    }
+}
+
+/// Ends the current line, unless the text is empty or already at the start of a line
+fn start_line(text: &mut String) {
+    let trimmed = text.trim_end_matches(' ').len();
+    text.truncate(trimmed);
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+/// The tags and title of a patch subject, such as `[PATCH v2 1/3] drm: fix b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchSubject {
+    /// The revision of the patch, `1` when the subject has no `vN` tag
+    pub version: usize,
+    /// The position of the patch in its series and the number of patches
+    /// in the series, if the subject is numbered
+    pub number: Option<(usize, usize)>,
+    /// The subject without the bracketed tags
+    pub title: ArcStr,
+}
+
+impl PatchSubject {
+    /// Parses a patch subject.
+    ///
+    /// Every leading bracketed group is read for tags, so `[RFC PATCH v3 2/4]` and
+    /// `[PATCH net-next] [v3 2/4]` are both understood. By convention, the first
+    /// revision of a series carries no version tag.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject of the message
+    ///
+    /// # Returns
+    /// The parsed subject, or `None` for replies and subjects without a bracketed tag.
+    pub fn parse(subject: &str) -> Option<Self> {
+        let mut rest = subject.trim();
+        if rest
+            .get(..3)
+            .is_some_and(|re| re.eq_ignore_ascii_case("re:"))
+        {
+            return None;
+        }
+
+        let mut version = 1;
+        let mut number = None;
+        let mut tagged = false;
+        while let Some(group) = rest.strip_prefix('[') {
+            let end = group.find(']')?;
+            for tag in group[..end].split_whitespace() {
+                if let Some(v) = tag.strip_prefix(['v', 'V'])
+                    && let Ok(v) = v.parse()
+                {
+                    version = v;
+                } else if let Some((index, total)) = tag.split_once('/')
+                    && let (Ok(index), Ok(total)) = (index.parse(), total.parse())
+                {
+                    number = Some((index, total));
+                }
+            }
+            tagged = true;
+            rest = group[end + 1..].trim_start();
+        }
+
+        tagged.then(|| Self {
+            version,
+            number,
+            title: ArcStr::from(rest.trim_end()),
+        })
+    }
+}
+
+/// Computes a stable fingerprint of a patch from its diff.
+///
+/// The fingerprint is the hex-encoded SHA-256 of the normalized diff, so the same
+/// change gets the same fingerprint whether it comes as a raw message or framed in
+/// an mbox, from any mirror. The hashed content is built as follows:
+/// - Everything before the first `diff --git` line is dropped: the mbox `From ` line,
+///   the mail headers, the commit message and the diffstat.
+/// - The trailing signature (the `-- ` line and everything after it) is dropped.
+/// - `index` lines are dropped, since they depend on the blobs of the base tree.
+/// - Hunk headers are reduced to `@@`, so the line offsets of the hunks don't matter.
+/// - Line endings are normalized to LF and trailing whitespace is removed from every line.
+///
+/// Everything else in the diff, including file names, modes and the hunk content,
+/// is part of the hash.
+///
+/// # Arguments
+/// * `patch` - The raw patch content
+///
+/// # Returns
+/// The fingerprint as a lowercase hex string, or `None` if the patch has no diff.
+///
+/// # Examples
+/// ```
+/// let fingerprint = fingerprint(&raw_patch);
+/// ```
+pub fn fingerprint(patch: &str) -> Option<ArcStr> {
+    let mut normalized = String::new();
+    let mut in_diff = false;
+
+    for line in patch.lines() {
+        let line = line.trim_end();
+        if !in_diff {
+            if !line.starts_with("diff --git ") {
+                continue;
+            }
+            in_diff = true;
+        }
+
+        if line == "--" {
+            break;
+        }
+        if line.starts_with("index ") {
+            continue;
+        }
+        if line.starts_with("@@") {
+            normalized.push_str("@@\n");
+            continue;
+        }
+        normalized.push_str(line);
+        normalized.push('\n');
+    }
+
+    if !in_diff {
+        return None;
+    }
+
+    let digest = ring::digest::digest(&ring::digest::SHA256, normalized.as_bytes());
+    let hex: String = digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Some(ArcStr::from(&hex))
+}
+
+/// Sorts the patches of a series into the order they apply in.
+///
+/// Numbered patches are sorted by their position in the series. Patches
+/// without numbers are sorted by the reply chain linking them, a patch
+/// referencing another one coming after it, then by their `Date` header, as
+/// `git send-email` dates each patch a second after the previous one, and
+/// finally in the order given. The cover letter, if given, comes first.
+///
+/// The messages must be from one series: numbered the same way, with the same
+/// version and number of patches, no position taken twice, and in one thread
+/// for those threaded.
+///
+/// # Arguments
+/// * `messages` - The ID and raw content of each message, in any order
+///
+/// # Returns
+/// The IDs as given, in apply order, or [`LoreApiError::NotOneSeries`] if the
+/// messages aren't from a single series.
+pub fn apply_order<S: AsRef<str>>(messages: &[(ArcStr, S)]) -> Result<Vec<ArcStr>, LoreApiError> {
+    let not_one_series = |reason: String| LoreApiError::NotOneSeries {
+        reason: ArcStr::from(&reason),
+    };
+
+    struct Patch {
+        id: ArcStr,
+        subject: Option<PatchSubject>,
+        links: MessageLinks,
+        date: Option<DateTime<chrono::FixedOffset>>,
+    }
+    let patches: Vec<Patch> = messages
+        .iter()
+        .map(|(id, raw)| {
+            let raw = raw.as_ref();
+            let headers = MailPart::parse(raw);
+            Patch {
+                id: id.clone(),
+                subject: headers.header("subject").and_then(PatchSubject::parse),
+                links: MessageLinks::parse(raw),
+                date: headers
+                    .header("date")
+                    .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok()),
+            }
+        })
+        .collect();
+
+    let numbers: Vec<_> = patches
+        .iter()
+        .filter_map(|patch| {
+            let subject = patch.subject.as_ref()?;
+            Some((subject.version, subject.number?))
+        })
+        .collect();
+    if !numbers.is_empty() && numbers.len() < patches.len() {
+        return Err(not_one_series(
+            "some patches are numbered and others aren't".to_string(),
+        ));
+    }
+    let mut positions = HashSet::new();
+    for (version, (index, total)) in &numbers {
+        let (first_version, (_, first_total)) = numbers[0];
+        if *version != first_version {
+            return Err(not_one_series(format!(
+                "patches of v{} and v{}",
+                first_version, version
+            )));
+        }
+        if *total != first_total {
+            return Err(not_one_series(format!(
+                "patches of series of {} and {} patches",
+                first_total, total
+            )));
+        }
+        if !positions.insert(*index) {
+            return Err(not_one_series(format!(
+                "two patches are numbered {}/{}",
+                index, total
+            )));
+        }
+    }
+
+    let ids: Vec<ArcStr> = patches
+        .iter()
+        .map(|patch| normalize_message_id(&patch.id))
+        .collect();
+    // Threaded messages are of one thread if they are linked through the IDs
+    // of the messages they are, reply to or reference, directly or not
+    let families: Vec<HashSet<&ArcStr>> = patches
+        .iter()
+        .zip(&ids)
+        .filter(|(patch, _)| patch.links.in_reply_to.is_some())
+        .map(|(patch, id)| {
+            let links = &patch.links;
+            let mut family: HashSet<&ArcStr> = links.references.iter().collect();
+            family.extend(&links.in_reply_to);
+            family.insert(id);
+            family
+        })
+        .collect();
+    if let Some((first, rest)) = families.split_first() {
+        let mut thread = first.clone();
+        let mut pending: Vec<_> = rest.iter().collect();
+        while let Some(linked) = pending
+            .iter()
+            .position(|family| !family.is_disjoint(&thread))
+        {
+            thread.extend(pending.swap_remove(linked));
+        }
+        if !pending.is_empty() {
+            return Err(not_one_series("messages of different threads".to_string()));
+        }
+    }
+
+    let mut order: Vec<usize> = (0..patches.len()).collect();
+    if numbers.is_empty() {
+        let depth = |i: usize| {
+            let links = &patches[i].links;
+            ids.iter()
+                .enumerate()
+                .filter(|(j, id)| {
+                    *j != i
+                        && (links.references.contains(id)
+                            || links.in_reply_to.as_ref() == Some(*id))
+                })
+                .count()
+        };
+        order.sort_by_key(|&i| (depth(i), patches[i].date));
+    } else {
+        order.sort_by_key(|&i| numbers[i].1.0);
+    }
+    Ok(order.into_iter().map(|i| patches[i].id.clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r##"<html><head><title>[PATCH] drm: fix b</title>
+<style>pre { white-space: pre-wrap }</style></head>
+<body><h1>[PATCH]   drm: fix
+  b</h1>
+<p>From: Jane Doe &lt;jane@example.com&gt;</p>
+<!-- message -->
+<pre>Use a &quot;long&quot; for b.
+
+diff --git a/a.h b/a.h
+<span class="hunk">@@ -1,3 +1,3 @@</span>
+ 	int a;
+<span class="del">-	int b;</span>
+<span class="add">+	long b;</span>
+</pre><a href="#">next</a>&nbsp;&amp;<br>done
+</body></html>"##;
+        assert_eq!(
+            &*html_to_text(html),
+            "[PATCH] drm: fix b\n\
+             From: Jane Doe <jane@example.com>\n\
+             Use a \"long\" for b.\n\
+             \n\
+             diff --git a/a.h b/a.h\n\
+             @@ -1,3 +1,3 @@\n \
+             \tint a;\n\
+             -\tint b;\n\
+             +\tlong b;\n\
+             next &\n\
+             done"
+        );
+    }
+
+    #[test]
+    fn test_to_mbox_escapes_from_lines() {
+        let mbox = to_mbox(&[
+            "Subject: [PATCH 1/2] a\r\n\r\nFrom the start\r\n",
+            "Subject: [PATCH 2/2] b\n\n>From quoted\nFromage\n",
+        ]);
+        assert_eq!(
+            &*mbox,
+            "From mboxrd@z Thu Jan  1 00:00:00 1970\n\
+             Subject: [PATCH 1/2] a\n\n>From the start\n\n\
+             From mboxrd@z Thu Jan  1 00:00:00 1970\n\
+             Subject: [PATCH 2/2] b\n\n>>From quoted\nFromage\n\n"
+        );
+    }
+
+    const DIFF: &str = "\
+diff --git a/drivers/gpu/drm/amd/amdgpu/amdgpu.h b/drivers/gpu/drm/amd/amdgpu/amdgpu.h
+index 1234567..89abcde 100644
+--- a/drivers/gpu/drm/amd/amdgpu/amdgpu.h
++++ b/drivers/gpu/drm/amd/amdgpu/amdgpu.h
+@@ -10,7 +10,7 @@ struct amdgpu_device {
+ \tint a;
+-\tint b;
++\tlong b;
+ \tint c;
+";
+
+    #[test]
+    fn test_fingerprint_ignores_mbox_framing() {
+        let mbox = format!(
+            "From 1234567890abcdef Mon Sep 17 00:00:00 2001\r\n\
+             From: Jane Doe <jane@example.com>\r\n\
+             Date: Fri, 1 Dec 2023 12:34:56 +0000\r\n\
+             Subject: [PATCH] drm/amdgpu: widen b\r\n\
+             \r\n\
+             Widen b to avoid an overflow.\r\n\
+             \r\n\
+             Signed-off-by: Jane Doe <jane@example.com>\r\n\
+             ---\r\n \
+             drivers/gpu/drm/amd/amdgpu/amdgpu.h | 2 +-\r\n\
+             {}-- \r\n\
+             2.43.0\r\n",
+            DIFF.replace('\n', "\r\n")
+        );
+
+        let framed = fingerprint(&mbox).unwrap();
+        let raw = fingerprint(DIFF).unwrap();
+        assert_eq!(framed, raw);
+        assert_eq!(raw.len(), 64);
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_index_and_offsets() {
+        let rebased = DIFF
+            .replace("index 1234567..89abcde", "index 7654321..edcba98")
+            .replace("@@ -10,7 +10,7 @@", "@@ -42,7 +42,7 @@");
+        assert_eq!(fingerprint(DIFF), fingerprint(&rebased));
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_content() {
+        let other = DIFF.replace("+\tlong b;", "+\tu64 b;");
+        assert_ne!(fingerprint(DIFF), fingerprint(&other));
+    }
+
+    #[test]
+    fn test_parse_subject() {
+        assert_eq!(
+            PatchSubject::parse("[RFC PATCH v3 2/4] drm: fix b"),
+            Some(PatchSubject {
+                version: 3,
+                number: Some((2, 4)),
+                title: ArcStr::from("drm: fix b"),
+            })
+        );
+        assert_eq!(
+            PatchSubject::parse("[PATCH] drm: fix b"),
+            Some(PatchSubject {
+                version: 1,
+                number: None,
+                title: ArcStr::from("drm: fix b"),
+            })
+        );
+        assert!(PatchSubject::parse("Re: [PATCH] drm: fix b").is_none());
+        assert!(PatchSubject::parse("drm: fix b").is_none());
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let mixed = "Subject: [PATCH] fix\r\n\r\n-\tint b;\r\n+\tlong b;\n keep\r inside";
+        assert_eq!(
+            &*normalize_line_endings(mixed, LineEnding::Lf),
+            "Subject: [PATCH] fix\n\n-\tint b;\n+\tlong b;\n keep\r inside"
+        );
+        assert_eq!(
+            &*normalize_line_endings("a\nb\r\n", LineEnding::CrLf),
+            "a\r\nb\r\n"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_without_diff() {
+        assert!(fingerprint("Subject: [PATCH 0/2] cover letter\n\nHello").is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! Periodic polling of the patch feed of a mailing list.
+
+use std::time::Duration;
+
+use tokio::{
+    sync::mpsc::{self, Receiver},
+    task::JoinHandle,
+};
+
+use crate::{ArcStr, api::lore::LoreApi, log::Log};
+
+/// The adaptive polling interval of a [`ListPoller`].
+///
+/// The interval starts at `base`. Each consecutive failed poll multiplies it by
+/// `multiplier`, up to `cap`, and a successful poll brings it back to `base`.
+///
+/// # Examples
+/// ```
+/// let backoff = PollBackoff::new(Duration::from_secs(60), Duration::from_secs(900), 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollBackoff {
+    /// The interval between polls while they succeed
+    base: Duration,
+    /// The longest interval between polls
+    cap: Duration,
+    /// The factor applied to the interval on each consecutive failure
+    multiplier: u32,
+    /// The interval until the next poll
+    current: Duration,
+}
+
+impl PollBackoff {
+    /// Creates a new polling backoff starting at the base interval.
+    ///
+    /// # Arguments
+    /// * `base` - The interval between polls while they succeed
+    /// * `cap` - The longest interval between polls
+    /// * `multiplier` - The factor applied to the interval on each consecutive failure
+    ///
+    /// # Returns
+    /// A new polling backoff.
+    pub fn new(base: Duration, cap: Duration, multiplier: u32) -> Self {
+        Self {
+            base,
+            cap: cap.max(base),
+            multiplier: multiplier.max(1),
+            current: base,
+        }
+    }
+
+    /// Gets the interval until the next poll.
+    ///
+    /// # Returns
+    /// The current polling interval.
+    pub fn interval(&self) -> Duration {
+        self.current
+    }
+
+    /// Grows the interval after a failed poll.
+    ///
+    /// # Returns
+    /// The new polling interval.
+    pub fn on_error(&mut self) -> Duration {
+        self.current = self.current.saturating_mul(self.multiplier).min(self.cap);
+        self.current
+    }
+
+    /// Resets the interval after a successful poll.
+    ///
+    /// # Returns
+    /// The new polling interval.
+    pub fn on_success(&mut self) -> Duration {
+        self.current = self.base;
+        self.current
+    }
+}
+
+/// Polls the first page of the patch feed of a mailing list at a regular interval.
+///
+/// The poller backs off while the feed can't be fetched, so a mirror that is
+/// down isn't hammered at the regular cadence, and returns to the base interval
+/// as soon as a poll succeeds.
+///
+/// # Examples
+/// ```
+/// let poller = ListPoller::new(lore_api, log, ArcStr::from("amd-gfx"), backoff);
+/// let (mut feeds, _) = poller.spawn();
+/// while let Some(feed) = feeds.recv().await {
+///     // ...
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ListPoller {
+    /// The Lore API actor used to fetch the feed
+    lore_api: LoreApi,
+    /// Logging interface for interval changes and errors
+    log: Log,
+    /// The mailing list to poll
+    target_list: ArcStr,
+    /// The adaptive polling interval
+    backoff: PollBackoff,
+}
+
+impl ListPoller {
+    /// Creates a new poller for a mailing list.
+    ///
+    /// # Arguments
+    /// * `lore_api` - The Lore API actor used to fetch the feed
+    /// * `log` - The logging actor
+    /// * `target_list` - The mailing list to poll
+    /// * `backoff` - The base, cap and multiplier of the polling interval
+    ///
+    /// # Returns
+    /// A new poller, which polls nothing until [`ListPoller::poll`] is called
+    /// or it is spawned.
+    pub fn new(lore_api: LoreApi, log: Log, target_list: ArcStr, backoff: PollBackoff) -> Self {
+        Self {
+            lore_api,
+            log,
+            target_list,
+            backoff,
+        }
+    }
+
+    /// Gets the interval until the next poll.
+    ///
+    /// # Returns
+    /// The current polling interval.
+    pub fn interval(&self) -> Duration {
+        self.backoff.interval()
+    }
+
+    /// Fetches the feed once and adapts the polling interval to the outcome.
+    ///
+    /// # Returns
+    /// The first page of the patch feed, or the error of the failed poll.
+    pub async fn poll(&mut self) -> anyhow::Result<ArcStr> {
+        let previous = self.backoff.interval();
+        let result = self.lore_api.get_patch_feed(&self.target_list, 0).await;
+        let interval = match &result {
+            Ok(_) => self.backoff.on_success(),
+            Err(err) => {
+                self.log.warn(format!(
+                    "Polling the feed of {} failed: {:#}",
+                    self.target_list, err
+                ));
+                self.backoff.on_error()
+            }
+        };
+        if interval != previous {
+            self.log.info(format!(
+                "Polling interval of {} changed from {:?} to {:?}",
+                self.target_list, previous, interval
+            ));
+        }
+        result
+    }
+
+    /// Transforms the poller into a background task.
+    ///
+    /// The task polls right away and then after each interval, sending every
+    /// fetched feed through the returned channel. It stops once the receiver
+    /// is dropped.
+    ///
+    /// # Returns
+    /// A tuple containing:
+    /// - The receiver of the fetched feeds
+    /// - A join handle for the spawned task
+    pub fn spawn(mut self) -> (Receiver<ArcStr>, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel(16);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                if let Ok(feed) = self.poll().await
+                    && tx.send(feed).await.is_err()
+                {
+                    break;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(self.backoff.interval()) => {}
+                    _ = tx.closed() => break,
+                }
+            }
+        });
+
+        (rx, handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let mut backoff = PollBackoff::new(Duration::from_secs(1), Duration::from_secs(5), 3);
+        assert_eq!(backoff.on_error(), Duration::from_secs(3));
+        assert_eq!(backoff.on_error(), Duration::from_secs(5));
+        assert_eq!(backoff.on_error(), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_poller_backs_off_then_resets() {
+        let lore_api = LoreApi::mock_empty();
+        let backoff = PollBackoff::new(Duration::from_secs(10), Duration::from_secs(60), 2);
+        let mut poller = ListPoller::new(
+            lore_api.clone(),
+            Log::mock(),
+            ArcStr::from("amd-gfx"),
+            backoff,
+        );
+
+        assert!(poller.poll().await.is_err());
+        assert_eq!(poller.interval(), Duration::from_secs(20));
+        assert!(poller.poll().await.is_err());
+        assert_eq!(poller.interval(), Duration::from_secs(40));
+
+        if let LoreApi::Mock(responses) = &lore_api {
+            responses
+                .lock()
+                .await
+                .insert("patch_feed_amd-gfx_0".to_string(), ArcStr::from("<feed/>"));
+        }
+        assert_eq!(poller.poll().await.unwrap(), ArcStr::from("<feed/>"));
+        assert_eq!(poller.interval(), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,158 @@
+//! Verification of the inline PGP signatures of patches fetched from the Lore Kernel Archive.
+
+use anyhow::Context;
+use pgp::{
+    Deserializable, SignedPublicKey,
+    composed::cleartext::CleartextSignedMessage,
+    types::{KeyId, PublicKeyTrait},
+};
+
+use crate::ArcStr;
+
+/// Marks the start of an inline (cleartext) signed block
+const SIGNED_MESSAGE_BEGIN: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+/// Marks the end of the signature closing an inline signed block
+const SIGNATURE_END: &str = "-----END PGP SIGNATURE-----";
+
+/// The outcome of verifying the signature of a patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// The signature is valid and was made by a key of the keyring
+    Good {
+        /// The hex-encoded id of the signing key
+        key_id: ArcStr,
+    },
+    /// The signed content was altered, or no key of the keyring made the signature
+    Bad {
+        /// The hex-encoded id of the key the signature claims, if it names one
+        key_id: Option<ArcStr>,
+    },
+    /// The message carries no inline signature
+    NoSignature,
+}
+
+/// Extracts the inline signed block of a message.
+///
+/// The block spans from the `BEGIN PGP SIGNED MESSAGE` armor line to the end of
+/// the signature that closes it. Mail headers and anything outside the block,
+/// such as a list footer, are left out.
+///
+/// # Arguments
+/// * `message` - The raw message
+///
+/// # Returns
+/// The signed block, or `None` if the message has no complete one.
+pub fn signed_block(message: &str) -> Option<&str> {
+    let start = message.find(SIGNED_MESSAGE_BEGIN)?;
+    let end = message[start..].find(SIGNATURE_END)? + start + SIGNATURE_END.len();
+    Some(&message[start..end])
+}
+
+/// Verifies the inline signature of a message against a keyring.
+///
+/// The signature is checked against every primary key and subkey of the
+/// keyring, and is good as soon as one of them validates it.
+///
+/// # Arguments
+/// * `message` - The raw message
+/// * `keyring` - The ASCII-armored public keys trusted to sign, as exported by
+///   `gpg --armor --export`
+///
+/// # Returns
+/// The status of the signature, or an error if the keyring or the signed block
+/// can't be parsed.
+///
+/// # Examples
+/// ```
+/// let status = verify(&raw_patch, &keyring)?;
+/// ```
+pub fn verify(message: &str, keyring: &str) -> anyhow::Result<SignatureStatus> {
+    let Some(block) = signed_block(message) else {
+        return Ok(SignatureStatus::NoSignature);
+    };
+    let (signed, _) =
+        CleartextSignedMessage::from_string(block).context("Parsing the signed message")?;
+
+    let (keys, _) =
+        SignedPublicKey::from_armor_many(keyring.as_bytes()).context("Parsing the keyring")?;
+    for key in keys {
+        let key = key.context("Parsing a key of the keyring")?;
+        if signed.verify(&key).is_ok() {
+            return Ok(SignatureStatus::Good {
+                key_id: encode_key_id(&key.key_id()),
+            });
+        }
+        for subkey in &key.public_subkeys {
+            if signed.verify(subkey).is_ok() {
+                return Ok(SignatureStatus::Good {
+                    key_id: encode_key_id(&subkey.key_id()),
+                });
+            }
+        }
+    }
+
+    let key_id = signed
+        .signatures()
+        .iter()
+        .flat_map(|signature| signature.signature.issuer())
+        .next()
+        .map(encode_key_id);
+    Ok(SignatureStatus::Bad { key_id })
+}
+
+/// Encodes a key id the way `gpg --keyid-format long` prints it
+fn encode_key_id(key_id: &KeyId) -> ArcStr {
+    let hex: String = key_id
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    ArcStr::from(&hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED_PATCH: &str = include_str!("../../../samples/signed_patch.eml");
+    const KEYRING: &str = include_str!("../../../samples/signing_key.asc");
+
+    #[test]
+    fn test_verify_good_signature() {
+        assert_eq!(
+            verify(SIGNED_PATCH, KEYRING).unwrap(),
+            SignatureStatus::Good {
+                key_id: ArcStr::from("789EC04DC824FDEA"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_tampered_patch() {
+        let tampered = SIGNED_PATCH.replace("+\tlong b;", "+\tlong long b;");
+        assert_ne!(tampered, SIGNED_PATCH);
+        assert_eq!(
+            verify(&tampered, KEYRING).unwrap(),
+            SignatureStatus::Bad {
+                key_id: Some(ArcStr::from("789EC04DC824FDEA")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_unsigned_patch() {
+        let unsigned = "Subject: [PATCH] drm: fix b\n\ndiff --git a/a.c b/a.c\n";
+        assert_eq!(
+            verify(unsigned, KEYRING).unwrap(),
+            SignatureStatus::NoSignature
+        );
+    }
+
+    #[test]
+    fn test_signed_block_skips_headers_and_footer() {
+        let message = format!("{}\n_______________\namd-gfx mailing list\n", SIGNED_PATCH);
+        let block = signed_block(&message).unwrap();
+        assert!(block.starts_with(SIGNED_MESSAGE_BEGIN));
+        assert!(block.ends_with(SIGNATURE_END));
+    }
+}
@@ -0,0 +1,284 @@
+//! Live updates of a mailing list through Server-Sent Events.
+//!
+//! The events are parsed as described by the HTML Living Standard, with lines
+//! ending in LF or CRLF. The data of each event carries one or more Atom
+//! `<entry>` elements, like the entries of a patch feed.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    sync::mpsc::{self, Receiver, Sender},
+    task::JoinHandle,
+};
+
+use crate::{
+    ArcStr,
+    api::lore::{LoreApi, LoreApiError, PatchEntry, PollBackoff, feed},
+    net::BodyStream,
+};
+
+/// The delay before reconnecting a subscription that dropped
+const RECONNECT_BASE: Duration = Duration::from_secs(1);
+/// The longest delay between attempts to reconnect a subscription
+const RECONNECT_CAP: Duration = Duration::from_secs(60);
+/// Number of entries buffered between the subscription task and its consumer
+const UPDATES_CHANNEL_CAPACITY: usize = 16;
+
+/// An event received from an event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The type of the event, if given
+    pub event: Option<ArcStr>,
+    /// The last event ID received, including this event's, if any
+    pub id: Option<ArcStr>,
+    /// The data of the event, its `data:` lines joined by LF
+    pub data: ArcStr,
+}
+
+/// Incremental parser of an event stream.
+///
+/// # Examples
+/// ```
+/// let mut parser = SseParser::default();
+/// for event in parser.push(&chunk) {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct SseParser {
+    /// The bytes of the line being received
+    buffer: Vec<u8>,
+    /// The type of the event being received, if given
+    event: Option<String>,
+    /// The data of the event being received, if any
+    data: Option<String>,
+    /// The last event ID received, kept across events
+    last_id: Option<ArcStr>,
+}
+
+impl SseParser {
+    /// Feeds a chunk of the stream to the parser.
+    ///
+    /// Comments, unknown fields and events without data are skipped.
+    ///
+    /// # Arguments
+    /// * `chunk` - The next bytes of the stream
+    ///
+    /// # Returns
+    /// The events completed by the chunk, in order.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.line(line.trim_end_matches(['\n', '\r'])) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Drops the event being received, as when the connection is lost.
+    ///
+    /// The last event ID is kept, to resume the stream from it.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.event = None;
+        self.data = None;
+    }
+
+    /// Gets the last event ID received.
+    ///
+    /// # Returns
+    /// The ID to send in `Last-Event-ID` when reconnecting, if any.
+    pub fn last_event_id(&self) -> Option<&ArcStr> {
+        self.last_id.as_ref()
+    }
+
+    /// Interprets a line, returning the event it completes, if any
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = self.event.take();
+            return self.data.take().map(|data| SseEvent {
+                event: event.map(|event| ArcStr::from(&event)),
+                id: self.last_id.clone(),
+                data: ArcStr::from(&data),
+            });
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "event" => self.event = Some(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(ArcStr::from(value)),
+            // Comments, `retry` and unknown fields
+            _ => {}
+        }
+        None
+    }
+}
+
+/// The stream of entries returned by [`LoreApi::subscribe_updates`].
+///
+/// The events are read by a background task, which is stopped when the
+/// subscription is dropped.
+#[derive(Debug)]
+pub(super) struct Subscription {
+    /// The entries received, or the errors of the attempts to reconnect
+    rx: Receiver<anyhow::Result<PatchEntry>>,
+    /// The task reading the events
+    task: JoinHandle<()>,
+}
+
+impl Subscription {
+    /// Reads the entries of an event stream, reconnecting when it drops.
+    ///
+    /// Reconnections send the last event ID received and back off while they
+    /// fail. Each failure is yielded as an error, and the subscription ends if
+    /// the mirror no longer supports live updates.
+    ///
+    /// # Arguments
+    /// * `lore_api` - The Lore API actor used to reconnect
+    /// * `target_list` - The mailing list followed
+    /// * `body` - The body of the event stream already opened
+    ///
+    /// # Returns
+    /// The subscription.
+    pub(super) fn spawn(lore_api: LoreApi, target_list: ArcStr, mut body: BodyStream) -> Self {
+        let (tx, rx) = mpsc::channel(UPDATES_CHANNEL_CAPACITY);
+        let task = tokio::spawn(async move {
+            let mut parser = SseParser::default();
+            let mut backoff = PollBackoff::new(RECONNECT_BASE, RECONNECT_CAP, 2);
+            loop {
+                if !forward(&mut body, &mut parser, &tx).await {
+                    return;
+                }
+                parser.reset();
+
+                loop {
+                    tokio::time::sleep(backoff.interval()).await;
+                    let last_event_id = parser.last_event_id().cloned();
+                    match lore_api.open_updates(&target_list, last_event_id).await {
+                        Ok(reopened) => {
+                            backoff.on_success();
+                            body = reopened;
+                            break;
+                        }
+                        Err(err) => {
+                            backoff.on_error();
+                            let unsupported = matches!(
+                                err.downcast_ref::<LoreApiError>(),
+                                Some(LoreApiError::UpdatesUnsupported { .. })
+                            );
+                            if tx.send(Err(err)).await.is_err() || unsupported {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Self { rx, task }
+    }
+
+    /// Reads the entries of an event stream once, without reconnecting.
+    ///
+    /// # Arguments
+    /// * `body` - The body of the event stream
+    ///
+    /// # Returns
+    /// The subscription, ending with the body.
+    pub(super) fn once(mut body: BodyStream) -> Self {
+        let (tx, rx) = mpsc::channel(UPDATES_CHANNEL_CAPACITY);
+        let task = tokio::spawn(async move {
+            forward(&mut body, &mut SseParser::default(), &tx).await;
+        });
+        Self { rx, task }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = anyhow::Result<PatchEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Forwards the entries of an event stream until it ends or fails.
+///
+/// Returns `false` once the subscription is dropped.
+async fn forward(
+    body: &mut BodyStream,
+    parser: &mut SseParser,
+    tx: &Sender<anyhow::Result<PatchEntry>>,
+) -> bool {
+    while let Some(Ok(chunk)) = std::future::poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)).await
+    {
+        for event in parser.push(&chunk) {
+            for entry in feed::parse_entries(&event.data) {
+                if tx.send(Ok(entry)).await.is_err() {
+                    return false;
+                }
+            }
+        }
+    }
+    !tx.is_closed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(
+            parser
+                .push(b": keep-alive\n\nid: 7\r\nevent: en")
+                .is_empty()
+        );
+
+        let events = parser.push(b"try\r\ndata: first\ndata:second\n\ndata: third\n\nretry: 10\n");
+        assert_eq!(
+            events,
+            [
+                SseEvent {
+                    event: Some(ArcStr::from("entry")),
+                    id: Some(ArcStr::from("7")),
+                    data: ArcStr::from("first\nsecond"),
+                },
+                SseEvent {
+                    event: None,
+                    id: Some(ArcStr::from("7")),
+                    data: ArcStr::from("third"),
+                },
+            ]
+        );
+
+        // An event cut short by a disconnection is dropped, its ID is kept
+        parser.push(b"id: 8\ndata: lost");
+        parser.reset();
+        assert!(parser.push(b"\n").is_empty());
+        assert_eq!(parser.last_event_id(), Some(&ArcStr::from("8")));
+    }
+}
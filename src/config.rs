@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
-use data::Data;
-pub use data::{PathOpt, USizeOpt};
+pub use data::{Data, PathOpt, USizeOpt};
 use message::Message;
 use tokio::sync::Mutex;
 
@@ -14,6 +14,40 @@ pub enum PathOpt {
 pub enum USizeOpt {
     /// Maximum age of log files in days before they are deleted
     MaxAge,
+    /// Maximum number of attempts for a single network request, including the first one, `1` for no retries
+    RetryMaxAttempts,
+    /// Delay in milliseconds before the first retry of a network request
+    RetryBaseDelay,
+    /// Maximum number of retries that may be spent in a burst across all network requests
+    RetryBudget,
+    /// Number of retries returned to the retry budget every second
+    RetryBudgetRefill,
+    /// Longest delay in milliseconds between two attempts of a network request, `0` for none
+    RetryMaxBackoff,
+    /// Longest time in milliseconds spent retrying a network request, `0` for none
+    RetryMaxElapsed,
+    /// Factor applied to the delay between two attempts of a network request after each retry
+    RetryMultiplier,
+    /// Largest share in percent randomly taken off each retry delay, `0` for none
+    RetryJitter,
+    /// Timeout in milliseconds applied to each attempt of a network request, `0` for none
+    DefaultTimeout,
+    /// Timeout in milliseconds for establishing a network connection, `0` for none
+    ConnectTimeout,
+    /// Timeout in milliseconds for each read of a network response, `0` for none
+    ReadTimeout,
+    /// Number of requests per second allowed to each host, `0` for no limit
+    HostRateLimit,
+    /// Number of requests that may be sent to a host in a burst
+    HostRateBurst,
+    /// Number of requests per second allowed across all hosts, `0` for no limit
+    TotalRateLimit,
+    /// Number of requests that may be sent in a burst across all hosts
+    TotalRateBurst,
+    /// Maximum number of redirects followed in a row by a network request
+    MaxRedirects,
+    /// Maximum number of network requests in flight at the same time
+    MaxConcurrentRequests,
 }
 
 /// The configuration data structure that holds all configurable values.
@@ -24,6 +58,7 @@ pub enum USizeOpt {
 /// # Thread Safety
 /// This type is designed to be safely shared between threads when wrapped in an `Arc<Mutex<>>`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Data {
     /// Directory where log files are stored
     log_dir: ArcPath,
@@ -31,6 +66,40 @@ pub struct Data {
     log_level: LogLevel,
     /// Maximum age of log files in days before they are deleted
     max_age: usize,
+    /// Maximum number of attempts for a single network request, `1` for no retries
+    retry_max_attempts: usize,
+    /// Delay in milliseconds before the first retry of a network request
+    retry_base_delay: usize,
+    /// Maximum number of retries that may be spent in a burst across all requests
+    retry_budget: usize,
+    /// Number of retries returned to the retry budget every second
+    retry_budget_refill: usize,
+    /// Longest delay in milliseconds between two attempts of a network request, `0` for none
+    retry_max_backoff: usize,
+    /// Longest time in milliseconds spent retrying a network request, `0` for none
+    retry_max_elapsed: usize,
+    /// Factor applied to the delay between two attempts after each retry
+    retry_multiplier: usize,
+    /// Largest share in percent randomly taken off each retry delay, `0` for none
+    retry_jitter: usize,
+    /// Timeout in milliseconds applied to each network request attempt, `0` for none
+    default_timeout: usize,
+    /// Timeout in milliseconds for establishing a network connection, `0` for none
+    connect_timeout: usize,
+    /// Timeout in milliseconds for each read of a network response, `0` for none
+    read_timeout: usize,
+    /// Number of requests per second allowed to each host, `0` for no limit
+    host_rate_limit: usize,
+    /// Number of requests that may be sent to a host in a burst
+    host_rate_burst: usize,
+    /// Number of requests per second allowed across all hosts, `0` for no limit
+    total_rate_limit: usize,
+    /// Number of requests that may be sent in a burst across all hosts
+    total_rate_burst: usize,
+    /// Maximum number of redirects followed in a row by a network request
+    max_redirects: usize,
+    /// Maximum number of network requests in flight at the same time
+    max_concurrent_requests: usize,
 }
 
 impl Default for Data {
@@ -39,6 +108,23 @@ impl Default for Data {
             log_dir: ArcPath::from("/tmp/patch-hub/logs"),
             log_level: LogLevel::Warning,
             max_age: 0,
+            retry_max_attempts: 1,
+            retry_base_delay: 200,
+            retry_budget: 10,
+            retry_budget_refill: 1,
+            retry_max_backoff: 30_000,
+            retry_max_elapsed: 0,
+            retry_multiplier: 2,
+            retry_jitter: 0,
+            default_timeout: 0,
+            connect_timeout: 10_000,
+            read_timeout: 30_000,
+            host_rate_limit: 10,
+            host_rate_burst: 10,
+            total_rate_limit: 0,
+            total_rate_burst: 10,
+            max_redirects: 10,
+            max_concurrent_requests: 8,
         }
     }
 }
@@ -94,6 +180,23 @@ impl Data {
     pub fn usize(&self, opt: USizeOpt) -> usize {
         match opt {
             USizeOpt::MaxAge => self.max_age,
+            USizeOpt::RetryMaxAttempts => self.retry_max_attempts,
+            USizeOpt::RetryBaseDelay => self.retry_base_delay,
+            USizeOpt::RetryBudget => self.retry_budget,
+            USizeOpt::RetryBudgetRefill => self.retry_budget_refill,
+            USizeOpt::RetryMaxBackoff => self.retry_max_backoff,
+            USizeOpt::RetryMaxElapsed => self.retry_max_elapsed,
+            USizeOpt::RetryMultiplier => self.retry_multiplier,
+            USizeOpt::RetryJitter => self.retry_jitter,
+            USizeOpt::DefaultTimeout => self.default_timeout,
+            USizeOpt::ConnectTimeout => self.connect_timeout,
+            USizeOpt::ReadTimeout => self.read_timeout,
+            USizeOpt::HostRateLimit => self.host_rate_limit,
+            USizeOpt::HostRateBurst => self.host_rate_burst,
+            USizeOpt::TotalRateLimit => self.total_rate_limit,
+            USizeOpt::TotalRateBurst => self.total_rate_burst,
+            USizeOpt::MaxRedirects => self.max_redirects,
+            USizeOpt::MaxConcurrentRequests => self.max_concurrent_requests,
         }
     }
 
@@ -105,6 +208,23 @@ impl Data {
     pub fn set_usize(&mut self, opt: USizeOpt, value: usize) {
         match opt {
             USizeOpt::MaxAge => self.max_age = value,
+            USizeOpt::RetryMaxAttempts => self.retry_max_attempts = value,
+            USizeOpt::RetryBaseDelay => self.retry_base_delay = value,
+            USizeOpt::RetryBudget => self.retry_budget = value,
+            USizeOpt::RetryBudgetRefill => self.retry_budget_refill = value,
+            USizeOpt::RetryMaxBackoff => self.retry_max_backoff = value,
+            USizeOpt::RetryMaxElapsed => self.retry_max_elapsed = value,
+            USizeOpt::RetryMultiplier => self.retry_multiplier = value,
+            USizeOpt::RetryJitter => self.retry_jitter = value,
+            USizeOpt::DefaultTimeout => self.default_timeout = value,
+            USizeOpt::ConnectTimeout => self.connect_timeout = value,
+            USizeOpt::ReadTimeout => self.read_timeout = value,
+            USizeOpt::HostRateLimit => self.host_rate_limit = value,
+            USizeOpt::HostRateBurst => self.host_rate_burst = value,
+            USizeOpt::TotalRateLimit => self.total_rate_limit = value,
+            USizeOpt::TotalRateBurst => self.total_rate_burst = value,
+            USizeOpt::MaxRedirects => self.max_redirects = value,
+            USizeOpt::MaxConcurrentRequests => self.max_concurrent_requests = value,
         }
     }
 }
@@ -122,6 +242,28 @@ mod tests {
             "/tmp/patch-hub/logs"
         );
         assert_eq!(data.usize(USizeOpt::MaxAge), 0);
+        assert_eq!(data.usize(USizeOpt::RetryMaxAttempts), 1);
+        assert_eq!(data.usize(USizeOpt::RetryBudget), 10);
+        assert_eq!(data.usize(USizeOpt::RetryMaxBackoff), 30_000);
+        assert_eq!(data.usize(USizeOpt::RetryMaxElapsed), 0);
+        assert_eq!(data.usize(USizeOpt::RetryMultiplier), 2);
+        assert_eq!(data.usize(USizeOpt::RetryJitter), 0);
+        assert_eq!(data.usize(USizeOpt::DefaultTimeout), 0);
+        assert_eq!(data.usize(USizeOpt::ConnectTimeout), 10_000);
+        assert_eq!(data.usize(USizeOpt::ReadTimeout), 30_000);
+        assert_eq!(data.usize(USizeOpt::HostRateLimit), 10);
+        assert_eq!(data.usize(USizeOpt::HostRateBurst), 10);
+        assert_eq!(data.usize(USizeOpt::TotalRateLimit), 0);
+        assert_eq!(data.usize(USizeOpt::MaxRedirects), 10);
+        assert_eq!(data.usize(USizeOpt::MaxConcurrentRequests), 8);
+    }
+
+    #[test]
+    fn test_data_missing_fields_use_defaults() {
+        let data: Data = toml::from_str("max_age = 30").unwrap();
+        assert_eq!(data.usize(USizeOpt::MaxAge), 30);
+        assert_eq!(data.usize(USizeOpt::RetryBaseDelay), 200);
+        assert_eq!(data.usize(USizeOpt::RetryBudgetRefill), 1);
     }
 
     #[test]
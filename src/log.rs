@@ -5,12 +5,15 @@ mod message;
 mod tests;
 
 pub use core::LogCore;
-pub use data::LogLevel;
-use data::LogMessage;
+use data::MockLog;
+pub use data::{Clock, ErrorPolicy, LogFormatter, LogLevel, LogMessage, LogSink, TextFormatter};
 
-use std::collections::VecDeque;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
 use std::fmt::Display;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::Sender;
 use tokio::task::JoinHandle;
@@ -35,12 +38,36 @@ pub enum Log {
     /// A real logging actor that writes to files and stderr
     Actual(Sender<message::Message>),
     /// A mock implementation for testing that stores messages in memory
-    Mock(Arc<Mutex<VecDeque<LogMessage>>>),
+    Mock(Arc<Mutex<MockLog>>),
+}
+
+/// Restores the print level of a logger when a temporary level scope is left early
+struct LevelGuard {
+    log: Log,
+    previous: Option<LogLevel>,
+}
+
+impl Drop for LevelGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            let log = self.log.clone();
+            tokio::spawn(async move {
+                // A flushed logger has no level left to restore
+                let _ = log.set_level(previous).await;
+            });
+        }
+    }
 }
 
 impl Log {
     /// Creates a new logging instance and spawns its actor.
     ///
+    /// Messages logged without a timestamp are stamped with the system clock; use
+    /// [`LogCore::with_clock`] to read the time from another [`Clock`]. Lines are
+    /// laid out by [`TextFormatter`]; use [`LogCore::with_formatter`] for another
+    /// layout. Errors are logged like any other message; use
+    /// [`LogCore::with_error_policy`] to be notified of them or to exit on them.
+    ///
     /// # Arguments
     /// * `fs` - The filesystem actor for file operations
     /// * `level` - The minimum log level to print to stderr
@@ -55,7 +82,34 @@ impl Log {
         max_age: usize,
         log_dir: crate::ArcPath,
     ) -> anyhow::Result<Self> {
-        let (log, _) = LogCore::build(fs, level, max_age, log_dir).await?.spawn();
+        Self::spawn_with_sinks(fs, level, max_age, log_dir, Vec::new()).await
+    }
+
+    /// Creates a new logging instance forwarding every message to the given
+    /// sinks, and spawns its actor.
+    ///
+    /// Messages are still written to the log files; the sinks receive them in
+    /// addition, in the order they are logged. Use [`LogCore::with_deduped_sink`]
+    /// for a sink that should not receive the repeats of a message.
+    ///
+    /// # Arguments
+    /// * `fs` - The filesystem actor for file operations
+    /// * `level` - The minimum log level to print to stderr
+    /// * `max_age` - Maximum age of log files in days before deletion
+    /// * `log_dir` - Directory where log files are stored
+    /// * `sinks` - Additional destinations of the logged messages
+    ///
+    /// # Returns
+    /// A new logging instance with a spawned actor.
+    pub async fn spawn_with_sinks(
+        fs: crate::fs::Fs,
+        level: LogLevel,
+        max_age: usize,
+        log_dir: crate::ArcPath,
+        sinks: Vec<LogSink>,
+    ) -> anyhow::Result<Self> {
+        let core = LogCore::build(fs, level, max_age, log_dir).await?;
+        let (log, _) = sinks.into_iter().fold(core, LogCore::with_sink).spawn();
         Ok(log)
     }
 
@@ -64,36 +118,56 @@ impl Log {
     /// # Returns
     /// A new mock logging instance that stores messages in memory.
     pub fn mock() -> Self {
-        Self::Mock(Arc::new(Mutex::new(VecDeque::new())))
+        Self::Mock(Arc::new(Mutex::new(MockLog::default())))
     }
 
     fn log(&self, message: String, level: LogLevel) {
+        self.send(LogMessage {
+            level,
+            message,
+            timestamp: None,
+        });
+    }
+
+    fn send(&self, message: LogMessage) {
         match self {
             Log::Actual(sender) => {
                 let sender = sender.clone();
                 tokio::spawn(async move {
-                    sender
-                        .send(message::Message::Log(LogMessage {
-                            level,
-                            message: message.to_string(),
-                        }))
-                        .await
-                        .expect("Attempt to use logger after a flush");
+                    // Once the logger is flushed the line is dropped, so that
+                    // the outcome of the caller never depends on logging
+                    let _ = sender.send(message::Message::Log(message)).await;
                 });
             }
-            Log::Mock(messages) => {
-                let messages = messages.clone();
+            Log::Mock(state) => {
+                let state = state.clone();
                 tokio::spawn(async move {
-                    let mut lock = messages.lock().await;
-                    lock.push_back(LogMessage {
-                        level,
-                        message: message.to_string(),
-                    });
+                    let mut lock = state.lock().await;
+                    lock.messages.push_back(message);
                 });
             }
         }
     }
 
+    /// Log a message with an explicit timestamp instead of the current time.
+    ///
+    /// The timestamp is written at the start of the log line, which allows
+    /// replaying or importing past events faithfully. Any timestamp already set
+    /// in the message is replaced.
+    ///
+    /// # Arguments
+    /// * `when` - The time the message refers to
+    /// * `msg` - The message to log
+    ///
+    /// # Examples
+    /// ```
+    /// log.log_at(event.time, LogMessage { level: LogLevel::Info, message, timestamp: None });
+    /// ```
+    pub fn log_at(&self, when: DateTime<Utc>, mut msg: LogMessage) {
+        msg.timestamp = Some(when);
+        self.send(msg);
+    }
+
     /// Log a message with the `INFO` level
     pub fn info<M: Display>(&self, message: M) {
         self.log(message.to_string(), LogLevel::Info);
@@ -148,8 +222,9 @@ impl Log {
     }
 
     /// Flushes the logger by printing its messages to [`stderr`] and closing
-    /// the log file. After this method is called, the logger is destroyed and
-    /// any attempt to use it will panic.
+    /// the log file. After this method is called, the logger is destroyed:
+    /// messages logged afterwards are silently dropped, and any other attempt
+    /// to use it will panic.
     pub fn flush(self) -> JoinHandle<()> {
         match self {
             Self::Actual(sender) => tokio::spawn(async move {
@@ -158,15 +233,55 @@ impl Log {
                     .await
                     .expect("Flushing a logger twice");
             }),
-            Self::Mock(messages) => tokio::spawn(async move {
-                let lock = messages.lock().await;
-                for message in lock.iter() {
-                    eprintln!("{}", message);
+            Self::Mock(state) => tokio::spawn(async move {
+                let lock = state.lock().await;
+                for message in lock.messages.iter() {
+                    if message.level >= lock.level {
+                        eprintln!("{}", message);
+                    }
                 }
             }),
         }
     }
 
+    /// Flushes the logger like [`Log::flush`], waiting a bounded time for it to finish.
+    ///
+    /// If the actor hasn't flushed and stopped within the timeout, e.g. because
+    /// it is stuck writing to the log file, its task is aborted and the messages
+    /// it still held are lost.
+    ///
+    /// # Arguments
+    /// * `handle` - The join handle returned by [`LogCore::spawn`]
+    /// * `timeout` - How long the actor is given to flush
+    ///
+    /// # Returns
+    /// `Ok(())` after a graceful shutdown, or a [`crate::utils::ShutdownError`]
+    /// telling whether the actor was aborted or failed.
+    ///
+    /// # Examples
+    /// ```
+    /// let (log, handle) = log_core.spawn();
+    /// log.shutdown_timeout(handle, Duration::from_secs(5)).await?;
+    /// ```
+    pub async fn shutdown_timeout(
+        self,
+        handle: JoinHandle<()>,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        let request = async move {
+            match self {
+                Self::Actual(sender) => {
+                    let _ = sender.send(message::Message::Flush).await;
+                }
+                mock => {
+                    let _ = mock.flush().await;
+                }
+            }
+        };
+        crate::utils::shutdown_timeout(handle, request, timeout).await?;
+        Ok(())
+    }
+
     /// Collects the garbage from the logs directory. Garbage logs are the ones
     /// older than the [`max_age`] set during the logger [`build`].
     pub async fn collect_garbage(&self) {
@@ -181,6 +296,83 @@ impl Log {
         }
     }
 
+    /// Gets the minimum level of messages printed to stderr.
+    ///
+    /// # Returns
+    /// The current print level, or an error if the logger was flushed.
+    pub async fn level(&self) -> anyhow::Result<LogLevel> {
+        match self {
+            Self::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(message::Message::GetLevel { tx })
+                    .await
+                    .context("Sending message to Log actor")?;
+                rx.await.context("Receiving response from Log actor")
+            }
+            Self::Mock(state) => Ok(state.lock().await.level),
+        }
+    }
+
+    /// Sets the minimum level of messages printed to stderr.
+    ///
+    /// Messages are always written to the log files, the level only affects
+    /// which of them are printed when the logger is flushed.
+    ///
+    /// # Arguments
+    /// * `level` - The new print level
+    ///
+    /// # Returns
+    /// The print level that was replaced, or an error if the logger was flushed.
+    pub async fn set_level(&self, level: LogLevel) -> anyhow::Result<LogLevel> {
+        match self {
+            Self::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(message::Message::SetLevel { level, tx })
+                    .await
+                    .context("Sending message to Log actor")?;
+                rx.await.context("Receiving response from Log actor")
+            }
+            Self::Mock(state) => Ok(std::mem::replace(&mut state.lock().await.level, level)),
+        }
+    }
+
+    /// Runs a future with a temporary print level.
+    ///
+    /// The level is set before the future is polled and the previous one is
+    /// restored once it completes. If the future panics or is dropped before
+    /// completing, the previous level is restored in the background. After a
+    /// flush the future just runs, as there is no level left to set.
+    ///
+    /// Each scope restores the level it replaced, so nested scopes behave as a
+    /// stack. Overlapping scopes that don't nest are not coordinated: the last
+    /// one to end writes the level it found when it started.
+    ///
+    /// # Arguments
+    /// * `level` - The print level to use while the future runs
+    /// * `fut` - The future to run
+    ///
+    /// # Returns
+    /// The output of the future.
+    ///
+    /// # Examples
+    /// ```
+    /// let feed = log.with_temporary_level(LogLevel::Info, lore_api.get_patch_feed("amd-gfx", 0)).await?;
+    /// ```
+    pub async fn with_temporary_level<F: Future>(&self, level: LogLevel, fut: F) -> F::Output {
+        let previous = self.set_level(level).await.ok();
+        let mut guard = LevelGuard {
+            log: self.clone(),
+            previous,
+        };
+        let output = fut.await;
+        if let Some(previous) = guard.previous.take() {
+            let _ = self.set_level(previous).await;
+        }
+        output
+    }
+
     /// Gets all logged messages from the mock implementation.
     /// This method is only available for mock instances and is useful for testing.
     ///
@@ -188,9 +380,9 @@ impl Log {
     /// A vector of all logged messages, or None if this is not a mock instance.
     pub async fn get_messages(&self) -> Option<Vec<LogMessage>> {
         match self {
-            Self::Mock(messages) => {
-                let lock = messages.lock().await;
-                Some(lock.iter().cloned().collect())
+            Self::Mock(state) => {
+                let lock = state.lock().await;
+                Some(lock.messages.iter().cloned().collect())
             }
             Self::Actual(_) => None,
         }
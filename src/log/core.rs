@@ -1,8 +1,11 @@
+use std::{panic::AssertUnwindSafe, sync::Arc, time::Duration};
+
 use anyhow::Context;
 use tokio::{io::AsyncWriteExt, task::JoinHandle};
 
-use super::data::{LogLevel, LogMessage};
+use super::data::{LogLevel, LogMessage, LogSink, RepeatFilter};
 use super::message::Message;
+use super::{Clock, ErrorPolicy, LogFormatter, TextFormatter};
 use crate::{ArcFile, ArcPath, fs::Fs};
 
 /// The core of the logging system that manages logging to both stderr and log files.
@@ -17,6 +20,25 @@ use crate::{ArcFile, ArcPath, fs::Fs};
 /// - Configurable log levels
 /// - Automatic log file rotation and cleanup
 /// - Buffered stderr output
+/// - Forwarding to user-provided sinks
+/// - Per-sink suppression of repeated messages, the log files keeping every line
+/// - Timestamping of unstamped messages with an injectable clock
+/// - Pluggable line layout through a [`LogFormatter`]
+/// - Notification or exit on errors through an [`ErrorPolicy`]
+/// - Optional summary line marking a clean shutdown
+///
+/// # Deduplication
+/// Each sink, and the stderr buffer, can suppress the repeats of a message
+/// within a time window. The dedup state is per sink: a message suppressed on
+/// one is still received by the others, and always written to the log files.
+///
+/// # Shutdown
+/// With [`LogCore::with_shutdown_summary`], flushing the logger first logs a
+/// last line, `shutdown clean=true processed=<n> dropped=<n>`, written to the
+/// log files and forwarded to the sinks like any message before the actor
+/// stops. `processed` counts the messages logged during the run, and `dropped`
+/// the deliveries to a sink or to stderr that didn't happen, as a repeat or
+/// because the sink was closed. A log lacking the line wasn't shut down cleanly.
 ///
 /// # Examples
 /// ```
@@ -41,10 +63,27 @@ pub struct LogCore {
     latest_log_file: ArcFile,
     /// Buffer of messages to be printed to stderr
     logs_to_print: Vec<LogMessage>,
+    /// Filter of the repeats buffered for stderr, if deduplicated
+    print_dedup: Option<RepeatFilter>,
     /// Minimum level of messages to be printed to stderr
     print_level: LogLevel,
     /// Maximum age of log files in days before they are deleted
     max_age: usize,
+    /// Additional destinations messages are forwarded to, with the filter of
+    /// their repeats if deduplicated
+    sinks: Vec<(LogSink, Option<RepeatFilter>)>,
+    /// Source of the timestamps of messages logged without one
+    clock: Clock,
+    /// Layout of the lines written to the log files and to stderr
+    formatter: Arc<dyn LogFormatter>,
+    /// What to do once an error is logged
+    on_error: ErrorPolicy,
+    /// Whether a summary line is logged when the logger is flushed
+    shutdown_summary: bool,
+    /// The number of messages logged so far
+    processed: usize,
+    /// The number of deliveries to a sink or to stderr suppressed or lost so far
+    dropped: usize,
 }
 
 impl LogCore {
@@ -84,11 +123,108 @@ impl LogCore {
             log_file,
             latest_log_file,
             logs_to_print: Vec::new(),
+            print_dedup: None,
             print_level: level,
             max_age,
+            sinks: Vec::new(),
+            clock: chrono::Utc::now,
+            formatter: Arc::new(TextFormatter),
+            on_error: ErrorPolicy::default(),
+            shutdown_summary: false,
+            processed: 0,
+            dropped: 0,
         })
     }
 
+    /// Adds a sink every logged message is forwarded to.
+    ///
+    /// # Arguments
+    /// * `sink` - The sink to add
+    ///
+    /// # Returns
+    /// The core forwarding to the given sink.
+    pub fn with_sink(mut self, sink: LogSink) -> Self {
+        self.sinks.push((sink, None));
+        self
+    }
+
+    /// Adds a sink messages are forwarded to, except for the repeats of a
+    /// message within the given window.
+    ///
+    /// The sink keeps its own dedup state, so the other sinks still receive
+    /// every message.
+    ///
+    /// # Arguments
+    /// * `sink` - The sink to add
+    /// * `window` - How long the repeats of a message are suppressed
+    ///
+    /// # Returns
+    /// The core forwarding to the given sink, without repeats.
+    pub fn with_deduped_sink(mut self, sink: LogSink, window: Duration) -> Self {
+        self.sinks.push((sink, Some(RepeatFilter::new(window))));
+        self
+    }
+
+    /// Suppresses the repeats of a message within the given window from
+    /// stderr, while the log files keep every line.
+    ///
+    /// # Arguments
+    /// * `window` - How long the repeats of a message are suppressed
+    ///
+    /// # Returns
+    /// The core printing messages to stderr without repeats.
+    pub fn with_print_dedup(mut self, window: Duration) -> Self {
+        self.print_dedup = Some(RepeatFilter::new(window));
+        self
+    }
+
+    /// Replaces the system clock used to timestamp messages and to age log files.
+    ///
+    /// # Arguments
+    /// * `clock` - The clock to read the time from
+    ///
+    /// # Returns
+    /// The core reading the time from the given clock.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Replaces the [`TextFormatter`] laying out the lines of the log files and of stderr.
+    ///
+    /// # Arguments
+    /// * `formatter` - The formatter to lay out the lines with
+    ///
+    /// # Returns
+    /// The core laying out its lines with the given formatter.
+    pub fn with_formatter<F: LogFormatter + 'static>(mut self, formatter: F) -> Self {
+        self.formatter = Arc::new(formatter);
+        self
+    }
+
+    /// Replaces the [`ErrorPolicy::Ignore`] policy applied when an error is logged.
+    ///
+    /// # Arguments
+    /// * `policy` - The policy to apply
+    ///
+    /// # Returns
+    /// The core applying the given policy to errors.
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.on_error = policy;
+        self
+    }
+
+    /// Logs a summary line when the logger is flushed, marking a clean shutdown.
+    ///
+    /// See the "Shutdown" section of [`LogCore`] for the content of the line.
+    ///
+    /// # Returns
+    /// The core logging a summary on shutdown.
+    pub fn with_shutdown_summary(mut self) -> Self {
+        self.shutdown_summary = true;
+        self
+    }
+
     pub fn spawn(mut self) -> (super::Log, JoinHandle<()>) {
         let (tx, mut rx) = tokio::sync::mpsc::channel(100);
         let handle = tokio::spawn(async move {
@@ -98,6 +234,9 @@ impl LogCore {
                         self.log(msg).await;
                     }
                     Message::Flush => {
+                        if self.shutdown_summary {
+                            self.log_shutdown_summary().await;
+                        }
                         self.flush();
                         rx.close();
                         break;
@@ -105,39 +244,136 @@ impl LogCore {
                     Message::CollectGarbage => {
                         self.collect_garbage().await;
                     }
+                    Message::SetLevel { level, tx } => {
+                        let _ = tx.send(std::mem::replace(&mut self.print_level, level));
+                    }
+                    Message::GetLevel { tx } => {
+                        let _ = tx.send(self.print_level);
+                    }
                 }
             }
         });
         (super::Log::Actual(tx), handle)
     }
 
-    async fn log(&mut self, message: LogMessage) {
+    async fn log(&mut self, mut message: LogMessage) {
+        if message.timestamp.is_none() {
+            message.timestamp = Some((self.clock)());
+        }
+        self.processed += 1;
+        let line = format!("{}\n", self.format(&message));
+
         let mut lock = self.log_file.write().await;
-        lock.write_all(format!("{}\n", &message).as_bytes())
+        lock.write_all(line.as_bytes())
             .await
             .expect("Failed to write to the current log file");
 
         lock.flush()
             .await
             .expect("Failed to flush the current log file");
+        drop(lock);
 
         let mut lock = self.latest_log_file.write().await;
-        lock.write_all(format!("{}\n", &message).as_bytes())
+        lock.write_all(line.as_bytes())
             .await
             .expect("Failed to write to the latest log file");
 
         lock.flush()
             .await
             .expect("Failed to flush the latest log file");
+        drop(lock);
 
+        self.forward(&message).await;
+
+        let is_error = message.level == LogLevel::Error;
+        let timestamp = message.timestamp.unwrap_or_else(self.clock);
         if message.level >= self.print_level {
-            self.logs_to_print.push(message);
+            if self
+                .print_dedup
+                .as_mut()
+                .is_none_or(|dedup| dedup.admit(&message, timestamp))
+            {
+                self.logs_to_print.push(message.clone());
+            } else {
+                self.dropped += 1;
+            }
+        }
+        if is_error {
+            self.on_error(message);
+        }
+    }
+
+    /// Applies the error policy to a logged error
+    fn on_error(&self, message: LogMessage) {
+        match &self.on_error {
+            ErrorPolicy::Ignore => {}
+            ErrorPolicy::Notify(tx) => {
+                // Nobody listening is not an error of the logger
+                let _ = tx.send(message);
+            }
+            ErrorPolicy::Abort(code) => {
+                self.print();
+                std::process::exit(*code);
+            }
         }
     }
 
+    async fn forward(&mut self, message: &LogMessage) {
+        let timestamp = message.timestamp.unwrap_or_else(self.clock);
+        let mut open = Vec::with_capacity(self.sinks.len());
+        for (sink, mut dedup) in self.sinks.drain(..) {
+            if !dedup
+                .as_mut()
+                .is_none_or(|dedup| dedup.admit(message, timestamp))
+            {
+                self.dropped += 1;
+                open.push((sink, dedup));
+                continue;
+            }
+            match &sink {
+                LogSink::Channel(tx) => {
+                    if tx.send(message.clone()).await.is_ok() {
+                        open.push((sink, dedup));
+                    } else {
+                        self.dropped += 1;
+                    }
+                }
+            }
+        }
+        self.sinks = open;
+    }
+
+    /// Lays out a message with the formatter, falling back to the text layout if it panics
+    fn format(&self, message: &LogMessage) -> String {
+        let timestamp = message.timestamp.unwrap_or_else(self.clock);
+        std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.formatter.format(message, timestamp)
+        }))
+        .unwrap_or_else(|_| TextFormatter.format(message, timestamp))
+    }
+
+    /// Logs the line marking a clean shutdown, with the counters of the run
+    async fn log_shutdown_summary(&mut self) {
+        let message = format!(
+            "shutdown clean=true processed={} dropped={}",
+            self.processed, self.dropped
+        );
+        self.log(LogMessage {
+            level: LogLevel::Info,
+            message,
+            timestamp: None,
+        })
+        .await;
+    }
+
     fn flush(self) {
+        self.print();
+    }
+
+    /// Prints the buffered messages to stderr
+    fn print(&self) {
         for message in &self.logs_to_print {
-            eprintln!("{}", message);
+            eprintln!("{}", self.format(message));
         }
         if !self.logs_to_print.is_empty() {
             eprintln!("Check the full log file: {}", self.log_path.display());
@@ -148,11 +384,12 @@ impl LogCore {
         if self.max_age == 0 {
             return;
         }
-        let now = std::time::SystemTime::now();
+        let now = std::time::SystemTime::from((self.clock)());
         let Ok(logs) = self.fs.read_dir(self.log_dir.clone()).await else {
             self.log(LogMessage {
                 level: LogLevel::Error,
                 message: "Failed to read the logs directory during garbage collection".into(),
+                timestamp: None,
             })
             .await;
             return;
@@ -177,6 +414,7 @@ impl LogCore {
                 self.log(LogMessage {
                     message: format!("Failed to remove the log file: {}", log.to_string_lossy()),
                     level: LogLevel::Warning,
+                    timestamp: None,
                 })
                 .await;
             }
@@ -223,6 +461,7 @@ mod tests {
         let msg = LogMessage {
             level: LogLevel::Info,
             message: "test".to_string(),
+            timestamp: None,
         };
         log_core.log(msg.clone()).await;
         assert_eq!(log_core.logs_to_print.len(), 1);
@@ -240,12 +479,14 @@ mod tests {
         let msg = LogMessage {
             level: LogLevel::Info,
             message: "info".to_string(),
+            timestamp: None,
         };
         log_core.log(msg.clone()).await;
         assert!(log_core.logs_to_print.is_empty());
         let msg2 = LogMessage {
             level: LogLevel::Warning,
             message: "warn".to_string(),
+            timestamp: None,
         };
         log_core.log(msg2.clone()).await;
         assert_eq!(log_core.logs_to_print.len(), 1);
@@ -1,9 +1,12 @@
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, str::FromStr};
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
+use tokio::sync::{broadcast, mpsc::Sender};
 
 /// Describes a message to be logged.
 ///
-/// Contains both the message content and its associated log level.
+/// Contains both the message content and its associated log level, and
+/// optionally the time the message refers to.
 /// This struct is used internally by the logger to manage log entries.
 ///
 /// # Examples
@@ -11,20 +14,188 @@ use std::{fmt::Display, str::FromStr};
 /// let msg = LogMessage {
 ///     level: LogLevel::Info,
 ///     message: "Application started".to_string(),
+///     timestamp: None,
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct LogMessage {
     pub level: LogLevel,
     pub message: String,
+    /// The time the message refers to, printed before the level when set.
+    /// Not serialized.
+    #[serde(skip)]
+    pub timestamp: Option<DateTime<Utc>>,
 }
 
 impl std::fmt::Display for LogMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(timestamp) = self.timestamp {
+            write!(
+                f,
+                "{} ",
+                timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+            )?;
+        }
         write!(f, "[{}] {}", self.level, self.message)
     }
 }
 
+/// The source of the time stamped on the messages logged without a timestamp.
+///
+/// The logging actor uses [`Utc::now`] unless another clock is given to
+/// [`LogCore::with_clock`](super::LogCore::with_clock), which lets tests
+/// control the time deterministically.
+///
+/// # Examples
+/// ```
+/// let (log, _) = LogCore::build(fs, LogLevel::Info, 7, log_dir).await?.with_clock(Utc::now).spawn();
+/// ```
+pub type Clock = fn() -> DateTime<Utc>;
+
+/// Lays out the lines the logging actor writes to its log files and to stderr.
+///
+/// The formatter is called on the actor's task, with the time the message is
+/// stamped with. A formatter that panics doesn't bring the actor down: the
+/// line is laid out by [`TextFormatter`] instead.
+///
+/// # Examples
+/// ```
+/// #[derive(Debug)]
+/// struct Logfmt;
+///
+/// impl LogFormatter for Logfmt {
+///     fn format(&self, message: &LogMessage, timestamp: DateTime<Utc>) -> String {
+///         format!("ts={} level={} msg={:?}", timestamp.timestamp(), message.level, message.message)
+///     }
+/// }
+///
+/// let (log, _) = LogCore::build(fs, LogLevel::Info, 7, log_dir).await?.with_formatter(Logfmt).spawn();
+/// ```
+pub trait LogFormatter: std::fmt::Debug + Send + Sync {
+    /// Lays out a message as a single line, without the line terminator.
+    ///
+    /// # Arguments
+    /// * `message` - The message to lay out
+    /// * `timestamp` - The time the message refers to
+    ///
+    /// # Returns
+    /// The line to write.
+    fn format(&self, message: &LogMessage, timestamp: DateTime<Utc>) -> String;
+}
+
+/// The default layout: an RFC 3339 timestamp, the level in brackets and the message.
+///
+/// # Examples
+/// ```
+/// // 2023-12-01T12:34:56Z [WARN] mirror went down
+/// let line = TextFormatter.format(&message, timestamp);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextFormatter;
+
+impl LogFormatter for TextFormatter {
+    fn format(&self, message: &LogMessage, timestamp: DateTime<Utc>) -> String {
+        format!(
+            "{} [{}] {}",
+            timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            message.level,
+            message.message
+        )
+    }
+}
+
+/// A destination the logging actor forwards each message to, besides its
+/// log files.
+///
+/// # Backpressure
+/// A sink is fed the same way the actor's own mailbox is: when it is full,
+/// the actor waits for room instead of dropping the message. A sink whose
+/// receiver was dropped is removed.
+///
+/// # Examples
+/// ```
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+/// let log = Log::spawn_with_sinks(fs, LogLevel::Info, 7, log_dir, vec![LogSink::Channel(tx)]).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub enum LogSink {
+    /// Forwards every message to a user-provided channel
+    Channel(Sender<LogMessage>),
+}
+
+/// Suppresses the repeats of a message within a time window.
+///
+/// Each sink deduplicating its messages holds its own filter, so what one sink
+/// suppresses has no effect on what the others, or the log files, receive.
+#[derive(Debug, Clone)]
+pub(crate) struct RepeatFilter {
+    /// How long the repeats of a message are suppressed after it was let through
+    window: chrono::Duration,
+    /// The last message let through, with its level and timestamp
+    last: Option<(LogLevel, String, DateTime<Utc>)>,
+}
+
+impl RepeatFilter {
+    /// Creates a filter suppressing repeats for the given window
+    pub(crate) fn new(window: std::time::Duration) -> Self {
+        Self {
+            window: chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX),
+            last: None,
+        }
+    }
+
+    /// Checks whether a message is let through, which is the case unless it
+    /// has the level and text of the last message let through, less than the
+    /// window earlier
+    pub(crate) fn admit(&mut self, message: &LogMessage, timestamp: DateTime<Utc>) -> bool {
+        if let Some((level, text, since)) = &self.last
+            && *level == message.level
+            && *text == message.message
+            && timestamp - *since < self.window
+        {
+            return false;
+        }
+        self.last = Some((message.level, message.message.clone(), timestamp));
+        true
+    }
+}
+
+/// What the logging actor does when a message of level [`LogLevel::Error`] is logged.
+///
+/// The message is always written to the log files and forwarded to the sinks
+/// first; the policy only decides what happens afterwards.
+///
+/// # Examples
+/// ```
+/// let (tx, mut errors) = tokio::sync::broadcast::channel(1);
+/// let (log, _) = LogCore::build(fs, LogLevel::Info, 7, log_dir)
+///     .await?
+///     .with_error_policy(ErrorPolicy::Notify(tx))
+///     .spawn();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub enum ErrorPolicy {
+    /// Keeps running as if the message was of any other level
+    #[default]
+    Ignore,
+    /// Broadcasts the message to the application, which decides how to stop
+    Notify(broadcast::Sender<LogMessage>),
+    /// Prints the buffered messages to stderr and exits the process with the given code
+    Abort(i32),
+}
+
+/// The state of a mock logger.
+///
+/// Stores every logged message in memory, along with the print level so that
+/// level changes can be observed in tests.
+#[derive(Debug, Clone, Default)]
+pub struct MockLog {
+    /// Every message logged so far, in order
+    pub messages: VecDeque<LogMessage>,
+    /// Minimum level of messages printed on flush
+    pub level: LogLevel,
+}
+
 /// Describes the log level of a message.
 ///
 /// This enum is used to determine the severity of a log message so the logger
@@ -110,23 +281,51 @@ mod tests {
         let msg = LogMessage {
             level: LogLevel::Error,
             message: "fail".to_string(),
+            timestamp: None,
         };
         assert_eq!(msg.to_string(), "[ERROR] fail");
     }
 
+    #[test]
+    fn test_log_message_display_with_timestamp() {
+        let msg = LogMessage {
+            level: LogLevel::Info,
+            message: "replayed".to_string(),
+            timestamp: Some(DateTime::from_timestamp(1_000_000_000, 0).unwrap()),
+        };
+        assert_eq!(msg.to_string(), "2001-09-09T01:46:40Z [INFO] replayed");
+    }
+
+    #[test]
+    fn test_text_formatter() {
+        let msg = LogMessage {
+            level: LogLevel::Warning,
+            message: "mirror went down".to_string(),
+            timestamp: None,
+        };
+        let timestamp = DateTime::from_timestamp(1_000_000_000, 0).unwrap();
+        assert_eq!(
+            TextFormatter.format(&msg, timestamp),
+            "2001-09-09T01:46:40Z [WARN] mirror went down"
+        );
+    }
+
     #[test]
     fn test_log_message_ordering_and_equality() {
         let a = LogMessage {
             level: LogLevel::Info,
             message: "a".to_string(),
+            timestamp: None,
         };
         let b = LogMessage {
             level: LogLevel::Warning,
             message: "b".to_string(),
+            timestamp: None,
         };
         let c = LogMessage {
             level: LogLevel::Info,
             message: "a".to_string(),
+            timestamp: None,
         };
         assert!(a < b);
         assert_eq!(a, c);
@@ -1,4 +1,6 @@
-use super::data::LogMessage;
+use tokio::sync::oneshot::Sender;
+
+use super::data::{LogLevel, LogMessage};
 
 /// Messages that can be sent to a [`LogCore`] actor.
 ///
@@ -12,6 +14,13 @@ pub enum Message {
     Flush,
     /// Runs the log garbage collector to delete old log files
     CollectGarbage,
+    /// Sets the minimum level of messages printed to stderr, replying with the previous one
+    SetLevel {
+        level: LogLevel,
+        tx: Sender<LogLevel>,
+    },
+    /// Gets the minimum level of messages printed to stderr
+    GetLevel { tx: Sender<LogLevel> },
 }
 
 #[cfg(test)]
@@ -24,6 +33,7 @@ mod tests {
         let msg = LogMessage {
             level: LogLevel::Info,
             message: "test".to_string(),
+            timestamp: None,
         };
         let m = Message::Log(msg.clone());
         match m {
@@ -68,3 +68,380 @@ async fn test_log_get_messages() {
     assert_eq!(messages[1].level, LogLevel::Warning);
     assert_eq!(messages[1].message, "warning message");
 }
+
+#[tokio::test]
+async fn test_log_with_temporary_level() {
+    let log = Log::mock();
+    log.set_level(LogLevel::Warning).await.unwrap();
+
+    let level = log
+        .with_temporary_level(LogLevel::Info, async { log.level().await.unwrap() })
+        .await;
+    assert_eq!(level, LogLevel::Info);
+    assert_eq!(log.level().await.unwrap(), LogLevel::Warning);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_log_temporary_level_restored_on_panic() {
+    let log = Log::mock();
+    log.set_level(LogLevel::Error).await.unwrap();
+
+    let scoped = log.clone();
+    let result = tokio::spawn(async move {
+        scoped
+            .with_temporary_level(LogLevel::Info, async {
+                panic!("operation failed");
+            })
+            .await
+    })
+    .await;
+    assert!(result.is_err());
+
+    // The level is restored in the background after the panic
+    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    assert_eq!(log.level().await.unwrap(), LogLevel::Error);
+}
+
+#[tokio::test]
+async fn test_log_level_after_flush_fails_without_panicking() {
+    // A logger whose actor already stopped
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    drop(rx);
+    let log = Log::Actual(tx);
+
+    assert!(log.level().await.is_err());
+    assert!(log.set_level(LogLevel::Info).await.is_err());
+    let output = log
+        .with_temporary_level(LogLevel::Info, async { "done" })
+        .await;
+    assert_eq!(output, "done");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_log_at_fixed_time() {
+    let log = Log::mock();
+    let when = chrono::DateTime::parse_from_rfc3339("2023-12-01T12:34:56Z")
+        .unwrap()
+        .to_utc();
+    log.log_at(
+        when,
+        LogMessage {
+            level: LogLevel::Warning,
+            message: "mirror went down".to_string(),
+            timestamp: None,
+        },
+    );
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+    let messages = log.get_messages().await.unwrap();
+    assert_eq!(messages[0].timestamp, Some(when));
+    assert_eq!(
+        messages[0].to_string(),
+        "2023-12-01T12:34:56Z [WARN] mirror went down"
+    );
+}
+
+#[tokio::test]
+async fn test_log_channel_sink() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    let log = Log::spawn_with_sinks(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+        vec![LogSink::Channel(tx)],
+    )
+    .await
+    .unwrap();
+
+    log.info("first");
+    let first = rx.recv().await.unwrap();
+    assert_eq!(first.level, LogLevel::Info);
+    assert_eq!(first.message, "first");
+
+    log.error("second");
+    let second = rx.recv().await.unwrap();
+    assert_eq!(second.level, LogLevel::Error);
+    assert_eq!(second.message, "second");
+}
+
+#[tokio::test]
+async fn test_log_shutdown_timeout_graceful() {
+    let dir = tempfile::tempdir().unwrap();
+    let core = LogCore::build(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+    )
+    .await
+    .unwrap();
+    let (log, handle) = core.spawn();
+
+    log.info("stopping");
+    log.shutdown_timeout(handle, std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_log_shutdown_timeout_aborts_stuck_core() {
+    // A core that never reads its mailbox, and so ignores the shutdown
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let handle = tokio::spawn(async move {
+        let _rx = rx;
+        std::future::pending::<()>().await
+    });
+    let task = handle.abort_handle();
+    let timeout = std::time::Duration::from_millis(50);
+
+    let error = Log::Actual(tx)
+        .shutdown_timeout(handle, timeout)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<crate::utils::ShutdownError>(),
+        Some(&crate::utils::ShutdownError::Forced { timeout })
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert!(task.is_finished());
+}
+
+/// A clock starting at 2023-12-01T12:34:56Z and moving a minute forward on each read.
+///
+/// The minutes are counted per thread: each test runs with the logging actor on
+/// its own single-threaded runtime, so tests running in parallel don't share a clock.
+fn fake_clock() -> chrono::DateTime<chrono::Utc> {
+    thread_local! {
+        static MINUTES: std::cell::Cell<i64> = const { std::cell::Cell::new(0) };
+    }
+    let minutes = MINUTES.with(|minutes| minutes.replace(minutes.get() + 1));
+    chrono::DateTime::from_timestamp(1_701_434_096 + minutes * 60, 0).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_log_stamps_with_injected_clock() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+    let (log, _) = LogCore::build(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+    )
+    .await
+    .unwrap()
+    .with_clock(fake_clock)
+    .with_sink(LogSink::Channel(tx))
+    .spawn();
+
+    log.info("first");
+    let first = rx.recv().await.unwrap();
+    log.warn("second");
+    let second = rx.recv().await.unwrap();
+
+    assert_eq!(first.to_string(), "2023-12-01T12:34:56Z [INFO] first");
+    assert_eq!(second.to_string(), "2023-12-01T12:35:56Z [WARN] second");
+}
+
+/// Lays out lines in the logfmt style
+#[derive(Debug)]
+struct Logfmt;
+
+impl LogFormatter for Logfmt {
+    fn format(&self, message: &LogMessage, timestamp: chrono::DateTime<chrono::Utc>) -> String {
+        format!(
+            "ts={} level={} msg={:?}",
+            timestamp.timestamp(),
+            message.level,
+            message.message
+        )
+    }
+}
+
+/// A formatter that fails on every line
+#[derive(Debug)]
+struct Broken;
+
+impl LogFormatter for Broken {
+    fn format(&self, _: &LogMessage, _: chrono::DateTime<chrono::Utc>) -> String {
+        panic!("broken formatter")
+    }
+}
+
+/// Logs two lines with a formatter and returns the content of the latest log file
+async fn format_lines<F: LogFormatter + 'static>(formatter: F) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let (log, handle) = LogCore::build(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+    )
+    .await
+    .unwrap()
+    .with_clock(|| chrono::DateTime::from_timestamp(1_701_434_096, 0).unwrap())
+    .with_formatter(formatter)
+    .spawn();
+
+    // Each line is sent from its own task, so let the first be written to keep
+    // them in order: with the clock paused, the sleep ends once every task is idle
+    log.info("first");
+    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    log.warn("second \"quoted\"");
+    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    log.shutdown_timeout(handle, std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+    std::fs::read_to_string(dir.path().join("latest.log")).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_log_custom_formatter() {
+    assert_eq!(
+        format_lines(Logfmt).await,
+        "ts=1701434096 level=INFO msg=\"first\"\n\
+         ts=1701434096 level=WARN msg=\"second \\\"quoted\\\"\"\n"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_log_panicking_formatter_falls_back_to_text() {
+    assert_eq!(
+        format_lines(Broken).await,
+        "2023-12-01T12:34:56Z [INFO] first\n2023-12-01T12:34:56Z [WARN] second \"quoted\"\n"
+    );
+}
+
+#[tokio::test]
+async fn test_log_error_policy_notify() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, mut errors) = tokio::sync::broadcast::channel(4);
+    let (log, _) = LogCore::build(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+    )
+    .await
+    .unwrap()
+    .with_error_policy(ErrorPolicy::Notify(tx))
+    .spawn();
+
+    log.info("not an error");
+    log.warn("not an error either");
+    log.error("mirror unreachable");
+
+    let error = tokio::time::timeout(std::time::Duration::from_secs(5), errors.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(error.level, LogLevel::Error);
+    assert_eq!(error.message, "mirror unreachable");
+    assert!(errors.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_log_dedup_is_per_sink() {
+    let dir = tempfile::tempdir().unwrap();
+    let (full_tx, mut full) = tokio::sync::mpsc::channel(16);
+    let (deduped_tx, mut deduped) = tokio::sync::mpsc::channel(16);
+    let (log, handle) = LogCore::build(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+    )
+    .await
+    .unwrap()
+    .with_clock(fake_clock)
+    .with_sink(LogSink::Channel(full_tx))
+    .with_deduped_sink(
+        LogSink::Channel(deduped_tx),
+        std::time::Duration::from_secs(60 * 60),
+    )
+    .spawn();
+
+    // Each line is sent from its own task, so wait for each to keep them in order
+    let lines = [
+        (LogLevel::Warning, "mirror down"),
+        (LogLevel::Warning, "mirror down"),
+        (LogLevel::Warning, "mirror down"),
+        (LogLevel::Warning, "mirror up"),
+        // A message of another level isn't a repeat
+        (LogLevel::Info, "mirror up"),
+    ];
+    for (level, message) in lines {
+        match level {
+            LogLevel::Info => log.info(message),
+            _ => log.warn(message),
+        }
+        assert_eq!(full.recv().await.unwrap().message, message);
+    }
+    log.shutdown_timeout(handle, std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let received: Vec<_> = std::iter::from_fn(|| deduped.try_recv().ok())
+        .map(|message| (message.level, message.message))
+        .collect();
+    assert_eq!(
+        received,
+        [
+            (LogLevel::Warning, "mirror down".to_string()),
+            (LogLevel::Warning, "mirror up".to_string()),
+            (LogLevel::Info, "mirror up".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_log_shutdown_summary_is_last() {
+    let dir = tempfile::tempdir().unwrap();
+    let (full_tx, mut full) = tokio::sync::mpsc::channel(16);
+    let (deduped_tx, mut deduped) = tokio::sync::mpsc::channel(16);
+    let (log, handle) = LogCore::build(
+        crate::fs::Fs::spawn(),
+        LogLevel::Error,
+        0,
+        crate::ArcPath::from(dir.path()),
+    )
+    .await
+    .unwrap()
+    .with_clock(fake_clock)
+    .with_sink(LogSink::Channel(full_tx))
+    .with_deduped_sink(
+        LogSink::Channel(deduped_tx),
+        std::time::Duration::from_secs(60 * 60),
+    )
+    .with_shutdown_summary()
+    .spawn();
+
+    // Each line is sent from its own task, so wait for each to keep them in order
+    for message in ["mirror down", "mirror down", "mirror up"] {
+        log.warn(message);
+        assert_eq!(full.recv().await.unwrap().message, message);
+    }
+    log.shutdown_timeout(handle, std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    // The repeat suppressed by the deduped sink is the only drop
+    let summary = "shutdown clean=true processed=3 dropped=1";
+    let last = full.recv().await.unwrap();
+    assert_eq!(
+        (last.level, last.message.as_str()),
+        (LogLevel::Info, summary)
+    );
+    assert!(full.try_recv().is_err());
+    let received: Vec<_> = std::iter::from_fn(|| deduped.try_recv().ok())
+        .map(|message| message.message)
+        .collect();
+    assert_eq!(received, ["mirror down", "mirror up", summary]);
+
+    let content = std::fs::read_to_string(dir.path().join("latest.log")).unwrap();
+    assert!(content.lines().last().unwrap().ends_with(summary));
+    assert_eq!(content.lines().count(), 4);
+}
@@ -1,13 +1,40 @@
 use anyhow::Context;
-use tokio::sync::mpsc::Sender;
+use futures_core::Stream;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
-use std::collections::HashMap;
+use tokio::sync::mpsc::Sender;
 
-use crate::{net::{message::{Message, MockRequestKey}, core::Core}, ArcStr};
+use crate::{
+    ArcPath, ArcStr,
+    net::{
+        core::Core,
+        message::{HttpMethod, Message, MockRequestKey},
+        mock::write_fixtures,
+    },
+    utils::ShutdownError,
+};
 
 mod core;
+mod data;
 pub mod message;
+pub mod mock;
+mod stream;
+#[cfg(test)]
+mod tests;
+mod transport;
+
+pub use data::{
+    Credentials, JsonEncoding, NetConfigSnapshot, NetError, NetMetrics, NetOptions, NetRequest,
+    NetResponse, Priority, RetryPolicy,
+};
+pub use mock::MockState;
+pub use stream::{BodyStream, ResponseStart};
+
+/// Maximum number of pages followed by [`Net::get_all_pages`]
+pub const MAX_LINK_PAGES: usize = 100;
 
 /// The networking actor that provides a thread-safe interface for network operations.
 ///
@@ -29,7 +56,7 @@ pub enum Net {
     /// A real networking actor that performs HTTP requests
     Actual(Sender<Message>),
     /// A mock implementation for testing
-    Mock(Arc<Mutex<HashMap<MockRequestKey, ArcStr>>>),
+    Mock(Arc<Mutex<MockState>>),
 }
 
 impl Net {
@@ -46,6 +73,83 @@ impl Net {
         net
     }
 
+    /// Stops the networking actor, waiting a bounded time for it to finish.
+    ///
+    /// The actor stops taking messages, answers the requests it already
+    /// received, then stops even if clones of the instance are still alive,
+    /// their later calls failing. If requests are still in flight when the
+    /// timeout expires, e.g. because they wait on a slow host, they are aborted.
+    ///
+    /// # Arguments
+    /// * `timeout` - How long the actor is given to stop
+    ///
+    /// # Returns
+    /// `Ok(())` after a graceful shutdown or if the actor had already stopped,
+    /// or a [`crate::utils::ShutdownError`] telling whether requests were
+    /// aborted or the actor failed.
+    ///
+    /// # Examples
+    /// ```
+    /// let net = Net::spawn(config, log);
+    /// net.shutdown_timeout(Duration::from_secs(5)).await?;
+    /// ```
+    pub async fn shutdown_timeout(&self, timeout: Duration) -> anyhow::Result<()> {
+        match self {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                if sender
+                    .send(Message::Shutdown { timeout, tx })
+                    .await
+                    .is_err()
+                {
+                    // The actor has already stopped
+                    return Ok(());
+                }
+                rx.await
+                    .unwrap_or_else(|err| Err(ShutdownError::Failed(err.to_string())))?;
+                Ok(())
+            }
+            Net::Mock(_) => Ok(()),
+        }
+    }
+
+    /// Creates a new networking instance that records its responses and spawns its actor.
+    ///
+    /// Requests are performed as usual, and the last response of each is kept
+    /// so [`Net::export_fixtures`] can write them to a fixture file for
+    /// [`Net::mock_from_file`] to serve later.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration actor for settings
+    /// * `log` - The logging actor for operation logging
+    ///
+    /// # Returns
+    /// A new recording networking instance with a spawned actor.
+    pub fn spawn_recording(config: crate::config::Config, log: crate::log::Log) -> Self {
+        let recorder = Arc::new(Mutex::new(MockState::default()));
+        let (net, _) = Core::new(config, log).with_recorder(recorder).spawn();
+        net
+    }
+
+    /// Creates a new networking instance with the given options and spawns its actor.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration actor for settings
+    /// * `log` - The logging actor for operation logging
+    /// * `options` - Settings that don't fit in the configuration, such as the
+    ///   default headers of each HTTP method
+    ///
+    /// # Returns
+    /// A new networking instance with a spawned actor.
+    pub fn spawn_with_options(
+        config: crate::config::Config,
+        log: crate::log::Log,
+        options: NetOptions,
+    ) -> Self {
+        let (net, _) = Core::new(config, log).with_options(options).spawn();
+        net
+    }
+
     /// Creates a new mock networking instance for testing.
     ///
     /// # Arguments
@@ -54,7 +158,63 @@ impl Net {
     /// # Returns
     /// A new mock networking instance that returns predefined responses.
     pub fn mock(responses: HashMap<MockRequestKey, ArcStr>) -> Self {
-        Self::Mock(Arc::new(Mutex::new(responses)))
+        Self::Mock(Arc::new(Mutex::new(MockState::from(responses))))
+    }
+
+    /// Creates a new mock networking instance serving binary responses.
+    ///
+    /// The bodies are served as-is by [`Net::get_bytes`], so binary payloads
+    /// such as compressed mbox archives can be mocked without a lossy conversion.
+    ///
+    /// # Arguments
+    /// * `responses` - Response cache mapping HTTP method + URL pairs to raw bodies
+    ///
+    /// # Returns
+    /// A new mock networking instance that returns predefined responses.
+    pub fn mock_bytes(responses: HashMap<MockRequestKey, Arc<[u8]>>) -> Self {
+        Self::Mock(Arc::new(Mutex::new(MockState::from(responses))))
+    }
+
+    /// Creates a new mock networking instance whose responses take time.
+    ///
+    /// Each response is served once its delay has elapsed, and a request whose
+    /// timeout is shorter than the delay fails once the timeout has elapsed
+    /// instead, as the actor would. With a paused clock, timeouts can thus be
+    /// tested deterministically.
+    ///
+    /// # Arguments
+    /// * `responses` - Response cache mapping HTTP method + URL pairs to a response
+    ///   and the time to wait before serving it
+    ///
+    /// # Returns
+    /// A new mock networking instance that returns predefined responses after a delay.
+    pub fn mock_with_delays(responses: HashMap<MockRequestKey, (ArcStr, Duration)>) -> Self {
+        Self::Mock(Arc::new(Mutex::new(MockState::from(responses))))
+    }
+
+    /// Creates a new mock networking instance serving the responses of a fixture file.
+    ///
+    /// The file is one written by [`Net::export_fixtures`], which closes the loop
+    /// of recording the responses of the real actor and replaying them in tests.
+    ///
+    /// # Arguments
+    /// * `path` - The fixture file
+    ///
+    /// # Returns
+    /// A new mock networking instance, or an error if the file can't be read
+    /// or isn't a valid fixture file.
+    ///
+    /// # Examples
+    /// ```
+    /// let net = Net::mock_from_file(ArcPath::from(&PathBuf::from("tests/fixtures/lore.json"))).await?;
+    /// ```
+    pub async fn mock_from_file(path: ArcPath) -> Result<Self, anyhow::Error> {
+        let fixtures = tokio::fs::read_to_string(&*path)
+            .await
+            .with_context(|| format!("Reading fixtures from {}", path.display()))?;
+        let state = MockState::from_fixtures(&fixtures)
+            .with_context(|| format!("Parsing fixtures from {}", path.display()))?;
+        Ok(Self::mock_with_state(Arc::new(Mutex::new(state))))
     }
 
     /// Creates a new empty mock networking instance for testing.
@@ -62,111 +222,784 @@ impl Net {
     /// # Returns
     /// A new mock networking instance with an empty response cache.
     pub fn mock_empty() -> Self {
-        Self::Mock(Arc::new(Mutex::new(HashMap::new())))
+        Self::Mock(Arc::new(Mutex::new(MockState::default())))
+    }
+
+    /// Creates a new mock networking instance from a shared mock state.
+    ///
+    /// The state can be kept by the caller to register responses with a status
+    /// code, or sequences of responses, and to inspect the requests served.
+    ///
+    /// # Arguments
+    /// * `state` - The shared mock state
+    ///
+    /// # Returns
+    /// A new mock networking instance serving responses from the given state.
+    pub fn mock_with_state(state: Arc<Mutex<MockState>>) -> Self {
+        Self::Mock(state)
+    }
+
+    /// Writes the recorded responses to a fixture file.
+    ///
+    /// The file is pretty-printed JSON, with one object per request method and
+    /// URL, and can be loaded back with [`Net::mock_from_file`]. Headers carrying
+    /// credentials, such as `Set-Cookie`, are redacted before writing. The
+    /// actor must be recording, see [`Net::spawn_recording`]; the mock exports
+    /// the responses it serves.
+    ///
+    /// # Arguments
+    /// * `path` - The fixture file, created with its parent directories if needed
+    ///
+    /// # Returns
+    /// An error if the actor isn't recording or the file can't be written.
+    ///
+    /// # Examples
+    /// ```
+    /// let net = Net::spawn_recording(config, log);
+    /// net.get(url, None).await?;
+    /// net.export_fixtures(ArcPath::from(&PathBuf::from("tests/fixtures/lore.json"))).await?;
+    /// ```
+    pub async fn export_fixtures(&self, path: ArcPath) -> Result<(), anyhow::Error> {
+        match self {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::ExportFixtures { path, tx })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => write_fixtures(state, &path).await,
+        }
+    }
+
+    /// Gives a priority to the requests sent through the returned handle.
+    ///
+    /// The requests of [`Net::get`], [`Net::post`] and the like are
+    /// [`Priority::Normal`]. Sent through the handle, they are handled ahead of
+    /// or behind the other requests waiting for the actor instead, like a
+    /// [`NetRequest`] given [`NetRequest::with_priority`]. The mock ignores
+    /// priorities.
+    ///
+    /// # Arguments
+    /// * `priority` - The priority of the requests
+    ///
+    /// # Returns
+    /// A handle sending its requests with the given priority.
+    ///
+    /// # Examples
+    /// ```
+    /// let feed = net.with_priority(Priority::High).get(url, None).await?;
+    /// ```
+    pub fn with_priority(&self, priority: Priority) -> PrioritizedNet<'_> {
+        PrioritizedNet {
+            net: self,
+            priority,
+        }
     }
 
     /// Performs an HTTP GET request to the specified URL.
     ///
+    /// By default, a response that is still not 2xx once retries are over fails
+    /// with [`NetError::Status`]. With [`NetOptions::error_on_non_2xx`] turned
+    /// off, the body is returned whatever the status, e.g. to scrape error
+    /// pages. The mock always fails on non-2xx statuses. Use [`Net::request`] to
+    /// get the status along with the body.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response body as a string, or an error if the request fails.
+    pub async fn get(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        self.with_priority(Priority::Normal).get(url, headers).await
+    }
+
+    /// Performs an HTTP GET request, giving up on each attempt after a timeout.
+    ///
+    /// The timeout replaces the default one of the actor, set through the
+    /// `DefaultTimeout` option of the configuration, for this request only. An
+    /// attempt still waiting for its response when the timeout elapses is
+    /// aborted and fails with [`NetError::TimedOut`], which tells a slow server
+    /// apart from one that can't be reached. Failed attempts are retried like
+    /// any GET, each with the full timeout.
+    ///
     /// # Arguments
     /// * `url` - The URL to send the GET request to
     /// * `headers` - Optional headers to include in the request
+    /// * `timeout` - How long each attempt waits for its response
     ///
     /// # Returns
     /// The response body as a string, or an error if the request fails.
-    pub async fn get(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>) -> Result<ArcStr, anyhow::Error> {
+    ///
+    /// # Examples
+    /// ```
+    /// match net.get_with_timeout(url, None, Duration::from_secs(10)).await {
+    ///     Err(err) if matches!(err.downcast_ref(), Some(NetError::TimedOut { .. })) => {
+    ///         println!("Lore is slow, trying a mirror");
+    ///     }
+    ///     result => println!("{:?}", result),
+    /// }
+    /// ```
+    pub async fn get_with_timeout(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        timeout: Duration,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let request = NetRequest::new(HttpMethod::Get, url, headers, None).with_timeout(timeout);
+        let response = self.request(request.clone()).await?;
+        Ok(request.check_status(response)?.text())
+    }
+
+    /// Performs an HTTP GET request and returns the raw response body.
+    ///
+    /// Unlike [`Net::get`], the body is returned as-is, without any UTF-8
+    /// conversion. The whole body is buffered before returning, so responses
+    /// sent with chunked transfer-encoding (no `Content-Length`) are complete.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response body bytes, or an error if the request fails.
+    pub async fn get_bytes(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<Arc<[u8]>, anyhow::Error> {
+        self.with_priority(Priority::Normal)
+            .get_bytes(url, headers)
+            .await
+    }
+
+    /// Fetches every page of a resource paginated through `Link` headers.
+    ///
+    /// Starting from `url`, this method follows the `rel="next"` link of each
+    /// response (RFC 8288) until a page without one is reached. The same headers
+    /// are sent with every page request. To guard against pagination loops, at most
+    /// [`MAX_LINK_PAGES`] pages are fetched.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the first page
+    /// * `headers` - Optional headers to include in every request
+    ///
+    /// # Returns
+    /// The responses of all the pages in order, or an error if any request fails
+    /// or the pagination goes beyond [`MAX_LINK_PAGES`] pages.
+    pub async fn get_all_pages(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<Vec<NetResponse>, anyhow::Error> {
+        let mut pages = Vec::new();
+        let mut next = Some(url);
+        while let Some(url) = next {
+            if pages.len() >= MAX_LINK_PAGES {
+                anyhow::bail!(
+                    "Pagination exceeded {} pages at URL: {}",
+                    MAX_LINK_PAGES,
+                    url
+                );
+            }
+            let request = NetRequest::new(HttpMethod::Get, url.clone(), headers.clone(), None);
+            let response = self.request(request).await?;
+            next = response.next_link(&url);
+            pages.push(response);
+        }
+        Ok(pages)
+    }
+
+    /// Performs a request and returns the full response.
+    ///
+    /// Unlike the per-method helpers, this method gives access to the status code
+    /// and headers of the response, which is needed for conditional requests.
+    /// The response is returned whatever its status; only transport failures are
+    /// errors.
+    ///
+    /// # Arguments
+    /// * `request` - The request to perform
+    ///
+    /// # Returns
+    /// The response, or an error if the request could not be performed.
+    ///
+    /// # Examples
+    /// ```
+    /// let request = NetRequest::new(HttpMethod::Get, url, Some(headers), None);
+    /// let response = net.request(request).await?;
+    /// ```
+    pub async fn request(&self, request: NetRequest) -> Result<NetResponse, anyhow::Error> {
         match self {
             Net::Actual(sender) => {
                 let (tx, rx) = tokio::sync::oneshot::channel();
-                sender.send(Message::Get { url, headers, tx }).await.context("Sending message to Net actor")?;
+                sender
+                    .send(Message::Request { request, tx })
+                    .await
+                    .context("Sending message to Net actor")?;
                 rx.await.context("Receiving response from Net actor")?
             }
-            Net::Mock(responses) => {
-                let responses = responses.lock().await;
-                let key = MockRequestKey::get(url);
-                responses.get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("GET request not found in mock responses: {}", key.url))
-            }
+            Net::Mock(state) => Self::mock_respond(state, request).await,
         }
     }
 
-    /// Performs an HTTP POST request to the specified URL.
+    /// Performs an HTTP GET request and returns the full response.
+    ///
+    /// Unlike [`Net::get`], a non-2xx response isn't an error: the status is
+    /// returned with the body, so a 404 error page can be told apart from the
+    /// content looked for. This is [`Net::request`] for a plain GET.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response whatever its status, or an error if the request could not
+    /// be performed.
+    ///
+    /// # Examples
+    /// ```
+    /// let response = net.get_full(url, None).await?;
+    /// if response.status == 404 {
+    ///     println!("Not archived");
+    /// }
+    /// ```
+    pub async fn get_full(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<NetResponse, anyhow::Error> {
+        self.request(NetRequest::new(HttpMethod::Get, url, headers, None))
+            .await
+    }
+
+    /// Performs an HTTP POST request and returns the full response.
+    ///
+    /// Like [`Net::post`], the request is only retried with an idempotency key,
+    /// but a non-2xx response isn't an error: the status is returned with the
+    /// body. This is [`Net::request`] for a POST.
     ///
     /// # Arguments
     /// * `url` - The URL to send the POST request to
     /// * `headers` - Optional headers to include in the request
     /// * `body` - Optional body content to send with the request
+    /// * `idempotency_key` - Optional key, unique to the operation, enabling retries
     ///
     /// # Returns
-    /// The response body as a string, or an error if the request fails.
-    pub async fn post(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>, body: Option<ArcStr>) -> Result<ArcStr, anyhow::Error> {
+    /// The response whatever its status, or an error if the request could not
+    /// be performed.
+    ///
+    /// # Examples
+    /// ```
+    /// let response = net.post_full(url, None, Some(body), None).await?;
+    /// println!("{}: {}", response.status, response.text());
+    /// ```
+    pub async fn post_full(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+        idempotency_key: Option<ArcStr>,
+    ) -> Result<NetResponse, anyhow::Error> {
+        let mut request = NetRequest::new(HttpMethod::Post, url, headers, body);
+        if let Some(key) = idempotency_key {
+            request = request.with_idempotency_key(key);
+        }
+        self.request(request).await
+    }
+
+    /// Gets the settings the networking actor is actually running with.
+    ///
+    /// This is meant for diagnostics, to confirm that the configuration and the
+    /// spawn options took effect. Credential headers and proxy credentials are
+    /// redacted. The mock returns a default snapshot.
+    ///
+    /// # Returns
+    /// A read-only snapshot of the effective settings, or an error if the actor
+    /// can't be reached.
+    ///
+    /// # Examples
+    /// ```
+    /// let snapshot = net.effective_config().await?;
+    /// println!("Retrying up to {} times", snapshot.retry_policy.max_attempts);
+    /// ```
+    pub async fn effective_config(&self) -> Result<NetConfigSnapshot, anyhow::Error> {
         match self {
             Net::Actual(sender) => {
                 let (tx, rx) = tokio::sync::oneshot::channel();
-                sender.send(Message::Post { url, headers, body, tx }).await.context("Sending message to Net actor")?;
-                rx.await.context("Receiving response from Net actor")?
+                sender
+                    .send(Message::GetConfig { tx })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")
             }
-            Net::Mock(responses) => {
-                let responses = responses.lock().await;
-                let key = MockRequestKey::post(url);
-                responses.get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("POST request not found in mock responses: {}", key.url))
+            Net::Mock(_) => Ok(NetConfigSnapshot::default()),
+        }
+    }
+
+    /// Gets the runtime metrics of the networking actor.
+    ///
+    /// This tells, among others, which hosts are cooling down after asking to
+    /// back off with `Retry-After`. The mock returns empty metrics.
+    ///
+    /// # Returns
+    /// A snapshot of the metrics, or an error if the actor can't be reached.
+    ///
+    /// # Examples
+    /// ```
+    /// if !net.metrics().await?.is_open("lore.kernel.org") {
+    ///     println!("Lore asked us to slow down");
+    /// }
+    /// ```
+    pub async fn metrics(&self) -> Result<NetMetrics, anyhow::Error> {
+        match self {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::GetMetrics { tx })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")
             }
+            Net::Mock(_) => Ok(NetMetrics::default()),
         }
     }
 
-    /// Performs an HTTP PUT request to the specified URL.
+    /// Performs an HTTP GET request and returns the body as a stream of chunks.
+    ///
+    /// The call returns once the response headers are received; the body is
+    /// then downloaded as the stream is consumed, so arbitrarily large bodies
+    /// can be processed in bounded memory. Streamed requests are not retried,
+    /// and the timeout only applies to the wait for the headers.
     ///
     /// # Arguments
-    /// * `url` - The URL to send the PUT request to
+    /// * `url` - The URL to send the GET request to
     /// * `headers` - Optional headers to include in the request
-    /// * `body` - Optional body content to send with the request
     ///
     /// # Returns
-    /// The response body as a string, or an error if the request fails.
-    pub async fn put(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>, body: Option<ArcStr>) -> Result<ArcStr, anyhow::Error> {
+    /// The body stream, or an error if the request fails. Non-2xx statuses
+    /// fail with [`NetError::Status`].
+    ///
+    /// # Examples
+    /// ```
+    /// let body = net.get_stream(url, None).await?;
+    /// ```
+    pub async fn get_stream(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<BodyStream, anyhow::Error> {
+        let request = NetRequest::new(HttpMethod::Get, url, headers, None);
         match self {
             Net::Actual(sender) => {
                 let (tx, rx) = tokio::sync::oneshot::channel();
-                sender.send(Message::Put { url, headers, body, tx }).await.context("Sending message to Net actor")?;
+                sender
+                    .send(Message::GetStream { request, tx })
+                    .await
+                    .context("Sending message to Net actor")?;
                 rx.await.context("Receiving response from Net actor")?
             }
-            Net::Mock(responses) => {
-                let responses = responses.lock().await;
-                let key = MockRequestKey::put(url);
-                responses.get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("PUT request not found in mock responses: {}", key.url))
+            Net::Mock(state) => {
+                let response = Self::mock_respond(state, request.clone()).await?;
+                Ok(BodyStream::once(request.check_status(response)?.body))
             }
         }
     }
 
-    /// Performs an HTTP DELETE request to the specified URL.
+    /// Downloads the body of an HTTP GET request to a file.
+    ///
+    /// The body is streamed to disk as it arrives, like [`Net::get_stream`],
+    /// so large archives never sit in memory. It is written to a `.part` file
+    /// next to the destination, renamed over the destination once complete:
+    /// a failed download removes the partial file and leaves any previous
+    /// file at the destination untouched. Missing parent directories are
+    /// created.
     ///
     /// # Arguments
-    /// * `url` - The URL to send the DELETE request to
+    /// * `url` - The URL to send the GET request to
+    /// * `path` - The path of the file to write
     /// * `headers` - Optional headers to include in the request
     ///
     /// # Returns
-    /// The response body as a string, or an error if the request fails.
-    pub async fn delete(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>) -> Result<ArcStr, anyhow::Error> {
+    /// The number of bytes written, or an error if the request or a write
+    /// fails. Non-2xx statuses fail with [`NetError::Status`].
+    ///
+    /// # Examples
+    /// ```
+    /// let written = net
+    ///     .download_to_file(url, ArcPath::from(&PathBuf::from("amd-gfx.mbox")), None)
+    ///     .await?;
+    /// ```
+    pub async fn download_to_file(
+        &self,
+        url: ArcStr,
+        path: ArcPath,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<u64, anyhow::Error> {
+        let mut body = self.get_stream(url.clone(), headers).await?;
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = std::path::PathBuf::from(partial);
+
+        let written = async {
+            let mut file = tokio::fs::File::create(&partial)
+                .await
+                .with_context(|| format!("Failed to create file: {}", partial.display()))?;
+            let mut written = 0u64;
+            while let Some(chunk) =
+                std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)).await
+            {
+                let chunk = chunk.with_context(|| format!("Failed to download: {}", url))?;
+                file.write_all(&chunk)
+                    .await
+                    .with_context(|| format!("Failed to write file: {}", partial.display()))?;
+                written += chunk.len() as u64;
+            }
+            file.flush()
+                .await
+                .with_context(|| format!("Failed to write file: {}", partial.display()))?;
+            tokio::fs::rename(&partial, &*path)
+                .await
+                .with_context(|| format!("Failed to move file to: {}", path.display()))?;
+            anyhow::Ok(written)
+        }
+        .await;
+        if written.is_err() {
+            let _ = tokio::fs::remove_file(&partial).await;
+        }
+        written
+    }
+
+    /// Performs an HTTP GET request and returns as soon as its headers arrive.
+    ///
+    /// This allows deciding from the status and the headers, e.g. the content
+    /// type or length, whether the body is worth downloading: it is only
+    /// downloaded as the body stream is consumed, and dropping the stream aborts
+    /// the download. Like [`Net::get_stream`], the request is not retried and the
+    /// timeout only applies to the wait for the headers. Unlike it, the response
+    /// is returned whatever its status.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The status, headers and body stream of the response, or an error if the
+    /// request could not be performed.
+    ///
+    /// # Examples
+    /// ```
+    /// let start = net.get_response_start(url, None).await?;
+    /// if start.status == 200 {
+    ///     let body = start.body;
+    ///     // ...
+    /// }
+    /// ```
+    pub async fn get_response_start(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ResponseStart, anyhow::Error> {
+        let request = NetRequest::new(HttpMethod::Get, url, headers, None);
         match self {
             Net::Actual(sender) => {
                 let (tx, rx) = tokio::sync::oneshot::channel();
-                sender.send(Message::Delete { url, headers, tx }).await.context("Sending message to Net actor")?;
+                sender
+                    .send(Message::GetResponseStart { request, tx })
+                    .await
+                    .context("Sending message to Net actor")?;
                 rx.await.context("Receiving response from Net actor")?
             }
-            Net::Mock(responses) => {
-                let responses = responses.lock().await;
-                let key = MockRequestKey::delete(url);
-                responses.get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("DELETE request not found in mock responses: {}", key.url))
+            Net::Mock(state) => {
+                let response = Self::mock_respond(state, request).await?;
+                Ok(ResponseStart {
+                    status: response.status,
+                    headers: response.headers,
+                    body: BodyStream::once(response.body),
+                })
             }
         }
     }
 
+    /// Performs an HTTP GET request and returns only the start of the body.
+    ///
+    /// The request asks for the first `max_bytes` bytes with a `Range` header.
+    /// Servers that ignore ranges answer with the whole body, in which case
+    /// reading stops once `max_bytes` bytes are received and the connection is
+    /// dropped, so the rest of the body is not downloaded either way. Fewer
+    /// bytes are returned only if the body is shorter than `max_bytes`.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `max_bytes` - The maximum number of bytes to return
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// At most `max_bytes` bytes from the start of the body, or an error if the
+    /// request fails.
+    ///
+    /// # Examples
+    /// ```
+    /// let prefix = net.get_prefix(url, 512, None).await?;
+    /// ```
+    pub async fn get_prefix(
+        &self,
+        url: ArcStr,
+        max_bytes: usize,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<Arc<[u8]>, anyhow::Error> {
+        if max_bytes == 0 {
+            return Ok(Arc::from(&[][..]));
+        }
+        let request =
+            NetRequest::new(HttpMethod::Get, url.clone(), headers, None).with_prefix(max_bytes);
+        let response = self.request(request.clone()).await?;
+        Ok(request.check_status(response)?.body)
+    }
+
+    /// Performs an HTTP GET request, failing on statuses that aren't accepted.
+    ///
+    /// Any 2xx status is a success, and so are the statuses of `accept_statuses`,
+    /// which lets probes treat an expected `404` as an answer rather than an
+    /// error. Accepted statuses are never retried.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    /// * `accept_statuses` - Non-2xx statuses to accept, `None` to accept only 2xx
+    ///
+    /// # Returns
+    /// The response, or an error if the request fails. Statuses that aren't
+    /// accepted fail with [`NetError::Status`].
+    ///
+    /// # Examples
+    /// ```
+    /// let response = net.get_checked(url, None, Some(HashSet::from([404]))).await?;
+    /// let exists = response.status != 404;
+    /// ```
+    pub async fn get_checked(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        accept_statuses: Option<HashSet<u16>>,
+    ) -> Result<NetResponse, anyhow::Error> {
+        let mut request = NetRequest::new(HttpMethod::Get, url, headers, None);
+        if let Some(statuses) = accept_statuses {
+            request = request.with_accept_statuses(statuses);
+        }
+        let response = self.request(request.clone()).await?;
+        Ok(request.check_status(response)?)
+    }
+
+    /// Performs an HTTP GET request and returns the raw body along with its charset.
+    ///
+    /// Some mirrors serve content in encodings other than UTF-8 and announce it in
+    /// the `Content-Type` header. The body is returned untouched so callers can
+    /// decode it with the announced charset, e.g. through `encoding_rs`.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response body bytes and the charset of the `Content-Type` header, if
+    /// any, or an error if the request fails.
+    ///
+    /// # Examples
+    /// ```
+    /// let (body, charset) = net.get_with_charset(url, None).await?;
+    /// ```
+    pub async fn get_with_charset(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<(Arc<[u8]>, Option<ArcStr>), anyhow::Error> {
+        let request = NetRequest::new(HttpMethod::Get, url, headers, None);
+        let response = self.request(request).await?;
+        let charset = response.charset();
+        Ok((response.body, charset))
+    }
+
+    /// Performs an HTTP GET request and returns the body along with the response headers.
+    ///
+    /// The headers give access to what the server says about the body, such as
+    /// its `Content-Type`, its `ETag` for caching, or the rate-limit headers of
+    /// the mirror. Header names are lowercase. Like [`Net::get`], a response that
+    /// isn't 2xx fails with [`NetError::Status`]; use [`Net::get_full`] to get the
+    /// headers of any response.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the GET request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response body as a string and the response headers, or an error if
+    /// the request fails.
+    ///
+    /// # Examples
+    /// ```
+    /// let (body, headers) = net.get_with_headers(url, None).await?;
+    /// let etag = headers.get(&ArcStr::from("etag"));
+    /// ```
+    pub async fn get_with_headers(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<(ArcStr, HashMap<ArcStr, ArcStr>), anyhow::Error> {
+        let request = NetRequest::new(HttpMethod::Get, url, headers, None);
+        let response = self.request(request.clone()).await?;
+        let response = request.check_status(response)?;
+        Ok((response.text(), response.headers))
+    }
+
+    /// Checks whether a URL is reachable.
+    ///
+    /// Sends a single HEAD request with a short timeout. Any HTTP response,
+    /// including error statuses such as `404`, means the server is up and counts
+    /// as reachable. Transport failures and timeouts count as unreachable.
+    /// The probe carries the default headers of HEAD requests and waits for the
+    /// pacing of its host like any other request.
+    /// This probes an external endpoint, not the networking actor itself.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to probe
+    ///
+    /// # Returns
+    /// `true` if the server answered, `false` otherwise.
+    pub async fn is_reachable(&self, url: ArcStr) -> bool {
+        match self {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                if sender.send(Message::IsReachable { url, tx }).await.is_err() {
+                    return false;
+                }
+                rx.await.unwrap_or(false)
+            }
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Head, url, None, None);
+                Self::mock_respond(state, request).await.is_ok()
+            }
+        }
+    }
+
+    /// Performs an HTTP POST request to the specified URL.
+    ///
+    /// POST requests are not retried, as sending them twice may repeat their
+    /// effect. When an idempotency key is given, it is sent in the
+    /// `Idempotency-Key` header and the request is retried like a GET, with the
+    /// same key on every attempt, so a server honoring the header performs the
+    /// operation at most once. Only use a key with endpoints that support it.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the POST request to
+    /// * `headers` - Optional headers to include in the request
+    /// * `body` - Optional body content to send with the request
+    /// * `idempotency_key` - Optional key, unique to the operation, enabling retries
+    ///
+    /// # Returns
+    /// The response body as a string, or an error if the request fails.
+    ///
+    /// # Examples
+    /// ```
+    /// let key = ArcStr::from("submit-2023-12-01-0001");
+    /// let response = net.post(url, None, Some(body), Some(key)).await?;
+    /// ```
+    pub async fn post(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+        idempotency_key: Option<ArcStr>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        self.with_priority(Priority::Normal)
+            .post(url, headers, body, idempotency_key)
+            .await
+    }
+
+    /// Performs an HTTP POST request with a JSON body.
+    ///
+    /// The value is serialized with the [`JsonEncoding`] of the actor, compact
+    /// unless [`NetOptions::json_encoding`] says otherwise, and sent as UTF-8
+    /// with a `Content-Type` of [`data::JSON_CONTENT_TYPE`], replacing any
+    /// given in `headers`. Like [`Net::post`], the request isn't retried. The
+    /// mock always sends compact JSON.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the POST request to
+    /// * `value` - The value to send as JSON
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response body as a string, or an error if the value can't be
+    /// serialized or the request fails.
+    ///
+    /// # Examples
+    /// ```
+    /// let body = serde_json::json!({ "list": "amd-gfx", "query": "s:drm" });
+    /// let response = net.post_json(url, &body, None).await?;
+    /// ```
+    pub async fn post_json<T: serde::Serialize + ?Sized>(
+        &self,
+        url: ArcStr,
+        value: &T,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        self.with_priority(Priority::Normal)
+            .post_json(url, value, headers)
+            .await
+    }
+
+    /// Performs an HTTP PUT request to the specified URL.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the PUT request to
+    /// * `headers` - Optional headers to include in the request
+    /// * `body` - Optional body content to send with the request
+    ///
+    /// # Returns
+    /// The response body as a string, or an error if the request fails.
+    pub async fn put(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        self.with_priority(Priority::Normal)
+            .put(url, headers, body)
+            .await
+    }
+
+    /// Performs an HTTP DELETE request to the specified URL.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to send the DELETE request to
+    /// * `headers` - Optional headers to include in the request
+    ///
+    /// # Returns
+    /// The response body as a string, or an error if the request fails.
+    pub async fn delete(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        self.with_priority(Priority::Normal)
+            .delete(url, headers)
+            .await
+    }
+
     /// Performs an HTTP PATCH request to the specified URL.
     ///
     /// # Arguments
@@ -176,19 +1009,275 @@ impl Net {
     ///
     /// # Returns
     /// The response body as a string, or an error if the request fails.
-    pub async fn patch(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>, body: Option<ArcStr>) -> Result<ArcStr, anyhow::Error> {
-        match self {
+    pub async fn patch(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        self.with_priority(Priority::Normal)
+            .patch(url, headers, body)
+            .await
+    }
+
+    /// Serves a request from the mock state once the delay of its key has elapsed
+    async fn mock_respond(
+        state: &Arc<Mutex<MockState>>,
+        request: NetRequest,
+    ) -> Result<NetResponse, anyhow::Error> {
+        let delay = state.lock().await.delay(&request);
+        if let Some(timeout) = request.timeout
+            && delay > timeout
+        {
+            tokio::time::sleep(timeout).await;
+            return Err(NetError::TimedOut {
+                url: request.url,
+                timeout,
+            }
+            .into());
+        }
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        let mut state = state.lock().await;
+        state
+            .respond(&request)
+            .map(NetResponse::from)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} request not found in mock responses: {}",
+                    request.method,
+                    request.url
+                )
+            })
+    }
+}
+
+/// A handle on a [`Net`] sending its requests with a given priority.
+///
+/// Returned by [`Net::with_priority`].
+#[derive(Debug, Clone, Copy)]
+pub struct PrioritizedNet<'a> {
+    /// The networking actor the requests are sent to
+    net: &'a Net,
+    /// The priority of the requests
+    priority: Priority,
+}
+
+impl PrioritizedNet<'_> {
+    /// Performs [`Net::get`] with the priority of the handle.
+    pub async fn get(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self.net {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::Get {
+                        url,
+                        headers,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Get, url, headers, None);
+                let response = Net::mock_respond(state, request.clone()).await?;
+                Ok(request.check_status(response)?.text())
+            }
+        }
+    }
+
+    /// Performs [`Net::get_bytes`] with the priority of the handle.
+    pub async fn get_bytes(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<Arc<[u8]>, anyhow::Error> {
+        match self.net {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::GetBytes {
+                        url,
+                        headers,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Get, url, headers, None);
+                Net::mock_respond(state, request)
+                    .await
+                    .map(|response| response.body)
+            }
+        }
+    }
+
+    /// Performs [`Net::post`] with the priority of the handle.
+    pub async fn post(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+        idempotency_key: Option<ArcStr>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self.net {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::Post {
+                        url,
+                        headers,
+                        body,
+                        idempotency_key,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => {
+                let mut request = NetRequest::new(HttpMethod::Post, url, headers, body);
+                if let Some(key) = idempotency_key {
+                    request = request.with_idempotency_key(key);
+                }
+                Net::mock_respond(state, request)
+                    .await
+                    .map(|response| response.text())
+            }
+        }
+    }
+
+    /// Performs [`Net::post_json`] with the priority of the handle.
+    pub async fn post_json<T: serde::Serialize + ?Sized>(
+        &self,
+        url: ArcStr,
+        value: &T,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        let value = serde_json::to_value(value).context("Serializing the JSON body")?;
+        match self.net {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::PostJson {
+                        url,
+                        headers,
+                        value,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Post, url, headers, None)
+                    .with_json(&value, JsonEncoding::default());
+                Net::mock_respond(state, request)
+                    .await
+                    .map(|response| response.text())
+            }
+        }
+    }
+
+    /// Performs [`Net::put`] with the priority of the handle.
+    pub async fn put(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self.net {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::Put {
+                        url,
+                        headers,
+                        body,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Put, url, headers, body);
+                Net::mock_respond(state, request)
+                    .await
+                    .map(|response| response.text())
+            }
+        }
+    }
+
+    /// Performs [`Net::delete`] with the priority of the handle.
+    pub async fn delete(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self.net {
+            Net::Actual(sender) => {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                sender
+                    .send(Message::Delete {
+                        url,
+                        headers,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
+                rx.await.context("Receiving response from Net actor")?
+            }
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Delete, url, headers, None);
+                Net::mock_respond(state, request)
+                    .await
+                    .map(|response| response.text())
+            }
+        }
+    }
+
+    /// Performs [`Net::patch`] with the priority of the handle.
+    pub async fn patch(
+        &self,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+    ) -> Result<ArcStr, anyhow::Error> {
+        match self.net {
             Net::Actual(sender) => {
                 let (tx, rx) = tokio::sync::oneshot::channel();
-                sender.send(Message::Patch { url, headers, body, tx }).await.context("Sending message to Net actor")?;
+                sender
+                    .send(Message::Patch {
+                        url,
+                        headers,
+                        body,
+                        priority: self.priority,
+                        tx,
+                    })
+                    .await
+                    .context("Sending message to Net actor")?;
                 rx.await.context("Receiving response from Net actor")?
             }
-            Net::Mock(responses) => {
-                let responses = responses.lock().await;
-                let key = MockRequestKey::patch(url);
-                responses.get(&key)
-                    .map(ArcStr::clone)
-                    .ok_or_else(|| anyhow::anyhow!("PATCH request not found in mock responses: {}", key.url))
+            Net::Mock(state) => {
+                let request = NetRequest::new(HttpMethod::Patch, url, headers, body);
+                Net::mock_respond(state, request)
+                    .await
+                    .map(|response| response.text())
             }
         }
     }
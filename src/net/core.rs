@@ -1,15 +1,43 @@
 use anyhow::Context;
-use reqwest::Client;
-use std::collections::HashMap;
-use tokio::task::JoinHandle;
+use reqwest::{Client, redirect::Policy};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    sync::{Arc, Mutex as StdMutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+use tokio::{
+    sync::Mutex,
+    task::{JoinHandle, JoinSet},
+    time::Instant,
+};
 
 use crate::{
     ArcStr,
-    config::Config,
+    config::{Config, USizeOpt},
     log::Log,
-    net::{Net, message::Message},
+    net::{
+        Credentials, Net,
+        data::{
+            DEFAULT_TRACE_HEADER, HostCooldown, HostRateLimiter, JsonEncoding, NetConfigSnapshot,
+            NetError, NetMetrics, NetOptions, NetRequest, NetResponse, Priority, ReplayGuard,
+            RetryBudget, RetryPolicy, RobotsRules, basic_realm, generate_trace_id, redact_header,
+        },
+        message::{HttpMethod, Message},
+        mock::{MockState, write_fixtures},
+        stream::{BodyStream, ResponseStart},
+        transport::Transport,
+    },
+    utils::Shutdown,
 };
 
+/// How many messages the channel of the actor and its priority buffer each
+/// hold before the senders wait
+pub(super) const MAILBOX_CAPACITY: usize = 100;
+
+/// How long a reachability probe waits for a response
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// The core of the networking system that handles HTTP requests.
 ///
 /// This struct provides thread-safe access to network operations through an actor pattern.
@@ -18,9 +46,58 @@ use crate::{
 /// # Features
 /// - Thread-safe network operations through actor pattern
 /// - HTTP client with automatic connection pooling
+/// - Automatic retries of idempotent requests, and of requests carrying an
+///   idempotency key, bounded by a global retry budget
 /// - Integration with logging system
+/// - Trace ids sent in a header and included in the log lines of each request
+/// - Optional refusal of duplicate POST submissions within a time window
+/// - Per-host rate limiting, pacing the requests sent to each host, with an
+///   optional limit across all hosts
+/// - Per-host cooldown after a `Retry-After`, holding every request to the host
+/// - Transparent gzip decompression, which can be turned off for some hosts
+/// - Optional respect of the `robots.txt` of each host, its rules and crawl delay
+/// - Optional recording of the responses, to be replayed by the mock
 /// - Configuration-based settings
 ///
+/// # Timeouts
+/// The connect and read timeouts of the configuration are set on the HTTP
+/// client: the first bounds establishing a connection, the second each read
+/// of the response, so a slow but steady transfer is never cut short. The
+/// per-request timeout (or the default one) bounds a whole attempt on top of
+/// them, and whichever expires first fails the attempt.
+///
+/// # Rate limiting
+/// Each attempt of a request, retries included, takes a token from the bucket
+/// of its host, refilled at the configured rate. When the bucket is empty the
+/// attempt waits for the next token instead of failing. Only the request
+/// waiting is held back: the others keep being handled meanwhile.
+///
+/// # Concurrency
+/// Each request is handled, retries and waits included, in a task of its own,
+/// so a slow or throttled host doesn't stall the callers of the other hosts.
+/// At most `MaxConcurrentRequests` of the configuration are in flight at once,
+/// the next ones waiting for a slot. Reading the settings, the metrics or
+/// exporting the fixtures is answered right away.
+///
+/// # Priorities
+/// Messages waiting for a slot are handled by the [`Priority`] of their
+/// request rather than in the order they arrived: up to [`MAILBOX_CAPACITY`]
+/// messages already sent are taken from the channel into a buffer before the
+/// next one is picked, the most urgent first and the oldest first within a
+/// priority. Once the buffer is full, messages stay in the channel and the
+/// senders wait when it is full too. Messages without a request are
+/// [`Priority::Normal`]. A request being handled is never interrupted.
+///
+/// # Redirects
+/// Redirects are followed by the core rather than the HTTP client, so mocked
+/// transports follow them too, up to the `MaxRedirects` option of the
+/// configuration in a row. Past it, the request fails with
+/// [`NetError::TooManyRedirects`] listing the URLs visited. Requests built with
+/// [`NetRequest::without_redirects`] get the redirect response itself. With the
+/// `same_host_only` option, a redirect to another host isn't followed and the
+/// redirect response is returned as is, which keeps untrusted URLs from
+/// bouncing requests to unexpected hosts.
+///
 /// # Examples
 /// ```
 /// let core = Core::new(config, log);
@@ -29,15 +106,61 @@ use crate::{
 ///
 /// # Thread Safety
 /// This type is designed to be safely shared between threads through the actor pattern.
-/// All network operations are handled sequentially to ensure consistency.
+/// The state shared by the requests in flight is kept behind locks never held across a wait.
 #[derive(Debug)]
 pub struct Core {
     /// Configuration interface for settings
     config: Config,
     /// Logging interface for operation logging
     log: Log,
-    /// HTTP client for making requests
-    client: Client,
+    /// Transport used to perform each request attempt
+    transport: Transport,
+    /// Transport used for the hosts whose responses aren't decompressed
+    raw_transport: Transport,
+    /// Retry policy applied to failed idempotent requests
+    retry_policy: RetryPolicy,
+    /// Budget limiting the rate of retries across all requests
+    retry_budget: StdMutex<RetryBudget>,
+    /// Token buckets limiting the rate of requests sent to each host
+    host_limiter: StdMutex<HostRateLimiter>,
+    /// Gates of the hosts cooling down after a `Retry-After`
+    host_cooldown: StdMutex<HostCooldown>,
+    /// Timeout applied to each attempt of requests that don't set their own
+    default_timeout: Option<Duration>,
+    /// Stall timeout of the bodies of requests that don't set their own
+    stall_timeout: Option<Duration>,
+    /// Credentials for HTTP Basic auth, keyed by realm
+    credentials: HashMap<ArcStr, Credentials>,
+    /// Whether the `robots.txt` of each host is honored
+    respect_robots: bool,
+    /// The `robots.txt` rules of each origin, and when it may be crawled next
+    robots: StdMutex<HashMap<ArcStr, (RobotsRules, Option<Instant>)>>,
+    /// Timeout for establishing a connection, set on the HTTP client
+    connect_timeout: Option<Duration>,
+    /// Timeout for each read of a response, set on the HTTP client
+    read_timeout: Option<Duration>,
+    /// The proxy the HTTP client takes from the environment, without credentials
+    proxy: Option<ArcStr>,
+    /// Default headers applied to the requests of each HTTP method
+    method_headers: HashMap<HttpMethod, HashMap<ArcStr, ArcStr>>,
+    /// The header the trace id of each request is sent in
+    trace_header: ArcStr,
+    /// Whether redirects to another host are refused
+    same_host_only: bool,
+    /// Maximum number of redirects followed in a row
+    max_redirects: usize,
+    /// Recent POST submissions, if duplicates are refused
+    replay_guard: Option<StdMutex<ReplayGuard>>,
+    /// Hosts whose responses aren't decompressed
+    no_decompression_hosts: HashSet<ArcStr>,
+    /// Whether GET requests for text fail on non-2xx responses
+    error_on_non_2xx: bool,
+    /// How JSON bodies are formatted
+    json_encoding: JsonEncoding,
+    /// Where the responses received are recorded, if recording
+    recorder: Option<Arc<Mutex<MockState>>>,
+    /// Maximum number of requests in flight at once
+    max_concurrent_requests: usize,
 }
 
 impl Core {
@@ -50,20 +173,111 @@ impl Core {
     /// # Returns
     /// A new instance of `Core` with a fresh HTTP client.
     pub fn new(config: Config, log: Log) -> Self {
-        let client = Client::new();
+        let client = Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .expect("Failed to build the HTTP client");
 
         Self {
             config,
             log,
-            client,
+            transport: Transport::Http(client),
+            raw_transport: Transport::Http(
+                Client::builder()
+                    .no_gzip()
+                    .redirect(Policy::none())
+                    .build()
+                    .expect("Failed to build the HTTP client"),
+            ),
+            retry_policy: RetryPolicy::default(),
+            retry_budget: StdMutex::default(),
+            host_limiter: StdMutex::default(),
+            host_cooldown: StdMutex::default(),
+            default_timeout: None,
+            stall_timeout: None,
+            credentials: HashMap::new(),
+            respect_robots: false,
+            robots: StdMutex::default(),
+            connect_timeout: None,
+            read_timeout: None,
+            proxy: None,
+            method_headers: HashMap::new(),
+            trace_header: ArcStr::from(DEFAULT_TRACE_HEADER),
+            same_host_only: false,
+            max_redirects: 10,
+            replay_guard: None,
+            no_decompression_hosts: HashSet::new(),
+            error_on_non_2xx: true,
+            json_encoding: JsonEncoding::Compact,
+            recorder: None,
+            max_concurrent_requests: 8,
+        }
+    }
+
+    /// Applies the given options to the core.
+    ///
+    /// # Arguments
+    /// * `options` - The options to apply
+    ///
+    /// # Returns
+    /// The core using the given options.
+    pub fn with_options(mut self, options: NetOptions) -> Self {
+        self.method_headers = options.method_headers;
+        if let Some(trace_header) = options.trace_header {
+            self.trace_header = trace_header;
         }
+        self.same_host_only = options.same_host_only;
+        self.replay_guard = options
+            .replay_window
+            .map(|window| StdMutex::new(ReplayGuard::new(window)));
+        self.no_decompression_hosts = options.no_decompression_hosts;
+        self.error_on_non_2xx = options.error_on_non_2xx;
+        self.stall_timeout = options.stall_timeout;
+        self.credentials = options.credentials;
+        self.respect_robots = options.respect_robots;
+        self.json_encoding = options.json_encoding;
+        self
+    }
+
+    /// Replaces the transport used to perform requests, for every host.
+    ///
+    /// # Arguments
+    /// * `transport` - The transport to use
+    ///
+    /// # Returns
+    /// The core using the given transport.
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.raw_transport = transport.clone();
+        self.transport = transport;
+        self
+    }
+
+    /// Records every response received into a mock state.
+    ///
+    /// The last response of each request, after retries, is kept along with
+    /// the request, and [`Net::export_fixtures`] writes them to a fixture file
+    /// that [`Net::mock_from_file`] serves again. Streamed bodies aren't
+    /// recorded, as they are handed over before they are received.
+    ///
+    /// # Arguments
+    /// * `recorder` - The mock state the responses are recorded into
+    ///
+    /// # Returns
+    /// The core recording its responses.
+    pub fn with_recorder(mut self, recorder: Arc<Mutex<MockState>>) -> Self {
+        self.recorder = Some(recorder);
+        self
     }
 
     /// Transforms the networking core instance into an actor.
     ///
     /// This method spawns a new task that will handle network operations
-    /// asynchronously through a message channel. All operations are processed
-    /// sequentially to ensure consistency.
+    /// asynchronously through a message channel. Each request is handled in a
+    /// task of its own, up to the configured number at once, the most urgent
+    /// waiting one first. The networking settings are read from the
+    /// configuration actor before the first message is handled. A shutdown
+    /// message stops the actor once the messages sent before it are handled,
+    /// or aborts what is left of them at its deadline.
     ///
     /// # Returns
     /// A tuple containing:
@@ -72,143 +286,945 @@ impl Core {
     ///
     /// # Panics
     /// This function will panic if the underlying task fails to spawn.
-    pub fn spawn(self) -> (Net, JoinHandle<()>) {
-        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+    pub fn spawn(mut self) -> (Net, JoinHandle<()>) {
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<Message>(MAILBOX_CAPACITY);
 
         let handle = tokio::spawn(async move {
-            while let Some(message) = rx.recv().await {
-                match message {
-                    Message::Get { url, headers, tx } => {
-                        let response = self.handle_get_request(url.clone(), headers).await
-                            .with_context(|| format!("GET request failed for URL: {}", url));
-                        let _ = tx.send(response);
-                    }
-                    Message::Post {
-                        url,
-                        headers,
-                        body,
-                        tx,
-                    } => {
-                        let response = self.handle_post_request(url.clone(), headers, body).await
-                            .with_context(|| format!("POST request failed for URL: {}", url));
-                        let _ = tx.send(response);
-                    }
-                    Message::Put {
-                        url,
-                        headers,
-                        body,
-                        tx,
-                    } => {
-                        let response = self.handle_put_request(url.clone(), headers, body).await
-                            .with_context(|| format!("PUT request failed for URL: {}", url));
-                        let _ = tx.send(response);
-                    }
-                    Message::Delete { url, headers, tx } => {
-                        let response = self.handle_delete_request(url.clone(), headers).await
-                            .with_context(|| format!("DELETE request failed for URL: {}", url));
-                        let _ = tx.send(response);
-                    }
-                    Message::Patch {
-                        url,
-                        headers,
-                        body,
-                        tx,
-                    } => {
-                        let response = self.handle_patch_request(url.clone(), headers, body).await
-                            .with_context(|| format!("PATCH request failed for URL: {}", url));
-                        let _ = tx.send(response);
+            self.load_settings().await;
+
+            let core = Arc::new(self);
+            let mut queue = MessageQueue::default();
+            // Dropped along with the actor, which aborts the requests in flight
+            let mut requests = JoinSet::new();
+            let mut open = true;
+            let mut shutdown = None;
+            // The actor only ends once the requests it received are answered,
+            // or once the deadline of a shutdown passes
+            while open || !queue.is_empty() || !requests.is_empty() {
+                let deadline = Shutdown::deadline(&shutdown);
+                tokio::select! {
+                    // The messages already sent are queued before the next one
+                    // is picked, so the most urgent waiting one goes first
+                    biased;
+                    _ = Shutdown::expired(deadline) => break,
+                    message = rx.recv(), if open && queue.len() < MAILBOX_CAPACITY => match message {
+                        Some(Message::Shutdown { timeout, tx }) => {
+                            // The messages already sent are still handled, the
+                            // later ones fail to be sent
+                            rx.close();
+                            Shutdown::request(&mut shutdown, timeout, tx);
+                        }
+                        Some(message) if message.is_control() => core.handle_message(message).await,
+                        Some(message) => queue.push(message),
+                        None => open = false,
+                    },
+                    Some(_) = requests.join_next(), if !requests.is_empty() => {}
+                    _ = std::future::ready(()), if !queue.is_empty()
+                        && requests.len() < core.max_concurrent_requests =>
+                    {
+                        let message = queue.pop().expect("The queue isn't empty");
+                        let core = core.clone();
+                        requests.spawn(async move { core.handle_message(message).await });
                     }
                 }
             }
+
+            let forced = !queue.is_empty() || !requests.is_empty();
+            requests.shutdown().await;
+            if let Some(shutdown) = shutdown {
+                shutdown.acknowledge(forced);
+            }
         });
 
         (Net::Actual(tx), handle)
     }
 
-    /// Handles GET requests with optional headers
-    async fn handle_get_request(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>) -> anyhow::Result<ArcStr> {
-        let mut request = self.client.get::<&str>(url.as_ref());
-        
-        if let Some(headers) = headers {
-            for (key, value) in headers {
-                request = request.header(<ArcStr as AsRef<str>>::as_ref(&key), <ArcStr as AsRef<str>>::as_ref(&value));
+    /// Handles a message, answering on its response channel
+    async fn handle_message(&self, message: Message) {
+        match message {
+            Message::Get {
+                url,
+                headers,
+                priority,
+                tx,
+            } => {
+                let request = NetRequest::new(HttpMethod::Get, url.clone(), headers, None)
+                    .with_priority(priority);
+                let response = self
+                    .handle_get(request)
+                    .await
+                    .with_context(|| format!("GET request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+            Message::GetBytes {
+                url,
+                headers,
+                priority,
+                tx,
+            } => {
+                let request = NetRequest::new(HttpMethod::Get, url.clone(), headers, None)
+                    .with_priority(priority);
+                let response = self
+                    .handle_request(request)
+                    .await
+                    .map(|response| response.body)
+                    .with_context(|| format!("GET request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+            Message::Request { request, tx } => {
+                let context = format!("{} request failed for URL: {}", request.method, request.url);
+                let response = self.handle_request(request).await.context(context);
+                let _ = tx.send(response);
+            }
+            Message::IsReachable { url, tx } => {
+                let _ = tx.send(self.handle_is_reachable(url).await);
             }
+            Message::GetStream { request, tx } => {
+                let context = format!("{} request failed for URL: {}", request.method, request.url);
+                let response = self.handle_stream(request).await.context(context);
+                let _ = tx.send(response);
+            }
+            Message::GetResponseStart { request, tx } => {
+                let context = format!("{} request failed for URL: {}", request.method, request.url);
+                let response = self.handle_response_start(request).await.context(context);
+                let _ = tx.send(response);
+            }
+            Message::GetConfig { tx } => {
+                let _ = tx.send(self.handle_get_config());
+            }
+            Message::GetMetrics { tx } => {
+                let _ = tx.send(NetMetrics {
+                    cooling_down: lock(&self.host_cooldown).closed(),
+                });
+            }
+            Message::ExportFixtures { path, tx } => {
+                let response = match &self.recorder {
+                    Some(recorder) => write_fixtures(recorder, &path).await,
+                    None => Err(anyhow::anyhow!("The Net actor isn't recording")),
+                };
+                let _ = tx.send(response);
+            }
+            Message::Shutdown { .. } => unreachable!("The actor loop handles its shutdown"),
+            Message::Post {
+                url,
+                headers,
+                body,
+                idempotency_key,
+                priority,
+                tx,
+            } => {
+                let mut request = NetRequest::new(HttpMethod::Post, url.clone(), headers, body)
+                    .with_priority(priority);
+                if let Some(key) = idempotency_key {
+                    request = request.with_idempotency_key(key);
+                }
+                let response = self
+                    .handle_text_request(request)
+                    .await
+                    .with_context(|| format!("POST request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+            Message::PostJson {
+                url,
+                headers,
+                value,
+                priority,
+                tx,
+            } => {
+                let request = NetRequest::new(HttpMethod::Post, url.clone(), headers, None)
+                    .with_json(&value, self.json_encoding)
+                    .with_priority(priority);
+                let response = self
+                    .handle_text_request(request)
+                    .await
+                    .with_context(|| format!("POST request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+            Message::Put {
+                url,
+                headers,
+                body,
+                priority,
+                tx,
+            } => {
+                let request = NetRequest::new(HttpMethod::Put, url.clone(), headers, body)
+                    .with_priority(priority);
+                let response = self
+                    .handle_text_request(request)
+                    .await
+                    .with_context(|| format!("PUT request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+            Message::Delete {
+                url,
+                headers,
+                priority,
+                tx,
+            } => {
+                let request = NetRequest::new(HttpMethod::Delete, url.clone(), headers, None)
+                    .with_priority(priority);
+                let response = self
+                    .handle_text_request(request)
+                    .await
+                    .with_context(|| format!("DELETE request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+            Message::Patch {
+                url,
+                headers,
+                body,
+                priority,
+                tx,
+            } => {
+                let request = NetRequest::new(HttpMethod::Patch, url.clone(), headers, body)
+                    .with_priority(priority);
+                let response = self
+                    .handle_text_request(request)
+                    .await
+                    .with_context(|| format!("PATCH request failed for URL: {}", url));
+                let _ = tx.send(response);
+            }
+        }
+    }
+
+    /// Reads the networking settings from the configuration actor
+    async fn load_settings(&mut self) {
+        self.retry_policy = RetryPolicy {
+            max_attempts: self.config.usize(USizeOpt::RetryMaxAttempts).await.max(1),
+            base_delay: Duration::from_millis(
+                self.config.usize(USizeOpt::RetryBaseDelay).await as u64,
+            ),
+            max_backoff: millis_opt(self.config.usize(USizeOpt::RetryMaxBackoff).await),
+            max_total_elapsed: millis_opt(self.config.usize(USizeOpt::RetryMaxElapsed).await),
+            multiplier: self.config.usize(USizeOpt::RetryMultiplier).await.max(1) as u32,
+            jitter: self.config.usize(USizeOpt::RetryJitter).await.min(100) as u32,
+        };
+        self.retry_budget = StdMutex::new(RetryBudget::new(
+            self.config.usize(USizeOpt::RetryBudget).await,
+            self.config.usize(USizeOpt::RetryBudgetRefill).await,
+        ));
+        self.host_limiter = StdMutex::new(
+            HostRateLimiter::new(
+                self.config.usize(USizeOpt::HostRateLimit).await,
+                self.config.usize(USizeOpt::HostRateBurst).await,
+            )
+            .with_total_limit(
+                self.config.usize(USizeOpt::TotalRateLimit).await,
+                self.config.usize(USizeOpt::TotalRateBurst).await,
+            ),
+        );
+        self.default_timeout = millis_opt(self.config.usize(USizeOpt::DefaultTimeout).await);
+        self.connect_timeout = millis_opt(self.config.usize(USizeOpt::ConnectTimeout).await);
+        self.read_timeout = millis_opt(self.config.usize(USizeOpt::ReadTimeout).await);
+        self.max_redirects = self.config.usize(USizeOpt::MaxRedirects).await;
+        self.max_concurrent_requests = self
+            .config
+            .usize(USizeOpt::MaxConcurrentRequests)
+            .await
+            .max(1);
+
+        if let Transport::Http(_) = self.transport {
+            self.proxy = proxy_from_env();
+            let clients =
+                build_client(self.connect_timeout, self.read_timeout, true).and_then(|client| {
+                    let raw = build_client(self.connect_timeout, self.read_timeout, false)?;
+                    Ok((client, raw))
+                });
+            match clients {
+                Ok((client, raw)) => {
+                    self.transport = Transport::Http(client);
+                    self.raw_transport = Transport::Http(raw);
+                }
+                Err(err) => self.log.error(format!(
+                    "Building HTTP client failed, using one without timeouts: {:#}",
+                    err
+                )),
+            }
+        }
+    }
+
+    /// Takes a snapshot of the settings, with the secrets redacted
+    fn handle_get_config(&self) -> NetConfigSnapshot {
+        let method_headers: HashMap<HttpMethod, HashMap<ArcStr, ArcStr>> = self
+            .method_headers
+            .iter()
+            .map(|(method, headers)| {
+                let headers = headers
+                    .iter()
+                    .map(|(key, value)| (key.clone(), redact_header(key, value)))
+                    .collect();
+                (method.clone(), headers)
+            })
+            .collect();
+        let user_agent = method_headers.get(&HttpMethod::Get).and_then(|headers| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("user-agent"))
+                .map(|(_, value)| value.clone())
+        });
+
+        let retry_budget = lock(&self.retry_budget);
+        let host_limiter = lock(&self.host_limiter);
+        NetConfigSnapshot {
+            default_timeout: self.default_timeout,
+            stall_timeout: self.stall_timeout,
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            retry_policy: self.retry_policy,
+            retry_budget_capacity: retry_budget.capacity(),
+            retry_budget_refill: retry_budget.refill_per_sec(),
+            user_agent,
+            proxy: self.proxy.clone(),
+            method_headers,
+            same_host_only: self.same_host_only,
+            replay_window: self.replay_guard.as_ref().map(|guard| lock(guard).window()),
+            host_rate_limit: host_limiter.rate(),
+            host_rate_burst: host_limiter.burst(),
+            total_rate_limit: host_limiter.total_rate(),
+            total_rate_burst: host_limiter.total_burst(),
+            no_decompression_hosts: self.no_decompression_hosts.clone(),
+        }
+    }
+
+    /// Probes a URL with a single HEAD request, without retries.
+    ///
+    /// The probe takes the path of any other request, default headers, robots
+    /// and pacing of the host included, but its attempt is bounded by the short
+    /// reachability timeout and redirects aren't followed. Any HTTP response
+    /// counts as reachable, only failures to get one don't.
+    async fn handle_is_reachable(&self, url: ArcStr) -> bool {
+        let request = NetRequest::new(HttpMethod::Head, url, None, None)
+            .with_timeout(REACHABILITY_TIMEOUT)
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 1,
+                ..self.retry_policy
+            })
+            .without_redirects();
+        self.handle_request(request).await.is_ok()
+    }
+
+    /// Performs a GET request for text, failing on non-2xx responses unless
+    /// turned off in the options
+    async fn handle_get(&self, request: NetRequest) -> anyhow::Result<ArcStr> {
+        let response = self.handle_request(request.clone()).await?;
+        if self.error_on_non_2xx {
+            return Ok(request.check_status(response)?.text());
         }
+        Ok(response.text())
+    }
 
-        let response = request.send().await.context("Sending GET request")?;
-        let text = response.text().await.context("Reading response body")?;
-        Ok(ArcStr::from(&text))
+    /// Handles requests whose response body is returned as text
+    async fn handle_text_request(&self, request: NetRequest) -> anyhow::Result<ArcStr> {
+        let response = self.handle_request(request).await?;
+        Ok(response.text())
     }
 
-    /// Handles POST requests with optional headers and body
-    async fn handle_post_request(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>, body: Option<ArcStr>) -> anyhow::Result<ArcStr> {
-        let mut request = self.client.post::<&str>(url.as_ref());
-        
-        if let Some(headers) = headers {
-            for (key, value) in headers {
-                request = request.header(<ArcStr as AsRef<str>>::as_ref(&key), <ArcStr as AsRef<str>>::as_ref(&value));
+    /// Performs a request, retrying idempotent or keyed ones on transient failures.
+    ///
+    /// Each retry takes a token from the retry budget. Once the budget is
+    /// exhausted, the last failure is returned right away. The retry policy of
+    /// the request, if any, replaces the one of the actor. The default headers
+    /// of the request method are merged under its own headers. Timeouts apply to
+    /// each attempt, and an attempt that times out is retried like any other
+    /// transport failure. No retry is made once its delay would take the request
+    /// past the total elapsed cap of the policy, which doesn't cut short an
+    /// attempt already sent. Every attempt carries the same trace id. With the
+    /// replay guard on, a POST duplicating a recent submission fails with
+    /// [`NetError::DuplicateRequest`] before any attempt, unless it is forced;
+    /// retries of a submission aren't duplicates.
+    async fn handle_request(&self, request: NetRequest) -> anyhow::Result<NetResponse> {
+        let duplicate = match &self.replay_guard {
+            Some(guard) if request.method == HttpMethod::Post && !request.force_replay => {
+                !lock(guard).check(&request.url, request.body.as_deref())
             }
+            _ => false,
+        };
+        if duplicate {
+            self.log.warn(format!(
+                "Refusing duplicate POST request for URL: {}",
+                request.url
+            ));
+            return Err(NetError::DuplicateRequest { url: request.url }.into());
         }
+        let request = match self.method_headers.get(&request.method) {
+            Some(defaults) => request.with_default_headers(defaults),
+            None => request,
+        };
+        let mut request = self.traced(request);
+        request.stall_timeout = request.stall_timeout.or(self.stall_timeout);
+        self.check_robots(&request).await?;
+        let trace_id = request.trace_id.clone().unwrap_or_default();
+        let retry_policy = request.retry_policy.unwrap_or(self.retry_policy);
+        let max_attempts = if request.is_retryable() {
+            retry_policy.max_attempts.max(1)
+        } else {
+            1
+        };
 
-        if let Some(body) = body {
-            request = request.body(<ArcStr as AsRef<str>>::as_ref(&body).to_string());
+        let start = tokio::time::Instant::now();
+        let mut attempt = 1;
+        loop {
+            self.pace(&request).await;
+            let result = self.send_following(&request).await;
+            if let Ok(response) = &result {
+                self.note_retry_after(&request, response.retry_after());
+            }
+            let retryable = match &result {
+                Ok(response) => response.is_retryable() && !request.accepts(response.status),
+                // Errors other than `NetError` come from the transport
+                Err(err) => err
+                    .downcast_ref::<NetError>()
+                    .is_none_or(NetError::is_transient),
+            };
+            if !retryable || attempt >= max_attempts {
+                if let Ok(response) = &result {
+                    self.log.info(format!(
+                        "{} {} returned {} [trace {}]",
+                        request.method, request.url, response.status, trace_id
+                    ));
+                }
+                return result;
+            }
+            let delay = retry_policy.jittered_delay(attempt - 1);
+            if let Some(max_total_elapsed) = retry_policy.max_total_elapsed
+                && start.elapsed() + delay > max_total_elapsed
+            {
+                self.log.warn(format!(
+                    "Retrying {} {} would exceed {:?}, giving up [trace {}]",
+                    request.method, request.url, max_total_elapsed, trace_id
+                ));
+                return result;
+            }
+            if !lock(&self.retry_budget).try_acquire() {
+                self.log.warn(format!(
+                    "Retry budget exhausted, not retrying {} {} [trace {}]",
+                    request.method, request.url, trace_id
+                ));
+                return result;
+            }
+
+            self.log.warn(format!(
+                "{} {} failed (attempt {}/{}), retrying in {:?} [trace {}]",
+                request.method, request.url, attempt, max_attempts, delay, trace_id
+            ));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
+    }
 
-        let response = request.send().await.context("Sending POST request")?;
-        let text = response.text().await.context("Reading response body")?;
-        Ok(ArcStr::from(&text))
+    /// Starts a streamed request, without retries.
+    ///
+    /// The timeout only bounds the wait for the response headers, the body is
+    /// read at the pace of the consumer of the stream.
+    async fn handle_stream(&self, request: NetRequest) -> anyhow::Result<BodyStream> {
+        let original = request.clone();
+        let start = self.handle_response_start(request).await?;
+        if !original.accepts(start.status) {
+            return Err(NetError::Status {
+                method: original.method,
+                url: original.url,
+                status: start.status,
+            }
+            .into());
+        }
+        Ok(start.body)
     }
 
-    /// Handles PUT requests with optional headers and body
-    async fn handle_put_request(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>, body: Option<ArcStr>) -> anyhow::Result<ArcStr> {
-        let mut request = self.client.put::<&str>(url.as_ref());
-        
-        if let Some(headers) = headers {
-            for (key, value) in headers {
-                request = request.header(<ArcStr as AsRef<str>>::as_ref(&key), <ArcStr as AsRef<str>>::as_ref(&value));
+    /// Handles requests returning as soon as the headers of the response arrive, whatever its status
+    async fn handle_response_start(&self, request: NetRequest) -> anyhow::Result<ResponseStart> {
+        let request = match self.method_headers.get(&request.method) {
+            Some(defaults) => request.with_default_headers(defaults),
+            None => request,
+        };
+        let mut request = self.traced(request);
+        request.stall_timeout = request.stall_timeout.or(self.stall_timeout);
+        self.check_robots(&request).await?;
+        self.pace(&request).await;
+        let mut chain = vec![request.url.clone()];
+        let (status, headers, body) = loop {
+            let transport = self.transport_for(&request);
+            let (status, headers, body) = match request.timeout.or(self.default_timeout) {
+                Some(timeout) => tokio::time::timeout(timeout, transport.send_streaming(&request))
+                    .await
+                    .map_err(|_| NetError::TimedOut {
+                        url: request.url.clone(),
+                        timeout,
+                    })??,
+                None => transport.send_streaming(&request).await?,
+            };
+            match self.next_hop(&request, status, &headers, &mut chain)? {
+                Some(next) => request = next,
+                None => break (status, headers, body),
             }
+        };
+        self.note_retry_after(&request, super::data::retry_after(&headers));
+        Ok(ResponseStart {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Closes the host of a request for the delay it asked for with `Retry-After`, if any
+    fn note_retry_after(&self, request: &NetRequest, delay: Option<Duration>) {
+        let Some(delay) = delay.filter(|delay| !delay.is_zero()) else {
+            return;
+        };
+        self.log.warn(format!(
+            "{} {} asked to retry after {:?}, holding requests to its host [trace {}]",
+            request.method,
+            request.url,
+            delay,
+            request.trace_id.as_deref().unwrap_or_default()
+        ));
+        lock(&self.host_cooldown).cool_down(&request.url, delay);
+    }
+
+    /// Fails a request the `robots.txt` of its host disallows, when robots are
+    /// respected.
+    ///
+    /// The `robots.txt` of a host is fetched on its first request and kept for
+    /// the life of the actor. A `robots.txt` that can't be fetched, or answers
+    /// with a non-2xx status, allows everything.
+    async fn check_robots(&self, request: &NetRequest) -> anyhow::Result<()> {
+        let Some((origin, path)) = self
+            .respect_robots
+            .then(|| robots_target(&request.url))
+            .flatten()
+        else {
+            return Ok(());
+        };
+        if path == "/robots.txt" {
+            return Ok(());
+        }
+        if !lock(&self.robots).contains_key(&origin) {
+            let user_agent = request
+                .headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("user-agent"))
+                .and_then(|(_, value)| value.split('/').next())
+                .map(str::trim);
+            let robots_url = ArcStr::from(&format!("{origin}/robots.txt"));
+            let robots_request = self.traced(NetRequest::new(
+                HttpMethod::Get,
+                robots_url.clone(),
+                None,
+                None,
+            ));
+            let rules = match self.send_following(&robots_request).await {
+                Ok(response) if (200..300).contains(&response.status) => {
+                    RobotsRules::parse(&String::from_utf8_lossy(&response.body), user_agent)
+                }
+                Ok(response) => {
+                    self.log.info(format!(
+                        "{} returned {}, allowing every path",
+                        robots_url, response.status
+                    ));
+                    RobotsRules::default()
+                }
+                Err(e) => {
+                    self.log.warn(format!(
+                        "Failed to fetch {}, allowing every path: {}",
+                        robots_url, e
+                    ));
+                    RobotsRules::default()
+                }
+            };
+            lock(&self.robots)
+                .entry(origin.clone())
+                .or_insert((rules, None));
+        }
+
+        if lock(&self.robots)[&origin].0.allows(&path) {
+            return Ok(());
         }
+        self.log.warn(format!(
+            "Refusing {} {} disallowed by robots.txt [trace {}]",
+            request.method,
+            request.url,
+            request.trace_id.as_deref().unwrap_or_default()
+        ));
+        Err(NetError::DisallowedByRobots {
+            url: request.url.clone(),
+        }
+        .into())
+    }
 
-        if let Some(body) = body {
-            request = request.body(<ArcStr as AsRef<str>>::as_ref(&body).to_string());
+    /// Waits for the crawl delay of the host of a request since the previous
+    /// one, then for the host to open if it is cooling down, then for a token
+    /// of the host, as given by the host rate limiter
+    async fn pace(&self, request: &NetRequest) {
+        let crawl_delay = self
+            .respect_robots
+            .then(|| robots_target(&request.url))
+            .flatten()
+            .and_then(|(origin, _)| {
+                let mut robots = lock(&self.robots);
+                let (rules, next_allowed) = robots.get_mut(&origin)?;
+                let crawl_delay = rules.crawl_delay()?;
+                let now = Instant::now();
+                let at = next_allowed.map_or(now, |next_allowed| next_allowed.max(now));
+                *next_allowed = Some(at + crawl_delay);
+                Some((now, at))
+            });
+        if let Some((now, at)) = crawl_delay
+            && at > now
+        {
+            self.log.info(format!(
+                "Crawl delay of the host, holding {} {} for {:?} [trace {}]",
+                request.method,
+                request.url,
+                at - now,
+                request.trace_id.as_deref().unwrap_or_default()
+            ));
+            tokio::time::sleep_until(at).await;
         }
+        let cooldown = lock(&self.host_cooldown).remaining(&request.url);
+        if !cooldown.is_zero() {
+            self.log.info(format!(
+                "Host cooling down, holding {} {} for {:?} [trace {}]",
+                request.method,
+                request.url,
+                cooldown,
+                request.trace_id.as_deref().unwrap_or_default()
+            ));
+            tokio::time::sleep(cooldown).await;
+        }
+        let delay = lock(&self.host_limiter).acquire(&request.url);
+        if !delay.is_zero() {
+            self.log.info(format!(
+                "Rate limiting {} {}, waiting {:?} [trace {}]",
+                request.method,
+                request.url,
+                delay,
+                request.trace_id.as_deref().unwrap_or_default()
+            ));
+            tokio::time::sleep(delay).await;
+        }
+    }
 
-        let response = request.send().await.context("Sending PUT request")?;
-        let text = response.text().await.context("Reading response body")?;
-        Ok(ArcStr::from(&text))
+    /// Gives a trace id to a request that has none and sends it in the trace header,
+    /// replacing any value the request set for the header itself
+    fn traced(&self, mut request: NetRequest) -> NetRequest {
+        let trace_id = request
+            .trace_id
+            .get_or_insert_with(generate_trace_id)
+            .clone();
+        request
+            .headers
+            .retain(|key, _| !key.eq_ignore_ascii_case(&self.trace_header));
+        request.headers.insert(self.trace_header.clone(), trace_id);
+        request
     }
 
-    /// Handles DELETE requests with optional headers
-    async fn handle_delete_request(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>) -> anyhow::Result<ArcStr> {
-        let mut request = self.client.delete::<&str>(url.as_ref());
-        
-        if let Some(headers) = headers {
-            for (key, value) in headers {
-                request = request.header(<ArcStr as AsRef<str>>::as_ref(&key), <ArcStr as AsRef<str>>::as_ref(&value));
+    /// Picks the transport for a request, skipping decompression for the hosts set to
+    fn transport_for(&self, request: &NetRequest) -> &Transport {
+        let skip = !self.no_decompression_hosts.is_empty()
+            && reqwest::Url::parse(&request.url).is_ok_and(|url| {
+                url.host_str()
+                    .is_some_and(|host| self.no_decompression_hosts.contains(&ArcStr::from(host)))
+            });
+        if !skip {
+            return &self.transport;
+        }
+        self.log.info(format!(
+            "Not decompressing the response of {} {} [trace {}]",
+            request.method,
+            request.url,
+            request.trace_id.as_deref().unwrap_or_default()
+        ));
+        &self.raw_transport
+    }
+
+    /// Sends a single attempt of a request, following the redirects of the
+    /// server unless the request asks not to
+    async fn send_following(&self, request: &NetRequest) -> anyhow::Result<NetResponse> {
+        let mut chain = vec![request.url.clone()];
+        let mut current = request.clone();
+        let mut response = self.send_attempt(request).await?;
+        while let Some(next) =
+            self.next_hop(&current, response.status, &response.headers, &mut chain)?
+        {
+            response = self.send_attempt(&next).await?;
+            current = next;
+        }
+        Ok(response)
+    }
+
+    /// Works out the request following a response, if it is a redirect to follow.
+    ///
+    /// The URL of the next request is added to the chain of URLs visited, and
+    /// the request fails with [`NetError::TooManyRedirects`] once the chain
+    /// holds more redirects than allowed.
+    fn next_hop(
+        &self,
+        request: &NetRequest,
+        status: u16,
+        headers: &HashMap<ArcStr, ArcStr>,
+        chain: &mut Vec<ArcStr>,
+    ) -> anyhow::Result<Option<NetRequest>> {
+        if !request.follow_redirects {
+            return Ok(None);
+        }
+        let Some(next) = request.redirected(status, headers.get(&ArcStr::from("location"))) else {
+            return Ok(None);
+        };
+        let host = |url: &str| reqwest::Url::parse(url).ok()?.host_str().map(str::to_owned);
+        if self.same_host_only && host(&request.url) != host(&next.url) {
+            self.log.info(format!(
+                "{} {} redirects to another host, not following it to {} [trace {}]",
+                request.method,
+                request.url,
+                next.url,
+                request.trace_id.as_deref().unwrap_or_default()
+            ));
+            return Ok(None);
+        }
+        chain.push(next.url.clone());
+        if chain.len() > self.max_redirects + 1 {
+            return Err(NetError::TooManyRedirects {
+                chain: chain.clone(),
             }
+            .into());
+        }
+        self.log.info(format!(
+            "{} {} redirected with {} to {} [trace {}]",
+            request.method,
+            request.url,
+            status,
+            next.url,
+            request.trace_id.as_deref().unwrap_or_default()
+        ));
+        Ok(Some(next))
+    }
+
+    /// Sends a single attempt of a request, sending it again once with the
+    /// credentials of the realm if the server asks for HTTP Basic auth
+    async fn send_attempt(&self, request: &NetRequest) -> anyhow::Result<NetResponse> {
+        let response = self.send_expecting(request).await?;
+        if response.status != 401
+            || request
+                .headers
+                .keys()
+                .any(|key| key.eq_ignore_ascii_case("authorization"))
+        {
+            return Ok(response);
         }
+        let Some(realm) = response
+            .headers
+            .get(&ArcStr::from("www-authenticate"))
+            .and_then(|challenge| basic_realm(challenge))
+        else {
+            return Ok(response);
+        };
+        let Some(credentials) = self.credentials.get(&realm) else {
+            self.log.warn(format!(
+                "{} {} asks for credentials of realm {:?}, none configured [trace {}]",
+                request.method,
+                request.url,
+                realm,
+                request.trace_id.as_deref().unwrap_or_default()
+            ));
+            return Ok(response);
+        };
 
-        let response = request.send().await.context("Sending DELETE request")?;
-        let text = response.text().await.context("Reading response body")?;
-        Ok(ArcStr::from(&text))
+        self.log.info(format!(
+            "{} {} asks for credentials, sending the ones of realm {:?} [trace {}]",
+            request.method,
+            request.url,
+            realm,
+            request.trace_id.as_deref().unwrap_or_default()
+        ));
+        let mut request = request.clone();
+        request
+            .headers
+            .insert(ArcStr::from("Authorization"), credentials.authorization());
+        self.send_expecting(&request).await
     }
 
-    /// Handles PATCH requests with optional headers and body
-    async fn handle_patch_request(&self, url: ArcStr, headers: Option<HashMap<ArcStr, ArcStr>>, body: Option<ArcStr>) -> anyhow::Result<ArcStr> {
-        let mut request = self.client.patch::<&str>(url.as_ref());
-        
-        if let Some(headers) = headers {
-            for (key, value) in headers {
-                request = request.header(<ArcStr as AsRef<str>>::as_ref(&key), <ArcStr as AsRef<str>>::as_ref(&value));
+    /// Sends a single attempt of a request, sending it again without its
+    /// expectation if the server doesn't support `Expect: 100-continue`
+    async fn send_expecting(&self, request: &NetRequest) -> anyhow::Result<NetResponse> {
+        let response = self.send_once(request).await?;
+        if !request.expect_continue || response.status != 417 {
+            return Ok(response);
+        }
+
+        self.log.info(format!(
+            "{} {} doesn't support Expect: 100-continue, sending the body right away",
+            request.method, request.url
+        ));
+        let mut request = request.clone();
+        request.expect_continue = false;
+        request
+            .headers
+            .retain(|key, _| !key.eq_ignore_ascii_case("expect"));
+        self.send_once(&request).await
+    }
+
+    /// Sends a request once, bounded by its timeout or the default one, and
+    /// records the response if recording
+    async fn send_once(&self, request: &NetRequest) -> anyhow::Result<NetResponse> {
+        let response = match request.timeout.or(self.default_timeout) {
+            Some(timeout) => {
+                tokio::time::timeout(timeout, self.transport_for(request).send(request))
+                    .await
+                    .map_err(|_| NetError::TimedOut {
+                        url: request.url.clone(),
+                        timeout,
+                    })?
             }
+            None => self.transport_for(request).send(request).await,
+        }?;
+        if let Some(recorder) = &self.recorder {
+            recorder.lock().await.record(request, &response);
         }
+        Ok(response)
+    }
+}
+
+/// Messages waiting for the networking actor, ordered by priority.
+#[derive(Debug, Default)]
+struct MessageQueue {
+    /// The waiting messages
+    heap: BinaryHeap<QueuedMessage>,
+    /// The number of messages pushed so far, which orders messages of the same priority
+    pushed: u64,
+}
 
-        if let Some(body) = body {
-            request = request.body(<ArcStr as AsRef<str>>::as_ref(&body).to_string());
+impl MessageQueue {
+    /// Adds a message to the queue.
+    fn push(&mut self, message: Message) {
+        self.heap.push(QueuedMessage {
+            priority: message.priority(),
+            order: Reverse(self.pushed),
+            message,
+        });
+        self.pushed += 1;
+    }
+
+    /// Takes the most urgent message out of the queue, the oldest one among equals.
+    fn pop(&mut self) -> Option<Message> {
+        self.heap.pop().map(|queued| queued.message)
+    }
+
+    /// Checks whether no message is waiting.
+    fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Counts the waiting messages.
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+/// A message waiting in a [`MessageQueue`].
+#[derive(Debug)]
+struct QueuedMessage {
+    /// The priority of the message
+    priority: Priority,
+    /// When the message was pushed, reversed so older messages compare as greater
+    order: Reverse<u64>,
+    /// The message itself
+    message: Message,
+}
+
+impl PartialEq for QueuedMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedMessage {}
+
+impl PartialOrd for QueuedMessage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedMessage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.priority, self.order).cmp(&(other.priority, other.order))
+    }
+}
+
+/// Splits a URL into its origin and the path with query its `robots.txt` rules apply to
+fn robots_target(url: &str) -> Option<(ArcStr, String)> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let origin = url.origin();
+    if !origin.is_tuple() {
+        return None;
+    }
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    Some((ArcStr::from(&origin.ascii_serialization()), path))
+}
+
+/// Builds the HTTP client with the given connect and read timeouts.
+///
+/// The client doesn't follow redirects: the core does.
+///
+/// # Arguments
+/// * `connect_timeout` - How long to wait for a connection, `None` for no limit
+/// * `read_timeout` - How long to wait for each read of a response, `None` for no limit
+/// * `decompress` - Whether to decompress gzip-encoded responses
+///
+/// # Returns
+/// The HTTP client, or an error if it could not be built.
+pub(super) fn build_client(
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    decompress: bool,
+) -> anyhow::Result<Client> {
+    let mut builder = Client::builder().redirect(Policy::none());
+    if !decompress {
+        builder = builder.no_gzip();
+    }
+    if let Some(timeout) = connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = read_timeout {
+        builder = builder.read_timeout(timeout);
+    }
+    builder.build().context("Building HTTP client")
+}
+
+/// Gets the proxy the HTTP client takes from the environment, without its credentials
+fn proxy_from_env() -> Option<ArcStr> {
+    let proxy = [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+    ]
+    .iter()
+    .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))?;
+
+    match reqwest::Url::parse(&proxy) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            Some(ArcStr::from(url.as_str()))
         }
+        Err(_) => Some(ArcStr::from("[redacted]")),
+    }
+}
+
+/// Locks the state shared by the requests in flight, even if one of them panicked
+fn lock<T>(mutex: &StdMutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
-        let response = request.send().await.context("Sending PATCH request")?;
-        let text = response.text().await.context("Reading response body")?;
-        Ok(ArcStr::from(&text))
+/// Converts a duration in milliseconds from the configuration, where `0` means none
+fn millis_opt(millis: usize) -> Option<Duration> {
+    match millis {
+        0 => None,
+        millis => Some(Duration::from_millis(millis as u64)),
     }
 }
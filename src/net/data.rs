@@ -0,0 +1,1748 @@
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    sync::Arc,
+    time::Duration,
+};
+
+use base64::Engine;
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::time::Instant;
+
+use crate::{ArcStr, net::message::HttpMethod};
+
+/// Describes a request to be performed by the networking actor.
+///
+/// This struct gathers everything the core needs to perform a request, so the
+/// same request can be sent again when it has to be retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetRequest {
+    /// The HTTP method of the request
+    pub method: HttpMethod,
+    /// The URL the request is sent to
+    pub url: ArcStr,
+    /// Headers to include in the request
+    pub headers: HashMap<ArcStr, ArcStr>,
+    /// Optional body content to send with the request
+    pub body: Option<ArcStr>,
+    /// Timeout applied to each attempt of this request, overriding the default
+    /// timeout of the networking actor
+    pub timeout: Option<Duration>,
+    /// Maximum number of body bytes to read, the rest of the body is discarded
+    pub body_limit: Option<usize>,
+    /// Key sent in the `Idempotency-Key` header, which makes the request safe
+    /// to retry whatever its method
+    pub idempotency_key: Option<ArcStr>,
+    /// Non-2xx statuses accepted as success, `None` to accept only 2xx
+    pub accept_statuses: Option<HashSet<u16>>,
+    /// Retry policy replacing the one of the networking actor for this request
+    pub retry_policy: Option<RetryPolicy>,
+    /// Id tying the request to its log lines and to the traces of the server,
+    /// generated by the networking actor when not given
+    pub trace_id: Option<ArcStr>,
+    /// Sends the request even if the replay guard of the networking actor saw
+    /// the same submission recently
+    pub force_replay: bool,
+    /// Sends `Expect: 100-continue` and holds the body back until the server
+    /// had a chance to reject the request from its headers
+    pub expect_continue: bool,
+    /// Longest wait for the next bytes of the body before the download fails
+    /// with [`NetError::Stalled`], overriding the one of the networking actor
+    pub stall_timeout: Option<Duration>,
+    /// How urgently the networking actor handles the request compared to the
+    /// other requests waiting for it
+    pub priority: Priority,
+    /// Whether redirect responses are followed, rather than returned as they are
+    pub follow_redirects: bool,
+}
+
+impl NetRequest {
+    /// Creates a new request description.
+    ///
+    /// # Arguments
+    /// * `method` - The HTTP method
+    /// * `url` - The URL of the request
+    /// * `headers` - Optional headers to include in the request
+    /// * `body` - Optional body content to send with the request
+    ///
+    /// # Returns
+    /// A new request description.
+    pub fn new(
+        method: HttpMethod,
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        body: Option<ArcStr>,
+    ) -> Self {
+        Self {
+            method,
+            url,
+            headers: headers.unwrap_or_default(),
+            body,
+            timeout: None,
+            body_limit: None,
+            idempotency_key: None,
+            accept_statuses: None,
+            retry_policy: None,
+            trace_id: None,
+            force_replay: false,
+            expect_continue: false,
+            stall_timeout: None,
+            priority: Priority::Normal,
+            follow_redirects: true,
+        }
+    }
+
+    /// Adds default headers to the request, keeping the ones it already sets.
+    ///
+    /// Header names are compared ignoring case.
+    ///
+    /// # Arguments
+    /// * `defaults` - The default headers
+    ///
+    /// # Returns
+    /// The request with the default headers merged in.
+    pub fn with_default_headers(mut self, defaults: &HashMap<ArcStr, ArcStr>) -> Self {
+        for (key, value) in defaults {
+            if !self
+                .headers
+                .keys()
+                .any(|existing| existing.eq_ignore_ascii_case(key))
+            {
+                self.headers.insert(key.clone(), value.clone());
+            }
+        }
+        self
+    }
+
+    /// Limits how much of the response body is read.
+    ///
+    /// Once `limit` bytes are received, the transport stops reading and the
+    /// rest of the body is never downloaded.
+    ///
+    /// # Arguments
+    /// * `limit` - The maximum number of body bytes to read
+    ///
+    /// # Returns
+    /// The request with the given body limit.
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = Some(limit);
+        self
+    }
+
+    /// Asks for the start of the body only.
+    ///
+    /// The request asks for the first `max_bytes` bytes with a `Range` header,
+    /// and reads at most as many in case the server ignores it.
+    ///
+    /// # Arguments
+    /// * `max_bytes` - The number of bytes to ask for, at least one
+    ///
+    /// # Returns
+    /// The request asking for the start of the body.
+    pub fn with_prefix(mut self, max_bytes: usize) -> Self {
+        self.headers.insert(
+            ArcStr::from("Range"),
+            ArcStr::from(&format!("bytes=0-{}", max_bytes - 1)),
+        );
+        self.with_body_limit(max_bytes)
+    }
+
+    /// Attaches an idempotency key to the request.
+    ///
+    /// The key is sent in the `Idempotency-Key` header. Servers supporting it
+    /// perform a request only once per key, so the request is retried like an
+    /// idempotent one even when its method, such as POST, normally isn't.
+    ///
+    /// # Arguments
+    /// * `key` - The idempotency key, unique to the operation
+    ///
+    /// # Returns
+    /// The request with the idempotency key.
+    pub fn with_idempotency_key(mut self, key: ArcStr) -> Self {
+        self.headers
+            .insert(ArcStr::from("Idempotency-Key"), key.clone());
+        self.idempotency_key = Some(key);
+        self
+    }
+
+    /// Sets a JSON body on the request.
+    ///
+    /// The value is encoded as UTF-8 in the given style, and the
+    /// `Content-Type` header is set to [`JSON_CONTENT_TYPE`], replacing any
+    /// content type already given whatever its case.
+    ///
+    /// # Arguments
+    /// * `value` - The JSON value to send
+    /// * `encoding` - Whether the JSON is compact or pretty-printed
+    ///
+    /// # Returns
+    /// The request with the JSON body.
+    pub fn with_json(mut self, value: &serde_json::Value, encoding: JsonEncoding) -> Self {
+        self.headers
+            .retain(|key, _| !key.eq_ignore_ascii_case("content-type"));
+        self.headers.insert(
+            ArcStr::from("Content-Type"),
+            ArcStr::from(JSON_CONTENT_TYPE),
+        );
+        self.body = Some(encoding.encode(value));
+        self
+    }
+
+    /// Accepts some non-2xx statuses as success.
+    ///
+    /// Responses with an accepted status are returned instead of failing with
+    /// [`NetError::Status`], and they are never retried, even when the status
+    /// is normally transient. This suits probes where, say, a `404` is an
+    /// expected answer rather than a failure.
+    ///
+    /// # Arguments
+    /// * `statuses` - The statuses to accept besides 2xx
+    ///
+    /// # Returns
+    /// The request accepting the given statuses.
+    pub fn with_accept_statuses(mut self, statuses: HashSet<u16>) -> Self {
+        self.accept_statuses = Some(statuses);
+        self
+    }
+
+    /// Checks whether a response status counts as success for the request.
+    ///
+    /// # Arguments
+    /// * `status` - The HTTP status code of the response
+    ///
+    /// # Returns
+    /// `true` for any 2xx status and for the accepted statuses of the request.
+    pub fn accepts(&self, status: u16) -> bool {
+        (200..300).contains(&status)
+            || self
+                .accept_statuses
+                .as_ref()
+                .is_some_and(|statuses| statuses.contains(&status))
+    }
+
+    /// Checks the status of a response to the request.
+    ///
+    /// # Arguments
+    /// * `response` - The response to check
+    ///
+    /// # Returns
+    /// The response if its status is accepted, or [`NetError::Status`] otherwise.
+    pub fn check_status(&self, response: NetResponse) -> Result<NetResponse, NetError> {
+        if self.accepts(response.status) {
+            Ok(response)
+        } else {
+            Err(NetError::Status {
+                method: self.method.clone(),
+                url: self.url.clone(),
+                status: response.status,
+            })
+        }
+    }
+
+    /// Replaces the retry policy of the networking actor for this request.
+    ///
+    /// The policy replaces the default one entirely, so a policy with at most
+    /// one attempt disables retries. It doesn't make non-idempotent requests
+    /// retryable, and every retry still takes a token from the global retry
+    /// budget of the actor: once the budget is exhausted, the request fails
+    /// right away whatever its policy.
+    ///
+    /// # Arguments
+    /// * `policy` - The retry policy of the request
+    ///
+    /// # Returns
+    /// The request with the given retry policy.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Sets the trace id of the request.
+    ///
+    /// The id is sent in the trace header of the networking actor and included
+    /// in the lines it logs about the request, so client and server logs of the
+    /// same request can be matched. Requests without one get a generated id.
+    ///
+    /// # Arguments
+    /// * `trace_id` - The id of the request, e.g. from an incoming trace
+    ///
+    /// # Returns
+    /// The request with the given trace id.
+    pub fn with_trace_id(mut self, trace_id: ArcStr) -> Self {
+        self.trace_id = Some(trace_id);
+        self
+    }
+
+    /// Lets the request through the replay guard of the networking actor.
+    ///
+    /// Use it when submitting the same body again is intended, e.g. to resend
+    /// a message the user explicitly asked to send twice.
+    ///
+    /// # Returns
+    /// The request, sent even if it duplicates a recent submission.
+    pub fn with_force_replay(mut self) -> Self {
+        self.force_replay = true;
+        self
+    }
+
+    /// Asks the server to accept the request before its body is uploaded.
+    ///
+    /// The `Expect: 100-continue` header is sent and the body is held back for
+    /// a moment, so a server refusing the request, e.g. for missing credentials
+    /// or a body over its size limit, answers before the body is uploaded. This
+    /// saves bandwidth on large rejected uploads at the cost of a short delay on
+    /// accepted ones.
+    ///
+    /// Servers and proxies that don't support the expectation either ignore it,
+    /// in which case the body is sent once the wait is over, or answer `417
+    /// Expectation Failed`, in which case the request is sent again at once
+    /// without it.
+    ///
+    /// # Returns
+    /// The request expecting `100 Continue`.
+    pub fn with_expect_continue(mut self) -> Self {
+        self.headers
+            .insert(ArcStr::from("Expect"), ArcStr::from("100-continue"));
+        self.expect_continue = true;
+        self
+    }
+
+    /// Checks whether the request may be sent again after a failure.
+    ///
+    /// # Returns
+    /// `true` if the method is idempotent or the request has an idempotency key.
+    pub fn is_retryable(&self) -> bool {
+        self.method.is_idempotent() || self.idempotency_key.is_some()
+    }
+
+    /// Sets the timeout of each attempt of the request.
+    ///
+    /// # Arguments
+    /// * `timeout` - How long to wait for the response of a single attempt
+    ///
+    /// # Returns
+    /// The request with the given timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long the body of the response may go without receiving any byte.
+    ///
+    /// Unlike [`NetRequest::with_timeout`], this doesn't bound the whole
+    /// download: a large body arriving steadily is never cut, but one that
+    /// stops flowing fails with [`NetError::Stalled`] after `stall_timeout`.
+    ///
+    /// # Arguments
+    /// * `stall_timeout` - The longest wait for the next bytes of the body
+    ///
+    /// # Returns
+    /// The request with the given stall timeout.
+    pub fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = Some(stall_timeout);
+        self
+    }
+
+    /// Sets how urgently the networking actor handles the request.
+    ///
+    /// Requests waiting for the actor are handled from the highest priority
+    /// to the lowest, and in the order they were sent within a priority.
+    ///
+    /// # Arguments
+    /// * `priority` - The priority of the request
+    ///
+    /// # Returns
+    /// The request with the given priority.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Returns redirect responses as they are instead of following them.
+    ///
+    /// # Returns
+    /// The request, whose redirects aren't followed.
+    pub fn without_redirects(mut self) -> Self {
+        self.follow_redirects = false;
+        self
+    }
+
+    /// Builds the request following a redirect response to this one.
+    ///
+    /// The location is resolved against the URL of the request. As browsers
+    /// do, `303 See Other` turns any request but HEAD into a GET, and so do
+    /// `301` and `302` for a POST: the body and the headers describing it are
+    /// then dropped. Credentials and cookies are only sent again to the same host.
+    ///
+    /// # Arguments
+    /// * `status` - The status of the response
+    /// * `location` - The `Location` header of the response, if any
+    ///
+    /// # Returns
+    /// The next request, or `None` if the response isn't a redirect or its
+    /// location is missing or invalid.
+    pub fn redirected(&self, status: u16, location: Option<&ArcStr>) -> Option<NetRequest> {
+        if !matches!(status, 301 | 302 | 303 | 307 | 308) {
+            return None;
+        }
+        let current = reqwest::Url::parse(&self.url).ok()?;
+        let next = current.join(location?.trim()).ok()?;
+
+        let mut request = self.clone();
+        request.url = ArcStr::from(next.as_str());
+        let to_get = match status {
+            303 => self.method != HttpMethod::Head,
+            301 | 302 => self.method == HttpMethod::Post,
+            _ => false,
+        };
+        if to_get {
+            request.method = HttpMethod::Get;
+            request.body = None;
+            request.expect_continue = false;
+            request.headers.retain(|key, _| {
+                ![
+                    "content-type",
+                    "content-length",
+                    "content-encoding",
+                    "expect",
+                ]
+                .iter()
+                .any(|header| key.eq_ignore_ascii_case(header))
+            });
+        }
+        if current.host_str() != next.host_str() {
+            request.headers.retain(|key, _| {
+                !["authorization", "cookie", "proxy-authorization"]
+                    .iter()
+                    .any(|header| key.eq_ignore_ascii_case(header))
+            });
+        }
+        Some(request)
+    }
+}
+
+/// How urgently the networking actor handles a request.
+///
+/// Variants are ordered from the least to the most urgent, so the highest
+/// priority compares as the greatest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Handled once nothing more urgent is waiting, e.g. prefetching
+    Low,
+    /// The priority of every request unless told otherwise
+    #[default]
+    Normal,
+    /// Handled before anything else waiting, e.g. what the user is looking at
+    High,
+}
+
+/// Errors reported by the networking actor that callers may want to tell apart.
+///
+/// They are wrapped in [`anyhow::Error`] like any other failure and can be
+/// recovered with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NetError {
+    /// The server answered with a status that isn't accepted as success
+    #[error("{method} request returned status {status} for URL: {url}")]
+    Status {
+        /// The HTTP method of the request
+        method: HttpMethod,
+        /// The URL of the request
+        url: ArcStr,
+        /// The HTTP status code of the response
+        status: u16,
+    },
+    /// The same POST body was submitted to the URL within the replay window
+    #[error("Refusing duplicate POST request within the replay window for URL: {url}")]
+    DuplicateRequest {
+        /// The URL of the request
+        url: ArcStr,
+    },
+    /// The `robots.txt` of the host disallows the path of the request
+    #[error("Request disallowed by the robots.txt of the host for URL: {url}")]
+    DisallowedByRobots {
+        /// The URL of the request
+        url: ArcStr,
+    },
+    /// No response was received within the timeout of an attempt
+    #[error("Request timed out after {timeout:?} for URL: {url}")]
+    TimedOut {
+        /// The URL of the request
+        url: ArcStr,
+        /// The timeout that elapsed
+        timeout: Duration,
+    },
+    /// No bytes of the response body were received for the stall timeout
+    #[error("Response body stalled for {timeout:?} for URL: {url}")]
+    Stalled {
+        /// The URL of the request
+        url: ArcStr,
+        /// The stall timeout that elapsed
+        timeout: Duration,
+    },
+    /// The server kept redirecting past the maximum number of redirects
+    #[error("Too many redirects: {}", .chain.iter().map(|url| &**url).collect::<Vec<_>>().join(" -> "))]
+    TooManyRedirects {
+        /// The URLs visited, from the one of the request to the last redirect target
+        chain: Vec<ArcStr>,
+    },
+}
+
+impl NetError {
+    /// Checks whether sending the request again may succeed.
+    ///
+    /// # Returns
+    /// `true` for timeouts and stalled bodies, `false` for the errors the same
+    /// request would run into again, such as a redirect loop.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, NetError::TimedOut { .. } | NetError::Stalled { .. })
+    }
+}
+
+/// Settings used when spawning a networking actor that don't fit in the
+/// configuration actor.
+///
+/// # Examples
+/// ```
+/// let mut options = NetOptions::default();
+/// options.method_headers.insert(
+///     HttpMethod::Post,
+///     HashMap::from([(ArcStr::from("Content-Type"), ArcStr::from("application/json"))]),
+/// );
+/// let net = Net::spawn_with_options(config, log, options);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NetOptions {
+    /// Default headers applied to every request of each HTTP method. Headers
+    /// set by the request itself take precedence, whatever their case.
+    pub method_headers: HashMap<HttpMethod, HashMap<ArcStr, ArcStr>>,
+    /// The header the trace id of each request is sent in, [`DEFAULT_TRACE_HEADER`]
+    /// when not given
+    pub trace_header: Option<ArcStr>,
+    /// Refuses to follow redirects to another host: the redirect response
+    /// itself is returned instead. Off by default.
+    pub same_host_only: bool,
+    /// How long submitted POST bodies are remembered to refuse duplicates with
+    /// [`NetError::DuplicateRequest`], `None` to disable the guard (the default)
+    pub replay_window: Option<Duration>,
+    /// Hosts whose gzip-encoded responses are returned as received instead of
+    /// being decompressed, to work around servers that compress bodies twice
+    pub no_decompression_hosts: HashSet<ArcStr>,
+    /// Makes [`crate::net::Net::get`] fail with [`NetError::Status`] when the
+    /// final response isn't 2xx. On by default: turn it off to get the body of
+    /// error pages as a success, e.g. to scrape them.
+    pub error_on_non_2xx: bool,
+    /// Longest wait for the next bytes of a response body before the request
+    /// fails with [`NetError::Stalled`], for requests that don't set their own.
+    /// `None` (the default) waits as long as the read timeout allows.
+    pub stall_timeout: Option<Duration>,
+    /// Credentials sent with HTTP Basic auth, keyed by the realm of the
+    /// `WWW-Authenticate` challenge of the servers asking for them
+    pub credentials: HashMap<ArcStr, Credentials>,
+    /// Honors the `robots.txt` of each host: requests to disallowed paths fail
+    /// with [`NetError::DisallowedByRobots`] and requests are spaced by the
+    /// `Crawl-delay` of the host. Off by default.
+    pub respect_robots: bool,
+    /// How the bodies of [`crate::net::Net::post_json`] are formatted, compact
+    /// by default
+    pub json_encoding: JsonEncoding,
+}
+
+impl Default for NetOptions {
+    fn default() -> Self {
+        Self {
+            method_headers: HashMap::new(),
+            trace_header: None,
+            same_host_only: false,
+            replay_window: None,
+            no_decompression_hosts: HashSet::new(),
+            error_on_non_2xx: true,
+            stall_timeout: None,
+            credentials: HashMap::new(),
+            respect_robots: false,
+            json_encoding: JsonEncoding::Compact,
+        }
+    }
+}
+
+/// A user name and password for HTTP Basic auth.
+///
+/// The password is left out of the `Debug` output, so options holding
+/// credentials can be logged.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// The user name
+    pub username: ArcStr,
+    /// The password
+    pub password: ArcStr,
+}
+
+impl Credentials {
+    /// Builds the value of the `Authorization` header carrying the credentials.
+    ///
+    /// # Returns
+    /// `Basic` followed by the Base64 encoding of `username:password`.
+    pub fn authorization(&self) -> ArcStr {
+        let token = base64::engine::general_purpose::STANDARD
+            .encode(format!("{}:{}", self.username, self.password));
+        ArcStr::from(&format!("Basic {}", token))
+    }
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"[redacted]")
+            .finish()
+    }
+}
+
+/// Finds the realm of the Basic challenge of a `WWW-Authenticate` header.
+///
+/// The header may carry several challenges, e.g.
+/// `Bearer realm="api", Basic realm="mirror", charset="UTF-8"`; only the
+/// parameters of the `Basic` one are considered.
+///
+/// # Arguments
+/// * `header` - The value of the `WWW-Authenticate` header
+///
+/// # Returns
+/// The realm, without quotes, or `None` if there is no Basic challenge with a realm.
+pub(crate) fn basic_realm(header: &str) -> Option<ArcStr> {
+    let mut in_basic = false;
+    for item in split_unquoted(header, ',') {
+        let item = item.trim();
+        let head = item
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(item.len());
+        let param = if item[head..].trim_start().starts_with('=') {
+            item
+        } else {
+            // A new challenge, optionally followed by its first parameter
+            in_basic = item[..head].eq_ignore_ascii_case("basic");
+            item[head..].trim()
+        };
+        if let Some((key, value)) = param.split_once('=')
+            && in_basic
+            && key.trim().eq_ignore_ascii_case("realm")
+        {
+            return Some(ArcStr::from(value.trim().trim_matches('"')));
+        }
+    }
+    None
+}
+
+/// Splits a header value on a separator, ignoring the separators in quoted strings
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (index, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if c == separator && !quoted => {
+                items.push(&value[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+    items
+}
+
+/// The header the trace id of each request is sent in by default
+pub const DEFAULT_TRACE_HEADER: &str = "X-Request-Id";
+
+/// The content type of the JSON bodies sent by the networking actor
+pub const JSON_CONTENT_TYPE: &str = "application/json; charset=utf-8";
+
+/// How the JSON bodies sent by the networking actor are formatted.
+///
+/// Bodies are always UTF-8, whatever the style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonEncoding {
+    /// Without any whitespace between tokens
+    #[default]
+    Compact,
+    /// Indented over several lines, for servers or people reading it as text
+    Pretty,
+}
+
+impl JsonEncoding {
+    /// Encodes a JSON value in this style.
+    ///
+    /// # Arguments
+    /// * `value` - The JSON value
+    ///
+    /// # Returns
+    /// The encoded JSON.
+    pub fn encode(&self, value: &serde_json::Value) -> ArcStr {
+        // Serializing a `Value` can't fail, its map keys are always strings
+        let json = match self {
+            JsonEncoding::Compact => value.to_string(),
+            JsonEncoding::Pretty => {
+                serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+            }
+        };
+        ArcStr::from(&json)
+    }
+}
+
+/// Generates a random trace id.
+///
+/// # Returns
+/// 128 bits of the random number generator of the system as a lowercase hex
+/// string.
+pub(crate) fn generate_trace_id() -> ArcStr {
+    ArcStr::from(&format!("{:016x}{:016x}", random_u64(), random_u64()))
+}
+
+/// Draws 64 bits from the random number generator of the system.
+///
+/// # Panics
+/// This function will panic if the system can't provide random bytes.
+fn random_u64() -> u64 {
+    let mut bytes = [0; 8];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to read the system random number generator");
+    u64::from_le_bytes(bytes)
+}
+
+/// The settings a networking actor is running with, as returned by
+/// [`crate::net::Net::effective_config`].
+///
+/// Secrets are redacted: the values of credential headers are replaced and the
+/// credentials of the proxy URL are removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetConfigSnapshot {
+    /// Timeout applied to each attempt of requests that don't set their own
+    pub default_timeout: Option<Duration>,
+    /// Stall timeout of the bodies of requests that don't set their own
+    pub stall_timeout: Option<Duration>,
+    /// Timeout for establishing a connection
+    pub connect_timeout: Option<Duration>,
+    /// Timeout for each read of a response
+    pub read_timeout: Option<Duration>,
+    /// Retry policy applied to failed idempotent requests
+    pub retry_policy: RetryPolicy,
+    /// Maximum number of retries that may be spent in a burst
+    pub retry_budget_capacity: usize,
+    /// Number of retries returned to the retry budget every second
+    pub retry_budget_refill: usize,
+    /// The `User-Agent` sent with GET requests, if any
+    pub user_agent: Option<ArcStr>,
+    /// The proxy taken from the environment by the HTTP client, if any
+    pub proxy: Option<ArcStr>,
+    /// Default headers applied to the requests of each HTTP method
+    pub method_headers: HashMap<HttpMethod, HashMap<ArcStr, ArcStr>>,
+    /// Whether redirects to another host are refused
+    pub same_host_only: bool,
+    /// How long submitted POST bodies are remembered, if the replay guard is on
+    pub replay_window: Option<Duration>,
+    /// Number of requests per second allowed to each host, `0` for no limit
+    pub host_rate_limit: usize,
+    /// Number of requests that may be sent to a host in a burst
+    pub host_rate_burst: usize,
+    /// Number of requests per second allowed across all hosts, `0` for no limit
+    pub total_rate_limit: usize,
+    /// Number of requests that may be sent in a burst across all hosts
+    pub total_rate_burst: usize,
+    /// Hosts whose responses are returned without automatic decompression
+    pub no_decompression_hosts: HashSet<ArcStr>,
+}
+
+/// Replaces the value of headers carrying credentials.
+///
+/// # Arguments
+/// * `key` - The header name
+/// * `value` - The header value
+///
+/// # Returns
+/// `[redacted]` for `Authorization`, `Proxy-Authorization`, `Cookie` and
+/// `Set-Cookie`, the value itself otherwise.
+pub(crate) fn redact_header(key: &str, value: &ArcStr) -> ArcStr {
+    const SECRET_HEADERS: [&str; 4] = [
+        "authorization",
+        "proxy-authorization",
+        "cookie",
+        "set-cookie",
+    ];
+    if SECRET_HEADERS
+        .iter()
+        .any(|secret| key.eq_ignore_ascii_case(secret))
+    {
+        ArcStr::from("[redacted]")
+    } else {
+        value.clone()
+    }
+}
+
+/// A response received by the networking actor.
+///
+/// Holds the status code, headers and the raw body of an HTTP response.
+/// Header names are stored in lowercase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetResponse {
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The response headers
+    pub headers: HashMap<ArcStr, ArcStr>,
+    /// The raw response body
+    pub body: Arc<[u8]>,
+}
+
+impl NetResponse {
+    /// Decodes the response body as text, replacing invalid UTF-8 sequences.
+    ///
+    /// # Returns
+    /// The response body as a string.
+    pub fn text(&self) -> ArcStr {
+        ArcStr::from(&String::from_utf8_lossy(&self.body))
+    }
+
+    /// Gets the charset announced in the `Content-Type` header.
+    ///
+    /// # Returns
+    /// The value of the `charset` parameter, without quotes and in lowercase,
+    /// or `None` if the header or the parameter is missing.
+    pub fn charset(&self) -> Option<ArcStr> {
+        let content_type = self.headers.get(&ArcStr::from("content-type"))?;
+        content_type.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| ArcStr::from(&value.trim().trim_matches('"').to_lowercase()))
+        })
+    }
+
+    /// Finds the URL of the next page advertised in the `Link` header (RFC 8288).
+    ///
+    /// Relative links are resolved against the URL the response was fetched from.
+    ///
+    /// # Arguments
+    /// * `base` - The URL the response was fetched from
+    ///
+    /// # Returns
+    /// The URL of the link with `rel="next"`, if any.
+    pub fn next_link(&self, base: &str) -> Option<ArcStr> {
+        let link = self.headers.get(&ArcStr::from("link"))?;
+        link.split(',').find_map(|entry| {
+            let mut parts = entry.split(';');
+            let target = parts.next()?.trim();
+            let target = target.strip_prefix('<')?.strip_suffix('>')?;
+            let is_next = parts.any(|param| {
+                let Some((key, value)) = param.split_once('=') else {
+                    return false;
+                };
+                key.trim().eq_ignore_ascii_case("rel")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("next"))
+            });
+            if !is_next {
+                return None;
+            }
+            let url = reqwest::Url::parse(base).ok()?.join(target).ok()?;
+            Some(ArcStr::from(url.as_str()))
+        })
+    }
+
+    /// Checks whether the response has a transient failure status worth retrying.
+    ///
+    /// # Returns
+    /// `true` for `429 Too Many Requests` and any `5xx` status.
+    pub fn is_retryable(&self) -> bool {
+        self.status == 429 || (500..600).contains(&self.status)
+    }
+
+    /// Gets how long the server asked to wait before sending it another request.
+    ///
+    /// # Returns
+    /// The delay given by the `Retry-After` header, if any; see [`retry_after`].
+    pub fn retry_after(&self) -> Option<Duration> {
+        retry_after(&self.headers)
+    }
+}
+
+/// Reads the `Retry-After` header of a response.
+///
+/// The header gives either a number of seconds or an HTTP date. A date in the
+/// past means no wait.
+///
+/// # Arguments
+/// * `headers` - The response headers, with lowercase names
+///
+/// # Returns
+/// How long to wait, or `None` if the header is missing or malformed.
+pub(crate) fn retry_after(headers: &HashMap<ArcStr, ArcStr>) -> Option<Duration> {
+    let value = headers.get(&ArcStr::from("retry-after"))?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// The retry policy applied by the networking actor to failed requests.
+///
+/// Only idempotent methods are retried. A request is retried when the transport
+/// fails or the server answers with a transient failure status, waiting
+/// `base_delay` before the first retry and multiplying the delay by `multiplier`
+/// on each subsequent one, up to `max_backoff`. Non-idempotent methods are only
+/// retried when opted in with [`NetRequest::with_idempotency_key`].
+///
+/// `jitter` spreads the retries of clients failing together: each delay is
+/// shortened by a random amount of up to `jitter` percent of it.
+///
+/// `max_total_elapsed` bounds the time spent on a request across its attempts:
+/// a retry whose delay would end past it isn't made, and the last failure is
+/// returned instead. It is checked before each retry, so an attempt already
+/// sent still runs up to its own timeout, and the worst case is the cap plus
+/// one attempt timeout.
+///
+/// The default policy makes a single attempt, so retries are opt-in: raise
+/// `max_attempts` here or the `RetryMaxAttempts` option of the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts for a single request, including the first one
+    pub max_attempts: usize,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Longest delay between two attempts, `None` to let it grow unbounded
+    pub max_backoff: Option<Duration>,
+    /// Longest time spent on a request before giving up on retries, `None` for no limit
+    pub max_total_elapsed: Option<Duration>,
+    /// Factor applied to the delay after each retry
+    pub multiplier: u32,
+    /// Largest share of each delay, in percent, randomly taken off it
+    pub jitter: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(200),
+            max_backoff: Some(Duration::from_secs(30)),
+            max_total_elapsed: None,
+            multiplier: 2,
+            jitter: 0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Computes how long to wait before a retry.
+    ///
+    /// # Arguments
+    /// * `retry` - The 0-based index of the retry about to be made
+    ///
+    /// # Returns
+    /// The delay to wait before sending the request again, at most `max_backoff`.
+    pub fn delay(&self, retry: usize) -> Duration {
+        let factor = self
+            .multiplier
+            .saturating_pow(retry.min(u32::MAX as usize) as u32);
+        let delay = self.base_delay.saturating_mul(factor);
+        match self.max_backoff {
+            Some(max_backoff) => delay.min(max_backoff),
+            None => delay,
+        }
+    }
+
+    /// Computes how long to wait before a retry, with the jitter applied.
+    ///
+    /// # Arguments
+    /// * `retry` - The 0-based index of the retry about to be made
+    ///
+    /// # Returns
+    /// A random delay between [`RetryPolicy::delay`] shortened by `jitter`
+    /// percent and [`RetryPolicy::delay`] itself.
+    pub fn jittered_delay(&self, retry: usize) -> Duration {
+        let delay = self.delay(retry);
+        let jitter = self.jitter.min(100);
+        if jitter == 0 {
+            return delay;
+        }
+        let sample = random_u64() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 - f64::from(jitter) / 100.0 * sample)
+    }
+}
+
+/// A token bucket limiting the rate of retries across all requests.
+///
+/// Every retry performed by the networking actor takes a token from the bucket,
+/// and tokens are returned at a fixed rate. When the bucket is empty, failed
+/// requests are surfaced immediately instead of being retried, so a broad outage
+/// does not multiply the request volume sent to the server.
+#[derive(Debug)]
+pub struct RetryBudget {
+    /// Maximum number of tokens the bucket can hold
+    capacity: usize,
+    /// Number of tokens returned to the bucket every second
+    refill_per_sec: usize,
+    /// Tokens currently available
+    tokens: f64,
+    /// Last time the bucket was refilled
+    last_refill: Instant,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(10, 1)
+    }
+}
+
+impl RetryBudget {
+    /// Creates a new, full retry budget.
+    ///
+    /// # Arguments
+    /// * `capacity` - Maximum number of retries that may be spent in a burst
+    /// * `refill_per_sec` - Number of retries returned to the budget every second
+    ///
+    /// # Returns
+    /// A new retry budget.
+    pub fn new(capacity: usize, refill_per_sec: usize) -> Self {
+        Self {
+            capacity,
+            refill_per_sec,
+            tokens: capacity as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token from the budget, if any is available.
+    ///
+    /// # Returns
+    /// `true` if a retry may be performed, `false` if the budget is exhausted.
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets the maximum number of retries that may be spent in a burst.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets the number of retries returned to the budget every second.
+    pub fn refill_per_sec(&self) -> usize {
+        self.refill_per_sec
+    }
+
+    /// Gets the number of retries currently available.
+    ///
+    /// # Returns
+    /// The number of whole tokens left in the budget.
+    pub fn available(&mut self) -> usize {
+        self.refill();
+        self.tokens as usize
+    }
+
+    /// Returns the tokens accumulated since the last refill to the bucket.
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.refill_per_sec as f64).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+}
+
+/// Token buckets limiting the rate of requests sent to each host.
+///
+/// Every host gets its own bucket, so pacing the requests sent to a busy
+/// mirror doesn't slow down the ones sent elsewhere. A request arriving while
+/// the bucket of its host is empty isn't refused: it reserves the next token
+/// and is told how long to wait for it.
+///
+/// A total limit can be set on top with [`HostRateLimiter::with_total_limit`]:
+/// every request then also takes a token from a bucket shared by all hosts,
+/// and waits for whichever of its two tokens comes last.
+#[derive(Debug)]
+pub struct HostRateLimiter {
+    /// Number of tokens returned to each bucket every second, `0` for no limit
+    rate: usize,
+    /// Maximum number of tokens a bucket can hold
+    burst: usize,
+    /// Tokens available for each host, negative when reserved ahead, with the
+    /// last time they were refilled
+    buckets: HashMap<ArcStr, (f64, Instant)>,
+    /// Number of tokens returned to the shared bucket every second, `0` for no limit
+    total_rate: usize,
+    /// Maximum number of tokens the shared bucket can hold
+    total_burst: usize,
+    /// Tokens available to all hosts, with the last time they were refilled
+    total: Option<(f64, Instant)>,
+}
+
+impl Default for HostRateLimiter {
+    fn default() -> Self {
+        Self::new(0, 1)
+    }
+}
+
+impl HostRateLimiter {
+    /// Creates a new limiter, with full buckets.
+    ///
+    /// # Arguments
+    /// * `rate` - Number of requests per second allowed to each host, `0` for no limit
+    /// * `burst` - Number of requests that may be sent to a host in a burst
+    ///
+    /// # Returns
+    /// A new host rate limiter.
+    pub fn new(rate: usize, burst: usize) -> Self {
+        Self {
+            rate,
+            burst: burst.max(1),
+            buckets: HashMap::new(),
+            total_rate: 0,
+            total_burst: 1,
+            total: None,
+        }
+    }
+
+    /// Limits the rate of requests sent to all hosts together.
+    ///
+    /// # Arguments
+    /// * `rate` - Number of requests per second allowed across all hosts, `0` for no limit
+    /// * `burst` - Number of requests that may be sent in a burst across all hosts
+    ///
+    /// # Returns
+    /// The limiter with the total limit set.
+    pub fn with_total_limit(mut self, rate: usize, burst: usize) -> Self {
+        self.total_rate = rate;
+        self.total_burst = burst.max(1);
+        self.total = None;
+        self
+    }
+
+    /// Gets the number of requests per second allowed to each host.
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    /// Gets the number of requests that may be sent to a host in a burst.
+    pub fn burst(&self) -> usize {
+        self.burst
+    }
+
+    /// Gets the number of requests per second allowed across all hosts.
+    pub fn total_rate(&self) -> usize {
+        self.total_rate
+    }
+
+    /// Gets the number of requests that may be sent in a burst across all hosts.
+    pub fn total_burst(&self) -> usize {
+        self.total_burst
+    }
+
+    /// Takes a token for a request to the host of a URL.
+    ///
+    /// URLs without a host, which can't be sent anyway, are never delayed.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request
+    ///
+    /// # Returns
+    /// How long to wait before sending the request, zero if it may go now.
+    pub fn acquire(&mut self, url: &str) -> Duration {
+        if self.rate == 0 && self.total_rate == 0 {
+            return Duration::ZERO;
+        }
+        let Some(host) = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(ArcStr::from))
+        else {
+            return Duration::ZERO;
+        };
+
+        let now = Instant::now();
+        let mut delay = Duration::ZERO;
+        if self.rate > 0 {
+            let bucket = self.buckets.entry(host).or_insert((self.burst as f64, now));
+            delay = take_token(bucket, self.rate, self.burst, now);
+        }
+        if self.total_rate > 0 {
+            let bucket = self.total.get_or_insert((self.total_burst as f64, now));
+            delay = delay.max(take_token(bucket, self.total_rate, self.total_burst, now));
+        }
+        delay
+    }
+}
+
+/// Refills a token bucket and takes a token from it.
+///
+/// # Arguments
+/// * `bucket` - The tokens of the bucket, with the last time they were refilled
+/// * `rate` - Number of tokens returned to the bucket every second, not `0`
+/// * `burst` - Maximum number of tokens the bucket can hold
+/// * `now` - The current time
+///
+/// # Returns
+/// How long to wait for the token, zero if it was available.
+fn take_token(bucket: &mut (f64, Instant), rate: usize, burst: usize, now: Instant) -> Duration {
+    let (tokens, last_refill) = bucket;
+    let rate = rate as f64;
+    *tokens = (*tokens + now.duration_since(*last_refill).as_secs_f64() * rate).min(burst as f64);
+    *last_refill = now;
+    *tokens -= 1.0;
+    if *tokens >= 0.0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(-*tokens / rate)
+    }
+}
+
+/// Gates closing the hosts that asked clients to back off.
+///
+/// When a host answers with `Retry-After`, it is cooling down until the delay
+/// elapses: every request to it waits for the gate to open again, instead of
+/// each discovering the back-off on its own. A later `Retry-After` only ever
+/// extends the cooldown.
+#[derive(Debug, Default)]
+pub struct HostCooldown {
+    /// The time each cooling host opens again
+    until: HashMap<ArcStr, Instant>,
+}
+
+impl HostCooldown {
+    /// Closes the host of a URL for the given delay.
+    ///
+    /// URLs without a host are ignored.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request answered with `Retry-After`
+    /// * `delay` - How long the host asked to wait
+    pub fn cool_down(&mut self, url: &str, delay: Duration) {
+        let Some(host) = host_of(url) else {
+            return;
+        };
+        let until = Instant::now() + delay;
+        let entry = self.until.entry(host).or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /// Gets how long a request to the host of a URL has to wait.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request
+    ///
+    /// # Returns
+    /// The time left before the host opens again, zero if it is open.
+    pub fn remaining(&mut self, url: &str) -> Duration {
+        let now = Instant::now();
+        self.until.retain(|_, until| *until > now);
+        host_of(url)
+            .and_then(|host| self.until.get(&host))
+            .map_or(Duration::ZERO, |until| until.duration_since(now))
+    }
+
+    /// Gets the hosts cooling down.
+    ///
+    /// # Returns
+    /// Each closed host with the time left before it opens again.
+    pub fn closed(&self) -> HashMap<ArcStr, Duration> {
+        let now = Instant::now();
+        self.until
+            .iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(host, until)| (host.clone(), until.duration_since(now)))
+            .collect()
+    }
+}
+
+/// Runtime metrics of a networking actor, as returned by
+/// [`crate::net::Net::metrics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetMetrics {
+    /// The hosts whose gate is closed after a `Retry-After`, with the time
+    /// left before they open again
+    pub cooling_down: HashMap<ArcStr, Duration>,
+}
+
+impl NetMetrics {
+    /// Checks whether requests to a host are let through right away.
+    ///
+    /// # Arguments
+    /// * `host` - The host name, as in the URLs sent to it
+    ///
+    /// # Returns
+    /// `false` while the host is cooling down, `true` otherwise.
+    pub fn is_open(&self, host: &str) -> bool {
+        !self.cooling_down.contains_key(&ArcStr::from(host))
+    }
+}
+
+/// Gets the host of a URL, if it has one
+fn host_of(url: &str) -> Option<ArcStr> {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(ArcStr::from))
+}
+
+/// The crawl rules a `robots.txt` sets for a user-agent.
+///
+/// A reasonable subset of the format is understood: `User-agent` groups,
+/// `Allow` and `Disallow` rules with the `*` wildcard and the `$` end anchor,
+/// and `Crawl-delay`. The most specific rule matching a path wins, `Allow`
+/// winning ties, and paths no rule matches are allowed.
+///
+/// # Examples
+/// ```
+/// let rules = RobotsRules::parse("User-agent: *\nDisallow: /private/\n", Some("patch-hub"));
+/// assert!(!rules.allows("/private/inbox"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    /// The rules of the group of the user-agent, as whether they allow and their pattern
+    rules: Vec<(bool, String)>,
+    /// The delay to leave between two requests, if given
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Parses a `robots.txt`, keeping the group of the given user-agent.
+    ///
+    /// The first group naming the user-agent is kept, or the `*` group when
+    /// none does. User-agents are compared without regard to case.
+    ///
+    /// # Arguments
+    /// * `content` - The content of the `robots.txt`
+    /// * `user_agent` - The product token of the crawler, `None` to only follow `*`
+    ///
+    /// # Returns
+    /// The rules of the user-agent, empty if the file has none for it.
+    pub fn parse(content: &str, user_agent: Option<&str>) -> Self {
+        let user_agent = user_agent.map(str::to_lowercase);
+        let mut groups: Vec<(Vec<String>, Self)> = Vec::new();
+        let mut in_agents = false;
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        groups.push((Vec::new(), Self::default()));
+                        in_agents = true;
+                    }
+                    if let Some((agents, _)) = groups.last_mut() {
+                        agents.push(value.to_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_agents = false;
+                    // An empty `Disallow` allows everything, like no rule
+                    if let Some((_, rules)) = groups.last_mut()
+                        && !value.is_empty()
+                    {
+                        rules.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_agents = false;
+                    if let Some((_, rules)) = groups.last_mut() {
+                        rules.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|delay| delay.is_finite() && *delay >= 0.0)
+                            .map(Duration::from_secs_f64);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let named = user_agent.and_then(|user_agent| {
+            groups.iter().position(|(agents, _)| {
+                agents
+                    .iter()
+                    .any(|agent| agent != "*" && user_agent.contains(agent.as_str()))
+            })
+        });
+        let index = named.or_else(|| {
+            groups
+                .iter()
+                .position(|(agents, _)| agents.iter().any(|agent| agent == "*"))
+        });
+        index
+            .map(|index| groups.swap_remove(index).1)
+            .unwrap_or_default()
+    }
+
+    /// Checks whether a path may be crawled.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the URL, with its query if any
+    ///
+    /// # Returns
+    /// `true` unless the most specific rule matching the path disallows it.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| robots_pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    /// Gets the delay to leave between two requests to the host, if given.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Matches a path against a `robots.txt` pattern, where `*` stands for any
+/// sequence of characters and a trailing `$` anchors the end of the path
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+    for (index, part) in parts.iter().enumerate() {
+        if anchored && index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// A time-bounded set of recent POST submissions.
+///
+/// Each submission is remembered by the SHA-256 hash of its URL and body for
+/// the length of the window, so an accidental second submission of the same
+/// content, such as a double click or a replayed command, can be refused
+/// before it reaches the server.
+#[derive(Debug)]
+pub struct ReplayGuard {
+    /// How long a submission is remembered
+    window: Duration,
+    /// Hashes of the submissions seen, with the time they were first seen
+    seen: HashMap<Vec<u8>, Instant>,
+}
+
+impl ReplayGuard {
+    /// Creates a new, empty replay guard.
+    ///
+    /// # Arguments
+    /// * `window` - How long a submission is remembered
+    ///
+    /// # Returns
+    /// A new replay guard.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Gets how long a submission is remembered.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a submission, unless it duplicates a recent one.
+    ///
+    /// Submissions older than the window are forgotten first.
+    ///
+    /// # Arguments
+    /// * `url` - The URL the body is submitted to
+    /// * `body` - The submitted body, if any
+    ///
+    /// # Returns
+    /// `true` if the submission is new and was recorded, `false` if the same
+    /// body was submitted to the URL within the window.
+    pub fn check(&mut self, url: &str, body: Option<&str>) -> bool {
+        let now = Instant::now();
+        let window = self.window;
+        self.seen
+            .retain(|_, seen_at| now.duration_since(*seen_at) < window);
+
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(url.as_bytes());
+        context.update(b"\n");
+        context.update(body.unwrap_or_default().as_bytes());
+        let hash = context.finish().as_ref().to_vec();
+        match self.seen.entry(hash) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirected_request() {
+        let request = NetRequest::new(
+            HttpMethod::Post,
+            ArcStr::from("https://lore.kernel.org/submit"),
+            Some(HashMap::from([
+                (ArcStr::from("Authorization"), ArcStr::from("Basic abc")),
+                (ArcStr::from("Content-Type"), ArcStr::from("text/plain")),
+            ])),
+            Some(ArcStr::from("body")),
+        );
+        assert_eq!(request.redirected(200, Some(&ArcStr::from("/done"))), None);
+        assert_eq!(request.redirected(302, None), None);
+
+        let next = request
+            .redirected(303, Some(&ArcStr::from("/done")))
+            .unwrap();
+        assert_eq!(next.method, HttpMethod::Get);
+        assert_eq!(&*next.url, "https://lore.kernel.org/done");
+        assert_eq!(next.body, None);
+        assert_eq!(next.headers.len(), 1);
+
+        let next = request
+            .redirected(
+                307,
+                Some(&ArcStr::from("https://mirror.example.org/submit")),
+            )
+            .unwrap();
+        assert_eq!(next.method, HttpMethod::Post);
+        assert_eq!(next.body, Some(ArcStr::from("body")));
+        assert_eq!(
+            next.headers.keys().cloned().collect::<Vec<_>>(),
+            vec![ArcStr::from("Content-Type")]
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_delay_doubles() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_backoff: None,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_retry_policy_delay_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_backoff: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(300));
+        assert_eq!(policy.delay(9), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_retry_policy_multiplier_and_jitter() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_backoff: None,
+            multiplier: 3,
+            jitter: 50,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(300));
+        assert_eq!(policy.delay(2), Duration::from_millis(900));
+        let delays: HashSet<_> = (0..100).map(|_| policy.jittered_delay(2)).collect();
+        assert!(delays.len() > 1);
+        for delay in delays {
+            assert!(delay >= Duration::from_millis(450) && delay <= Duration::from_millis(900));
+        }
+
+        let policy = RetryPolicy {
+            jitter: 0,
+            ..policy
+        };
+        assert_eq!(policy.jittered_delay(2), Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_generated_trace_ids_are_random() {
+        let ids: HashSet<_> = (0..100).map(|_| generate_trace_id()).collect();
+        assert_eq!(ids.len(), 100);
+        for id in ids {
+            assert_eq!(id.len(), 32);
+            assert!(id.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
+        }
+    }
+
+    #[test]
+    fn test_retry_budget_exhaustion() {
+        let mut budget = RetryBudget::new(2, 0);
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+        assert_eq!(budget.available(), 0);
+    }
+
+    #[test]
+    fn test_host_rate_limiter_total_limit_spans_hosts() {
+        let mut limiter = HostRateLimiter::new(0, 1).with_total_limit(10, 1);
+        assert_eq!(limiter.acquire("https://lore.kernel.org/a"), Duration::ZERO);
+        let delay = limiter.acquire("https://mirror.example.org/a");
+        assert!(delay > Duration::from_millis(90) && delay <= Duration::from_millis(100));
+
+        // The host limit applies too, and the longest wait wins
+        let mut limiter = HostRateLimiter::new(5, 1).with_total_limit(10, 2);
+        assert_eq!(limiter.acquire("https://lore.kernel.org/a"), Duration::ZERO);
+        let delay = limiter.acquire("https://lore.kernel.org/b");
+        assert!(delay > Duration::from_millis(190) && delay <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_host_rate_limiter_paces_each_host() {
+        let mut limiter = HostRateLimiter::new(10, 2);
+        assert_eq!(limiter.acquire("https://lore.kernel.org/a"), Duration::ZERO);
+        assert_eq!(limiter.acquire("https://lore.kernel.org/b"), Duration::ZERO);
+        let delay = limiter.acquire("https://lore.kernel.org/c");
+        assert!(delay > Duration::from_millis(90) && delay <= Duration::from_millis(100));
+        let delay = limiter.acquire("https://lore.kernel.org/d");
+        assert!(delay > Duration::from_millis(190) && delay <= Duration::from_millis(200));
+        assert_eq!(
+            limiter.acquire("https://mirror.example.org/a"),
+            Duration::ZERO
+        );
+
+        let mut unlimited = HostRateLimiter::new(0, 1);
+        for _ in 0..5 {
+            assert_eq!(
+                unlimited.acquire("https://lore.kernel.org/a"),
+                Duration::ZERO
+            );
+        }
+    }
+
+    #[test]
+    fn test_replay_guard_forgets_after_window() {
+        let mut guard = ReplayGuard::new(Duration::from_millis(20));
+        assert!(guard.check("https://lore.kernel.org/submit", Some("body")));
+        assert!(!guard.check("https://lore.kernel.org/submit", Some("body")));
+        assert!(guard.check("https://lore.kernel.org/other", Some("body")));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(guard.check("https://lore.kernel.org/submit", Some("body")));
+    }
+
+    #[test]
+    fn test_basic_realm() {
+        assert_eq!(
+            basic_realm(r#"Basic realm="lore mirror", charset="UTF-8""#),
+            Some(ArcStr::from("lore mirror"))
+        );
+        assert_eq!(
+            basic_realm(
+                r#"Bearer realm="api, v2", error="invalid_token", basic charset="UTF-8", realm="archive""#
+            ),
+            Some(ArcStr::from("archive"))
+        );
+        assert_eq!(basic_realm(r#"Bearer realm="api""#), None);
+        assert_eq!(basic_realm("Basic"), None);
+    }
+
+    #[test]
+    fn test_robots_rules() {
+        let content = "# Crawlers\n\
+            User-agent: patch-hub\n\
+            User-agent: b4\n\
+            Disallow: /*/raw$\n\
+            Crawl-delay: 2.5\n\
+            \n\
+            User-agent: *\n\
+            Disallow: /all/\n\
+            Allow: /all/new.atom\n\
+            Disallow:\n";
+
+        let rules = RobotsRules::parse(content, Some("Patch-Hub"));
+        assert!(!rules.allows("/amd-gfx/20231201.1-1@amd.com/raw"));
+        assert!(rules.allows("/amd-gfx/20231201.1-1@amd.com/raw/extra"));
+        assert!(rules.allows("/all/"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(2500)));
+
+        let rules = RobotsRules::parse(content, None);
+        assert!(!rules.allows("/all/?q=s:patch"));
+        assert!(rules.allows("/all/new.atom"));
+        assert!(rules.allows("/amd-gfx/20231201.1-1@amd.com/raw"));
+        assert_eq!(rules.crawl_delay(), None);
+
+        assert_eq!(
+            RobotsRules::parse("Disallow: /\n", None),
+            RobotsRules::default()
+        );
+    }
+
+    #[test]
+    fn test_charset() {
+        let response = |content_type: &str| NetResponse {
+            status: 200,
+            headers: HashMap::from([(ArcStr::from("content-type"), ArcStr::from(content_type))]),
+            body: Arc::from(&b""[..]),
+        };
+        assert_eq!(
+            response("text/plain; Charset=\"UTF-8\"").charset(),
+            Some(ArcStr::from("utf-8"))
+        );
+        assert_eq!(response("text/html").charset(), None);
+    }
+
+    #[test]
+    fn test_next_link() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            ArcStr::from("link"),
+            ArcStr::from(r#"<https://example.org/?page=1>; rel="prev", </?page=3>; rel="next""#),
+        );
+        let response = NetResponse {
+            status: 200,
+            headers,
+            body: Arc::from(&b""[..]),
+        };
+        assert_eq!(
+            response.next_link("https://example.org/?page=2"),
+            Some(ArcStr::from("https://example.org/?page=3"))
+        );
+    }
+
+    #[test]
+    fn test_response_retryable_statuses() {
+        let response = |status| NetResponse {
+            status,
+            headers: HashMap::new(),
+            body: Arc::from(&b""[..]),
+        };
+        assert!(response(503).is_retryable());
+        assert!(response(429).is_retryable());
+        assert!(!response(404).is_retryable());
+        assert!(!response(200).is_retryable());
+    }
+}
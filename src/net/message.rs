@@ -1,9 +1,18 @@
-use tokio::sync::oneshot::Sender;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot::Sender;
 
-use crate::ArcStr;
+use crate::{
+    ArcPath, ArcStr,
+    net::{
+        data::{NetConfigSnapshot, NetMetrics, NetRequest, NetResponse, Priority},
+        stream::{BodyStream, ResponseStart},
+    },
+    utils::ShutdownError,
+};
 
 /// Represents HTTP methods supported by the networking actor.
 ///
@@ -21,6 +30,8 @@ pub enum HttpMethod {
     Delete,
     /// HTTP PATCH method
     Patch,
+    /// HTTP HEAD method
+    Head,
 }
 
 impl HttpMethod {
@@ -35,8 +46,20 @@ impl HttpMethod {
             HttpMethod::Put => "PUT",
             HttpMethod::Delete => "DELETE",
             HttpMethod::Patch => "PATCH",
+            HttpMethod::Head => "HEAD",
         }
     }
+
+    /// Checks whether repeating a request with this method has the same effect as sending it once.
+    ///
+    /// # Returns
+    /// `true` for GET, HEAD, PUT and DELETE, which are safe to retry.
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            HttpMethod::Get | HttpMethod::Head | HttpMethod::Put | HttpMethod::Delete
+        )
+    }
 }
 
 impl Display for HttpMethod {
@@ -54,6 +77,7 @@ impl FromStr for HttpMethod {
             "PUT" => Ok(HttpMethod::Put),
             "DELETE" => Ok(HttpMethod::Delete),
             "PATCH" => Ok(HttpMethod::Patch),
+            "HEAD" => Ok(HttpMethod::Head),
             _ => Err(anyhow::anyhow!("Invalid HTTP method: {}", s)),
         }
     }
@@ -139,6 +163,17 @@ impl MockRequestKey {
     pub fn patch(url: ArcStr) -> Self {
         Self::new(HttpMethod::Patch, url)
     }
+
+    /// Creates a HEAD request key.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request
+    ///
+    /// # Returns
+    /// A new mock request key for a HEAD request.
+    pub fn head(url: ArcStr) -> Self {
+        Self::new(HttpMethod::Head, url)
+    }
 }
 
 /// Messages that can be sent to a [`NetCore`] actor.
@@ -151,13 +186,63 @@ pub enum Message {
     Get {
         url: ArcStr,
         headers: Option<HashMap<ArcStr, ArcStr>>,
+        priority: Priority,
         tx: Sender<anyhow::Result<ArcStr>>,
     },
+    /// Performs an HTTP GET request and returns the raw response body
+    GetBytes {
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        priority: Priority,
+        tx: Sender<anyhow::Result<Arc<[u8]>>>,
+    },
+    /// Performs a request and returns the full response
+    Request {
+        request: NetRequest,
+        tx: Sender<anyhow::Result<NetResponse>>,
+    },
+    /// Performs a request and returns its body as a stream once the headers arrive
+    GetStream {
+        request: NetRequest,
+        tx: Sender<anyhow::Result<BodyStream>>,
+    },
+    /// Performs a request and returns its status, headers and body stream once the headers arrive
+    GetResponseStart {
+        request: NetRequest,
+        tx: Sender<anyhow::Result<ResponseStart>>,
+    },
+    /// Checks whether a URL answers a HEAD request at all
+    IsReachable { url: ArcStr, tx: Sender<bool> },
+    /// Gets the settings the actor is running with
+    GetConfig { tx: Sender<NetConfigSnapshot> },
+    /// Gets the runtime metrics of the actor
+    GetMetrics { tx: Sender<NetMetrics> },
+    /// Writes the responses recorded by the actor to a fixture file
+    ExportFixtures {
+        path: ArcPath,
+        tx: Sender<anyhow::Result<()>>,
+    },
+    /// Stops the actor once the messages already sent are handled, aborting
+    /// what is left of them past the timeout
+    Shutdown {
+        timeout: Duration,
+        tx: Sender<Result<(), ShutdownError>>,
+    },
     /// Performs an HTTP POST request to the specified URL
     Post {
         url: ArcStr,
         headers: Option<HashMap<ArcStr, ArcStr>>,
         body: Option<ArcStr>,
+        idempotency_key: Option<ArcStr>,
+        priority: Priority,
+        tx: Sender<anyhow::Result<ArcStr>>,
+    },
+    /// Performs an HTTP POST request with a JSON body to the specified URL
+    PostJson {
+        url: ArcStr,
+        headers: Option<HashMap<ArcStr, ArcStr>>,
+        value: serde_json::Value,
+        priority: Priority,
         tx: Sender<anyhow::Result<ArcStr>>,
     },
     /// Performs an HTTP PUT request to the specified URL
@@ -165,12 +250,14 @@ pub enum Message {
         url: ArcStr,
         headers: Option<HashMap<ArcStr, ArcStr>>,
         body: Option<ArcStr>,
+        priority: Priority,
         tx: Sender<anyhow::Result<ArcStr>>,
     },
     /// Performs an HTTP DELETE request to the specified URL
     Delete {
         url: ArcStr,
         headers: Option<HashMap<ArcStr, ArcStr>>,
+        priority: Priority,
         tx: Sender<anyhow::Result<ArcStr>>,
     },
     /// Performs an HTTP PATCH request to the specified URL
@@ -178,6 +265,42 @@ pub enum Message {
         url: ArcStr,
         headers: Option<HashMap<ArcStr, ArcStr>>,
         body: Option<ArcStr>,
+        priority: Priority,
         tx: Sender<anyhow::Result<ArcStr>>,
     },
 }
+
+impl Message {
+    /// Gets how urgently the message has to be handled.
+    ///
+    /// # Returns
+    /// The priority of the request carried by the message, or
+    /// [`Priority::Normal`] for the messages without a request.
+    pub fn priority(&self) -> Priority {
+        match self {
+            Message::Request { request, .. }
+            | Message::GetStream { request, .. }
+            | Message::GetResponseStart { request, .. } => request.priority,
+            Message::Get { priority, .. }
+            | Message::GetBytes { priority, .. }
+            | Message::Post { priority, .. }
+            | Message::PostJson { priority, .. }
+            | Message::Put { priority, .. }
+            | Message::Delete { priority, .. }
+            | Message::Patch { priority, .. } => *priority,
+            _ => Priority::Normal,
+        }
+    }
+
+    /// Checks whether the message only reads the state of the actor.
+    ///
+    /// # Returns
+    /// `true` for the messages answered right away instead of waiting for a
+    /// slot behind the network requests.
+    pub fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Message::GetConfig { .. } | Message::GetMetrics { .. } | Message::ExportFixtures { .. }
+        )
+    }
+}
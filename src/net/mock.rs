@@ -0,0 +1,366 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    ArcStr,
+    net::{
+        data::{NetRequest, NetResponse, redact_header},
+        message::MockRequestKey,
+    },
+};
+
+/// A canned response served by the mock networking implementation.
+///
+/// # Examples
+/// ```
+/// let response = MockResponse::with_status(404, ArcStr::from("not found"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    /// The HTTP status code to answer with
+    pub status: u16,
+    /// The response headers to answer with
+    pub headers: HashMap<ArcStr, ArcStr>,
+    /// The raw response body
+    pub body: Arc<[u8]>,
+}
+
+impl MockResponse {
+    /// Creates a successful (`200 OK`) mock response.
+    ///
+    /// # Arguments
+    /// * `body` - The response body
+    ///
+    /// # Returns
+    /// A new mock response.
+    pub fn ok(body: ArcStr) -> Self {
+        Self::with_status(200, body)
+    }
+
+    /// Creates a mock response with the given status code.
+    ///
+    /// # Arguments
+    /// * `status` - The HTTP status code
+    /// * `body` - The response body
+    ///
+    /// # Returns
+    /// A new mock response.
+    pub fn with_status(status: u16, body: ArcStr) -> Self {
+        Self {
+            status,
+            headers: HashMap::new(),
+            body: Arc::from(body.as_bytes()),
+        }
+    }
+
+    /// Creates a successful (`200 OK`) mock response with a binary body.
+    ///
+    /// # Arguments
+    /// * `body` - The raw response body, which doesn't need to be valid UTF-8
+    ///
+    /// # Returns
+    /// A new mock response.
+    pub fn bytes(body: Arc<[u8]>) -> Self {
+        Self {
+            status: 200,
+            headers: HashMap::new(),
+            body,
+        }
+    }
+
+    /// Adds a header to the mock response.
+    ///
+    /// # Arguments
+    /// * `key` - The header name
+    /// * `value` - The header value
+    ///
+    /// # Returns
+    /// The mock response with the header set.
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers
+            .insert(ArcStr::from(&key.to_lowercase()), ArcStr::from(value));
+        self
+    }
+}
+
+impl From<MockResponse> for NetResponse {
+    fn from(response: MockResponse) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        }
+    }
+}
+
+/// The state shared by the mock networking implementations.
+///
+/// Holds the canned responses keyed by HTTP method and URL and records every
+/// request it serves so tests can inspect them. Each key may hold a sequence of
+/// responses: they are served in order and the last one is repeated forever.
+/// A key may also have a delay, waited before each of its responses is served,
+/// to exercise timeouts without a real slow server. Keys have no delay by default.
+#[derive(Debug, Default)]
+pub struct MockState {
+    /// Canned responses for each request key
+    responses: HashMap<MockRequestKey, VecDeque<MockResponse>>,
+    /// Time waited before serving the responses of each request key
+    delays: HashMap<MockRequestKey, Duration>,
+    /// Requests received so far, in order
+    requests: Vec<NetRequest>,
+}
+
+impl From<HashMap<MockRequestKey, ArcStr>> for MockState {
+    fn from(responses: HashMap<MockRequestKey, ArcStr>) -> Self {
+        let mut state = Self::default();
+        for (key, body) in responses {
+            state.insert(key, MockResponse::ok(body));
+        }
+        state
+    }
+}
+
+impl From<HashMap<MockRequestKey, Arc<[u8]>>> for MockState {
+    fn from(responses: HashMap<MockRequestKey, Arc<[u8]>>) -> Self {
+        let mut state = Self::default();
+        for (key, body) in responses {
+            state.insert(key, MockResponse::bytes(body));
+        }
+        state
+    }
+}
+
+impl From<HashMap<MockRequestKey, (ArcStr, Duration)>> for MockState {
+    fn from(responses: HashMap<MockRequestKey, (ArcStr, Duration)>) -> Self {
+        let mut state = Self::default();
+        for (key, (body, delay)) in responses {
+            state.set_delay(key.clone(), delay);
+            state.insert(key, MockResponse::ok(body));
+        }
+        state
+    }
+}
+
+impl MockState {
+    /// Sets the response for a request key, replacing any previous ones.
+    ///
+    /// # Arguments
+    /// * `key` - The request key
+    /// * `response` - The response to serve
+    pub fn insert(&mut self, key: MockRequestKey, response: MockResponse) {
+        self.responses.insert(key, VecDeque::from([response]));
+    }
+
+    /// Appends a response to the sequence served for a request key.
+    ///
+    /// # Arguments
+    /// * `key` - The request key
+    /// * `response` - The response to serve after the ones already registered
+    pub fn push(&mut self, key: MockRequestKey, response: MockResponse) {
+        self.responses.entry(key).or_default().push_back(response);
+    }
+
+    /// Sets the time waited before serving the responses of a request key.
+    ///
+    /// # Arguments
+    /// * `key` - The request key
+    /// * `delay` - The time to wait, [`Duration::ZERO`] for none
+    pub fn set_delay(&mut self, key: MockRequestKey, delay: Duration) {
+        if delay.is_zero() {
+            self.delays.remove(&key);
+        } else {
+            self.delays.insert(key, delay);
+        }
+    }
+
+    /// Gets the time to wait before serving a request.
+    ///
+    /// # Arguments
+    /// * `request` - The request about to be served
+    ///
+    /// # Returns
+    /// The delay of the request key, [`Duration::ZERO`] if it has none.
+    pub fn delay(&self, request: &NetRequest) -> Duration {
+        let key = MockRequestKey::new(request.method.clone(), request.url.clone());
+        self.delays.get(&key).copied().unwrap_or_default()
+    }
+
+    /// Records a request and looks up its response.
+    ///
+    /// # Arguments
+    /// * `request` - The request being served
+    ///
+    /// # Returns
+    /// The next response for the request, or `None` if none was registered.
+    pub fn respond(&mut self, request: &NetRequest) -> Option<MockResponse> {
+        self.requests.push(request.clone());
+        let key = MockRequestKey::new(request.method.clone(), request.url.clone());
+        let queue = self.responses.get_mut(&key)?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+
+    /// Records a request and the response it got, for the response to be served
+    /// to the same request later.
+    ///
+    /// The response replaces any previously registered for the request key, so
+    /// the last response of a retried request is the one kept.
+    ///
+    /// # Arguments
+    /// * `request` - The request performed
+    /// * `response` - The response received
+    pub fn record(&mut self, request: &NetRequest, response: &NetResponse) {
+        self.requests.push(request.clone());
+        let key = MockRequestKey::new(request.method.clone(), request.url.clone());
+        self.insert(
+            key,
+            MockResponse {
+                status: response.status,
+                headers: response.headers.clone(),
+                body: response.body.clone(),
+            },
+        );
+    }
+
+    /// Serializes the registered responses as a fixture file.
+    ///
+    /// The fixtures are a pretty-printed JSON array with one object per
+    /// response, sorted by method and URL so the file diffs well. Headers
+    /// carrying credentials are redacted, and bodies that aren't UTF-8 are
+    /// written in base64. Delays and recorded requests aren't part of fixtures.
+    ///
+    /// # Returns
+    /// The JSON content of the fixture file.
+    pub fn to_fixtures(&self) -> anyhow::Result<String> {
+        let mut keys: Vec<_> = self.responses.keys().collect();
+        keys.sort_by(|a, b| (a.method.as_str(), &a.url).cmp(&(b.method.as_str(), &b.url)));
+
+        let fixtures: Vec<Fixture> = keys
+            .into_iter()
+            .flat_map(|key| {
+                self.responses[key]
+                    .iter()
+                    .map(move |response| (key, response))
+            })
+            .map(|(key, response)| {
+                let (body, body_base64) = match std::str::from_utf8(&response.body) {
+                    Ok(body) => (Some(ArcStr::from(body)), None),
+                    Err(_) => (
+                        None,
+                        Some(ArcStr::from(
+                            &base64::engine::general_purpose::STANDARD.encode(&response.body),
+                        )),
+                    ),
+                };
+                Fixture {
+                    method: ArcStr::from(key.method.as_str()),
+                    url: key.url.clone(),
+                    status: response.status,
+                    headers: response
+                        .headers
+                        .iter()
+                        .map(|(name, value)| (name.clone(), redact_header(name, value)))
+                        .collect(),
+                    body,
+                    body_base64,
+                }
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&fixtures)?)
+    }
+
+    /// Loads the responses of a fixture file written by [`MockState::to_fixtures`].
+    ///
+    /// The responses of a request key are served in the order they appear in
+    /// the file, the last one repeated forever.
+    ///
+    /// # Arguments
+    /// * `json` - The JSON content of the fixture file
+    ///
+    /// # Returns
+    /// A mock state serving the fixtures, or an error if the content is invalid.
+    pub fn from_fixtures(json: &str) -> anyhow::Result<Self> {
+        let fixtures: Vec<Fixture> = serde_json::from_str(json)?;
+        let mut state = Self::default();
+        for fixture in fixtures {
+            let body: Arc<[u8]> = match (fixture.body, fixture.body_base64) {
+                (_, Some(encoded)) => {
+                    Arc::from(base64::engine::general_purpose::STANDARD.decode(&*encoded)?)
+                }
+                (Some(body), None) => Arc::from(body.as_bytes()),
+                (None, None) => Arc::from(&[][..]),
+            };
+            state.push(
+                MockRequestKey::new(fixture.method.parse()?, fixture.url),
+                MockResponse {
+                    status: fixture.status,
+                    headers: fixture.headers.into_iter().collect(),
+                    body,
+                },
+            );
+        }
+        Ok(state)
+    }
+
+    /// Gets the requests served so far.
+    ///
+    /// # Returns
+    /// The recorded requests, in the order they were received.
+    pub fn requests(&self) -> &[NetRequest] {
+        &self.requests
+    }
+}
+
+/// Writes the responses of a mock state to a fixture file.
+///
+/// # Arguments
+/// * `state` - The mock state, or the recording, to export
+/// * `path` - The fixture file, created with its parent directories if needed
+///
+/// # Returns
+/// An error if the fixtures can't be serialized or written.
+pub(crate) async fn write_fixtures(state: &Mutex<MockState>, path: &Path) -> anyhow::Result<()> {
+    let fixtures = state.lock().await.to_fixtures()?;
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Creating directory {}", parent.display()))?;
+    }
+    tokio::fs::write(path, fixtures)
+        .await
+        .with_context(|| format!("Writing fixtures to {}", path.display()))
+}
+
+/// A response of a fixture file, keyed by the method and URL of its request.
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    /// The HTTP method of the request
+    method: ArcStr,
+    /// The URL of the request
+    url: ArcStr,
+    /// The HTTP status code of the response
+    status: u16,
+    /// The response headers, credentials redacted
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<ArcStr, ArcStr>,
+    /// The response body, if it is UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<ArcStr>,
+    /// The response body in base64, if it isn't UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<ArcStr>,
+}
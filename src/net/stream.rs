@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::ArcStr;
+
+/// Number of chunks buffered between the task reading a body and its consumer
+const BODY_CHANNEL_CAPACITY: usize = 16;
+
+/// The body of a response, received chunk by chunk as it is downloaded.
+///
+/// The download runs in a background task that stays at most a few chunks
+/// ahead of the consumer, so memory stays bounded whatever the size of the
+/// body. Dropping the stream stops the download.
+///
+/// # Examples
+/// ```
+/// let mut body = net.get_stream(url, None).await?;
+/// while let Some(chunk) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+///     let chunk = chunk?;
+///     // ...
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BodyStream {
+    /// The chunks of the body, or the error that interrupted the download
+    rx: Receiver<anyhow::Result<Arc<[u8]>>>,
+}
+
+impl BodyStream {
+    /// Creates a body stream fed through a channel.
+    ///
+    /// # Returns
+    /// A tuple containing:
+    /// - The sender of the chunks, whose drop ends the body
+    /// - The body stream
+    pub fn channel() -> (Sender<anyhow::Result<Arc<[u8]>>>, Self) {
+        let (tx, rx) = mpsc::channel(BODY_CHANNEL_CAPACITY);
+        (tx, Self { rx })
+    }
+
+    /// Creates a body stream yielding an already received body as a single chunk.
+    ///
+    /// # Arguments
+    /// * `body` - The whole body
+    ///
+    /// # Returns
+    /// A body stream ending after the given body.
+    pub fn once(body: Arc<[u8]>) -> Self {
+        let (tx, stream) = Self::channel();
+        if !body.is_empty() {
+            let _ = tx.try_send(Ok(body));
+        }
+        stream
+    }
+}
+
+/// The start of a response: its status and headers, with the body still to download.
+///
+/// Only the headers are received when this is returned. The body is downloaded
+/// as [`ResponseStart::body`] is consumed, and dropping it without reading it
+/// aborts the download.
+///
+/// # Examples
+/// ```
+/// let start = net.get_response_start(url, None).await?;
+/// if start.headers.get(&ArcStr::from("content-type")).is_some_and(|kind| kind.starts_with("text/")) {
+///     let body = start.body;
+///     // ...
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ResponseStart {
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The response headers, with lowercase names
+    pub headers: HashMap<ArcStr, ArcStr>,
+    /// The body of the response, downloaded as it is consumed
+    pub body: BodyStream,
+}
+
+impl Stream for BodyStream {
+    type Item = anyhow::Result<Arc<[u8]>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
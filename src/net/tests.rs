@@ -0,0 +1,1871 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::Mutex,
+};
+
+use crate::{
+    ArcPath, ArcStr,
+    config::{Config, Data, USizeOpt},
+    fs::Fs,
+    log::{Log, LogLevel},
+};
+
+use super::{
+    Credentials, JsonEncoding, MockState, Net, NetError, NetOptions, NetRequest, Priority,
+    RetryPolicy,
+    core::{Core, MAILBOX_CAPACITY, build_client},
+    data::{DEFAULT_TRACE_HEADER, JSON_CONTENT_TYPE},
+    message::{HttpMethod, Message, MockRequestKey},
+    mock::MockResponse,
+    transport::Transport,
+};
+
+/// Spawns a networking core serving responses from the given mock state
+fn spawn_with_state(state: &Arc<Mutex<MockState>>, data: Data) -> Net {
+    let (net, _) = Core::new(Config::mock(data), Log::mock())
+        .with_transport(Transport::Mock(state.clone()))
+        .spawn();
+    net
+}
+
+/// Serves a single raw HTTP response on a local port and returns its URL
+async fn serve_once(response: &'static str) -> ArcStr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = socket.read(&mut buf).await;
+        socket.write_all(response.as_bytes()).await.unwrap();
+        let _ = socket.shutdown().await;
+    });
+    ArcStr::from(&format!("http://{}/", addr))
+}
+
+/// Serves the start of a raw HTTP response on a local port, then holds the
+/// connection open without sending the rest, and returns its URL
+async fn serve_stalled(response_start: &'static str) -> ArcStr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            socket.write_all(response_start.as_bytes()).await.unwrap();
+            sockets.push(socket);
+        }
+    });
+    ArcStr::from(&format!("http://{}/", addr))
+}
+
+/// Accepts connections on a local port without ever answering and returns its URL
+async fn serve_nothing() -> ArcStr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+    ArcStr::from(&format!("http://{}/", addr))
+}
+
+/// Spawns a logger writing to a temporary directory, which is kept alive
+/// as long as the returned guard
+async fn spawn_log() -> (Log, tempfile::TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let log = Log::spawn(Fs::spawn(), LogLevel::Info, 0, ArcPath::from(dir.path()))
+        .await
+        .unwrap();
+    (log, dir)
+}
+
+/// Configuration data with instant retries
+fn retry_data(max_attempts: usize, budget: usize) -> Data {
+    let mut data = Data::default();
+    data.set_usize(USizeOpt::RetryMaxAttempts, max_attempts);
+    data.set_usize(USizeOpt::RetryBaseDelay, 0);
+    data.set_usize(USizeOpt::RetryBudget, budget);
+    data.set_usize(USizeOpt::RetryBudgetRefill, 0);
+    data
+}
+
+#[tokio::test]
+async fn test_mock_get() {
+    let url = ArcStr::from("https://lore.kernel.org/test");
+    let mut responses = HashMap::new();
+    responses.insert(MockRequestKey::get(url.clone()), ArcStr::from("body"));
+    let net = Net::mock(responses);
+
+    assert_eq!(
+        net.get(url.clone(), None).await.unwrap(),
+        ArcStr::from("body")
+    );
+    assert!(net.post(url, None, None, None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_retry_on_server_error() {
+    let url = ArcStr::from("https://lore.kernel.org/flaky");
+    let mut state = MockState::default();
+    state.push(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    state.push(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("ok")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(3, 10));
+
+    assert_eq!(net.get(url, None).await.unwrap(), ArcStr::from("ok"));
+    assert_eq!(state.lock().await.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_post_is_not_retried() {
+    let url = ArcStr::from("https://lore.kernel.org/submit");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::post(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(3, 10));
+
+    net.post(url, None, None, None).await.unwrap();
+    assert_eq!(state.lock().await.requests().len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_delay_of_a_host_does_not_stall_other_hosts() {
+    let flaky = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let other = ArcStr::from("https://mirror.example.org/lkml/new.atom");
+    let mut state = MockState::default();
+    state.push(
+        MockRequestKey::get(flaky.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    state.push(
+        MockRequestKey::get(flaky.clone()),
+        MockResponse::ok(ArcStr::from("flaky")),
+    );
+    state.insert(
+        MockRequestKey::get(other.clone()),
+        MockResponse::ok(ArcStr::from("other")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let mut data = retry_data(2, 10);
+    data.set_usize(USizeOpt::RetryBaseDelay, 30_000);
+    let net = spawn_with_state(&state, data);
+
+    let start = tokio::time::Instant::now();
+    let busy = tokio::spawn({
+        let net = net.clone();
+        async move { net.get(flaky, None).await }
+    });
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // The other host is served while the first request waits to be retried
+    assert_eq!(net.get(other, None).await.unwrap(), ArcStr::from("other"));
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(!busy.is_finished());
+
+    assert_eq!(busy.await.unwrap().unwrap(), ArcStr::from("flaky"));
+    assert!(start.elapsed() >= Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn test_post_with_idempotency_key_is_retried() {
+    let url = ArcStr::from("https://lore.kernel.org/submit");
+    let flaky = || {
+        let mut state = MockState::default();
+        state.push(
+            MockRequestKey::post(url.clone()),
+            MockResponse::with_status(503, ArcStr::from("unavailable")),
+        );
+        state.push(
+            MockRequestKey::post(url.clone()),
+            MockResponse::ok(ArcStr::from("created")),
+        );
+        Arc::new(Mutex::new(state))
+    };
+
+    let keyed = flaky();
+    let net = spawn_with_state(&keyed, retry_data(3, 10));
+    let response = net
+        .post(url.clone(), None, None, Some(ArcStr::from("op-1")))
+        .await
+        .unwrap();
+    assert_eq!(response, ArcStr::from("created"));
+    let keyed = keyed.lock().await;
+    assert_eq!(keyed.requests().len(), 2);
+    assert!(keyed.requests().iter().all(|request| {
+        request.headers.get(&ArcStr::from("Idempotency-Key")) == Some(&ArcStr::from("op-1"))
+    }));
+
+    let unkeyed = flaky();
+    let net = spawn_with_state(&unkeyed, retry_data(3, 10));
+    let response = net.post(url, None, None, None).await.unwrap();
+    assert_eq!(response, ArcStr::from("unavailable"));
+    assert_eq!(unkeyed.lock().await.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_budget_stops_retries_when_exhausted() {
+    let url = ArcStr::from("https://lore.kernel.org/down");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(3, 2));
+
+    // The first request spends the whole budget on its two retries
+    net.get(url.clone(), None).await.unwrap_err();
+    assert_eq!(state.lock().await.requests().len(), 3);
+
+    // With the budget exhausted, the failure surfaces without retrying
+    net.get(url, None).await.unwrap_err();
+    assert_eq!(state.lock().await.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn test_get_bytes_reads_chunked_body() {
+    let url = serve_once(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+         5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+    )
+    .await;
+    let net = Net::spawn(Config::mock(retry_data(1, 0)), Log::mock());
+
+    let body = net.get_bytes(url, None).await.unwrap();
+    assert_eq!(&body[..], b"hello world");
+    assert_eq!(body.len(), 11);
+}
+
+#[tokio::test]
+async fn test_mock_get_bytes() {
+    let url = ArcStr::from("https://lore.kernel.org/bytes");
+    let mut responses = HashMap::new();
+    responses.insert(MockRequestKey::get(url.clone()), ArcStr::from("raw"));
+    let net = Net::mock(responses);
+
+    assert_eq!(&net.get_bytes(url, None).await.unwrap()[..], b"raw");
+}
+
+#[tokio::test]
+async fn test_mock_get_bytes_keeps_binary_body() {
+    let url = ArcStr::from("https://lore.kernel.org/lkml/t.mbox.gz");
+    // The gzip magic number followed by bytes that aren't valid UTF-8
+    let gzip: Arc<[u8]> = Arc::from(&[0x1f, 0x8b, 0x08, 0x00, 0xff, 0xfe, 0x80][..]);
+    let net = Net::mock_bytes(HashMap::from([(
+        MockRequestKey::get(url.clone()),
+        gzip.clone(),
+    )]));
+
+    assert_eq!(net.get_bytes(url, None).await.unwrap(), gzip);
+}
+
+#[tokio::test]
+async fn test_is_reachable() {
+    let up = ArcStr::from("https://lore.kernel.org/missing");
+    let down = ArcStr::from("https://down.example.org/");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::head(up.clone()),
+        MockResponse::with_status(404, ArcStr::from("")),
+    );
+    let state = Arc::new(Mutex::new(state));
+
+    let mock = Net::mock_with_state(state.clone());
+    assert!(mock.is_reachable(up.clone()).await);
+    assert!(!mock.is_reachable(down.clone()).await);
+
+    let net = spawn_with_state(&state, retry_data(3, 10));
+    assert!(net.is_reachable(up).await);
+    assert!(!net.is_reachable(down).await);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_is_reachable_is_paced_with_default_headers() {
+    let url = ArcStr::from("https://lore.kernel.org/");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::head(url.clone()),
+        MockResponse::ok(ArcStr::from("")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let user_agent = (ArcStr::from("User-Agent"), ArcStr::from("patch-hub/1.0"));
+    let options = NetOptions {
+        method_headers: HashMap::from([(HttpMethod::Head, HashMap::from([user_agent.clone()]))]),
+        ..Default::default()
+    };
+    let mut data = retry_data(3, 10);
+    data.set_usize(USizeOpt::HostRateLimit, 1);
+    data.set_usize(USizeOpt::HostRateBurst, 1);
+    let (net, _) = Core::new(Config::mock(data), Log::mock())
+        .with_transport(Transport::Mock(state.clone()))
+        .with_options(options)
+        .spawn();
+
+    let start = tokio::time::Instant::now();
+    assert!(net.is_reachable(url.clone()).await);
+    assert!(net.is_reachable(url).await);
+    // The second probe waits for the next token of the host
+    assert!(start.elapsed() >= Duration::from_secs(1));
+
+    let state = state.lock().await;
+    assert_eq!(state.requests().len(), 2);
+    for request in state.requests() {
+        assert_eq!(request.headers.get(&user_agent.0), Some(&user_agent.1));
+    }
+}
+
+#[tokio::test]
+async fn test_get_all_pages_follows_next_links() {
+    let first = ArcStr::from("https://lore.kernel.org/pages?page=1");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(first.clone()),
+        MockResponse::ok(ArcStr::from("one")).header("Link", "</pages?page=2>; rel=\"next\""),
+    );
+    state.insert(
+        MockRequestKey::get(ArcStr::from("https://lore.kernel.org/pages?page=2")),
+        MockResponse::ok(ArcStr::from("two")).header(
+            "Link",
+            "<https://lore.kernel.org/pages?page=3>; rel=\"next\", </pages?page=1>; rel=\"first\"",
+        ),
+    );
+    state.insert(
+        MockRequestKey::get(ArcStr::from("https://lore.kernel.org/pages?page=3")),
+        MockResponse::ok(ArcStr::from("three")).header("Link", "</pages?page=1>; rel=\"first\""),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    let pages = net.get_all_pages(first, None).await.unwrap();
+    let bodies: Vec<ArcStr> = pages.iter().map(|page| page.text()).collect();
+    assert_eq!(
+        bodies,
+        [
+            ArcStr::from("one"),
+            ArcStr::from("two"),
+            ArcStr::from("three")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_default_timeout_applies_to_each_attempt() {
+    let url = serve_nothing().await;
+    let mut data = retry_data(2, 10);
+    data.set_usize(USizeOpt::DefaultTimeout, 50);
+    let net = Net::spawn(Config::mock(data), Log::mock());
+
+    let started = tokio::time::Instant::now();
+    let err = net.get(url, None).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("timed out"));
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_mock_delay_beyond_timeout_times_out() {
+    let slow = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let fast = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let responses = HashMap::from([
+        (
+            MockRequestKey::get(slow.clone()),
+            (ArcStr::from("slow feed"), Duration::from_secs(10)),
+        ),
+        (
+            MockRequestKey::get(fast.clone()),
+            (ArcStr::from("fast feed"), Duration::from_secs(1)),
+        ),
+    ]);
+
+    // Through the actor, with the configured default timeout
+    let state = Arc::new(Mutex::new(MockState::from(responses.clone())));
+    let mut data = retry_data(1, 0);
+    data.set_usize(USizeOpt::DefaultTimeout, 5000);
+    let net = spawn_with_state(&state, data);
+
+    let started = tokio::time::Instant::now();
+    let err = net.get(slow.clone(), None).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("timed out"));
+    assert!(started.elapsed() >= Duration::from_secs(5));
+    assert!(started.elapsed() < Duration::from_secs(6));
+    assert_eq!(
+        net.get(fast.clone(), None).await.unwrap(),
+        ArcStr::from("fast feed")
+    );
+    assert!(started.elapsed() >= Duration::from_secs(6));
+
+    // Straight from the mock, with the timeout of the request
+    let net = Net::mock_with_delays(responses);
+    let request =
+        NetRequest::new(HttpMethod::Get, slow, None, None).with_timeout(Duration::from_secs(5));
+    let err = net.request(request).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("timed out"));
+    assert_eq!(
+        net.get(fast, None).await.unwrap(),
+        ArcStr::from("fast feed")
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_shutdown_timeout_waits_for_requests_in_flight() {
+    let url = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("feed")),
+    );
+    state.set_delay(MockRequestKey::get(url.clone()), Duration::from_secs(2));
+    let state = Arc::new(Mutex::new(state));
+
+    // The request in flight is answered before the actor stops, and the
+    // clones still alive can't send anything afterwards
+    let net = spawn_with_state(&state, Data::default());
+    let pending = tokio::spawn({
+        let net = net.clone();
+        let url = url.clone();
+        async move { net.get(url, None).await }
+    });
+    tokio::task::yield_now().await;
+    let clone = net.clone();
+    net.shutdown_timeout(Duration::from_secs(5)).await.unwrap();
+    assert_eq!(pending.await.unwrap().unwrap(), ArcStr::from("feed"));
+    clone.get(url.clone(), None).await.unwrap_err();
+    clone
+        .shutdown_timeout(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    // Past the timeout, the request in flight is aborted
+    let net = spawn_with_state(&state, Data::default());
+    let pending = tokio::spawn({
+        let net = net.clone();
+        async move { net.get(url, None).await }
+    });
+    tokio::task::yield_now().await;
+    let timeout = Duration::from_secs(1);
+    let error = net.shutdown_timeout(timeout).await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<crate::utils::ShutdownError>(),
+        Some(&crate::utils::ShutdownError::Forced { timeout })
+    );
+    pending.await.unwrap().unwrap_err();
+}
+
+#[tokio::test]
+async fn test_get_with_charset() {
+    let url = ArcStr::from("https://mirror.example.org/latin1");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse {
+            status: 200,
+            headers: HashMap::new(),
+            body: Arc::from(&b"caf\xe9"[..]),
+        }
+        .header("Content-Type", "text/html; charset=iso-8859-1"),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    let (body, charset) = net.get_with_charset(url, None).await.unwrap();
+    assert_eq!(&body[..], b"caf\xe9");
+    assert_eq!(charset, Some(ArcStr::from("iso-8859-1")));
+}
+
+#[tokio::test]
+async fn test_method_default_headers() {
+    let url = ArcStr::from("https://lore.kernel.org/headers");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("")),
+    );
+    state.insert(
+        MockRequestKey::post(url.clone()),
+        MockResponse::ok(ArcStr::from("")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let header = |key: &str, value: &str| (ArcStr::from(key), ArcStr::from(value));
+    let options = NetOptions {
+        method_headers: HashMap::from([
+            (
+                HttpMethod::Get,
+                HashMap::from([header("Accept", "text/html")]),
+            ),
+            (
+                HttpMethod::Post,
+                HashMap::from([
+                    header("Content-Type", "application/json"),
+                    header("Accept", "application/json"),
+                ]),
+            ),
+        ]),
+        ..Default::default()
+    };
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_transport(Transport::Mock(state.clone()))
+        .with_options(options)
+        .spawn();
+
+    net.get(url.clone(), None).await.unwrap();
+    net.post(
+        url,
+        Some(HashMap::from([header("accept", "text/plain")])),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let state = state.lock().await;
+    let requests = state.requests();
+    let trace = |request: &NetRequest| {
+        (
+            ArcStr::from(DEFAULT_TRACE_HEADER),
+            request.trace_id.clone().unwrap(),
+        )
+    };
+    assert_eq!(
+        requests[0].headers,
+        HashMap::from([header("Accept", "text/html"), trace(&requests[0])])
+    );
+    assert_eq!(
+        requests[1].headers,
+        HashMap::from([
+            header("Content-Type", "application/json"),
+            header("accept", "text/plain"),
+            trace(&requests[1]),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_get_prefix_truncates_large_body() {
+    let url = ArcStr::from("https://lore.kernel.org/all.mbox");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from(&"x".repeat(100_000))),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    let prefix = net.get_prefix(url, 512, None).await.unwrap();
+    assert_eq!(prefix.len(), 512);
+
+    let state = state.lock().await;
+    assert_eq!(
+        state.requests()[0].headers.get(&ArcStr::from("Range")),
+        Some(&ArcStr::from("bytes=0-511"))
+    );
+}
+
+#[tokio::test]
+async fn test_get_prefix_when_server_ignores_ranges() {
+    let url = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world").await;
+    let net = Net::spawn(Config::mock(retry_data(1, 0)), Log::mock());
+
+    let prefix = net.get_prefix(url, 5, None).await.unwrap();
+    assert_eq!(&prefix[..], b"hello");
+}
+
+#[tokio::test]
+async fn test_request_succeeds_after_log_shutdown() {
+    let url = ArcStr::from("https://lore.kernel.org/flaky");
+    let mut state = MockState::default();
+    state.push(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    state.push(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("ok")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let (log, _dir) = spawn_log().await;
+    let (net, _) = Core::new(Config::mock(retry_data(3, 10)), log.clone())
+        .with_transport(Transport::Mock(state.clone()))
+        .spawn();
+
+    log.clone().flush().await.unwrap();
+
+    // The retry is logged to the shut down logger
+    assert_eq!(net.get(url, None).await.unwrap(), ArcStr::from("ok"));
+    tokio::time::sleep(Duration::from_millis(10)).await;
+}
+
+#[test]
+fn test_client_builds_with_each_timeout() {
+    let timeout = Some(Duration::from_secs(1));
+    for (connect_timeout, read_timeout) in [
+        (None, None),
+        (timeout, None),
+        (None, timeout),
+        (timeout, timeout),
+    ] {
+        assert!(build_client(connect_timeout, read_timeout, true).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn test_read_timeout_fails_silent_server() {
+    let url = serve_nothing().await;
+    let mut data = retry_data(1, 0);
+    data.set_usize(USizeOpt::ReadTimeout, 50);
+    let net = Net::spawn(Config::mock(data), Log::mock());
+
+    let result = net.get(url, None).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_effective_config_reflects_settings() {
+    let mut data = retry_data(5, 7);
+    data.set_usize(USizeOpt::DefaultTimeout, 1500);
+    data.set_usize(USizeOpt::ConnectTimeout, 0);
+    let mut options = NetOptions::default();
+    options.method_headers.insert(
+        HttpMethod::Get,
+        HashMap::from([
+            (ArcStr::from("User-Agent"), ArcStr::from("patch-hub/1.0")),
+            (ArcStr::from("Authorization"), ArcStr::from("Bearer secret")),
+        ]),
+    );
+    let net = Net::spawn_with_options(Config::mock(data), Log::mock(), options);
+
+    let snapshot = net.effective_config().await.unwrap();
+    assert_eq!(snapshot.default_timeout, Some(Duration::from_millis(1500)));
+    assert_eq!(snapshot.connect_timeout, None);
+    assert_eq!(snapshot.read_timeout, Some(Duration::from_secs(30)));
+    assert_eq!(snapshot.retry_policy.max_attempts, 5);
+    assert_eq!(snapshot.retry_policy.base_delay, Duration::ZERO);
+    assert_eq!(snapshot.retry_budget_capacity, 7);
+    assert_eq!(snapshot.user_agent, Some(ArcStr::from("patch-hub/1.0")));
+    assert_eq!(
+        snapshot.method_headers[&HttpMethod::Get][&ArcStr::from("Authorization")],
+        ArcStr::from("[redacted]")
+    );
+
+    assert_eq!(
+        Net::mock_empty().effective_config().await.unwrap(),
+        super::NetConfigSnapshot::default()
+    );
+}
+
+#[tokio::test]
+async fn test_get_checked_accepts_listed_statuses() {
+    let url = ArcStr::from("https://lore.kernel.org/missing");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(404, ArcStr::from("no such message")),
+    );
+    let net = Net::mock_with_state(Arc::new(Mutex::new(state)));
+
+    let response = net
+        .get_checked(url.clone(), None, Some(HashSet::from([404])))
+        .await
+        .unwrap();
+    assert_eq!(response.status, 404);
+    assert_eq!(response.text(), ArcStr::from("no such message"));
+
+    let err = net.get_checked(url, None, None).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<NetError>(),
+        Some(NetError::Status { status: 404, .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_get_non_2xx_handling() {
+    let url = ArcStr::from("https://lore.kernel.org/missing");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(404, ArcStr::from("no such message")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let spawn = |error_on_non_2xx| {
+        let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+            .with_transport(Transport::Mock(state.clone()))
+            .with_options(NetOptions {
+                error_on_non_2xx,
+                ..Default::default()
+            })
+            .spawn();
+        net
+    };
+
+    assert!(NetOptions::default().error_on_non_2xx);
+    let err = spawn(true).get(url.clone(), None).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<NetError>(),
+        Some(NetError::Status { status: 404, .. })
+    ));
+    let err = Net::mock_with_state(state.clone())
+        .get(url.clone(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<NetError>(),
+        Some(NetError::Status { status: 404, .. })
+    ));
+
+    let body = spawn(false).get(url, None).await.unwrap();
+    assert_eq!(body, ArcStr::from("no such message"));
+}
+
+#[tokio::test]
+async fn test_request_retry_policy_overrides_default() {
+    let url = ArcStr::from("https://lore.kernel.org/flaky");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(3, 10));
+
+    let request =
+        NetRequest::new(HttpMethod::Get, url, None, None).with_retry_policy(RetryPolicy {
+            max_attempts: 0,
+            base_delay: Duration::ZERO,
+            ..Default::default()
+        });
+    let response = net.request(request.clone()).await.unwrap();
+    assert_eq!(response.status, 503);
+    assert_eq!(state.lock().await.requests().len(), 1);
+
+    net.request(request.with_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::ZERO,
+        ..Default::default()
+    }))
+    .await
+    .unwrap();
+    assert_eq!(state.lock().await.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn test_trace_id_sent_and_logged() {
+    let url = ArcStr::from("https://lore.kernel.org/traced");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("ok")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let log = Log::mock();
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), log.clone())
+        .with_transport(Transport::Mock(state.clone()))
+        .spawn();
+
+    net.get(url, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let state = state.lock().await;
+    let request = &state.requests()[0];
+    let trace_id = request.trace_id.clone().unwrap();
+    assert!(!trace_id.is_empty());
+    assert_eq!(
+        request.headers.get(&ArcStr::from(DEFAULT_TRACE_HEADER)),
+        Some(&trace_id)
+    );
+    let messages = log.get_messages().await.unwrap();
+    assert!(
+        messages
+            .iter()
+            .any(|message| message.message.contains(&*trace_id))
+    );
+}
+
+#[tokio::test]
+async fn test_trace_id_given_with_custom_header() {
+    let url = ArcStr::from("https://lore.kernel.org/traced");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("ok")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let options = NetOptions {
+        trace_header: Some(ArcStr::from("X-Trace")),
+        ..Default::default()
+    };
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_transport(Transport::Mock(state.clone()))
+        .with_options(options)
+        .spawn();
+
+    let request =
+        NetRequest::new(HttpMethod::Get, url, None, None).with_trace_id(ArcStr::from("trace-1"));
+    net.request(request).await.unwrap();
+
+    let state = state.lock().await;
+    assert_eq!(
+        state.requests()[0].headers.get(&ArcStr::from("X-Trace")),
+        Some(&ArcStr::from("trace-1"))
+    );
+}
+
+#[tokio::test]
+async fn test_same_host_only_refuses_cross_host_redirect() {
+    let url = serve_once(
+        "HTTP/1.1 302 Found\r\nLocation: http://evil.example.invalid/\r\nContent-Length: 0\r\n\r\n",
+    )
+    .await;
+    let options = NetOptions {
+        same_host_only: true,
+        ..Default::default()
+    };
+    let net = Net::spawn_with_options(Config::mock(retry_data(1, 0)), Log::mock(), options);
+
+    let request = NetRequest::new(HttpMethod::Get, url, None, None);
+    let response = net.request(request).await.unwrap();
+    assert_eq!(response.status, 302);
+    assert_eq!(
+        response.headers.get(&ArcStr::from("location")),
+        Some(&ArcStr::from("http://evil.example.invalid/"))
+    );
+}
+
+#[tokio::test]
+async fn test_redirects_are_followed() {
+    let url = ArcStr::from("https://lore.kernel.org/r/20240101-fix@example.org");
+    let canonical = ArcStr::from("https://lore.kernel.org/lkml/20240101-fix@example.org/");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(301, ArcStr::from("moved"))
+            .header("Location", "/lkml/20240101-fix@example.org/"),
+    );
+    state.insert(
+        MockRequestKey::get(canonical.clone()),
+        MockResponse::ok(ArcStr::from("patch")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    assert_eq!(
+        net.get(url.clone(), None).await.unwrap(),
+        ArcStr::from("patch")
+    );
+    let urls: Vec<_> = state
+        .lock()
+        .await
+        .requests()
+        .iter()
+        .map(|request| request.url.clone())
+        .collect();
+    assert_eq!(urls, vec![url.clone(), canonical]);
+
+    // Disabled for one request, the redirect response itself is returned
+    let request = NetRequest::new(HttpMethod::Get, url, None, None).without_redirects();
+    let response = net.request(request).await.unwrap();
+    assert_eq!(response.status, 301);
+    assert_eq!(response.text(), ArcStr::from("moved"));
+}
+
+#[tokio::test]
+async fn test_too_many_redirects_lists_chain() {
+    let urls: Vec<_> = (0..4)
+        .map(|hop| ArcStr::from(&format!("https://lore.kernel.org/hop/{}", hop)))
+        .collect();
+    let mut state = MockState::default();
+    for hop in urls.windows(2) {
+        state.insert(
+            MockRequestKey::get(hop[0].clone()),
+            MockResponse::with_status(302, ArcStr::from("")).header("Location", &hop[1]),
+        );
+    }
+    let state = Arc::new(Mutex::new(state));
+    let mut data = retry_data(3, 10);
+    data.set_usize(USizeOpt::MaxRedirects, 2);
+    let net = spawn_with_state(&state, data);
+
+    let err = net.get(urls[0].clone(), None).await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<NetError>(),
+        Some(&NetError::TooManyRedirects {
+            chain: urls.clone()
+        })
+    );
+    assert!(format!("{:#}", err).contains(&format!("{} -> {}", urls[2], urls[3])));
+    // A redirect loop isn't transient, so it isn't retried
+    assert_eq!(state.lock().await.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_replay_guard_refuses_duplicate_post() {
+    let url = ArcStr::from("https://lore.kernel.org/submit");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::post(url.clone()),
+        MockResponse::ok(ArcStr::from("created")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let options = NetOptions {
+        replay_window: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_transport(Transport::Mock(state.clone()))
+        .with_options(options)
+        .spawn();
+
+    let body = Some(ArcStr::from("[PATCH] drm: fix b"));
+    net.post(url.clone(), None, body.clone(), None)
+        .await
+        .unwrap();
+    let error = net
+        .post(url.clone(), None, body.clone(), None)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<NetError>(),
+        Some(&NetError::DuplicateRequest { url: url.clone() })
+    );
+    assert_eq!(state.lock().await.requests().len(), 1);
+
+    net.post(
+        url.clone(),
+        None,
+        Some(ArcStr::from("[PATCH v2] drm: fix b")),
+        None,
+    )
+    .await
+    .unwrap();
+    let forced = NetRequest::new(HttpMethod::Post, url, None, body).with_force_replay();
+    net.request(forced).await.unwrap();
+    assert_eq!(state.lock().await.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_host_rate_limit_paces_requests() {
+    let url = ArcStr::from("https://lore.kernel.org/paced");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("ok")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let mut data = retry_data(1, 0);
+    data.set_usize(USizeOpt::HostRateLimit, 20);
+    data.set_usize(USizeOpt::HostRateBurst, 1);
+    let net = spawn_with_state(&state, data);
+
+    let start = tokio::time::Instant::now();
+    for _ in 0..5 {
+        net.get(url.clone(), None).await.unwrap();
+    }
+    // The first request uses the burst, the 4 others wait 50ms each
+    assert!(start.elapsed() >= Duration::from_millis(190));
+    assert_eq!(state.lock().await.requests().len(), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_total_rate_limit_paces_requests_across_hosts() {
+    let urls = [
+        ArcStr::from("https://lore.kernel.org/paced"),
+        ArcStr::from("https://mirror.example.org/paced"),
+    ];
+    let mut state = MockState::default();
+    for url in &urls {
+        state.insert(
+            MockRequestKey::get(url.clone()),
+            MockResponse::ok(ArcStr::from("ok")),
+        );
+    }
+    let state = Arc::new(Mutex::new(state));
+    let mut data = retry_data(1, 0);
+    data.set_usize(USizeOpt::HostRateLimit, 0);
+    data.set_usize(USizeOpt::TotalRateLimit, 20);
+    data.set_usize(USizeOpt::TotalRateBurst, 1);
+    let net = spawn_with_state(&state, data);
+
+    let start = tokio::time::Instant::now();
+    for url in urls.iter().cycle().take(4) {
+        net.get(url.clone(), None).await.unwrap();
+    }
+    // The first request uses the burst, the 3 others wait 50ms each
+    assert!(start.elapsed() >= Duration::from_millis(150));
+    assert!(start.elapsed() < Duration::from_millis(200));
+    assert_eq!(state.lock().await.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn test_no_decompression_host_returns_raw_body() {
+    const RESPONSE: &str = "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 9\r\nConnection: close\r\n\r\nnot gzip!";
+    let options = NetOptions {
+        no_decompression_hosts: HashSet::from([ArcStr::from("127.0.0.1")]),
+        ..Default::default()
+    };
+    let net = Net::spawn_with_options(Config::mock(retry_data(1, 0)), Log::mock(), options);
+    let url = serve_once(RESPONSE).await;
+    assert_eq!(net.get(url, None).await.unwrap(), ArcStr::from("not gzip!"));
+
+    // Elsewhere the body is decompressed, and fails as it isn't gzip
+    let net = Net::spawn_with_options(
+        Config::mock(retry_data(1, 0)),
+        Log::mock(),
+        NetOptions::default(),
+    );
+    let url = serve_once(RESPONSE).await;
+    assert!(net.get(url, None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_retries_stop_at_total_elapsed_cap() {
+    let url = ArcStr::from("https://lore.kernel.org/flaky");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 10));
+
+    // Retries wait 20ms then 40ms: the second one would end past the 50ms cap
+    let request =
+        NetRequest::new(HttpMethod::Get, url, None, None).with_retry_policy(RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(20),
+            max_backoff: None,
+            max_total_elapsed: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+    let start = tokio::time::Instant::now();
+    let response = net.request(request).await.unwrap();
+    assert_eq!(response.status, 503);
+    assert_eq!(state.lock().await.requests().len(), 2);
+    assert!(start.elapsed() < Duration::from_millis(200));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_delays_follow_configured_multiplier() {
+    let url = ArcStr::from("https://lore.kernel.org/flaky");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(503, ArcStr::from("unavailable")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let mut data = retry_data(4, 10);
+    data.set_usize(USizeOpt::RetryBaseDelay, 10);
+    data.set_usize(USizeOpt::RetryMultiplier, 3);
+    let net = spawn_with_state(&state, data);
+
+    // Retries wait 10ms, 30ms then 90ms
+    let start = tokio::time::Instant::now();
+    let response = net.get_full(url, None).await.unwrap();
+    assert_eq!(response.status, 503);
+    assert_eq!(state.lock().await.requests().len(), 4);
+    assert!(start.elapsed() >= Duration::from_millis(130));
+    assert!(start.elapsed() < Duration::from_millis(140));
+}
+
+#[tokio::test]
+async fn test_expect_continue_rejected_before_body_upload() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = ArcStr::from(&format!("http://{}/upload", listener.local_addr().unwrap()));
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !received.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = socket.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..read]);
+        }
+        let head = String::from_utf8_lossy(&received).to_lowercase();
+        // Reject from the headers, then count the body bytes sent anyway
+        socket
+            .write_all(
+                b"HTTP/1.1 413 Payload Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let header_end = head.find("\r\n\r\n").unwrap() + 4;
+        let mut body_bytes = received.len() - header_end;
+        while let Ok(Ok(read)) =
+            tokio::time::timeout(Duration::from_millis(300), socket.read(&mut buf)).await
+        {
+            if read == 0 {
+                break;
+            }
+            body_bytes += read;
+        }
+        let _ = tx.send((head.contains("expect: 100-continue"), body_bytes));
+    });
+    let net = Net::spawn(Config::mock(retry_data(1, 0)), Log::mock());
+
+    let body = ArcStr::from(&"x".repeat(256 * 1024));
+    let request = NetRequest::new(HttpMethod::Post, url, None, Some(body)).with_expect_continue();
+    let response = net.request(request).await.unwrap();
+    assert_eq!(response.status, 413);
+
+    let (expected, body_bytes) = rx.await.unwrap();
+    assert!(expected);
+    assert_eq!(body_bytes, 0);
+}
+
+#[tokio::test]
+async fn test_expect_continue_falls_back_on_417() {
+    let url = ArcStr::from("https://mirror.example.com/upload");
+    let mut state = MockState::default();
+    state.push(
+        MockRequestKey::post(url.clone()),
+        MockResponse::with_status(417, ArcStr::from("expectation failed")),
+    );
+    state.push(
+        MockRequestKey::post(url.clone()),
+        MockResponse::ok(ArcStr::from("stored")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    let request = NetRequest::new(HttpMethod::Post, url, None, Some(ArcStr::from("patch")))
+        .with_expect_continue();
+    let response = net.request(request).await.unwrap();
+    assert_eq!(response.text(), ArcStr::from("stored"));
+
+    let state = state.lock().await;
+    let requests = state.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].headers.contains_key(&ArcStr::from("Expect")));
+    assert!(!requests[1].headers.contains_key(&ArcStr::from("Expect")));
+}
+
+#[tokio::test]
+async fn test_response_start_dropped_before_body() {
+    const BODY_LEN: usize = 64 * 1024 * 1024;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = ArcStr::from(&format!(
+        "http://{}/big.tar",
+        listener.local_addr().unwrap()
+    ));
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = socket.read(&mut buf).await;
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/x-tar\r\nContent-Length: {}\r\n\r\n",
+            BODY_LEN
+        );
+        socket.write_all(head.as_bytes()).await.unwrap();
+        // Write the body until the client hangs up
+        let chunk = vec![0u8; 64 * 1024];
+        let mut written = 0;
+        while written < BODY_LEN && socket.write_all(&chunk).await.is_ok() {
+            written += chunk.len();
+        }
+        let _ = tx.send(written);
+    });
+    let net = Net::spawn(Config::mock(retry_data(1, 0)), Log::mock());
+
+    let start = net.get_response_start(url, None).await.unwrap();
+    assert_eq!(start.status, 200);
+    assert_eq!(
+        start.headers.get(&ArcStr::from("content-type")),
+        Some(&ArcStr::from("application/x-tar"))
+    );
+    drop(start);
+
+    let written = tokio::time::timeout(Duration::from_secs(10), rx)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(written < BODY_LEN);
+}
+
+#[tokio::test]
+async fn test_mock_response_start_keeps_status() {
+    let url = ArcStr::from("https://lore.kernel.org/missing");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::with_status(404, ArcStr::from("not found"))
+            .header("Content-Type", "text/plain"),
+    );
+    let net = Net::mock_with_state(Arc::new(Mutex::new(state)));
+
+    let start = net.get_response_start(url, None).await.unwrap();
+    assert_eq!(start.status, 404);
+    assert_eq!(
+        start.headers.get(&ArcStr::from("content-type")),
+        Some(&ArcStr::from("text/plain"))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_after_holds_requests_to_host() {
+    let throttled = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let next = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let elsewhere = ArcStr::from("https://mirror.example.org/lkml/new.atom");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(throttled.clone()),
+        MockResponse::with_status(429, ArcStr::from("slow down")).header("Retry-After", "30"),
+    );
+    state.insert(
+        MockRequestKey::get(next.clone()),
+        MockResponse::ok(ArcStr::from("feed")),
+    );
+    state.insert(
+        MockRequestKey::get(elsewhere.clone()),
+        MockResponse::ok(ArcStr::from("mirrored feed")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    net.get(throttled, None).await.unwrap_err();
+    let metrics = net.metrics().await.unwrap();
+    assert!(!metrics.is_open("lore.kernel.org"));
+    assert!(metrics.is_open("mirror.example.org"));
+
+    // Other hosts aren't held
+    let start = tokio::time::Instant::now();
+    net.get(elsewhere, None).await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // The next request to the host waits for the cooldown instead of hitting it
+    net.get(next, None).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(30));
+    assert!(net.metrics().await.unwrap().is_open("lore.kernel.org"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_higher_priority_requests_are_served_first() {
+    let throttled = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let held = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(throttled.clone()),
+        MockResponse::with_status(429, ArcStr::from("slow down")).header("Retry-After", "30"),
+    );
+    state.insert(
+        MockRequestKey::get(held.clone()),
+        MockResponse::ok(ArcStr::from("feed")),
+    );
+    let priorities = [
+        ("low", Priority::Low),
+        ("normal", Priority::Normal),
+        ("high", Priority::High),
+        ("second-low", Priority::Low),
+        ("second-high", Priority::High),
+    ];
+    for (name, _) in priorities {
+        state.insert(
+            MockRequestKey::get(ArcStr::from(&format!("https://mirror.example.org/{name}"))),
+            MockResponse::ok(ArcStr::from(name)),
+        );
+    }
+    let state = Arc::new(Mutex::new(state));
+    let mut data = retry_data(1, 0);
+    data.set_usize(USizeOpt::MaxConcurrentRequests, 1);
+    let net = spawn_with_state(&state, data);
+
+    // Keeps the only slot busy waiting for the cooldown of the host
+    net.get(throttled, None).await.unwrap_err();
+    let busy = tokio::spawn({
+        let net = net.clone();
+        async move { net.get(held, None).await }
+    });
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let queued: Vec<_> = priorities
+        .into_iter()
+        .map(|(name, priority)| {
+            let net = net.clone();
+            let url = ArcStr::from(&format!("https://mirror.example.org/{name}"));
+            tokio::spawn(async move {
+                match name {
+                    // Plain requests are sent with the normal priority
+                    "normal" => net.get(url, None).await,
+                    "second-low" | "second-high" => {
+                        net.with_priority(priority).get(url, None).await
+                    }
+                    _ => {
+                        let request = NetRequest::new(HttpMethod::Get, url, None, None)
+                            .with_priority(priority);
+                        net.request(request).await.map(|response| response.text())
+                    }
+                }
+            })
+        })
+        .collect();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    busy.await.unwrap().unwrap();
+    for handle in queued {
+        handle.await.unwrap().unwrap();
+    }
+    let served: Vec<_> = state.lock().await.requests()[2..]
+        .iter()
+        .map(|request| request.url.clone())
+        .collect();
+    assert_eq!(
+        served,
+        ["high", "second-high", "normal", "low", "second-low"]
+            .map(|name| ArcStr::from(&format!("https://mirror.example.org/{name}")))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_senders_wait_once_the_priority_buffer_is_full() {
+    let url = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(url.clone()),
+        MockResponse::ok(ArcStr::from("feed")),
+    );
+    state.set_delay(MockRequestKey::get(url.clone()), Duration::from_secs(60));
+    let state = Arc::new(Mutex::new(state));
+    let mut data = Data::default();
+    data.set_usize(USizeOpt::MaxConcurrentRequests, 1);
+    let net = spawn_with_state(&state, data);
+    let Net::Actual(sender) = &net else {
+        unreachable!("The core spawns an actual actor");
+    };
+    let get = || {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let message = Message::Get {
+            url: url.clone(),
+            headers: None,
+            priority: Priority::Normal,
+            tx,
+        };
+        (message, rx)
+    };
+
+    // One request in flight, a full buffer behind it and a full channel
+    let mut answers = Vec::new();
+    for _ in 0..1 + 2 * MAILBOX_CAPACITY {
+        let (message, rx) = get();
+        sender.send(message).await.unwrap();
+        answers.push(rx);
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let (message, rx) = get();
+    let blocked = tokio::time::timeout(Duration::from_secs(10), sender.send(message)).await;
+    assert!(blocked.is_err());
+    drop(rx);
+
+    // The first answer makes room for one more message
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    answers.remove(0).await.unwrap().unwrap();
+    let (message, _rx) = get();
+    sender.try_send(message).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_cooldown_of_a_host_does_not_stall_other_hosts() {
+    let throttled = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let held = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let other = ArcStr::from("https://mirror.example.org/lkml/new.atom");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(throttled.clone()),
+        MockResponse::with_status(429, ArcStr::from("slow down")).header("Retry-After", "30"),
+    );
+    state.insert(
+        MockRequestKey::get(held.clone()),
+        MockResponse::ok(ArcStr::from("held")),
+    );
+    state.insert(
+        MockRequestKey::get(other.clone()),
+        MockResponse::ok(ArcStr::from("other")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let net = spawn_with_state(&state, retry_data(1, 0));
+
+    net.get(throttled, None).await.unwrap_err();
+    let start = tokio::time::Instant::now();
+    let busy = tokio::spawn({
+        let net = net.clone();
+        async move { net.get(held, None).await }
+    });
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // The other host is served while the request to the first one waits
+    assert_eq!(net.get(other, None).await.unwrap(), ArcStr::from("other"));
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(!busy.is_finished());
+
+    assert_eq!(busy.await.unwrap().unwrap(), ArcStr::from("held"));
+    assert!(start.elapsed() >= Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn test_stalled_body_fails() {
+    const RESPONSE_START: &str = "HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\nfirst bytes";
+    let stall_timeout = Duration::from_millis(100);
+    let url = serve_stalled(RESPONSE_START).await;
+    let stalled = NetError::Stalled {
+        url: url.clone(),
+        timeout: stall_timeout,
+    };
+
+    // The bytes received before the stall are streamed, then the stall fails the body
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_options(NetOptions {
+            stall_timeout: Some(stall_timeout),
+            ..Default::default()
+        })
+        .spawn();
+    let mut body = net.get_stream(url.clone(), None).await.unwrap();
+    let mut received = Vec::new();
+    let err = loop {
+        match std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)).await {
+            Some(Ok(chunk)) => received.extend_from_slice(&chunk),
+            Some(Err(err)) => break err,
+            None => panic!("the stalled body ended"),
+        }
+    };
+    assert_eq!(received, b"first bytes");
+    assert_eq!(err.downcast_ref::<NetError>(), Some(&stalled));
+
+    // Buffered requests fail the same way, here with a stall timeout of their own
+    let net = Net::spawn(Config::mock(retry_data(1, 0)), Log::mock());
+    let request =
+        NetRequest::new(HttpMethod::Get, url, None, None).with_stall_timeout(stall_timeout);
+    let err = net.request(request).await.unwrap_err();
+    assert_eq!(err.downcast_ref::<NetError>(), Some(&stalled));
+}
+
+#[tokio::test]
+async fn test_basic_auth_retries_with_realm_credentials() {
+    let protected = ArcStr::from("https://mirror.example.org/amd-gfx/new.atom");
+    let unknown = ArcStr::from("https://private.example.org/amd-gfx/new.atom");
+    let challenge = |realm: &str| {
+        MockResponse::with_status(401, ArcStr::from("unauthorized"))
+            .header("WWW-Authenticate", &format!("Basic realm=\"{}\"", realm))
+    };
+    let mut state = MockState::default();
+    state.push(MockRequestKey::get(protected.clone()), challenge("mirror"));
+    state.push(
+        MockRequestKey::get(protected.clone()),
+        MockResponse::ok(ArcStr::from("feed")),
+    );
+    state.insert(MockRequestKey::get(unknown.clone()), challenge("private"));
+    let state = Arc::new(Mutex::new(state));
+    let options = NetOptions {
+        credentials: HashMap::from([(
+            ArcStr::from("mirror"),
+            Credentials {
+                username: ArcStr::from("alice"),
+                password: ArcStr::from("secret"),
+            },
+        )]),
+        ..Default::default()
+    };
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_transport(Transport::Mock(state.clone()))
+        .with_options(options)
+        .spawn();
+
+    assert_eq!(
+        net.get(protected, None).await.unwrap(),
+        ArcStr::from("feed")
+    );
+    {
+        let state = state.lock().await;
+        let requests = state.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(
+            !requests[0]
+                .headers
+                .contains_key(&ArcStr::from("Authorization"))
+        );
+        assert_eq!(
+            requests[1].headers.get(&ArcStr::from("Authorization")),
+            Some(&ArcStr::from("Basic YWxpY2U6c2VjcmV0"))
+        );
+    }
+
+    // Without credentials for the realm, the 401 is returned as is
+    let request = NetRequest::new(HttpMethod::Get, unknown, None, None);
+    let response = net.request(request).await.unwrap();
+    assert_eq!(response.status, 401);
+    assert_eq!(state.lock().await.requests().len(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_robots_disallowed_paths_are_refused() {
+    let robots = ArcStr::from("https://lore.kernel.org/robots.txt");
+    let private = ArcStr::from("https://lore.kernel.org/private/inbox");
+    let public = ArcStr::from("https://lore.kernel.org/private/public");
+    let feed = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(robots.clone()),
+        MockResponse::ok(ArcStr::from(
+            "User-agent: *\nDisallow: /private/\nAllow: /private/public\nCrawl-delay: 5\n",
+        )),
+    );
+    for url in [&private, &public, &feed] {
+        state.insert(
+            MockRequestKey::get(url.clone()),
+            MockResponse::ok(ArcStr::from("content")),
+        );
+    }
+    let state = Arc::new(Mutex::new(state));
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_options(NetOptions {
+            respect_robots: true,
+            ..Default::default()
+        })
+        .with_transport(Transport::Mock(state.clone()))
+        .spawn();
+
+    let err = net.get(private.clone(), None).await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<NetError>(),
+        Some(&NetError::DisallowedByRobots { url: private })
+    );
+
+    // Allowed paths go through, spaced by the crawl delay
+    let start = tokio::time::Instant::now();
+    net.get(public, None).await.unwrap();
+    net.get(feed, None).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(5));
+
+    // The robots.txt is fetched once, and the disallowed path never is
+    let requests = state.lock().await.requests().to_vec();
+    let urls: Vec<&str> = requests.iter().map(|request| &*request.url).collect();
+    assert_eq!(
+        urls,
+        [
+            "https://lore.kernel.org/robots.txt",
+            "https://lore.kernel.org/private/public",
+            "https://lore.kernel.org/amd-gfx/new.atom",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_download_to_file() {
+    let archive = ArcStr::from("https://lore.kernel.org/amd-gfx/t.mbox.gz");
+    let missing = ArcStr::from("https://lore.kernel.org/amd-gfx/missing.mbox.gz");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(archive.clone()),
+        MockResponse::ok(ArcStr::from(
+            "From mboxrd@z Thu Jan  1 00:00:00 1970\n\nbody\n",
+        )),
+    );
+    state.insert(
+        MockRequestKey::get(missing.clone()),
+        MockResponse::with_status(404, ArcStr::from("not found")),
+    );
+    let net = Net::mock_with_state(Arc::new(Mutex::new(state)));
+    let dir = tempfile::tempdir().unwrap();
+
+    // Missing parent directories are created
+    let path = dir.path().join("archives").join("amd-gfx.mbox");
+    let written = net
+        .download_to_file(archive, ArcPath::from(&path), None)
+        .await
+        .unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(content, "From mboxrd@z Thu Jan  1 00:00:00 1970\n\nbody\n");
+    assert_eq!(written, content.len() as u64);
+
+    // A failed download leaves the previous file as it was, without a partial file
+    let err = net
+        .download_to_file(missing, ArcPath::from(&path), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<NetError>(),
+        Some(NetError::Status { status: 404, .. })
+    ));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+    assert_eq!(
+        std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_recorded_fixtures_round_trip() {
+    let feed = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let missing = ArcStr::from("https://lore.kernel.org/amd-gfx/missing/raw");
+    let archive = ArcStr::from("https://lore.kernel.org/amd-gfx/t.mbox.gz");
+    let mut upstream = MockState::default();
+    upstream.insert(
+        MockRequestKey::get(feed.clone()),
+        MockResponse::ok(ArcStr::from("<feed/>"))
+            .header("Content-Type", "application/atom+xml")
+            .header("Set-Cookie", "session=hunter2"),
+    );
+    upstream.insert(
+        MockRequestKey::get(missing.clone()),
+        MockResponse::with_status(404, ArcStr::from("not found")),
+    );
+    upstream.insert(
+        MockRequestKey::get(archive.clone()),
+        MockResponse {
+            status: 200,
+            headers: HashMap::new(),
+            body: Arc::from(&b"\x1f\x8b\x08\x00"[..]),
+        },
+    );
+    let recorder = Arc::new(Mutex::new(MockState::default()));
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_transport(Transport::Mock(Arc::new(Mutex::new(upstream))))
+        .with_recorder(recorder.clone())
+        .spawn();
+
+    net.get(feed.clone(), None).await.unwrap();
+    net.get(missing.clone(), None).await.unwrap_err();
+    let body = net.get_bytes(archive.clone(), None).await.unwrap();
+    assert_eq!(recorder.lock().await.requests().len(), 3);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fixtures").join("lore.json");
+    net.export_fixtures(ArcPath::from(&path)).await.unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(!content.contains("hunter2"));
+    assert!(content.contains("[redacted]"));
+
+    // The reloaded mock serves what was recorded
+    let replay = Net::mock_from_file(ArcPath::from(&path)).await.unwrap();
+    assert_eq!(
+        replay.get(feed, None).await.unwrap(),
+        ArcStr::from("<feed/>")
+    );
+    assert_eq!(replay.get_bytes(archive, None).await.unwrap(), body);
+    let response = replay
+        .request(NetRequest::new(HttpMethod::Get, missing, None, None))
+        .await
+        .unwrap();
+    assert_eq!(response.status, 404);
+
+    // Exporting the reloaded mock gives the same file back
+    let again = dir.path().join("again.json");
+    replay.export_fixtures(ArcPath::from(&again)).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&again).unwrap(), content);
+
+    // Only a recording actor can export
+    let plain = spawn_with_state(
+        &Arc::new(Mutex::new(MockState::default())),
+        retry_data(1, 0),
+    );
+    assert!(plain.export_fixtures(ArcPath::from(&again)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_post_json_encoding() {
+    let url = ArcStr::from("https://patchwork.example.org/api/events");
+    let value = serde_json::json!({ "list": "amd-gfx", "ids": [1, 2] });
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::post(url.clone()),
+        MockResponse::ok(ArcStr::from("created")),
+    );
+    let state = Arc::new(Mutex::new(state));
+    let content_type = |request: &NetRequest| {
+        request
+            .headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>()
+    };
+
+    // Compact by default, and the given content type is replaced
+    let net = spawn_with_state(&state, retry_data(1, 0));
+    let headers = HashMap::from([(ArcStr::from("content-type"), ArcStr::from("text/plain"))]);
+    let response = net
+        .post_json(url.clone(), &value, Some(headers))
+        .await
+        .unwrap();
+    assert_eq!(response, ArcStr::from("created"));
+    let request = state.lock().await.requests()[0].clone();
+    assert_eq!(
+        request.body,
+        Some(ArcStr::from(r#"{"ids":[1,2],"list":"amd-gfx"}"#))
+    );
+    assert_eq!(content_type(&request), [ArcStr::from(JSON_CONTENT_TYPE)]);
+
+    let (net, _) = Core::new(Config::mock(retry_data(1, 0)), Log::mock())
+        .with_options(NetOptions {
+            json_encoding: JsonEncoding::Pretty,
+            ..Default::default()
+        })
+        .with_transport(Transport::Mock(state.clone()))
+        .spawn();
+    net.post_json(url, &value, None).await.unwrap();
+    let request = state.lock().await.requests()[1].clone();
+    assert_eq!(
+        request.body,
+        Some(ArcStr::from(
+            "{\n  \"ids\": [\n    1,\n    2\n  ],\n  \"list\": \"amd-gfx\"\n}"
+        ))
+    );
+    assert_eq!(content_type(&request), [ArcStr::from(JSON_CONTENT_TYPE)]);
+}
+
+#[tokio::test]
+async fn test_full_responses_carry_status() {
+    let patch = ArcStr::from("https://lore.kernel.org/amd-gfx/1-1@amd.com/raw");
+    let missing = ArcStr::from("https://lore.kernel.org/amd-gfx/missing/raw");
+    let submit = ArcStr::from("https://patchwork.example.org/api/comments");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(patch.clone()),
+        MockResponse::ok(ArcStr::from("From: Jane\n")),
+    );
+    state.insert(
+        MockRequestKey::get(missing.clone()),
+        MockResponse::with_status(404, ArcStr::from("<html>Not Found</html>")),
+    );
+    state.insert(
+        MockRequestKey::post(submit.clone()),
+        MockResponse::with_status(201, ArcStr::from("created")),
+    );
+    let state = Arc::new(Mutex::new(state));
+
+    for net in [
+        Net::mock_with_state(state.clone()),
+        spawn_with_state(&state, retry_data(1, 0)),
+    ] {
+        let response = net.get_full(patch.clone(), None).await.unwrap();
+        assert_eq!(
+            (response.status, response.text()),
+            (200, ArcStr::from("From: Jane\n"))
+        );
+
+        // The error page is returned, not mistaken for the content
+        let response = net.get_full(missing.clone(), None).await.unwrap();
+        assert_eq!(
+            (response.status, response.text()),
+            (404, ArcStr::from("<html>Not Found</html>"))
+        );
+        assert!(net.get(missing.clone(), None).await.is_err());
+
+        let response = net
+            .post_full(submit.clone(), None, Some(ArcStr::from("LGTM")), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status, 201);
+    }
+}
+
+#[tokio::test]
+async fn test_get_with_headers() {
+    let feed = ArcStr::from("https://lore.kernel.org/amd-gfx/new.atom");
+    let missing = ArcStr::from("https://lore.kernel.org/amd-gfx/missing/raw");
+    let mut state = MockState::default();
+    state.insert(
+        MockRequestKey::get(feed.clone()),
+        MockResponse::ok(ArcStr::from("<feed/>"))
+            .header("Content-Type", "application/atom+xml")
+            .header("ETag", "\"5f3a\"")
+            .header("X-RateLimit-Remaining", "59"),
+    );
+    state.insert(
+        MockRequestKey::get(missing.clone()),
+        MockResponse::with_status(404, ArcStr::from("not found")).header("ETag", "\"0\""),
+    );
+    let state = Arc::new(Mutex::new(state));
+
+    for net in [
+        Net::mock_with_state(state.clone()),
+        spawn_with_state(&state, retry_data(1, 0)),
+    ] {
+        let (body, headers) = net.get_with_headers(feed.clone(), None).await.unwrap();
+        assert_eq!(body, ArcStr::from("<feed/>"));
+        assert_eq!(
+            headers.get(&ArcStr::from("etag")),
+            Some(&ArcStr::from("\"5f3a\""))
+        );
+        assert_eq!(
+            headers.get(&ArcStr::from("content-type")),
+            Some(&ArcStr::from("application/atom+xml"))
+        );
+        assert_eq!(
+            headers.get(&ArcStr::from("x-ratelimit-remaining")),
+            Some(&ArcStr::from("59"))
+        );
+
+        let err = net
+            .get_with_headers(missing.clone(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<NetError>(),
+            Some(NetError::Status { status: 404, .. })
+        ));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_get_with_timeout() {
+    let slow = ArcStr::from("https://lore.kernel.org/lkml/new.atom");
+    let unknown = ArcStr::from("https://lore.kernel.org/unknown/new.atom");
+    let responses = HashMap::from([(
+        MockRequestKey::get(slow.clone()),
+        (ArcStr::from("feed"), Duration::from_secs(10)),
+    )]);
+    let state = Arc::new(Mutex::new(MockState::from(responses)));
+
+    for net in [
+        Net::mock_with_state(state.clone()),
+        spawn_with_state(&state, retry_data(1, 0)),
+    ] {
+        let started = tokio::time::Instant::now();
+        let err = net
+            .get_with_timeout(slow.clone(), None, Duration::from_secs(2))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<NetError>(),
+            Some(&NetError::TimedOut {
+                url: slow.clone(),
+                timeout: Duration::from_secs(2),
+            })
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // A longer timeout lets the response arrive
+        assert_eq!(
+            net.get_with_timeout(slow.clone(), None, Duration::from_secs(20))
+                .await
+                .unwrap(),
+            ArcStr::from("feed")
+        );
+
+        // Other failures aren't timeouts
+        let err = net
+            .get_with_timeout(unknown.clone(), None, Duration::from_secs(2))
+            .await
+            .unwrap_err();
+        assert!(!matches!(
+            err.downcast_ref::<NetError>(),
+            Some(NetError::TimedOut { .. })
+        ));
+    }
+}
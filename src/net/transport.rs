@@ -0,0 +1,255 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    ops::Deref,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll, ready},
+    time::Duration,
+};
+
+use anyhow::Context;
+use futures_core::Stream;
+use reqwest::{Body, Client, Method, RequestBuilder, Response, header::CONTENT_LENGTH};
+use tokio::{sync::Mutex, time::Sleep};
+
+use crate::{
+    ArcStr,
+    net::{
+        data::{NetError, NetRequest, NetResponse},
+        mock::MockState,
+        stream::BodyStream,
+    },
+};
+
+/// How long the body of a request expecting `100 Continue` is held back, as curl does
+const CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The transport used by the networking core to perform a single request attempt.
+///
+/// Retries and every other policy live in the core, so the core logic can be
+/// exercised against canned responses without touching the network.
+#[derive(Debug, Clone)]
+pub enum Transport {
+    /// Performs real HTTP requests through a reqwest client
+    Http(Client),
+    /// Serves canned responses from a shared mock state
+    Mock(Arc<Mutex<MockState>>),
+}
+
+impl Transport {
+    /// Sends a request and waits for its full response.
+    ///
+    /// When the request expects `100 Continue`, its body is held back for a
+    /// moment after the headers are sent, so a server rejecting the request
+    /// from its headers answers before the body is uploaded. The interim
+    /// `100 Continue` itself isn't reported by the HTTP client, so the body is
+    /// sent once the wait is over even if the server asked for it earlier.
+    ///
+    /// The body is buffered until the server signals its end, whatever the
+    /// framing: chunked responses without a `Content-Length` are read in full,
+    /// and the length of the returned body is the number of bytes actually
+    /// received rather than any length announced in the headers. When the
+    /// request has a body limit, reading stops as soon as the limit is reached
+    /// and the body is truncated to it. When the request has a stall timeout,
+    /// the body fails with [`NetError::Stalled`] once no bytes arrived for that long.
+    ///
+    /// # Arguments
+    /// * `request` - The request to send
+    ///
+    /// # Returns
+    /// The response received, or an error if the request could not be performed.
+    pub async fn send(&self, request: &NetRequest) -> anyhow::Result<NetResponse> {
+        match self {
+            Transport::Http(client) => {
+                let method = Method::from_bytes(request.method.as_str().as_bytes())
+                    .context("Converting HTTP method")?;
+                let mut builder = client.request(method, &*request.url);
+
+                for (key, value) in &request.headers {
+                    builder = builder.header(&**key, &**value);
+                }
+
+                builder = with_body(builder, request);
+
+                let mut response = builder
+                    .send()
+                    .await
+                    .with_context(|| format!("Sending {} request", request.method))?;
+                let status = response.status().as_u16();
+                let headers = response
+                    .headers()
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let value = value.to_str().ok()?;
+                        Some((ArcStr::from(key.as_str()), ArcStr::from(value)))
+                    })
+                    .collect();
+                let limit = request.body_limit.unwrap_or(usize::MAX);
+                let mut body = Vec::new();
+                while body.len() < limit {
+                    let Some(chunk) = next_chunk(&mut response, request).await? else {
+                        break;
+                    };
+                    body.extend_from_slice(&chunk);
+                }
+                body.truncate(limit);
+
+                Ok(NetResponse {
+                    status,
+                    headers,
+                    body: Arc::from(body),
+                })
+            }
+            Transport::Mock(state) => {
+                let delay = state.lock().await.delay(request);
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                let mut state = state.lock().await;
+                state
+                    .respond(request)
+                    .map(|response| {
+                        let mut response = NetResponse::from(response);
+                        if let Some(limit) = request.body_limit
+                            && response.body.len() > limit
+                        {
+                            response.body = Arc::from(&response.body[..limit]);
+                        }
+                        response
+                    })
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{} request not found in mock responses: {}",
+                            request.method,
+                            request.url
+                        )
+                    })
+            }
+        }
+    }
+
+    /// Sends a request and returns as soon as the response headers are received.
+    ///
+    /// The body is downloaded by a background task and handed over chunk by
+    /// chunk through the returned stream. The download stops when the stream
+    /// is dropped, or with [`NetError::Stalled`] once no bytes arrived for the
+    /// stall timeout of the request. Mock responses are streamed as a single chunk.
+    ///
+    /// # Arguments
+    /// * `request` - The request to send
+    ///
+    /// # Returns
+    /// The status code, the headers and the body stream of the response, or an
+    /// error if the request could not be performed.
+    pub async fn send_streaming(
+        &self,
+        request: &NetRequest,
+    ) -> anyhow::Result<(u16, HashMap<ArcStr, ArcStr>, BodyStream)> {
+        match self {
+            Transport::Http(client) => {
+                let method = Method::from_bytes(request.method.as_str().as_bytes())
+                    .context("Converting HTTP method")?;
+                let mut builder = client.request(method, &*request.url);
+
+                for (key, value) in &request.headers {
+                    builder = builder.header(&**key, &**value);
+                }
+
+                builder = with_body(builder, request);
+
+                let mut response = builder
+                    .send()
+                    .await
+                    .with_context(|| format!("Sending {} request", request.method))?;
+                let status = response.status().as_u16();
+                let headers = response
+                    .headers()
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let value = value.to_str().ok()?;
+                        Some((ArcStr::from(key.as_str()), ArcStr::from(value)))
+                    })
+                    .collect();
+
+                let (tx, body) = BodyStream::channel();
+                let request = request.clone();
+                tokio::spawn(async move {
+                    loop {
+                        let chunk = match next_chunk(&mut response, &request).await {
+                            Ok(Some(chunk)) => Ok(Arc::from(&chunk[..])),
+                            Ok(None) => break,
+                            Err(err) => Err(err),
+                        };
+                        let failed = chunk.is_err();
+                        if tx.send(chunk).await.is_err() || failed {
+                            break;
+                        }
+                    }
+                });
+
+                Ok((status, headers, body))
+            }
+            Transport::Mock(_) => {
+                let response = self.send(request).await?;
+                Ok((
+                    response.status,
+                    response.headers,
+                    BodyStream::once(response.body),
+                ))
+            }
+        }
+    }
+}
+
+/// Reads the next chunk of a response body, failing with [`NetError::Stalled`]
+/// if none arrives within the stall timeout of the request
+async fn next_chunk(
+    response: &mut Response,
+    request: &NetRequest,
+) -> anyhow::Result<Option<impl Deref<Target = [u8]>>> {
+    let Some(timeout) = request.stall_timeout else {
+        return response.chunk().await.context("Reading response body");
+    };
+    match tokio::time::timeout(timeout, response.chunk()).await {
+        Ok(chunk) => chunk.context("Reading response body"),
+        Err(_) => Err(NetError::Stalled {
+            url: request.url.clone(),
+            timeout,
+        }
+        .into()),
+    }
+}
+
+/// Attaches the body of a request to its builder, held back when the request expects `100 Continue`
+fn with_body(builder: RequestBuilder, request: &NetRequest) -> RequestBuilder {
+    match &request.body {
+        Some(body) if request.expect_continue => {
+            builder
+                .header(CONTENT_LENGTH, body.len())
+                .body(Body::wrap_stream(DeferredBody {
+                    delay: Box::pin(tokio::time::sleep(CONTINUE_TIMEOUT)),
+                    body: Some(body.to_string()),
+                }))
+        }
+        Some(body) => builder.body(body.to_string()),
+        None => builder,
+    }
+}
+
+/// A request body yielded in one piece once a delay is over
+struct DeferredBody {
+    /// The wait before the body is yielded
+    delay: Pin<Box<Sleep>>,
+    /// The body, until it is yielded
+    body: Option<String>,
+}
+
+impl Stream for DeferredBody {
+    type Item = Result<String, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        ready!(self.delay.as_mut().poll(cx));
+        Poll::Ready(self.body.take().map(Ok))
+    }
+}
@@ -1,6 +1,13 @@
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{ffi::OsStr, fmt::Display, ops::Deref, path::Path, sync::Arc};
-use tokio::{fs::File, sync::RwLock};
+use std::{
+    ffi::OsStr, fmt::Display, future::Future, ops::Deref, path::Path, sync::Arc, time::Duration,
+};
+use tokio::{
+    fs::File,
+    sync::{RwLock, oneshot},
+    task::JoinHandle,
+    time::Instant,
+};
 
 /// Installs custom panic and error hooks that restore the terminal state before printing errors.
 ///
@@ -24,6 +31,145 @@ pub fn install_panic_hook() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// How stopping an actor within a bounded time went wrong.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ShutdownError {
+    /// The actor didn't stop in time, and its task was aborted
+    #[error("Actor did not stop within {timeout:?}, its task was aborted")]
+    Forced {
+        /// How long the actor was given to stop
+        timeout: Duration,
+    },
+    /// The task of the actor panicked or was cancelled before stopping
+    #[error("Actor task failed while stopping: {0}")]
+    Failed(String),
+}
+
+/// Asks an actor to stop and waits for its task to end, aborting it past a timeout.
+///
+/// The timeout covers both sending the request, which may wait on a full
+/// mailbox, and the actor finishing what it does on the way out. An actor that
+/// doesn't stop in time is aborted, so teardown is always bounded.
+///
+/// # Arguments
+/// * `handle` - The join handle of the task of the actor, as returned by its `spawn`
+/// * `request` - Sends the actor its shutdown message
+/// * `timeout` - How long the actor is given to stop
+///
+/// # Returns
+/// `Ok(())` if the actor stopped on its own, or the reason it didn't.
+///
+/// # Examples
+/// ```
+/// let (log, handle) = log_core.spawn();
+/// shutdown_timeout(handle, async move { drop(log) }, Duration::from_secs(5)).await?;
+/// ```
+pub async fn shutdown_timeout<F>(
+    mut handle: JoinHandle<()>,
+    request: F,
+    timeout: Duration,
+) -> Result<(), ShutdownError>
+where
+    F: Future<Output = ()>,
+{
+    let stopped = tokio::time::timeout(timeout, async {
+        request.await;
+        (&mut handle).await
+    })
+    .await;
+    match stopped {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(ShutdownError::Failed(err.to_string())),
+        Err(_) => {
+            handle.abort();
+            Err(ShutdownError::Forced { timeout })
+        }
+    }
+}
+
+/// The shutdown of an actor handling its own shutdown message, as requested
+/// by the messages received so far.
+///
+/// The actor keeps handling the messages it already received until they are
+/// done or the earliest deadline of the requests passes, then acknowledges
+/// every request with how it went.
+#[derive(Debug)]
+pub struct Shutdown {
+    /// How long the actor was given to stop by the request with the earliest deadline
+    timeout: Duration,
+    /// When the work left is aborted
+    deadline: Instant,
+    /// The acknowledgement channel of each request
+    acks: Vec<oneshot::Sender<Result<(), ShutdownError>>>,
+}
+
+impl Shutdown {
+    /// Records a shutdown request, received now.
+    ///
+    /// # Arguments
+    /// * `shutdown` - The shutdown in progress, if any, updated with the request
+    /// * `timeout` - How long the request gives the actor to stop
+    /// * `ack` - Where the outcome of the shutdown is sent
+    pub fn request(
+        shutdown: &mut Option<Self>,
+        timeout: Duration,
+        ack: oneshot::Sender<Result<(), ShutdownError>>,
+    ) {
+        let deadline = Instant::now() + timeout;
+        let shutdown = shutdown.get_or_insert_with(|| Self {
+            timeout,
+            deadline,
+            acks: Vec::new(),
+        });
+        if deadline < shutdown.deadline {
+            shutdown.timeout = timeout;
+            shutdown.deadline = deadline;
+        }
+        shutdown.acks.push(ack);
+    }
+
+    /// Gets when the work left is aborted.
+    ///
+    /// # Arguments
+    /// * `shutdown` - The shutdown in progress, if any
+    ///
+    /// # Returns
+    /// The deadline of the shutdown, or `None` if none was requested.
+    pub fn deadline(shutdown: &Option<Self>) -> Option<Instant> {
+        shutdown.as_ref().map(|shutdown| shutdown.deadline)
+    }
+
+    /// Waits for a deadline to pass.
+    ///
+    /// # Arguments
+    /// * `deadline` - The deadline, as given by [`Shutdown::deadline`]
+    ///
+    /// # Returns
+    /// Once the deadline passed, never if there is none.
+    pub async fn expired(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Acknowledges every request, once the actor is done.
+    ///
+    /// # Arguments
+    /// * `forced` - Whether work left was aborted at the deadline
+    pub fn acknowledge(self, forced: bool) {
+        let outcome = match forced {
+            true => Err(ShutdownError::Forced {
+                timeout: self.timeout,
+            }),
+            false => Ok(()),
+        };
+        for ack in self.acks {
+            let _ = ack.send(outcome.clone());
+        }
+    }
+}
+
 /// A thread-safe reference-counted string type.
 /// This type is used throughout the application for sharing string data between threads.
 ///